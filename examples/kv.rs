@@ -2,8 +2,8 @@ use cid::Cid;
 use futures::sink::unfold;
 use rhizome::{
     kernel::math,
-    runtime::{client::Client, reactor::Reactor},
-    tuple::{InputTuple, Tuple},
+    runtime::{client::Client, reactor::Reactor, SinkEvent},
+    tuple::InputTuple,
     types::Any,
     value::Val,
 };
@@ -81,13 +81,17 @@ async fn main() -> Result<(), Box<dyn Error>> {
         let db = Arc::clone(&db);
         async move {
             client
-                .register_sink(
+                .register_diff_sink(
                     "head",
                     Box::new({
                         let db = Arc::clone(&db);
 
                         || {
-                            Box::new(unfold(db, move |db, fact: Tuple| async move {
+                            Box::new(unfold(db, move |db, event: SinkEvent| async move {
+                                let SinkEvent::Diff(fact, _, diff) = event else {
+                                    return Ok(db);
+                                };
+
                                 let Some(Val::Cid(cid)) = fact.col(&"cid".into()) else {
                                     panic!("cid is not a cid");
                                 };
@@ -100,10 +104,16 @@ async fn main() -> Result<(), Box<dyn Error>> {
                                     panic!("val is not a string");
                                 };
 
-                                db.map
-                                    .write()
-                                    .unwrap()
-                                    .insert(key.to_string(), (cid, val.to_string()));
+                                let mut map = db.map.write().unwrap();
+
+                                if diff > 0 {
+                                    map.insert(key.to_string(), (cid, val.to_string()));
+                                } else if map.get(key.as_ref()).map(|(head, _)| *head) == Some(cid)
+                                {
+                                    map.remove(key.as_ref());
+                                }
+
+                                drop(map);
 
                                 Ok(db)
                             }))
@@ -383,45 +393,41 @@ async fn run_reactor(reactor: Reactor) {
 
                 b.search("set", (("cid", cid), ("store", store), ("key", k)))?;
                 b.search("links", (("from", cid), ("to", parent)))?;
-                b.search("root", (("cid", parent), ("store", store), ("key", k)))?;
-
-                Ok(())
-            })?;
+                b.any(|alt| {
+                    alt.or(|b| b.search("root", (("cid", parent), ("store", store), ("key", k))))?;
+                    alt.or(|b| b.search("child", (("cid", parent),)))?;
 
-            p.rule::<(Cid, Cid, i32, Any)>("child", &|h, b, (cid, parent, store, k)| {
-                h.bind((("cid", cid), ("parent", parent)))?;
-
-                b.search("set", (("cid", cid), ("store", store), ("key", k)))?;
-                b.search("links", (("from", cid), ("to", parent)))?;
-                b.search("child", (("cid", parent),))?;
+                    Ok(())
+                })?;
 
                 Ok(())
             })?;
 
-            p.rule::<(Cid, i32, Any)>("latestSibling", &|h, b, (cid, store, k)| {
+            p.rule::<(Cid, Cid, i32, Any)>("latestSibling", &|h, b, (cid, parent, store, k)| {
                 h.bind((("cid", cid),))?;
 
-                b.search("root", (("store", store), ("key", k)))?;
-                b.group_by(
-                    cid,
-                    "root",
-                    (("cid", cid), ("store", store), ("key", k)),
-                    math::min(cid),
-                )?;
-
-                Ok(())
-            })?;
-
-            p.rule::<(Cid, Cid)>("latestSibling", &|h, b, (cid, parent)| {
-                h.bind((("cid", cid),))?;
-
-                b.search("latestSibling", (("cid", parent),))?;
-                b.group_by(
-                    cid,
-                    "child",
-                    (("cid", cid), ("parent", parent)),
-                    math::min(cid),
-                )?;
+                b.any(|alt| {
+                    alt.or(|b| {
+                        b.search("root", (("store", store), ("key", k)))?;
+                        b.group_by(
+                            cid,
+                            "root",
+                            (("cid", cid), ("store", store), ("key", k)),
+                            math::min(cid),
+                        )
+                    })?;
+                    alt.or(|b| {
+                        b.search("latestSibling", (("cid", parent),))?;
+                        b.group_by(
+                            cid,
+                            "child",
+                            (("cid", cid), ("parent", parent)),
+                            math::min(cid),
+                        )
+                    })?;
+
+                    Ok(())
+                })?;
 
                 Ok(())
             })?;
@@ -4,7 +4,7 @@ use syn::{
     parenthesized,
     parse::{Parse, ParseStream},
     punctuated::Punctuated,
-    Attribute, Generics, Ident, Token, Type,
+    Attribute, Expr, ExprPath, Generics, Ident, Token, Type,
 };
 
 pub(crate) fn expand(input: RhizomeFunctionDecl) -> TokenStream {
@@ -27,8 +27,14 @@ pub(crate) fn expand(input: RhizomeFunctionDecl) -> TokenStream {
         .find(|attr| attr.meta.path().is_ident("predicate"))
         .cloned();
 
+    let is_function = attributes
+        .iter()
+        .find(|attr| attr.meta.path().is_ident("function"))
+        .cloned();
+
     attributes.retain(|attr| !attr.meta.path().is_ident("aggregate"));
     attributes.retain(|attr| !attr.meta.path().is_ident("predicate"));
+    attributes.retain(|attr| !attr.meta.path().is_ident("function"));
 
     let (ref arg_name, ref arg_type): (Vec<_>, Vec<_>) =
         args.iter().map(|arg| (&arg.name, &arg.ty)).unzip();
@@ -78,13 +84,27 @@ pub(crate) fn expand(input: RhizomeFunctionDecl) -> TokenStream {
     if let Some(agg_expr) = is_aggregate {
         let agg_expr = agg_expr.meta.require_name_value().unwrap().value.clone();
 
+        // Aggregates over types other than their output name them explicitly,
+        // as in `#[aggregate = ArgMax::<K, V>]`.
+        let agg_type = match &agg_expr {
+            Expr::Path(ExprPath { path, .. })
+                if path
+                    .segments
+                    .last()
+                    .is_some_and(|segment| !segment.arguments.is_empty()) =>
+            {
+                quote!(#agg_expr)
+            }
+            _ => quote!(#agg_expr<#return_type>),
+        };
+
         tokens = quote! {
             #tokens
 
             impl #impl_generics ::rhizome::aggregation::AggregateGroupBy<#input_type #return_type> for HelperType #ty_generics
             #where_clause
             {
-                type Aggregate = #agg_expr<#return_type>;
+                type Aggregate = #agg_type;
 
                 fn as_args(&self) -> Vec<Var> {
                     let mut result = Vec::default();
@@ -112,6 +132,32 @@ pub(crate) fn expand(input: RhizomeFunctionDecl) -> TokenStream {
                     Self::Predicate::default()
                 }
 
+                fn as_args(&self) -> Vec<Var> {
+                    let mut result = Vec::default();
+
+                    #(
+                        result.push(self.#arg_name);
+                    )*
+
+                    result
+                }
+            }
+        }
+    } else if let Some(fn_expr) = is_function {
+        let fn_expr = fn_expr.meta.require_name_value().unwrap().value.clone();
+
+        tokens = quote! {
+            #tokens
+
+            impl #impl_generics ::rhizome::function::FunctionWhere<#input_type #return_type> for HelperType #ty_generics
+            #where_clause
+            {
+                type Function = #fn_expr<(#(#arg_type,)*)>;
+
+                fn into_function(self) -> Self::Function {
+                    Self::Function::default()
+                }
+
                 fn as_args(&self) -> Vec<Var> {
                     let mut result = Vec::default();
 
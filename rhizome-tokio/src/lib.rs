@@ -9,12 +9,15 @@ mod tests {
         sync::{Arc, Mutex},
     };
 
-    use futures::{sink::unfold, StreamExt};
+    use futures::{channel::mpsc, sink::unfold, stream, SinkExt, StreamExt};
     use tokio::{spawn, test};
 
     use rhizome::{
-        runtime::client::Client,
+        error::Error,
+        runtime::{client::Client, reactor::Reactor, DiffSink, SinkEvent},
+        timestamp::PairTimestamp,
         tuple::{InputTuple, Tuple},
+        value::Val,
     };
 
     #[test]
@@ -112,4 +115,354 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    async fn test_subscribe() -> Result<()> {
+        let (mut client, mut rx, reactor) = Client::new();
+
+        spawn(run_transitive_closure(reactor));
+
+        spawn(async move {
+            loop {
+                let _ = rx.next().await;
+            }
+        });
+
+        // Waits for the end of the next epoch, returning the diffs sent before it
+        async fn epoch(rx: &mut mpsc::UnboundedReceiver<SinkEvent>) -> BTreeSet<(Tuple, isize)> {
+            let mut diffs = BTreeSet::default();
+
+            while let Some(event) = rx.next().await {
+                match event {
+                    SinkEvent::Diff(fact, _, diff) => diffs.insert((fact, diff)),
+                    SinkEvent::EpochComplete(_) => return diffs,
+                };
+            }
+
+            panic!("sink closed before the end of the epoch");
+        }
+
+        let path = |from: i32, to: i32| Tuple::new("path", [("from", from), ("to", to)], None);
+
+        let (create_sink, mut epochs) = forward();
+        client
+            .register_diff_sink("path", Box::new(create_sink))
+            .await?;
+
+        for (from, to) in [(0, 1), (1, 2)] {
+            client
+                .insert_fact(InputTuple::new(from, "to", to, vec![]))
+                .await?;
+
+            epoch(&mut epochs).await;
+        }
+
+        // Subscribers connecting late are first sent what's already been derived
+        let (create_sink, mut subscribed) = forward();
+        let subscription = client
+            .subscribe("path", [("from", 0)], Box::new(create_sink))
+            .await?;
+
+        assert_eq!(
+            epoch(&mut subscribed).await,
+            BTreeSet::from_iter([(path(0, 1), 1), (path(0, 2), 1)])
+        );
+
+        client
+            .insert_fact(InputTuple::new(2, "to", 3, vec![]))
+            .await?;
+
+        epoch(&mut epochs).await;
+
+        assert_eq!(
+            epoch(&mut subscribed).await,
+            BTreeSet::from_iter([(path(0, 3), 1)])
+        );
+
+        client.unsubscribe(subscription).await?;
+        client
+            .insert_fact(InputTuple::new(3, "to", 4, vec![]))
+            .await?;
+
+        epoch(&mut epochs).await;
+
+        assert_eq!(subscribed.next().await, None);
+
+        // Filters are checked against the relation's columns
+        let (create_sink, _) = forward();
+
+        assert!(client
+            .subscribe("path", [("form", 0)], Box::new(create_sink))
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    async fn test_transact() -> Result<()> {
+        let (mut client, mut rx, reactor) = Client::new();
+
+        spawn(run_transitive_closure(reactor));
+
+        spawn(async move {
+            loop {
+                let _ = rx.next().await;
+            }
+        });
+
+        let path = |from: i32, to: i32| (Tuple::new("path", [("from", from), ("to", to)], None), 1);
+
+        let (create_sink, mut events) = forward();
+        client
+            .register_diff_sink("path", Box::new(create_sink))
+            .await?;
+
+        let first = client
+            .transact(vec![
+                InputTuple::new(0, "to", 1, vec![]),
+                InputTuple::new(1, "to", 2, vec![]),
+                InputTuple::new(2, "to", 3, vec![]),
+            ])
+            .await?;
+
+        // Every fact in the batch is evaluated in the same epoch
+        assert_eq!(
+            epoch_at(&mut events, first).await,
+            BTreeSet::from_iter([
+                path(0, 1),
+                path(0, 2),
+                path(0, 3),
+                path(1, 2),
+                path(1, 3),
+                path(2, 3),
+            ])
+        );
+
+        let second = client
+            .transact(vec![InputTuple::new(3, "to", 4, vec![])])
+            .await?;
+
+        assert!(second > first);
+        assert_eq!(
+            epoch_at(&mut events, second).await,
+            BTreeSet::from_iter([path(0, 4), path(1, 4), path(2, 4), path(3, 4)])
+        );
+
+        // A batch with a fact that can't be stored is rejected as a whole
+        assert!(client
+            .transact(vec![
+                InputTuple::new(4, "to", 5, vec![]),
+                InputTuple::new(5, "to", f64::NAN, vec![]),
+            ])
+            .await
+            .is_err());
+
+        let third = client
+            .transact(vec![InputTuple::new(5, "to", 6, vec![])])
+            .await?;
+
+        assert_eq!(
+            epoch_at(&mut events, third).await,
+            BTreeSet::from_iter([path(5, 6)])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    async fn test_transact_empty() -> Result<()> {
+        let (mut client, mut rx, reactor) = Client::new();
+
+        spawn(run_transitive_closure(reactor));
+
+        spawn(async move {
+            loop {
+                let _ = rx.next().await;
+            }
+        });
+
+        let path = |from: i32, to: i32| (Tuple::new("path", [("from", from), ("to", to)], None), 1);
+
+        let (create_sink, mut events) = forward();
+        client
+            .register_diff_sink("path", Box::new(create_sink))
+            .await?;
+
+        // No epoch is evaluated for an empty batch, so there is none to return
+        assert!(client.transact(vec![]).await.is_err());
+
+        let first = client
+            .transact(vec![InputTuple::new(0, "to", 1, vec![])])
+            .await?;
+
+        assert_eq!(
+            epoch_at(&mut events, first).await,
+            BTreeSet::from_iter([path(0, 1)])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    async fn test_insert() -> Result<()> {
+        let (mut client, mut rx, reactor) = Client::new();
+
+        spawn(async move {
+            reactor
+                .async_run(|p| {
+                    p.input("edge", |h| h.column::<i32>("from").column::<i32>("to"))?;
+                    p.output("path", |h| h.column::<i32>("from").column::<i32>("to"))?;
+
+                    p.rule::<(i32, i32)>("path", &|h, b, (x, y)| {
+                        h.bind((("from", x), ("to", y)))?;
+                        b.search("edge", (("from", x), ("to", y)))?;
+
+                        Ok(())
+                    })?;
+
+                    Ok(p)
+                })
+                .await
+                .unwrap()
+        });
+
+        spawn(async move {
+            loop {
+                let _ = rx.next().await;
+            }
+        });
+
+        let edge = |from: i32, to: i32| Tuple::new("edge", [("from", from), ("to", to)], None);
+        let path = |from: i32, to: i32| (Tuple::new("path", [("from", from), ("to", to)], None), 1);
+
+        let (create_sink, mut events) = forward();
+        client
+            .register_diff_sink("path", Box::new(create_sink))
+            .await?;
+
+        client.insert("edge", edge(0, 1)).await?;
+
+        assert!(client
+            .insert("edge", Tuple::new("edge", [("from", 1)], None))
+            .await
+            .is_err());
+
+        // Streams can only feed input relations
+        assert!(client
+            .register_tuple_stream("path", Box::new(|| Box::new(stream::empty())))
+            .await
+            .is_err());
+
+        // Tuples that don't match the declaration are skipped, and their errors reported
+        let (mut tx, stream) = mpsc::unbounded();
+        let mut errors = client
+            .register_tuple_stream("edge", Box::new(move || Box::new(stream)))
+            .await?;
+
+        tx.send(edge(1, 2)).await?;
+        tx.send(Tuple::new(
+            "edge",
+            [("from", Val::S32(2)), ("to", Val::Bool(true))],
+            None,
+        ))
+        .await?;
+        tx.send(edge(2, 3)).await?;
+        tx.close_channel();
+
+        let mut derived = BTreeSet::default();
+        while derived.len() < 3 {
+            match events.next().await {
+                Some(SinkEvent::Diff(fact, _, diff)) => {
+                    derived.insert((fact, diff));
+                }
+                Some(SinkEvent::EpochComplete(_)) => (),
+                None => panic!("sink closed"),
+            }
+        }
+
+        assert_eq!(
+            derived,
+            BTreeSet::from_iter([path(0, 1), path(1, 2), path(2, 3)])
+        );
+
+        let err = errors.next().await.unwrap();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::ColumnValueTypeConflict(..))
+        ));
+
+        // The receiver closes once the stream ends and no tuples remain to be checked
+        assert!(errors.next().await.is_none());
+
+        Ok(())
+    }
+
+    async fn run_transitive_closure(reactor: Reactor) {
+        reactor
+            .async_run(|p| {
+                p.output("path", |h| h.column::<i32>("from").column::<i32>("to"))?;
+
+                p.rule::<(i32, i32)>("path", &|h, b, (x, y)| {
+                    h.bind((("from", x), ("to", y)))?;
+                    b.search("evac", (("entity", x), ("attribute", "to"), ("value", y)))?;
+
+                    Ok(())
+                })?;
+
+                p.rule::<(i32, i32, i32)>("path", &|h, b, (x, y, z)| {
+                    h.bind((("from", x), ("to", z)))?;
+
+                    b.search("path", (("from", x), ("to", y)))?;
+                    b.search("path", (("from", y), ("to", z)))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            })
+            .await
+            .unwrap()
+    }
+
+    /// Forwards every event sent to a sink to the returned receiver.
+    fn forward() -> (
+        impl FnOnce() -> DiffSink + Send,
+        mpsc::UnboundedReceiver<SinkEvent>,
+    ) {
+        let (tx, rx) = mpsc::unbounded();
+        let create_sink = move || -> DiffSink {
+            Box::new(unfold(tx, |mut tx, event| async move {
+                tx.send(event).await.unwrap();
+
+                Ok::<_, Error>(tx)
+            }))
+        };
+
+        (create_sink, rx)
+    }
+
+    /// Waits for the epoch starting at `timestamp` to complete, returning the diffs
+    /// sent during it.
+    async fn epoch_at(
+        rx: &mut mpsc::UnboundedReceiver<SinkEvent>,
+        timestamp: PairTimestamp,
+    ) -> BTreeSet<(Tuple, isize)> {
+        let mut diffs = BTreeSet::default();
+
+        while let Some(event) = rx.next().await {
+            match event {
+                SinkEvent::Diff(fact, _, diff) => {
+                    diffs.insert((fact, diff));
+                }
+                SinkEvent::EpochComplete(completed) if completed == timestamp => return diffs,
+                SinkEvent::EpochComplete(completed) if completed > timestamp => {
+                    panic!("epoch {timestamp:?} never completed")
+                }
+                SinkEvent::EpochComplete(_) => diffs.clear(),
+            };
+        }
+
+        panic!("sink closed before the end of the epoch");
+    }
 }
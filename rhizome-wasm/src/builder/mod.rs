@@ -1,7 +1,8 @@
 use std::{cell::RefCell, cmp::max, rc::Rc};
 
-use anyhow::Result;
-use rhizome::{types::Any, var::TypedVar, RuleVars};
+use anyhow::{anyhow, Result};
+use rhizome::{kernel::Registry, types::Any, value::Val, var::TypedVar, RuleVars};
+use serde::Serialize;
 use wasm_bindgen::{prelude::*, JsCast, JsValue};
 use wasm_bindgen_downcast::DowncastJS;
 
@@ -19,6 +20,16 @@ pub struct Var {
     idx: usize,
 }
 
+#[derive(Debug, Serialize)]
+struct Diagnostic {
+    message: String,
+    rule: Option<usize>,
+    relation: Option<String>,
+    column: Option<String>,
+    variable: Option<String>,
+    suggestion: Option<String>,
+}
+
 impl ProgramBuilder {
     pub fn new(p: rhizome::ProgramBuilder) -> Result<Self> {
         Ok(Self {
@@ -32,6 +43,41 @@ impl ProgramBuilder {
 
 #[wasm_bindgen]
 impl ProgramBuilder {
+    pub fn collect_diagnostics(&self) {
+        self.inner.borrow().collect_diagnostics();
+    }
+
+    /// The diagnostics collected so far, as objects with the error's `message`,
+    /// and its `rule` index, `relation`, `column`, `variable`, and `suggestion`,
+    /// where they're known.
+    pub fn diagnostics(&self) -> Result<JsValue, JsValue> {
+        let diagnostics = self
+            .inner
+            .borrow()
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| Diagnostic {
+                message: diagnostic.kind().to_string(),
+                rule: diagnostic.clause(),
+                relation: diagnostic.relation().map(|id| id.to_string()),
+                column: diagnostic.col().map(|id| id.to_string()),
+                variable: diagnostic.var().map(|id| id.to_string()),
+                suggestion: diagnostic.suggestion().map(str::to_owned),
+            })
+            .collect::<Vec<_>>();
+
+        Ok(serde_wasm_bindgen::to_value(&diagnostics)?)
+    }
+
+    pub fn lint(&self) -> js_sys::Array {
+        self.inner
+            .borrow()
+            .lint()
+            .iter()
+            .map(|lint| JsValue::from(lint.to_string()))
+            .collect()
+    }
+
     pub fn input(&self, id: &str, schema: &js_sys::Object) -> Result<(), JsValue> {
         self.inner
             .borrow_mut()
@@ -195,7 +241,7 @@ impl ProgramBuilder {
                     .dyn_into()
                     .unwrap();
 
-                body_terms.iter().for_each(|term| {
+                body_terms.iter().try_for_each(|term| -> Result<()> {
                     let op = js_sys::Reflect::get(&term, &JsValue::from("op"))
                         .unwrap()
                         .as_string()
@@ -290,13 +336,60 @@ impl ProgramBuilder {
                             })
                             .unwrap();
                         }
+                        "predicate" => {
+                            let name = js_sys::Reflect::get(&term, &JsValue::from("name"))
+                                .ok()
+                                .and_then(|name| name.as_string())
+                                .ok_or_else(|| anyhow!("predicate name must be a string"))?;
+
+                            let f = Registry::default()
+                                .predicate(&name)
+                                .ok_or_else(|| anyhow!("unrecognized predicate: {name}"))?;
+
+                            let args: js_sys::Array =
+                                js_sys::Reflect::get(&term, &JsValue::from("args"))
+                                    .ok()
+                                    .and_then(|args| args.dyn_into().ok())
+                                    .ok_or_else(|| {
+                                        anyhow!("arguments to {name} must be an array")
+                                    })?;
+
+                            let args = args
+                                .iter()
+                                .map(|arg| {
+                                    if let Some(val) = arg.as_bool() {
+                                        Ok(Val::from(val).into())
+                                    } else if let Some(val) = arg.as_f64() {
+                                        Ok(Val::from(val as i64).into())
+                                    } else if let Some(val) = arg.as_string() {
+                                        Ok(Val::from(val.as_str()).into())
+                                    } else if let Ok(val) =
+                                        serde_wasm_bindgen::from_value::<Cid>(arg.clone())
+                                    {
+                                        Ok(Val::from(val.inner()).into())
+                                    } else if let Some(var) = Var::downcast_js_ref(&arg) {
+                                        let var = vars
+                                            .get(var.idx)
+                                            .ok_or_else(|| anyhow!("unknown variable"))?;
+
+                                        Ok(rhizome::var::Var::from(*var).into())
+                                    } else {
+                                        Err(anyhow!(
+                                            "arguments to {name} must be variables or literals"
+                                        ))
+                                    }
+                                })
+                                .collect::<Result<Vec<_>>>()?;
+
+                            b.var_predicate(args, f)?;
+                        }
                         _ => panic!("unrecognized op"),
                     };
-                });
 
-                Ok(())
+                    Ok(())
+                })
             })
-            .unwrap();
+            .map_err(|e| JsError::new(&e.to_string()))?;
 
         Ok(())
     }
@@ -77,16 +77,10 @@ impl Rhizome {
     }
 
     #[wasm_bindgen(js_name = registerStream)]
-    pub async fn register_stream(
-        &self,
-        id: &str,
-        async_iterator: AsyncIterator,
-    ) -> Result<(), JsValue> {
+    pub async fn register_stream(&self, async_iterator: AsyncIterator) -> Result<(), JsValue> {
         self.client
             .borrow_mut()
-            .register_stream(
-                id,
-                Box::new(move || {
+            .register_stream(Box::new(move || {
                     Box::new(JsStream::from(async_iterator).map(|fact| {
                         let fact = fact.unwrap();
                         let fact = InputFact::downcast_js_ref(&fact).unwrap();
@@ -45,7 +45,7 @@ export function runRhizomeTest(
       let resolver;
       const p = new Promise((resolve) => { resolver = resolve });
 
-      await client.registerStream("evac", async function*() {
+      await client.registerStream(async function*() {
         yield new InputFact("1", "value", 1, {});
         yield new InputFact("1", "value", 2, {});
         yield new InputFact("1", "value", 3, {});
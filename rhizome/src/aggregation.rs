@@ -1,4 +1,13 @@
-use crate::{args::Args, types::IntoColType, value::Val, var::Var};
+use anyhow::Result;
+
+use crate::{
+    args::Args,
+    error::{error, Error},
+    lattice::Monotonicity,
+    types::IntoColType,
+    value::Val,
+    var::Var,
+};
 
 pub trait AggAcc:
     IntoColType + Into<Val> + TryFrom<Val, Error = ()> + Send + Sync + 'static
@@ -15,14 +24,46 @@ where
 pub trait AggregateGroupBy<I, O> {
     type Aggregate: Aggregate<Input = I, Output = O>;
 
-    // TODO: support arbitrary expressions here, instead of just vars
+    /// The variables the aggregate is applied to. To apply it to an expression, bind the
+    /// expression to a variable with [`RuleBodyBuilder::compute`].
+    ///
+    /// [`RuleBodyBuilder::compute`]: crate::RuleBodyBuilder::compute
     fn as_args(&self) -> Vec<Var>;
+
+    /// Creates the aggregate, carrying over any parameters that aren't bound
+    /// to columns, such as the separator of a string join. Fails if any of
+    /// those parameters is invalid.
+    fn into_aggregate(self) -> Result<Self::Aggregate>
+    where
+        Self: Sized,
+    {
+        Ok(Self::Aggregate::default())
+    }
 }
 
 pub trait Aggregate: Sized + Default {
     type Input;
     type Output;
 
+    /// The name of a built-in aggregate, which programs can refer to it by.
+    fn name(&self) -> Option<&str> {
+        None
+    }
+
+    /// The direction in which the result moves as the aggregate is applied to
+    /// more tuples, in the order of [`Output`](Aggregate::Output), if it only
+    /// ever moves one way. Such an aggregate can be used recursively, to derive
+    /// the lattice column of a relation that's joined in the same direction.
+    fn monotonicity(&self) -> Option<Monotonicity> {
+        None
+    }
+
+    /// Creates an empty accumulator for a new group, with the same parameters
+    /// as this one.
+    fn init(&self) -> Self {
+        Self::default()
+    }
+
     fn step(&mut self, args: Self::Input);
     fn finalize(&self) -> Option<Self::Output>;
 }
@@ -34,6 +75,18 @@ where
     type Input = T::Input;
     type Output = T::Output;
 
+    fn name(&self) -> Option<&str> {
+        (**self).name()
+    }
+
+    fn monotonicity(&self) -> Option<Monotonicity> {
+        (**self).monotonicity()
+    }
+
+    fn init(&self) -> Self {
+        Box::new(T::init(self))
+    }
+
     fn step(&mut self, args: Self::Input) {
         (**self).step(args);
     }
@@ -44,8 +97,10 @@ where
 }
 
 pub trait AggregateWrapper: Send + Sync + 'static {
+    fn name(&self) -> Option<&str>;
+    fn monotonicity(&self) -> Option<Monotonicity>;
     fn init(&self) -> Box<dyn AggregateWrapper>;
-    fn step(&mut self, args: Vec<Val>);
+    fn step(&mut self, args: Vec<Val>) -> Result<()>;
     fn finalize(&self) -> Option<Val>;
 }
 
@@ -55,14 +110,35 @@ where
     I: Args,
     O: AggAcc,
 {
+    fn name(&self) -> Option<&str> {
+        T::name(self)
+    }
+
+    fn monotonicity(&self) -> Option<Monotonicity> {
+        T::monotonicity(self)
+    }
+
     fn init(&self) -> Box<dyn AggregateWrapper> {
-        Box::<T>::default()
+        Box::new(T::init(self))
     }
 
-    fn step(&mut self, args: Vec<Val>) {
-        let args = <T::Input as Args>::instantiate(args).unwrap();
+    fn step(&mut self, args: Vec<Val>) -> Result<()> {
+        let Ok(input) = <T::Input as Args>::instantiate(&args) else {
+            let types = args
+                .iter()
+                .map(|arg| arg.type_of().to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            return error(Error::FunctionSignatureMismatch(
+                T::name(self).unwrap_or("UDF").to_owned(),
+                format!("({types})"),
+            ));
+        };
+
+        T::step(self, input);
 
-        T::step(self, args);
+        Ok(())
     }
 
     fn finalize(&self) -> Option<Val> {
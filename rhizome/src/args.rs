@@ -1,13 +1,22 @@
-use crate::{types::IntoColType, value::Val};
+use crate::{
+    types::{ColType, IntoColType},
+    value::Val,
+};
 
 pub trait Args: Sized + Send + Sync + 'static {
+    fn col_types() -> Vec<ColType>;
+
     // TODO: return an InternalRhizomeError instead of ()
     #[allow(clippy::result_unit_err)]
-    fn instantiate(bindings: Vec<Val>) -> Result<Self, ()>;
+    fn instantiate(bindings: &[Val]) -> Result<Self, ()>;
 }
 
 impl Args for () {
-    fn instantiate(_bindings: Vec<Val>) -> Result<Self, ()> {
+    fn col_types() -> Vec<ColType> {
+        vec![]
+    }
+
+    fn instantiate(_bindings: &[Val]) -> Result<Self, ()> {
         Ok(())
     }
 }
@@ -21,9 +30,13 @@ macro_rules! impl_args {
                     [< T $Ts >]: IntoColType + TryFrom<Val, Error = ()> + Send + Sync + 'static,
                 )*
             {
+                fn col_types() -> Vec<ColType> {
+                    vec![$([< T $Ts >]::into_col_type(),)*]
+                }
+
                 #[allow(unused_variables)]
                 #[allow(clippy::unused_unit)]
-                fn instantiate(bindings: Vec<Val>) -> Result<Self, ()> {
+                fn instantiate(bindings: &[Val]) -> Result<Self, ()> {
                     Ok((
                         $(
                             bindings[$Ts].clone().try_into()?,
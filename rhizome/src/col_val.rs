@@ -1,11 +1,41 @@
 use std::fmt::{self, Display};
 
-use crate::{value::Val, var::Var};
+use derive_more::IsVariant;
+use pretty::RcDoc;
 
-#[derive(Debug, Clone, PartialEq)]
+use crate::{expr::Expr, pretty::Pretty, value::Val, var::Var};
+
+#[derive(Debug, Clone, PartialEq, IsVariant)]
 pub enum ColVal {
     Lit(Val),
     Binding(Var),
+    Expr(Expr),
+    /// Matches any value, the same as leaving the column unbound, so builders
+    /// drop wildcards from the atoms they build.
+    Wildcard,
+}
+
+impl ColVal {
+    pub(crate) fn vars(&self) -> Vec<Var> {
+        match self {
+            ColVal::Lit(_) => vec![],
+            ColVal::Binding(var) => vec![*var],
+            ColVal::Expr(expr) => expr.vars(),
+            ColVal::Wildcard => vec![],
+        }
+    }
+}
+
+impl From<Val> for ColVal {
+    fn from(val: Val) -> Self {
+        Self::Lit(val)
+    }
+}
+
+impl From<Var> for ColVal {
+    fn from(var: Var) -> Self {
+        Self::Binding(var)
+    }
 }
 
 impl Display for ColVal {
@@ -13,6 +43,19 @@ impl Display for ColVal {
         match self {
             ColVal::Lit(inner) => Display::fmt(&inner, f),
             ColVal::Binding(inner) => Display::fmt(&inner, f),
+            ColVal::Expr(inner) => Display::fmt(&inner, f),
+            ColVal::Wildcard => f.write_str("_"),
+        }
+    }
+}
+
+impl Pretty for ColVal {
+    fn to_doc(&self) -> RcDoc<'_, ()> {
+        match self {
+            ColVal::Lit(inner) => inner.to_doc(),
+            ColVal::Binding(inner) => RcDoc::as_string(inner.id()),
+            ColVal::Expr(inner) => inner.to_doc(),
+            ColVal::Wildcard => RcDoc::text("_"),
         }
     }
 }
@@ -5,7 +5,9 @@ use thiserror::Error;
 
 use crate::{
     col_val::ColVal,
+    expr::Op,
     id::{ColId, RelationId, VarId},
+    logic::{Cycle, Diagnostic, Span},
     types::{ColType, Type},
 };
 
@@ -16,14 +18,21 @@ pub enum Error {
         "An unexpected error occurred in Rhizome: {0}. This is a bug: please consider filing an issue"
     )]
     InternalRhizomeError(String),
-    #[error("Program could not be stratified")]
-    ProgramUnstratifiable,
+    #[error(
+        "Program is invalid:{}",
+        .0.iter().map(|diagnostic| format!("\n    {diagnostic}")).collect::<String>()
+    )]
+    InvalidProgram(Vec<Diagnostic>),
+    #[error("Program could not be stratified: {0}")]
+    ProgramUnstratifiable(Cycle),
     #[error("Clause not range restricted: variable {1}, in attribute {0} of head must be bound")]
     ClauseNotRangeRestricted(ColId, VarId),
     #[error("Clause not domain independent: variable {0} must be bound")]
     ClauseNotDomainIndependent(VarId),
     #[error("Duplicate column: {1} in schema for relation {0}")]
     DuplicateDeclarationCol(RelationId, ColId),
+    #[error("Duplicate lattice column: {1} in schema for relation {0}, which already has one")]
+    DuplicateLatticeCol(RelationId, ColId),
     #[error("Relation already declared: {0}")]
     ConflictingRelationDeclaration(RelationId),
     #[error("Column {1} already bound in clause head, for relation {0}")]
@@ -36,6 +45,10 @@ pub enum Error {
     UnrecognizedRelation(String),
     #[error("Clause head must be an output relation: {0}")]
     ClauseHeadEDB(RelationId),
+    #[error("Tuples can only be inserted into input relations: {0}")]
+    InsertIDB(RelationId),
+    #[error("Attempted to insert a tuple of relation {1} into relation {0}")]
+    TupleRelationMismatch(RelationId, RelationId),
     #[error("Type mismatch: expected {0}, got {1}")]
     TypeMismatch(Type, Type),
     #[error("Attempted to bind {2} to {1} of type {3} in {0}")]
@@ -46,8 +59,34 @@ pub enum Error {
     AggregationUnboundGroupBy(VarId, ColId, RelationId),
     #[error("Attempted to aggregate into a bound variable {0}")]
     AggregationBoundTarget(VarId),
+    #[error("Disjunctions are not supported in the body of an aggregation")]
+    AggregationDisjunction,
+    #[error("Invalid parameter for aggregate {0}: {1}")]
+    InvalidAggregateParameter(String, String),
+    #[error("Attempted to compute into a bound variable {0}")]
+    ComputationBoundTarget(VarId),
+    #[error("Disjunction must have at least one alternative")]
+    EmptyDisjunction,
     #[error("Attempted to bind to CID of IDB relation {0}")]
     ContentAddressedIDB(RelationId),
+    #[error("Expressions can only be bound in the head of a rule: attempted to bind {2} to {1} of relation {0}")]
+    UnexpectedExpression(RelationId, ColId, ColVal),
+    #[error("Wildcards can only be bound in the body of a rule: attempted to bind _ to {1} of relation {0}")]
+    UnexpectedWildcard(RelationId, ColId),
+    #[error("Operator {0} is not defined for values of type {1}")]
+    OperatorTypeMismatch(Op, Type),
+    #[error("Function expects {0} arguments, got {1}")]
+    FunctionArityMismatch(usize, usize),
+    #[error("Parse error at {0}: {1}")]
+    ParseError(Span, String),
+    #[error("Unrecognized function: {0}")]
+    UnrecognizedFunction(String),
+    #[error("Function {0} is not defined for arguments of type {1}")]
+    FunctionSignatureMismatch(String, String),
+    #[error("Transactions must insert at least one fact")]
+    EmptyTransaction,
+    #[error("Tuples can only be explained by programs that record provenance")]
+    ProvenanceNotRecorded,
 }
 
 pub fn error<T>(err: impl std::error::Error + Send + Sync + 'static) -> Result<T> {
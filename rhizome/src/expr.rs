@@ -0,0 +1,330 @@
+use std::{
+    fmt::{self, Debug, Display},
+    ops,
+    sync::Arc,
+};
+
+use num_traits::{CheckedAdd, CheckedDiv, CheckedMul, CheckedRem, CheckedSub, Float};
+use ordered_float::OrderedFloat;
+use pretty::RcDoc;
+
+use crate::{
+    function::FunctionWrapper,
+    pretty::{Pretty, ANONYMOUS},
+    types::IntoColType,
+    value::Val,
+    var::{TypedVar, Var},
+};
+
+/// A value computed from bound variables, which can be bound to a column in the
+/// head of a rule.
+#[derive(Clone)]
+pub enum Expr {
+    Lit(Val),
+    Var(Var),
+    Op(Op, Box<Expr>, Box<Expr>),
+    Concat(Vec<Expr>),
+    Call(Arc<dyn FunctionWrapper>, Vec<Expr>),
+}
+
+impl Expr {
+    pub fn concat<I, E>(args: I) -> Self
+    where
+        I: IntoIterator<Item = E>,
+        E: Into<Expr>,
+    {
+        Self::Concat(args.into_iter().map(Into::into).collect())
+    }
+
+    pub fn call<F, I, E>(f: F, args: I) -> Self
+    where
+        F: FunctionWrapper,
+        I: IntoIterator<Item = E>,
+        E: Into<Expr>,
+    {
+        Self::Call(Arc::new(f), args.into_iter().map(Into::into).collect())
+    }
+
+    pub(crate) fn vars(&self) -> Vec<Var> {
+        match self {
+            Expr::Lit(_) => vec![],
+            Expr::Var(var) => vec![*var],
+            Expr::Op(_, left, right) => {
+                let mut vars = left.vars();
+                vars.extend(right.vars());
+
+                vars
+            }
+            Expr::Concat(args) | Expr::Call(_, args) => args.iter().flat_map(Expr::vars).collect(),
+        }
+    }
+}
+
+impl PartialEq for Expr {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Expr::Lit(l), Expr::Lit(r)) => l == r,
+            (Expr::Var(l), Expr::Var(r)) => l == r,
+            (Expr::Op(l_op, l_left, l_right), Expr::Op(r_op, r_left, r_right)) => {
+                l_op == r_op && l_left == r_left && l_right == r_right
+            }
+            (Expr::Concat(l), Expr::Concat(r)) => l == r,
+            (Expr::Call(l_f, l_args), Expr::Call(r_f, r_args)) => {
+                Arc::ptr_eq(l_f, r_f) && l_args == r_args
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Debug for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Lit(val) => f.debug_tuple("Lit").field(val).finish(),
+            Expr::Var(var) => f.debug_tuple("Var").field(var).finish(),
+            Expr::Op(op, left, right) => f
+                .debug_tuple("Op")
+                .field(op)
+                .field(left)
+                .field(right)
+                .finish(),
+            Expr::Concat(args) => f.debug_tuple("Concat").field(args).finish(),
+            Expr::Call(_, args) => f.debug_tuple("Call").field(args).finish(),
+        }
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_args(f: &mut fmt::Formatter<'_>, name: &str, args: &[Expr]) -> fmt::Result {
+            f.write_str(name)?;
+            f.write_str("(")?;
+
+            for (i, arg) in args.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+
+                Display::fmt(arg, f)?;
+            }
+
+            f.write_str(")")
+        }
+
+        match self {
+            Expr::Lit(val) => Display::fmt(val, f),
+            Expr::Var(var) => Display::fmt(&var.id(), f),
+            Expr::Op(op, left, right) => f.write_fmt(format_args!("({left} {op} {right})")),
+            Expr::Concat(args) => write_args(f, "concat", args),
+            Expr::Call(g, args) => write_args(f, g.name().unwrap_or(ANONYMOUS), args),
+        }
+    }
+}
+
+impl Pretty for Expr {
+    fn to_doc(&self) -> RcDoc<'_, ()> {
+        fn args_doc<'a>(name: &'a str, args: &'a [Expr]) -> RcDoc<'a, ()> {
+            RcDoc::concat([
+                RcDoc::text(name),
+                RcDoc::text("("),
+                RcDoc::intersperse(
+                    args.iter().map(|arg| arg.to_doc()),
+                    RcDoc::text(",").append(RcDoc::line()),
+                )
+                .nest(2)
+                .group(),
+                RcDoc::text(")"),
+            ])
+        }
+
+        // Only nested operators need parentheses
+        fn operand_doc(expr: &Expr) -> RcDoc<'_, ()> {
+            match expr {
+                Expr::Op(..) => RcDoc::text("(")
+                    .append(expr.to_doc())
+                    .append(RcDoc::text(")")),
+                _ => expr.to_doc(),
+            }
+        }
+
+        match self {
+            Expr::Lit(val) => val.to_doc(),
+            Expr::Var(var) => RcDoc::as_string(var.id()),
+            Expr::Op(op, left, right) => RcDoc::concat([
+                operand_doc(left),
+                RcDoc::text(" "),
+                RcDoc::as_string(op),
+                RcDoc::text(" "),
+                operand_doc(right),
+            ]),
+            Expr::Concat(args) => args_doc("concat", args),
+            Expr::Call(f, args) => args_doc(f.name().unwrap_or(ANONYMOUS), args),
+        }
+    }
+}
+
+impl From<Var> for Expr {
+    fn from(value: Var) -> Self {
+        Self::Var(value)
+    }
+}
+
+impl<T> From<TypedVar<T>> for Expr
+where
+    T: IntoColType,
+{
+    fn from(value: TypedVar<T>) -> Self {
+        Self::Var(value.into())
+    }
+}
+
+impl<T> From<T> for Expr
+where
+    T: Into<Val>,
+{
+    fn from(value: T) -> Self {
+        Self::Lit(value.into())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl Op {
+    /// Applies the operator to two values of the same numeric type. Integer
+    /// overflow and division by zero produce no value, rather than panicking.
+    pub(crate) fn apply(&self, left: Val, right: Val) -> Option<Val> {
+        match (left, right) {
+            (Val::S8(l), Val::S8(r)) => self.apply_int(l, r).map(Val::S8),
+            (Val::U8(l), Val::U8(r)) => self.apply_int(l, r).map(Val::U8),
+            (Val::S16(l), Val::S16(r)) => self.apply_int(l, r).map(Val::S16),
+            (Val::U16(l), Val::U16(r)) => self.apply_int(l, r).map(Val::U16),
+            (Val::S32(l), Val::S32(r)) => self.apply_int(l, r).map(Val::S32),
+            (Val::U32(l), Val::U32(r)) => self.apply_int(l, r).map(Val::U32),
+            (Val::S64(l), Val::S64(r)) => self.apply_int(l, r).map(Val::S64),
+            (Val::U64(l), Val::U64(r)) => self.apply_int(l, r).map(Val::U64),
+            (Val::F32(l), Val::F32(r)) => Some(Val::F32(self.apply_float(l, r))),
+            (Val::F64(l), Val::F64(r)) => Some(Val::F64(self.apply_float(l, r))),
+            _ => None,
+        }
+    }
+
+    fn apply_int<T>(&self, left: T, right: T) -> Option<T>
+    where
+        T: CheckedAdd + CheckedSub + CheckedMul + CheckedDiv + CheckedRem,
+    {
+        match self {
+            Op::Add => left.checked_add(&right),
+            Op::Sub => left.checked_sub(&right),
+            Op::Mul => left.checked_mul(&right),
+            Op::Div => left.checked_div(&right),
+            Op::Rem => left.checked_rem(&right),
+        }
+    }
+
+    fn apply_float<T>(&self, left: OrderedFloat<T>, right: OrderedFloat<T>) -> OrderedFloat<T>
+    where
+        T: Float,
+    {
+        match self {
+            Op::Add => left + right,
+            Op::Sub => left - right,
+            Op::Mul => left * right,
+            Op::Div => left / right,
+            Op::Rem => left % right,
+        }
+    }
+}
+
+impl Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Op::Add => "+",
+            Op::Sub => "-",
+            Op::Mul => "*",
+            Op::Div => "/",
+            Op::Rem => "%",
+        };
+
+        f.write_str(s)
+    }
+}
+
+/// Concatenates string values, producing no value if any argument isn't a string.
+pub(crate) fn concat(args: Vec<Val>) -> Option<Val> {
+    let mut result = String::default();
+
+    for arg in args {
+        let Val::String(s) = arg else {
+            return None;
+        };
+
+        result.push_str(&s);
+    }
+
+    Some(Val::from(result))
+}
+
+macro_rules! impl_op {
+    ($trait:ident, $method:ident, $op:ident) => {
+        impl<R> ops::$trait<R> for Expr
+        where
+            R: Into<Expr>,
+        {
+            type Output = Expr;
+
+            fn $method(self, rhs: R) -> Self::Output {
+                Expr::Op(Op::$op, Box::new(self), Box::new(rhs.into()))
+            }
+        }
+
+        impl<T, R> ops::$trait<R> for TypedVar<T>
+        where
+            T: IntoColType,
+            R: Into<Expr>,
+        {
+            type Output = Expr;
+
+            fn $method(self, rhs: R) -> Self::Output {
+                Expr::Op(Op::$op, Box::new(self.into()), Box::new(rhs.into()))
+            }
+        }
+    };
+}
+
+impl_op!(Add, add, Add);
+impl_op!(Sub, sub, Sub);
+impl_op!(Mul, mul, Mul);
+impl_op!(Div, div, Div);
+impl_op!(Rem, rem, Rem);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_op_apply() {
+        assert_eq!(Some(Val::S32(3)), Op::Add.apply(Val::S32(1), Val::S32(2)));
+        assert_eq!(Some(Val::U8(1)), Op::Rem.apply(Val::U8(7), Val::U8(3)));
+        assert_eq!(Some(Val::from(1.5)), Op::Div.apply(3.0.into(), 2.0.into()));
+        assert_eq!(None, Op::Add.apply(Val::U8(255), Val::U8(1)));
+        assert_eq!(None, Op::Div.apply(Val::S32(1), Val::S32(0)));
+        assert_eq!(None, Op::Add.apply(Val::S32(1), Val::S64(1)));
+        assert_eq!(None, Op::Add.apply("a".into(), "b".into()));
+    }
+
+    #[test]
+    fn test_concat() {
+        assert_eq!(
+            Some(Val::from("Ada Lovelace")),
+            concat(vec!["Ada".into(), " ".into(), "Lovelace".into()])
+        );
+        assert_eq!(None, concat(vec!["Ada".into(), Val::S32(1)]));
+    }
+}
@@ -0,0 +1,73 @@
+use crate::{
+    args::Args,
+    types::{ColType, IntoColType},
+    value::Val,
+    var::Var,
+};
+
+pub trait FunctionWhere<I, O> {
+    type Function: Function<Input = I, Output = O>;
+
+    fn into_function(self) -> Self::Function;
+    fn as_args(&self) -> Vec<Var>;
+}
+
+pub trait Function: Sized {
+    type Input;
+    type Output;
+
+    /// The name of a function, which pretty-printed programs refer to it by.
+    fn name(&self) -> Option<&str> {
+        None
+    }
+
+    fn apply(&self, args: Self::Input) -> Option<Self::Output>;
+}
+
+impl<T> Function for Box<T>
+where
+    T: Function,
+{
+    type Input = T::Input;
+    type Output = T::Output;
+
+    fn name(&self) -> Option<&str> {
+        (**self).name()
+    }
+
+    fn apply(&self, args: Self::Input) -> Option<Self::Output> {
+        (**self).apply(args)
+    }
+}
+
+pub trait FunctionWrapper: Send + Sync + 'static {
+    fn name(&self) -> Option<&str>;
+    fn arg_types(&self) -> Vec<ColType>;
+    fn output_type(&self) -> ColType;
+    fn apply(&self, args: Vec<Val>) -> Option<Val>;
+}
+
+impl<T, I, O> FunctionWrapper for T
+where
+    T: Function<Input = I, Output = O> + Send + Sync + 'static,
+    I: Args,
+    O: IntoColType + Into<Val>,
+{
+    fn name(&self) -> Option<&str> {
+        T::name(self)
+    }
+
+    fn arg_types(&self) -> Vec<ColType> {
+        I::col_types()
+    }
+
+    fn output_type(&self) -> ColType {
+        O::into_col_type()
+    }
+
+    fn apply(&self, args: Vec<Val>) -> Option<Val> {
+        let args = <T::Input as Args>::instantiate(&args).ok()?;
+
+        T::apply(self, args).map(Into::into)
+    }
+}
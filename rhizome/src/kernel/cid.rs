@@ -0,0 +1,87 @@
+use std::marker::PhantomData;
+
+use cid::Cid;
+use rhizome_macro::rhizome_fn;
+
+use crate::predicate::Predicate;
+
+/// The multicodec code for DAG-CBOR.
+pub const DAG_CBOR: u64 = 0x71;
+
+rhizome_fn! {
+    #[predicate = HasCodec]
+    fn has_codec(cid: Cid, codec: u64);
+}
+
+rhizome_fn! {
+    #[predicate = HasMultihash]
+    fn has_multihash(cid: Cid, code: u64);
+}
+
+rhizome_fn! {
+    #[predicate = IsDagCbor]
+    fn is_dag_cbor(cid: Cid);
+}
+
+#[derive(Debug)]
+pub struct HasCodec<T>(PhantomData<T>);
+
+impl<T> Default for HasCodec<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl Predicate for HasCodec<(Cid, u64)> {
+    type Input = (Cid, u64);
+
+    fn name(&self) -> Option<&str> {
+        Some("has_codec")
+    }
+
+    fn apply(&self, (cid, codec): Self::Input) -> Option<bool> {
+        Some(cid.codec() == codec)
+    }
+}
+
+#[derive(Debug)]
+pub struct HasMultihash<T>(PhantomData<T>);
+
+impl<T> Default for HasMultihash<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl Predicate for HasMultihash<(Cid, u64)> {
+    type Input = (Cid, u64);
+
+    fn name(&self) -> Option<&str> {
+        Some("has_multihash")
+    }
+
+    fn apply(&self, (cid, code): Self::Input) -> Option<bool> {
+        Some(cid.hash().code() == code)
+    }
+}
+
+#[derive(Debug)]
+pub struct IsDagCbor<T>(PhantomData<T>);
+
+impl<T> Default for IsDagCbor<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl Predicate for IsDagCbor<(Cid,)> {
+    type Input = (Cid,);
+
+    fn name(&self) -> Option<&str> {
+        Some("is_dag_cbor")
+    }
+
+    fn apply(&self, (cid,): Self::Input) -> Option<bool> {
+        Some(cid.codec() == DAG_CBOR)
+    }
+}
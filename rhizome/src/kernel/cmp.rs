@@ -0,0 +1,62 @@
+use std::{marker::PhantomData, mem};
+
+use rhizome_macro::rhizome_fn;
+
+use crate::{
+    predicate::{Predicate, PredicateWrapper},
+    types::RhizomeType,
+    value::Val,
+};
+
+macro_rules! comparison {
+    ($fn_name:ident, $name:ident, $op:tt) => {
+        rhizome_fn! {
+            #[predicate = $name]
+            fn $fn_name<T: RhizomeType + PartialOrd>(a: T, b: T);
+        }
+
+        #[derive(Debug)]
+        pub struct $name<T>(PhantomData<T>);
+
+        impl<T> Default for $name<T> {
+            fn default() -> Self {
+                Self(PhantomData)
+            }
+        }
+
+        impl<T> Predicate for $name<(T, T)>
+        where
+            T: RhizomeType + PartialOrd,
+        {
+            type Input = (T, T);
+
+            fn name(&self) -> Option<&str> {
+                Some(stringify!($fn_name))
+            }
+
+            fn apply(&self, (a, b): Self::Input) -> Option<bool> {
+                Some(a $op b)
+            }
+        }
+
+        /// Compares values of any type, as long as both are of the same type.
+        impl PredicateWrapper for $name<Val> {
+            fn name(&self) -> Option<&str> {
+                Some(stringify!($fn_name))
+            }
+
+            fn apply(&self, args: Vec<Val>) -> Option<bool> {
+                match args.as_slice() {
+                    [a, b] if mem::discriminant(a) == mem::discriminant(b) => Some(a $op b),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+comparison!(lt, Lt, <);
+comparison!(le, Le, <=);
+comparison!(gt, Gt, >);
+comparison!(ge, Ge, >=);
+comparison!(ne, Ne, !=);
@@ -1,11 +1,25 @@
+mod arg_max;
+mod arg_min;
+mod collect;
 mod count;
+mod count_distinct;
+mod join;
 mod max;
 mod mean;
+mod median;
 mod min;
+mod percentile;
 mod sum;
 
+pub use arg_max::*;
+pub use arg_min::*;
+pub use collect::*;
 pub use count::*;
+pub use count_distinct::*;
+pub use join::*;
 pub use max::*;
 pub use mean::*;
+pub use median::*;
 pub use min::*;
+pub use percentile::*;
 pub use sum::*;
@@ -0,0 +1,52 @@
+use std::cmp;
+
+use rhizome_macro::rhizome_fn;
+
+use crate::{aggregation::Aggregate, types::RhizomeType};
+
+rhizome_fn! {
+    #[aggregate = ArgMax::<K, V>]
+    fn arg_max<K: RhizomeType + Ord, V: RhizomeType + Ord>(key: K, arg: V) -> V;
+}
+
+/// The value associated with the greatest key. Ties between equal keys
+/// are broken by the values themselves, so that the result doesn't depend on
+/// the order in which they were aggregated.
+#[derive(Debug)]
+pub struct ArgMax<K, V>(Option<(K, V)>);
+
+impl<K, V> Default for ArgMax<K, V>
+where
+    K: RhizomeType + Ord,
+    V: RhizomeType + Ord,
+{
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+impl<K, V> Aggregate for ArgMax<K, V>
+where
+    K: RhizomeType + Ord,
+    V: RhizomeType + Ord,
+{
+    type Input = (K, V);
+    type Output = V;
+
+    fn name(&self) -> Option<&str> {
+        Some("arg_max")
+    }
+
+    fn step(&mut self, (k, v): (K, V)) {
+        let result = match self.0.take() {
+            Some(acc) => cmp::max(acc, (k, v)),
+            None => (k, v),
+        };
+
+        self.0 = Some(result)
+    }
+
+    fn finalize(&self) -> Option<Self::Output> {
+        self.0.as_ref().map(|(_, v)| v.clone())
+    }
+}
@@ -0,0 +1,52 @@
+use std::cmp;
+
+use rhizome_macro::rhizome_fn;
+
+use crate::{aggregation::Aggregate, types::RhizomeType};
+
+rhizome_fn! {
+    #[aggregate = ArgMin::<K, V>]
+    fn arg_min<K: RhizomeType + Ord, V: RhizomeType + Ord>(key: K, arg: V) -> V;
+}
+
+/// The value associated with the least key. Ties between equal keys
+/// are broken by the values themselves, so that the result doesn't depend on
+/// the order in which they were aggregated.
+#[derive(Debug)]
+pub struct ArgMin<K, V>(Option<(K, V)>);
+
+impl<K, V> Default for ArgMin<K, V>
+where
+    K: RhizomeType + Ord,
+    V: RhizomeType + Ord,
+{
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+impl<K, V> Aggregate for ArgMin<K, V>
+where
+    K: RhizomeType + Ord,
+    V: RhizomeType + Ord,
+{
+    type Input = (K, V);
+    type Output = V;
+
+    fn name(&self) -> Option<&str> {
+        Some("arg_min")
+    }
+
+    fn step(&mut self, (k, v): (K, V)) {
+        let result = match self.0.take() {
+            Some(acc) => cmp::min(acc, (k, v)),
+            None => (k, v),
+        };
+
+        self.0 = Some(result)
+    }
+
+    fn finalize(&self) -> Option<Self::Output> {
+        self.0.as_ref().map(|(_, v)| v.clone())
+    }
+}
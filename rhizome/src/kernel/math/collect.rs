@@ -0,0 +1,44 @@
+use rhizome_macro::rhizome_fn;
+
+use crate::{aggregation::Aggregate, types::RhizomeType};
+
+rhizome_fn! {
+    #[aggregate = Collect::<T>]
+    fn collect<T: RhizomeType + Ord>(arg: T) -> Vec<T>;
+}
+
+/// Collects the aggregated values into a list, in order.
+#[derive(Debug)]
+pub struct Collect<T>(Vec<T>);
+
+impl<T> Default for Collect<T>
+where
+    T: RhizomeType + Ord,
+{
+    fn default() -> Self {
+        Self(Vec::default())
+    }
+}
+
+impl<T> Aggregate for Collect<T>
+where
+    T: RhizomeType + Ord,
+{
+    type Input = (T,);
+    type Output = Vec<T>;
+
+    fn name(&self) -> Option<&str> {
+        Some("collect")
+    }
+
+    fn step(&mut self, (t,): (T,)) {
+        self.0.push(t);
+    }
+
+    fn finalize(&self) -> Option<Self::Output> {
+        let mut values = self.0.clone();
+        values.sort();
+
+        Some(values)
+    }
+}
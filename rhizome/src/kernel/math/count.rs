@@ -3,7 +3,7 @@ use std::ops::AddAssign;
 use num_traits::{One, Zero};
 use rhizome_macro::rhizome_fn;
 
-use crate::{aggregation::Aggregate, types::RhizomeType};
+use crate::{aggregation::Aggregate, lattice::Monotonicity, types::RhizomeType};
 
 rhizome_fn! {
     #[aggregate = Count]
@@ -29,6 +29,14 @@ where
     type Input = ();
     type Output = T;
 
+    fn name(&self) -> Option<&str> {
+        Some("count")
+    }
+
+    fn monotonicity(&self) -> Option<Monotonicity> {
+        Some(Monotonicity::Increasing)
+    }
+
     fn step(&mut self, _: ()) {
         self.0 += T::one();
     }
@@ -0,0 +1,55 @@
+use std::{collections::BTreeSet, marker::PhantomData, ops::AddAssign};
+
+use num_traits::{One, Zero};
+use rhizome_macro::rhizome_fn;
+
+use crate::{aggregation::Aggregate, lattice::Monotonicity, types::RhizomeType};
+
+rhizome_fn! {
+    #[aggregate = CountDistinct::<T, O>]
+    fn count_distinct<T: RhizomeType + Ord, O: RhizomeType + AddAssign + One + Zero>(arg: T) -> O;
+}
+
+#[derive(Debug)]
+pub struct CountDistinct<T, O>(BTreeSet<T>, PhantomData<O>);
+
+impl<T, O> Default for CountDistinct<T, O>
+where
+    T: RhizomeType + Ord,
+    O: RhizomeType + AddAssign + One + Zero,
+{
+    fn default() -> Self {
+        Self(BTreeSet::default(), PhantomData)
+    }
+}
+
+impl<T, O> Aggregate for CountDistinct<T, O>
+where
+    T: RhizomeType + Ord,
+    O: RhizomeType + AddAssign + One + Zero,
+{
+    type Input = (T,);
+    type Output = O;
+
+    fn name(&self) -> Option<&str> {
+        Some("count_distinct")
+    }
+
+    fn monotonicity(&self) -> Option<Monotonicity> {
+        Some(Monotonicity::Increasing)
+    }
+
+    fn step(&mut self, (t,): (T,)) {
+        self.0.insert(t);
+    }
+
+    fn finalize(&self) -> Option<Self::Output> {
+        let mut result = O::zero();
+
+        for _ in self.0.iter() {
+            result += O::one();
+        }
+
+        Some(result)
+    }
+}
@@ -0,0 +1,74 @@
+use anyhow::Result;
+use std::sync::Arc;
+
+use crate::{
+    aggregation::{Aggregate, AggregateGroupBy},
+    var::{TypedVar, Var},
+};
+
+/// The values of `arg`, in order, separated by `separator`.
+pub fn join(arg: TypedVar<Arc<str>>, separator: &str) -> JoinGroupBy {
+    JoinGroupBy {
+        arg: arg.into(),
+        separator: Arc::from(separator),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct JoinGroupBy {
+    arg: Var,
+    separator: Arc<str>,
+}
+
+impl AggregateGroupBy<(Arc<str>,), Arc<str>> for JoinGroupBy {
+    type Aggregate = Join;
+
+    fn as_args(&self) -> Vec<Var> {
+        vec![self.arg]
+    }
+
+    fn into_aggregate(self) -> Result<Self::Aggregate> {
+        Ok(Join::new(self.separator))
+    }
+}
+
+/// Joins strings in order, so that the result doesn't depend on the order in
+/// which they were aggregated. Defaults to an empty separator.
+#[derive(Debug, Default)]
+pub struct Join {
+    separator: Arc<str>,
+    values: Vec<Arc<str>>,
+}
+
+impl Join {
+    pub fn new(separator: Arc<str>) -> Self {
+        Self {
+            separator,
+            values: Vec::default(),
+        }
+    }
+}
+
+impl Aggregate for Join {
+    type Input = (Arc<str>,);
+    type Output = Arc<str>;
+
+    fn name(&self) -> Option<&str> {
+        Some("join")
+    }
+
+    fn init(&self) -> Self {
+        Self::new(self.separator.clone())
+    }
+
+    fn step(&mut self, (s,): (Arc<str>,)) {
+        self.values.push(s);
+    }
+
+    fn finalize(&self) -> Option<Self::Output> {
+        let mut values = self.values.clone();
+        values.sort();
+
+        Some(Arc::from(values.join(&*self.separator)))
+    }
+}
@@ -31,6 +31,10 @@ where
     type Input = (T,);
     type Output = <T as Div>::Output;
 
+    fn name(&self) -> Option<&str> {
+        Some("mean")
+    }
+
     fn step(&mut self, (t,): (T,)) {
         self.0 += t;
         self.1 += One::one();
@@ -0,0 +1,46 @@
+use rhizome_macro::rhizome_fn;
+
+use crate::{aggregation::Aggregate, types::RhizomeType};
+
+rhizome_fn! {
+    #[aggregate = Median]
+    fn median<T: RhizomeType + Ord>(arg: T) -> T;
+}
+
+/// The lower median, which is always one of the aggregated values.
+#[derive(Debug)]
+pub struct Median<T>(Vec<T>);
+
+impl<T> Default for Median<T>
+where
+    T: RhizomeType + Ord,
+{
+    fn default() -> Self {
+        Self(Vec::default())
+    }
+}
+
+impl<T> Aggregate for Median<T>
+where
+    T: RhizomeType + Ord,
+{
+    type Input = (T,);
+    type Output = T;
+
+    fn name(&self) -> Option<&str> {
+        Some("median")
+    }
+
+    fn step(&mut self, (t,): (T,)) {
+        self.0.push(t);
+    }
+
+    fn finalize(&self) -> Option<Self::Output> {
+        let mut values = self.0.clone();
+        values.sort();
+
+        let index = values.len().checked_sub(1)? / 2;
+
+        values.get(index).cloned()
+    }
+}
@@ -2,7 +2,7 @@ use std::cmp;
 
 use rhizome_macro::rhizome_fn;
 
-use crate::{aggregation::Aggregate, types::RhizomeType};
+use crate::{aggregation::Aggregate, lattice::Monotonicity, types::RhizomeType};
 
 rhizome_fn! {
     #[aggregate = Min]
@@ -28,6 +28,14 @@ where
     type Input = (T,);
     type Output = T;
 
+    fn name(&self) -> Option<&str> {
+        Some("min")
+    }
+
+    fn monotonicity(&self) -> Option<Monotonicity> {
+        Some(Monotonicity::Decreasing)
+    }
+
     fn step(&mut self, (t,): (T,)) {
         let result = match self.0.take() {
             Some(v) => cmp::min(v, t),
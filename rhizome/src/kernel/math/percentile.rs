@@ -0,0 +1,109 @@
+use anyhow::Result;
+use std::marker::PhantomData;
+
+use crate::{
+    aggregation::{Aggregate, AggregateGroupBy},
+    error::{error, Error},
+    types::RhizomeType,
+    var::{TypedVar, Var},
+};
+
+/// The `p`th percentile of `arg`, for `p` between 0 and 100.
+pub fn percentile<T>(arg: TypedVar<T>, p: f64) -> PercentileGroupBy<T>
+where
+    T: RhizomeType + Ord,
+{
+    PercentileGroupBy {
+        arg: arg.into(),
+        p,
+        _marker: PhantomData,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PercentileGroupBy<T> {
+    arg: Var,
+    p: f64,
+    _marker: PhantomData<T>,
+}
+
+impl<T> AggregateGroupBy<(T,), T> for PercentileGroupBy<T>
+where
+    T: RhizomeType + Ord,
+{
+    type Aggregate = Percentile<T>;
+
+    fn as_args(&self) -> Vec<Var> {
+        vec![self.arg]
+    }
+
+    fn into_aggregate(self) -> Result<Self::Aggregate> {
+        if !(0.0..=100.0).contains(&self.p) {
+            return error(Error::InvalidAggregateParameter(
+                "percentile".to_owned(),
+                format!("expected p between 0 and 100, got {}", self.p),
+            ));
+        }
+
+        Ok(Percentile::new(self.p))
+    }
+}
+
+/// A percentile by the nearest-rank method, which is always one of the
+/// aggregated values. Defaults to the 50th percentile.
+#[derive(Debug)]
+pub struct Percentile<T> {
+    p: f64,
+    values: Vec<T>,
+}
+
+impl<T> Percentile<T>
+where
+    T: RhizomeType + Ord,
+{
+    pub fn new(p: f64) -> Self {
+        Self {
+            p,
+            values: Vec::default(),
+        }
+    }
+}
+
+impl<T> Default for Percentile<T>
+where
+    T: RhizomeType + Ord,
+{
+    fn default() -> Self {
+        Self::new(50.0)
+    }
+}
+
+impl<T> Aggregate for Percentile<T>
+where
+    T: RhizomeType + Ord,
+{
+    type Input = (T,);
+    type Output = T;
+
+    fn name(&self) -> Option<&str> {
+        Some("percentile")
+    }
+
+    fn init(&self) -> Self {
+        Self::new(self.p)
+    }
+
+    fn step(&mut self, (t,): (T,)) {
+        self.values.push(t);
+    }
+
+    fn finalize(&self) -> Option<Self::Output> {
+        let mut values = self.values.clone();
+        values.sort();
+
+        let rank = (self.p / 100.0 * values.len() as f64).ceil() as usize;
+        let index = rank.clamp(1, values.len()).checked_sub(1)?;
+
+        values.get(index).cloned()
+    }
+}
@@ -29,6 +29,10 @@ where
     type Input = (T,);
     type Output = T;
 
+    fn name(&self) -> Option<&str> {
+        Some("sum")
+    }
+
     fn step(&mut self, (t,): (T,)) {
         self.0 += t;
     }
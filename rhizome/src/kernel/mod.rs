@@ -1,12 +1,22 @@
-use std::{fmt, marker::PhantomData};
+use anyhow::Result;
+use std::{fmt, marker::PhantomData, sync::Arc};
 
 use crate::{
+    aggregation::{Aggregate, AggregateGroupBy},
+    function::Function,
     predicate::{Predicate, PredicateWhere},
     typed_vars::TypedVars,
     var::Var,
 };
 
+pub mod cid;
+pub mod cmp;
 pub mod math;
+pub mod string;
+
+mod registry;
+
+pub use registry::Registry;
 
 pub fn when<F, V, I>(args: V, f: F) -> FnPredicate<F, V, I>
 where
@@ -16,6 +26,40 @@ where
     FnPredicate(f, args, PhantomData)
 }
 
+pub fn func<F, I, O>(f: F) -> FnFunction<F, I, O>
+where
+    F: Fn(I) -> O + Send + Sync + 'static,
+{
+    FnFunction(f, PhantomData)
+}
+
+/// An aggregate over `args`, which folds each group with `step`, starting from
+/// `init`, and derives the result from the accumulator with `finalize`. Groups
+/// for which `finalize` returns `None` are dropped.
+///
+/// Tuples are folded in the order they're read from the relation, which isn't
+/// specified, so `step` should give the same result in any order, as it does
+/// when it's commutative and associative.
+pub fn fold<V, I, A, O, S, F>(args: V, init: A, step: S, finalize: F) -> FoldGroupBy<V, I, A, O>
+where
+    V: TypedVars<Args = I>,
+    A: Clone,
+    S: Fn(A, I) -> A + Send + Sync + 'static,
+    F: Fn(&A) -> Option<O> + Send + Sync + 'static,
+{
+    FoldGroupBy {
+        args,
+        fold: Fold {
+            fns: Some(Arc::new(FoldFns {
+                init,
+                step: Box::new(step),
+                finalize: Box::new(finalize),
+            })),
+            acc: None,
+        },
+    }
+}
+
 pub struct FnPredicate<F, V, I>(F, V, PhantomData<I>)
 where
     V: TypedVars<Args = I>,
@@ -58,3 +102,115 @@ where
         f.debug_struct("FnPredicate").finish()
     }
 }
+
+pub struct FnFunction<F, I, O>(F, PhantomData<fn(I) -> O>)
+where
+    F: Fn(I) -> O + Send + Sync + 'static;
+
+impl<F, I, O> Function for FnFunction<F, I, O>
+where
+    F: Fn(I) -> O + Send + Sync + 'static,
+{
+    type Input = I;
+    type Output = O;
+
+    fn apply(&self, args: Self::Input) -> Option<Self::Output> {
+        Some(self.0(args))
+    }
+}
+
+impl<F, I, O> fmt::Debug for FnFunction<F, I, O>
+where
+    F: Fn(I) -> O + Send + Sync + 'static,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FnFunction").finish()
+    }
+}
+
+pub struct FoldGroupBy<V, I, A, O> {
+    args: V,
+    fold: Fold<I, A, O>,
+}
+
+impl<V, I, A, O> AggregateGroupBy<I, O> for FoldGroupBy<V, I, A, O>
+where
+    V: TypedVars<Args = I>,
+    A: Clone,
+{
+    type Aggregate = Fold<I, A, O>;
+
+    fn as_args(&self) -> Vec<Var> {
+        self.args.vars()
+    }
+
+    fn into_aggregate(self) -> Result<Self::Aggregate> {
+        Ok(self.fold)
+    }
+}
+
+impl<V, I, A, O> fmt::Debug for FoldGroupBy<V, I, A, O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FoldGroupBy").finish()
+    }
+}
+
+type FoldStep<I, A> = dyn Fn(A, I) -> A + Send + Sync;
+type FoldFinalize<A, O> = dyn Fn(&A) -> Option<O> + Send + Sync;
+
+struct FoldFns<I, A, O> {
+    init: A,
+    step: Box<FoldStep<I, A>>,
+    finalize: Box<FoldFinalize<A, O>>,
+}
+
+/// An aggregate built from closures with [`fold`]. The default fold has no
+/// closures, so it ignores its inputs and never produces a value.
+pub struct Fold<I, A, O> {
+    fns: Option<Arc<FoldFns<I, A, O>>>,
+    acc: Option<A>,
+}
+
+impl<I, A, O> Default for Fold<I, A, O> {
+    fn default() -> Self {
+        Self {
+            fns: None,
+            acc: None,
+        }
+    }
+}
+
+impl<I, A, O> Aggregate for Fold<I, A, O>
+where
+    A: Clone,
+{
+    type Input = I;
+    type Output = O;
+
+    fn init(&self) -> Self {
+        Self {
+            fns: self.fns.clone(),
+            acc: None,
+        }
+    }
+
+    fn step(&mut self, args: I) {
+        if let Some(fns) = &self.fns {
+            let acc = self.acc.take().unwrap_or_else(|| fns.init.clone());
+
+            self.acc = Some((fns.step)(acc, args));
+        }
+    }
+
+    fn finalize(&self) -> Option<O> {
+        let fns = self.fns.as_ref()?;
+
+        (fns.finalize)(self.acc.as_ref().unwrap_or(&fns.init))
+    }
+}
+
+impl<I, A, O> fmt::Debug for Fold<I, A, O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Fold").finish()
+    }
+}
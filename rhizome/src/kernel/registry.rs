@@ -0,0 +1,268 @@
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use anyhow::Result;
+use cid::Cid;
+
+use crate::{
+    aggregation::AggregateWrapper,
+    kernel::{self, cmp, math, string},
+    lattice::Monotonicity,
+    predicate::PredicateWrapper,
+    types::{ColType, Type},
+    value::Val,
+};
+
+type AggregateFactory =
+    dyn Fn(&[ColType], ColType) -> Option<Arc<dyn AggregateWrapper>> + Send + Sync;
+
+macro_rules! aggregate {
+    ($agg:expr) => {
+        Some(Arc::new($agg) as Arc<dyn AggregateWrapper>)
+    };
+}
+
+/// Evaluates `$body` with `$T` aliased to the Rust type of `$typ`, or returns
+/// `None` if `$typ` isn't one of the listed types.
+macro_rules! with_type {
+    ($typ:expr, $T:ident => $body:expr, [$($variant:ident => $t:ty),*]) => {
+        match $typ {
+            $(
+                ColType::Type(Type::$variant) => {
+                    type $T = $t;
+
+                    $body
+                }
+            )*
+            _ => None,
+        }
+    };
+}
+
+macro_rules! with_numeric_type {
+    ($typ:expr, $T:ident => $body:expr) => {
+        with_type!($typ, $T => $body, [
+            S8 => i8, U8 => u8, S16 => i16, U16 => u16, S32 => i32,
+            U32 => u32, S64 => i64, U64 => u64, F64 => f64
+        ])
+    };
+}
+
+macro_rules! with_ordered_type {
+    ($typ:expr, $T:ident => $body:expr) => {
+        with_type!($typ, $T => $body, [
+            Bool => bool, S8 => i8, U8 => u8, S16 => i16, U16 => u16, S32 => i32,
+            U32 => u32, S64 => i64, U64 => u64, Char => char, String => Arc<str>,
+            Cid => Cid
+        ])
+    };
+}
+
+/// Named predicates and aggregates, for programs that can't refer to Rust
+/// functions directly, such as those parsed from source.
+///
+/// Predicates are registered as instances. Aggregates are registered as
+/// factories, which receive the types of the aggregated columns and of the
+/// target, since an aggregate's accumulator is specialized to its types.
+#[derive(Clone)]
+pub struct Registry {
+    predicates: HashMap<String, Arc<dyn PredicateWrapper>>,
+    aggregates: HashMap<String, Arc<AggregateFactory>>,
+}
+
+impl Registry {
+    /// A registry without any predicates or aggregates.
+    pub fn empty() -> Self {
+        Self {
+            predicates: HashMap::default(),
+            aggregates: HashMap::default(),
+        }
+    }
+
+    pub fn register_predicate<P>(&mut self, name: &str, predicate: P) -> &mut Self
+    where
+        P: PredicateWrapper,
+    {
+        let predicate = NamedPredicate {
+            name: Arc::from(name),
+            inner: Arc::new(predicate),
+        };
+
+        self.predicates.insert(name.to_owned(), Arc::new(predicate));
+
+        self
+    }
+
+    pub fn register_aggregate<F>(&mut self, name: &str, factory: F) -> &mut Self
+    where
+        F: Fn(&[ColType], ColType) -> Option<Arc<dyn AggregateWrapper>> + Send + Sync + 'static,
+    {
+        self.aggregates.insert(name.to_owned(), Arc::new(factory));
+
+        self
+    }
+
+    pub fn predicate(&self, name: &str) -> Option<Arc<dyn PredicateWrapper>> {
+        self.predicates.get(name).cloned()
+    }
+
+    /// Instantiates the aggregate `name` for arguments of type `args`, aggregating into
+    /// a target of type `target`. Returns `None` if `name` is not registered, and
+    /// `Some(None)` if it is not defined for the given types.
+    pub fn aggregate(
+        &self,
+        name: &str,
+        args: &[ColType],
+        target: ColType,
+    ) -> Option<Option<Arc<dyn AggregateWrapper>>> {
+        self.aggregates.get(name).map(|factory| {
+            factory(args, target).map(|agg| {
+                Arc::new(NamedAggregate {
+                    name: Arc::from(name),
+                    inner: agg.init(),
+                }) as Arc<dyn AggregateWrapper>
+            })
+        })
+    }
+}
+
+/// A registered predicate, which is printed by the name it was registered under.
+struct NamedPredicate {
+    name: Arc<str>,
+    inner: Arc<dyn PredicateWrapper>,
+}
+
+impl PredicateWrapper for NamedPredicate {
+    fn name(&self) -> Option<&str> {
+        Some(&self.name)
+    }
+
+    fn apply(&self, args: Vec<Val>) -> Option<bool> {
+        self.inner.apply(args)
+    }
+}
+
+/// A registered aggregate, which is printed by the name it was registered under.
+struct NamedAggregate {
+    name: Arc<str>,
+    inner: Box<dyn AggregateWrapper>,
+}
+
+impl AggregateWrapper for NamedAggregate {
+    fn name(&self) -> Option<&str> {
+        Some(&self.name)
+    }
+
+    fn monotonicity(&self) -> Option<Monotonicity> {
+        self.inner.monotonicity()
+    }
+
+    fn init(&self) -> Box<dyn AggregateWrapper> {
+        Box::new(Self {
+            name: Arc::clone(&self.name),
+            inner: self.inner.init(),
+        })
+    }
+
+    fn step(&mut self, args: Vec<Val>) -> Result<()> {
+        self.inner.step(args)
+    }
+
+    fn finalize(&self) -> Option<Val> {
+        self.inner.finalize()
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+
+        registry
+            .register_predicate("lt", cmp::Lt::<Val>::default())
+            .register_predicate("le", cmp::Le::<Val>::default())
+            .register_predicate("gt", cmp::Gt::<Val>::default())
+            .register_predicate("ge", cmp::Ge::<Val>::default())
+            .register_predicate("ne", cmp::Ne::<Val>::default())
+            .register_predicate(
+                "starts_with",
+                string::StartsWith::<(Arc<str>, Arc<str>)>::default(),
+            )
+            .register_predicate(
+                "ends_with",
+                string::EndsWith::<(Arc<str>, Arc<str>)>::default(),
+            )
+            .register_predicate(
+                "contains",
+                string::Contains::<(Arc<str>, Arc<str>)>::default(),
+            )
+            .register_predicate("len", string::Len::<(Arc<str>, u64)>::default())
+            .register_predicate(
+                "matches",
+                string::Matches::<(Arc<str>, Arc<str>)>::default(),
+            )
+            .register_predicate("has_codec", kernel::cid::HasCodec::<(Cid, u64)>::default())
+            .register_predicate(
+                "has_multihash",
+                kernel::cid::HasMultihash::<(Cid, u64)>::default(),
+            )
+            .register_predicate("is_dag_cbor", kernel::cid::IsDagCbor::<(Cid,)>::default());
+
+        registry
+            .register_aggregate("count", |args, target| match args {
+                [] => with_numeric_type!(target, T => aggregate!(math::Count::<T>::default())),
+                _ => None,
+            })
+            .register_aggregate("count_distinct", |args, target| match args {
+                [arg] => with_ordered_type!(arg, T => with_numeric_type!(target, O => {
+                    aggregate!(math::CountDistinct::<T, O>::default())
+                })),
+                _ => None,
+            })
+            .register_aggregate("sum", |args, _| match args {
+                [arg] => with_numeric_type!(arg, T => aggregate!(math::Sum::<T>::default())),
+                _ => None,
+            })
+            .register_aggregate("mean", |args, _| match args {
+                [arg] => with_numeric_type!(arg, T => aggregate!(math::Mean::<T>::default())),
+                _ => None,
+            })
+            .register_aggregate("min", |args, _| match args {
+                [arg] => with_ordered_type!(arg, T => aggregate!(math::Min::<T>::default())),
+                _ => None,
+            })
+            .register_aggregate("max", |args, _| match args {
+                [arg] => with_ordered_type!(arg, T => aggregate!(math::Max::<T>::default())),
+                _ => None,
+            })
+            .register_aggregate("median", |args, _| match args {
+                [arg] => with_ordered_type!(arg, T => aggregate!(math::Median::<T>::default())),
+                _ => None,
+            })
+            .register_aggregate("arg_min", |args, _| match args {
+                [key, arg] => with_ordered_type!(key, K => with_ordered_type!(arg, V => {
+                    aggregate!(math::ArgMin::<K, V>::default())
+                })),
+                _ => None,
+            })
+            .register_aggregate("arg_max", |args, _| match args {
+                [key, arg] => with_ordered_type!(key, K => with_ordered_type!(arg, V => {
+                    aggregate!(math::ArgMax::<K, V>::default())
+                })),
+                _ => None,
+            })
+            .register_aggregate("collect", |args, _| match args {
+                [arg] => with_ordered_type!(arg, T => aggregate!(math::Collect::<T>::default())),
+                _ => None,
+            });
+
+        registry
+    }
+}
+
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Registry")
+            .field("predicates", &self.predicates.keys())
+            .field("aggregates", &self.aggregates.keys())
+            .finish()
+    }
+}
@@ -0,0 +1,103 @@
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+};
+
+use regex::Regex;
+use rhizome_macro::rhizome_fn;
+
+use crate::predicate::Predicate;
+
+macro_rules! string_predicate {
+    ($fn_name:ident, $name:ident, $arg:ident: $arg_type:ty, |$s:ident, $a:ident| $body:expr) => {
+        rhizome_fn! {
+            #[predicate = $name]
+            fn $fn_name(s: Arc<str>, $arg: $arg_type);
+        }
+
+        #[derive(Debug)]
+        pub struct $name<T>(PhantomData<T>);
+
+        impl<T> Default for $name<T> {
+            fn default() -> Self {
+                Self(PhantomData)
+            }
+        }
+
+        impl Predicate for $name<(Arc<str>, $arg_type)> {
+            type Input = (Arc<str>, $arg_type);
+
+            fn name(&self) -> Option<&str> {
+                Some(stringify!($fn_name))
+            }
+
+            fn apply(&self, ($s, $a): Self::Input) -> Option<bool> {
+                $body
+            }
+        }
+    };
+}
+
+string_predicate!(starts_with, StartsWith, prefix: Arc<str>, |s, prefix| {
+    Some(s.starts_with(&*prefix))
+});
+
+string_predicate!(ends_with, EndsWith, suffix: Arc<str>, |s, suffix| {
+    Some(s.ends_with(&*suffix))
+});
+
+string_predicate!(contains, Contains, pattern: Arc<str>, |s, pattern| {
+    Some(s.contains(&*pattern))
+});
+
+// Length is measured in characters, rather than bytes
+string_predicate!(len, Len, n: u64, |s, n| Some(s.chars().count() as u64 == n));
+
+/// The most patterns [`Matches`] keeps compiled, before they're compiled again.
+const REGEX_CACHE_SIZE: usize = 64;
+
+rhizome_fn! {
+    #[predicate = Matches]
+    fn matches(s: Arc<str>, pattern: Arc<str>);
+}
+
+/// Matches strings against a regular expression. Each pattern is compiled the
+/// first time it's used, and kept for the tuples after.
+#[derive(Debug)]
+pub struct Matches<T> {
+    cache: Mutex<HashMap<Arc<str>, Option<Regex>>>,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Default for Matches<T> {
+    fn default() -> Self {
+        Self {
+            cache: Mutex::default(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl Predicate for Matches<(Arc<str>, Arc<str>)> {
+    type Input = (Arc<str>, Arc<str>);
+
+    fn name(&self) -> Option<&str> {
+        Some("matches")
+    }
+
+    // An invalid pattern produces no value, rather than failing to match
+    fn apply(&self, (s, pattern): Self::Input) -> Option<bool> {
+        let mut cache = self.cache.lock().ok()?;
+
+        if cache.len() >= REGEX_CACHE_SIZE && !cache.contains_key(&pattern) {
+            cache.clear();
+        }
+
+        cache
+            .entry(Arc::clone(&pattern))
+            .or_insert_with(|| Regex::new(&pattern).ok())
+            .as_ref()
+            .map(|re| re.is_match(&s))
+    }
+}
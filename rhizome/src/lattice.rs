@@ -1,4 +1,6 @@
-use std::cmp::Ordering;
+use std::{cmp::Ordering, fmt::Debug, marker::PhantomData};
+
+use crate::{types::IntoColType, value::Val};
 
 pub trait Lattice {
     const BOTTOM: Self;
@@ -74,6 +76,143 @@ impl_lattice!(i64, i64::MIN, i64::MAX);
 impl_lattice!(u128, 0, u128::MAX);
 impl_lattice!(i128, i128::MIN, i128::MAX);
 
+/// The direction in which a value only ever moves: the join of a lattice column, or
+/// the result of an aggregate as it's applied to more tuples.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Monotonicity {
+    Increasing,
+    Decreasing,
+}
+
+/// Reverses the order of the lattice `T`, so that joining keeps the least value.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct Min<T>(pub T);
+
+impl<T> Lattice for Min<T>
+where
+    T: Lattice,
+{
+    const BOTTOM: Self = Min(T::TOP);
+    const TOP: Self = Min(T::BOTTOM);
+
+    fn cmp(&self, other: &Self) -> Option<Ordering> {
+        T::cmp(&other.0, &self.0)
+    }
+
+    fn join(&self, other: &Self) -> Self {
+        Min(T::meet(&self.0, &other.0))
+    }
+
+    fn meet(&self, other: &Self) -> Self {
+        Min(T::join(&self.0, &other.0))
+    }
+}
+
+/// Keeps the order of the lattice `T`, so that joining keeps the greatest value.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct Max<T>(pub T);
+
+impl<T> Lattice for Max<T>
+where
+    T: Lattice,
+{
+    const BOTTOM: Self = Max(T::BOTTOM);
+    const TOP: Self = Max(T::TOP);
+
+    fn cmp(&self, other: &Self) -> Option<Ordering> {
+        T::cmp(&self.0, &other.0)
+    }
+
+    fn join(&self, other: &Self) -> Self {
+        Max(T::join(&self.0, &other.0))
+    }
+
+    fn meet(&self, other: &Self) -> Self {
+        Max(T::meet(&self.0, &other.0))
+    }
+}
+
+/// A lattice that can be stored in a column of a relation, as a value of
+/// its underlying type.
+pub trait ColLattice: Lattice + Debug + Send + Sync + Sized + 'static {
+    type Value: IntoColType + Into<Val> + TryFrom<Val>;
+
+    /// The direction in which joining moves values, in the order of
+    /// [`Value`](ColLattice::Value), if it only ever moves them one way.
+    const MONOTONICITY: Option<Monotonicity> = None;
+
+    fn from_value(value: Self::Value) -> Self;
+    fn into_value(self) -> Self::Value;
+}
+
+impl<T> ColLattice for Min<T>
+where
+    T: Lattice + IntoColType + Into<Val> + TryFrom<Val> + Debug + Send + Sync + 'static,
+{
+    type Value = T;
+
+    const MONOTONICITY: Option<Monotonicity> = Some(Monotonicity::Decreasing);
+
+    fn from_value(value: T) -> Self {
+        Min(value)
+    }
+
+    fn into_value(self) -> T {
+        self.0
+    }
+}
+
+impl<T> ColLattice for Max<T>
+where
+    T: Lattice + IntoColType + Into<Val> + TryFrom<Val> + Debug + Send + Sync + 'static,
+{
+    type Value = T;
+
+    const MONOTONICITY: Option<Monotonicity> = Some(Monotonicity::Increasing);
+
+    fn from_value(value: T) -> Self {
+        Max(value)
+    }
+
+    fn into_value(self) -> T {
+        self.0
+    }
+}
+
+/// Joins the values of a lattice column, independent of the lattice's type.
+pub(crate) trait LatticeJoin: Debug + Send + Sync {
+    /// Returns the join of `left` and `right`, or `None` if either isn't a
+    /// value of the lattice.
+    fn join(&self, left: &Val, right: &Val) -> Option<Val>;
+
+    fn monotonicity(&self) -> Option<Monotonicity>;
+}
+
+#[derive(Debug)]
+pub(crate) struct ColJoin<L>(PhantomData<L>);
+
+impl<L> Default for ColJoin<L> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<L> LatticeJoin for ColJoin<L>
+where
+    L: ColLattice,
+{
+    fn join(&self, left: &Val, right: &Val) -> Option<Val> {
+        let left = L::from_value(L::Value::try_from(left.clone()).ok()?);
+        let right = L::from_value(L::Value::try_from(right.clone()).ok()?);
+
+        Some(L::join(&left, &right).into_value().into())
+    }
+
+    fn monotonicity(&self) -> Option<Monotonicity> {
+        L::MONOTONICITY
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -137,6 +276,23 @@ mod tests {
         assert_eq!((1, 2), Lattice::meet(&(1, 4), &(3, 2)));
     }
 
+    #[test]
+    fn min_max_lattice_tests() {
+        assert_eq!(Min(3), Lattice::join(&Min(3), &Min(5)));
+        assert_eq!(Min(5), Lattice::meet(&Min(3), &Min(5)));
+        assert_eq!(Some(Ordering::Greater), Lattice::cmp(&Min(3), &Min(5)));
+        assert_eq!(Min(u64::MAX), <Min<u64>>::BOTTOM);
+
+        assert_eq!(Max(5), Lattice::join(&Max(3), &Max(5)));
+        assert_eq!(Max(3), Lattice::meet(&Max(3), &Max(5)));
+        assert_eq!(Some(Ordering::Less), Lattice::cmp(&Max(3), &Max(5)));
+        assert_eq!(Max(0), <Max<u64>>::BOTTOM);
+
+        let join = ColJoin::<Min<u64>>::default();
+        assert_eq!(Some(Val::U64(3)), join.join(&Val::U64(3), &Val::U64(5)));
+        assert_eq!(None, join.join(&Val::U64(3), &Val::S64(5)));
+    }
+
     test_numeric_lattice!(usize, 0, usize::MAX);
     test_numeric_lattice!(isize, isize::MIN, isize::MAX);
     test_numeric_lattice!(u8, 0, u8::MAX);
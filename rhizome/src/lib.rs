@@ -8,7 +8,6 @@ pub(crate) mod col;
 pub(crate) mod col_val;
 pub(crate) mod id;
 pub(crate) mod interner;
-pub(crate) mod lattice;
 pub(crate) mod logic;
 pub(crate) mod ram;
 pub(crate) mod relation;
@@ -16,9 +15,13 @@ pub(crate) mod relation;
 pub mod aggregation;
 pub mod args;
 pub mod error;
+pub mod expr;
+pub mod function;
 pub mod kernel;
+pub mod lattice;
 pub mod predicate;
 pub mod pretty;
+pub mod provenance;
 pub mod runtime;
 pub mod storage;
 pub mod timestamp;
@@ -28,7 +31,10 @@ pub mod types;
 pub mod value;
 pub mod var;
 
-pub use logic::{build, AtomBinding, AtomBindings, ProgramBuilder, RuleBodyBuilder, RuleVars};
+pub use logic::{
+    build, parse, AtomBinding, AtomBindings, Cycle, Diagnostic, DisjunctionBuilder, Evaluation,
+    Lint, NegativeDependency, Position, ProgramBuilder, RuleBodyBuilder, RuleVars, Semantics, Span,
+};
 
 /// Test utilities.
 #[cfg(any(test, feature = "test_utils"))]
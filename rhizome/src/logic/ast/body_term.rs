@@ -4,22 +4,39 @@ use std::{
     sync::Arc,
 };
 
+use pretty::RcDoc;
+
 use crate::{
     aggregation::AggregateWrapper,
+    function::FunctionWrapper,
     id::{ColId, VarId},
     predicate::PredicateWrapper,
+    pretty::{Pretty, ANONYMOUS},
     var::Var,
 };
 
-use super::{CidValue, Declaration};
+use super::{atom_doc, CidValue, Declaration};
 use crate::col_val::ColVal;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum BodyTerm {
     VarPredicate(VarPredicate),
     RelPredicate(RelPredicate),
     Negation(Negation),
     Aggregation(Aggregation),
+    Computation(Computation),
+}
+
+impl Pretty for BodyTerm {
+    fn to_doc(&self) -> RcDoc<'_, ()> {
+        match self {
+            BodyTerm::VarPredicate(inner) => inner.to_doc(),
+            BodyTerm::RelPredicate(inner) => inner.to_doc(),
+            BodyTerm::Negation(inner) => inner.to_doc(),
+            BodyTerm::Aggregation(inner) => inner.to_doc(),
+            BodyTerm::Computation(inner) => inner.to_doc(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -56,9 +73,9 @@ impl RelPredicate {
 
     pub fn vars(&self) -> HashSet<&Var> {
         self.args
-            .iter()
-            .filter_map(|(_, v)| match v {
-                ColVal::Lit(_) => None,
+            .values()
+            .filter_map(|v| match v {
+                ColVal::Lit(_) | ColVal::Expr(_) | ColVal::Wildcard => None,
                 ColVal::Binding(var) => Some(var),
             })
             .collect()
@@ -83,6 +100,17 @@ impl RelPredicate {
     }
 }
 
+impl Pretty for RelPredicate {
+    fn to_doc(&self) -> RcDoc<'_, ()> {
+        let cid_doc = self.cid.map(|cid| match cid {
+            CidValue::Cid(cid) => RcDoc::text(format!("cid\"{cid}\"")),
+            CidValue::Var(var) => RcDoc::as_string(var.id()),
+        });
+
+        atom_doc(self.relation.id(), cid_doc, &self.args)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Negation {
     relation: Arc<Declaration>,
@@ -108,28 +136,40 @@ impl Negation {
 
     pub fn vars(&self) -> HashSet<&Var> {
         self.args
-            .iter()
-            .filter_map(|(_, v)| match v {
-                ColVal::Lit(_) => None,
+            .values()
+            .filter_map(|v| match v {
+                ColVal::Lit(_) | ColVal::Expr(_) | ColVal::Wildcard => None,
                 ColVal::Binding(var) => Some(var),
             })
             .collect()
     }
 }
 
+impl Pretty for Negation {
+    fn to_doc(&self) -> RcDoc<'_, ()> {
+        RcDoc::text("!").append(atom_doc(self.relation.id(), None, &self.args))
+    }
+}
+
 #[derive(Clone)]
 pub struct VarPredicate {
-    vars: Vec<Var>,
+    args: Vec<ColVal>,
     f: Arc<dyn PredicateWrapper>,
 }
 
 impl VarPredicate {
-    pub fn new(vars: Vec<Var>, f: Arc<dyn PredicateWrapper>) -> Self {
-        Self { vars, f }
+    pub fn new(args: Vec<ColVal>, f: Arc<dyn PredicateWrapper>) -> Self {
+        Self { args, f }
     }
 
-    pub fn vars(&self) -> &Vec<Var> {
-        &self.vars
+    /// The arguments the predicate is applied to, each either a literal or a
+    /// variable.
+    pub fn args(&self) -> &Vec<ColVal> {
+        &self.args
+    }
+
+    pub fn vars(&self) -> Vec<Var> {
+        self.args.iter().flat_map(ColVal::vars).collect()
     }
 
     pub fn f(&self) -> Arc<dyn PredicateWrapper> {
@@ -144,17 +184,83 @@ impl VarPredicate {
 impl Debug for VarPredicate {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("VarPredicate")
+            .field("args", &self.args)
+            .finish()
+    }
+}
+
+impl Pretty for VarPredicate {
+    fn to_doc(&self) -> RcDoc<'_, ()> {
+        RcDoc::text(self.f.name().unwrap_or(ANONYMOUS))
+            .append(RcDoc::text("("))
+            .append(
+                RcDoc::intersperse(
+                    self.args.iter().map(|arg| arg.to_doc()),
+                    RcDoc::text(",").append(RcDoc::line()),
+                )
+                .nest(2)
+                .group(),
+            )
+            .append(RcDoc::text(")"))
+    }
+}
+
+#[derive(Clone)]
+pub struct Computation {
+    target: Var,
+    vars: Vec<Var>,
+    f: Arc<dyn FunctionWrapper>,
+}
+
+impl Computation {
+    pub fn new(target: Var, vars: Vec<Var>, f: Arc<dyn FunctionWrapper>) -> Self {
+        Self { target, vars, f }
+    }
+
+    pub fn target(&self) -> &Var {
+        &self.target
+    }
+
+    pub fn vars(&self) -> &Vec<Var> {
+        &self.vars
+    }
+
+    pub fn f(&self) -> Arc<dyn FunctionWrapper> {
+        Arc::clone(&self.f)
+    }
+
+    pub fn is_vars_bound(&self, bindings: &HashSet<VarId>) -> bool {
+        self.vars().iter().all(|var| bindings.contains(&var.id()))
+    }
+}
+
+impl Debug for Computation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Computation")
+            .field("target", &self.target)
             .field("vars", &self.vars)
             .finish()
     }
 }
 
+impl Pretty for Computation {
+    fn to_doc(&self) -> RcDoc<'_, ()> {
+        RcDoc::concat([
+            RcDoc::as_string(self.target.id()),
+            RcDoc::text(" = "),
+            RcDoc::text(self.f.name().unwrap_or(ANONYMOUS)),
+            vars_doc(&self.vars),
+        ])
+    }
+}
+
+/// Binds `target` to the result of aggregating `vars` over every solution to
+/// `body`, grouped by the variables it shares with the rest of the rule.
 #[derive(Clone)]
 pub struct Aggregation {
     target: Var,
     vars: Vec<Var>,
-    relation: Arc<Declaration>,
-    group_by_cols: HashMap<ColId, ColVal>,
+    body: Vec<BodyTerm>,
     agg: Arc<dyn AggregateWrapper>,
 }
 
@@ -162,15 +268,13 @@ impl Aggregation {
     pub fn new(
         target: Var,
         vars: Vec<Var>,
-        relation: Arc<Declaration>,
-        group_by_cols: HashMap<ColId, ColVal>,
+        body: Vec<BodyTerm>,
         f: Arc<dyn AggregateWrapper>,
     ) -> Self {
         Self {
             target,
             vars,
-            relation,
-            group_by_cols,
+            body,
             agg: f,
         }
     }
@@ -183,18 +287,28 @@ impl Aggregation {
         &self.vars
     }
 
-    pub fn relation(&self) -> Arc<Declaration> {
-        Arc::clone(&self.relation)
-    }
-
-    pub fn group_by_cols(&self) -> &HashMap<ColId, ColVal> {
-        &self.group_by_cols
+    pub fn body(&self) -> &Vec<BodyTerm> {
+        &self.body
     }
 
     pub fn agg(&self) -> Arc<dyn AggregateWrapper> {
         Arc::clone(&self.agg)
     }
 
+    /// The relations searched or negated in the body, including those of any
+    /// nested aggregations.
+    pub fn relations(&self) -> Vec<Arc<Declaration>> {
+        self.body
+            .iter()
+            .flat_map(|term| match term {
+                BodyTerm::RelPredicate(inner) => vec![inner.relation()],
+                BodyTerm::Negation(inner) => vec![inner.relation()],
+                BodyTerm::Aggregation(inner) => inner.relations(),
+                BodyTerm::VarPredicate(_) | BodyTerm::Computation(_) => vec![],
+            })
+            .collect()
+    }
+
     pub fn bound_vars(&self, bindings: &HashSet<VarId>) -> HashSet<VarId> {
         self.vars()
             .iter()
@@ -215,8 +329,47 @@ impl Debug for Aggregation {
         f.debug_struct("Aggregation")
             .field("target", &self.target)
             .field("vars", &self.vars)
-            .field("relation", &self.relation)
-            .field("group_by_cols", &self.group_by_cols)
+            .field("body", &self.body)
             .finish()
     }
 }
+
+impl Pretty for Aggregation {
+    fn to_doc(&self) -> RcDoc<'_, ()> {
+        let body_doc = match self.body.as_slice() {
+            [term] => term.to_doc(),
+            terms => RcDoc::concat([
+                RcDoc::text("("),
+                RcDoc::intersperse(
+                    terms.iter().map(|term| term.to_doc()),
+                    RcDoc::text(",").append(RcDoc::line()),
+                )
+                .nest(1)
+                .group(),
+                RcDoc::text(")"),
+            ]),
+        };
+
+        RcDoc::concat([
+            RcDoc::as_string(self.target.id()),
+            RcDoc::text(" = "),
+            RcDoc::text(self.agg.name().unwrap_or(ANONYMOUS)),
+            vars_doc(&self.vars),
+            RcDoc::text(" : "),
+            body_doc,
+        ])
+    }
+}
+
+fn vars_doc(vars: &[Var]) -> RcDoc<'_, ()> {
+    RcDoc::text("(")
+        .append(
+            RcDoc::intersperse(
+                vars.iter().map(|var| RcDoc::as_string(var.id())),
+                RcDoc::text(",").append(RcDoc::line()),
+            )
+            .nest(2)
+            .group(),
+        )
+        .append(RcDoc::text(")"))
+}
@@ -1,4 +1,6 @@
-use crate::id::RelationId;
+use pretty::RcDoc;
+
+use crate::{id::RelationId, pretty::Pretty};
 
 use super::{Fact, Rule};
 
@@ -16,3 +18,12 @@ impl Clause {
         }
     }
 }
+
+impl Pretty for Clause {
+    fn to_doc(&self) -> RcDoc<'_, ()> {
+        match self {
+            Clause::Fact(fact) => fact.to_doc(),
+            Clause::Rule(rule) => rule.to_doc(),
+        }
+    }
+}
@@ -1,8 +1,12 @@
+use pretty::RcDoc;
+use std::sync::Arc;
+
 use crate::{
     id::RelationId,
+    pretty::Pretty,
     relation::{Relation, Source},
+    types::{ColType, Type},
 };
-use std::sync::Arc;
 
 use super::Schema;
 
@@ -45,3 +49,44 @@ impl Declaration {
         dyn_clone::clone_box(&*self.relation)
     }
 }
+
+impl Pretty for Declaration {
+    fn to_doc(&self) -> RcDoc<'_, ()> {
+        let source_doc = match self.source {
+            Source::Edb => RcDoc::text(".input "),
+            Source::Idb => RcDoc::text(".output "),
+        };
+
+        let mut cols = self.schema.cols().values().collect::<Vec<_>>();
+        cols.sort_by_key(|col| col.id().resolve());
+
+        let cols_doc = RcDoc::intersperse(
+            cols.into_iter().map(|col| {
+                RcDoc::concat([
+                    RcDoc::as_string(col.id()),
+                    RcDoc::text(": "),
+                    col_type_doc(col.col_type()),
+                ])
+            }),
+            RcDoc::text(",").append(RcDoc::line()),
+        )
+        .nest(2)
+        .group();
+
+        RcDoc::concat([
+            source_doc,
+            RcDoc::as_string(self.id),
+            RcDoc::text("("),
+            cols_doc,
+            RcDoc::text(")"),
+        ])
+    }
+}
+
+// `Type`'s `Display` spells the CID type `CID`, but the parser's keyword is `cid`.
+fn col_type_doc(col_type: &ColType) -> RcDoc<'_, ()> {
+    match col_type {
+        ColType::Type(Type::Cid) => RcDoc::text("cid"),
+        _ => RcDoc::as_string(col_type),
+    }
+}
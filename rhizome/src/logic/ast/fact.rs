@@ -1,10 +1,14 @@
+use pretty::RcDoc;
 use std::collections::HashMap;
 
 use crate::{
     id::{ColId, RelationId},
+    pretty::Pretty,
     value::Val,
 };
 
+use super::atom_doc;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Fact {
     head: RelationId,
@@ -24,3 +28,9 @@ impl Fact {
         &self.args
     }
 }
+
+impl Pretty for Fact {
+    fn to_doc(&self) -> RcDoc<'_, ()> {
+        atom_doc(self.head, None, &self.args).append(RcDoc::text("."))
+    }
+}
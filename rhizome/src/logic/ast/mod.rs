@@ -1,9 +1,18 @@
+use pretty::RcDoc;
+use std::collections::HashMap;
+
+use crate::{
+    id::{ColId, RelationId},
+    pretty::Pretty,
+};
+
 pub(super) mod body_term;
 pub(super) mod cid_value;
 pub(super) mod clause;
 pub(super) mod declaration;
 pub(super) mod fact;
 pub(super) mod program;
+pub(super) mod query;
 pub(super) mod rule;
 pub(super) mod schema;
 pub(super) mod stratum;
@@ -14,5 +23,42 @@ pub(super) use clause::*;
 pub(super) use declaration::*;
 pub(super) use fact::*;
 pub(super) use program::*;
+pub use program::{Evaluation, Semantics};
+pub(super) use query::*;
 pub(super) use rule::*;
 pub(super) use schema::*;
+
+/// Renders `id(col: val, ...)`, with columns in a stable order.
+pub(super) fn atom_doc<'a, V>(
+    id: RelationId,
+    cid: Option<RcDoc<'a, ()>>,
+    args: &'a HashMap<ColId, V>,
+) -> RcDoc<'a, ()>
+where
+    V: Pretty,
+{
+    let mut args = args.iter().collect::<Vec<_>>();
+    args.sort_by_key(|(col_id, _)| col_id.resolve());
+
+    let args_doc = RcDoc::intersperse(
+        args.into_iter().map(|(col_id, val)| {
+            RcDoc::concat([RcDoc::as_string(col_id), RcDoc::text(": "), val.to_doc()])
+        }),
+        RcDoc::text(",").append(RcDoc::line()),
+    )
+    .nest(2)
+    .group();
+
+    let cid_doc = match cid {
+        Some(cid) => RcDoc::text("@").append(cid),
+        None => RcDoc::nil(),
+    };
+
+    RcDoc::concat([
+        RcDoc::as_string(id),
+        cid_doc,
+        RcDoc::text("("),
+        args_doc,
+        RcDoc::text(")"),
+    ])
+}
@@ -1,11 +1,48 @@
+use pretty::RcDoc;
 use std::sync::Arc;
 
+use crate::{
+    logic::lint::{lint, Lint},
+    pretty::Pretty,
+};
+
 use super::{Clause, Declaration};
 
+/// How a program that negates relations within a recursive cycle is evaluated.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum Semantics {
+    /// Every program must be stratifiable, so that no relation depends
+    /// negatively on itself.
+    #[default]
+    Stratified,
+    /// Strata that negate relations within a recursive cycle are evaluated to
+    /// their well-founded model, in which each tuple is either true or undefined.
+    WellFounded,
+}
+
+/// How a program's relations are kept up to date as its inputs change between
+/// epochs.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum Evaluation {
+    /// Each stratum is maintained from the changes to the relations it depends
+    /// on, deleting and rederiving tuples when its inputs are retracted, and only
+    /// recomputed from scratch when a relation it negates or aggregates over
+    /// changes.
+    #[default]
+    Incremental,
+    /// Every stratum is recomputed from scratch each epoch, from the inputs
+    /// accumulated so far. Slower, but each epoch's result is exactly that of
+    /// evaluating the program over every input at once.
+    Recompute,
+}
+
 #[derive(Debug)]
 pub struct Program {
     declarations: Vec<Arc<Declaration>>,
     clauses: Vec<Clause>,
+    semantics: Semantics,
+    evaluation: Evaluation,
+    provenance: bool,
 }
 
 impl Program {
@@ -13,9 +50,27 @@ impl Program {
         Self {
             declarations,
             clauses,
+            semantics: Semantics::default(),
+            evaluation: Evaluation::default(),
+            provenance: false,
         }
     }
 
+    pub fn with_semantics(mut self, semantics: Semantics) -> Self {
+        self.semantics = semantics;
+        self
+    }
+
+    pub fn with_evaluation(mut self, evaluation: Evaluation) -> Self {
+        self.evaluation = evaluation;
+        self
+    }
+
+    pub fn with_provenance(mut self, provenance: bool) -> Self {
+        self.provenance = provenance;
+        self
+    }
+
     pub fn declarations(&self) -> &[Arc<Declaration>] {
         &self.declarations
     }
@@ -23,4 +78,50 @@ impl Program {
     pub fn clauses(&self) -> &[Clause] {
         &self.clauses
     }
+
+    pub fn semantics(&self) -> Semantics {
+        self.semantics
+    }
+
+    pub fn evaluation(&self) -> Evaluation {
+        self.evaluation
+    }
+
+    pub fn provenance(&self) -> bool {
+        self.provenance
+    }
+
+    /// Checks the program for likely mistakes, such as relations that are never
+    /// used or can never contain a tuple.
+    pub fn lint(&self) -> Vec<Lint> {
+        lint(&self.declarations, &self.clauses)
+    }
+}
+
+impl Pretty for Program {
+    fn to_doc(&self) -> RcDoc<'_, ()> {
+        let mut declarations = self.declarations().iter().collect::<Vec<_>>();
+        declarations.sort_by_key(|declaration| declaration.id().resolve());
+
+        let declarations_doc = RcDoc::intersperse(
+            declarations
+                .into_iter()
+                .map(|declaration| declaration.to_doc()),
+            RcDoc::hardline(),
+        );
+
+        if self.clauses().is_empty() {
+            return declarations_doc;
+        }
+
+        let clauses_doc = RcDoc::intersperse(
+            self.clauses().iter().map(|clause| clause.to_doc()),
+            RcDoc::hardline(),
+        );
+
+        declarations_doc
+            .append(RcDoc::hardline())
+            .append(RcDoc::hardline())
+            .append(clauses_doc)
+    }
 }
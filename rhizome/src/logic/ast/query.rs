@@ -0,0 +1,25 @@
+use crate::var::Var;
+
+use super::BodyTerm;
+
+/// A query against the relations of a program, whose solutions are the values
+/// of its variables that satisfy the body.
+#[derive(Debug)]
+pub struct Query {
+    body: Vec<BodyTerm>,
+    vars: Vec<Var>,
+}
+
+impl Query {
+    pub fn new(body: Vec<BodyTerm>, vars: Vec<Var>) -> Self {
+        Self { body, vars }
+    }
+
+    pub fn body(&self) -> &[BodyTerm] {
+        &self.body
+    }
+
+    pub fn vars(&self) -> &[Var] {
+        &self.vars
+    }
+}
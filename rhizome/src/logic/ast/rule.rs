@@ -1,11 +1,13 @@
+use pretty::RcDoc;
 use std::collections::HashMap;
 
 use crate::{
     col_val::ColVal,
     id::{ColId, RelationId},
+    pretty::Pretty,
 };
 
-use super::{Aggregation, BodyTerm, Negation, RelPredicate, VarPredicate};
+use super::{atom_doc, Aggregation, BodyTerm, Computation, Negation, RelPredicate, VarPredicate};
 
 #[derive(Debug)]
 pub struct Rule {
@@ -82,4 +84,34 @@ impl Rule {
             })
             .collect()
     }
+
+    pub fn computation_terms(&self) -> Vec<&Computation> {
+        self.body
+            .iter()
+            .filter_map(|term| {
+                if let BodyTerm::Computation(inner) = term {
+                    Some(inner)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+impl Pretty for Rule {
+    fn to_doc(&self) -> RcDoc<'_, ()> {
+        let body_doc = RcDoc::intersperse(
+            self.body().iter().map(|term| term.to_doc()),
+            RcDoc::text(",").append(RcDoc::line()),
+        );
+
+        RcDoc::concat([
+            atom_doc(self.head, None, &self.args),
+            RcDoc::text(" :-"),
+            RcDoc::line().append(body_doc).nest(2),
+            RcDoc::text("."),
+        ])
+        .group()
+    }
 }
@@ -3,17 +3,33 @@ use std::collections::HashMap;
 use crate::{
     col::Col,
     id::{ColId, RelationId},
+    lattice::Monotonicity,
 };
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Schema {
     id: RelationId,
     cols: HashMap<ColId, Col>,
+    lattice: Option<ColId>,
+    lattice_monotonicity: Option<Monotonicity>,
 }
 
 impl Schema {
     pub fn new(id: RelationId, cols: HashMap<ColId, Col>) -> Self {
-        Self { id, cols }
+        Self {
+            id,
+            cols,
+            lattice: None,
+            lattice_monotonicity: None,
+        }
+    }
+
+    /// Marks `col` as the lattice column, which holds the join of the values
+    /// derived for each combination of the other columns.
+    pub fn with_lattice(mut self, col: ColId, monotonicity: Option<Monotonicity>) -> Self {
+        self.lattice = Some(col);
+        self.lattice_monotonicity = monotonicity;
+        self
     }
 
     pub fn id(&self) -> RelationId {
@@ -31,4 +47,13 @@ impl Schema {
     pub fn cols(&self) -> &HashMap<ColId, Col> {
         &self.cols
     }
+
+    pub fn lattice(&self) -> Option<ColId> {
+        self.lattice
+    }
+
+    /// The direction in which joining moves the values of the lattice column.
+    pub fn lattice_monotonicity(&self) -> Option<Monotonicity> {
+        self.lattice_monotonicity
+    }
 }
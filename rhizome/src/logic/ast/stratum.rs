@@ -9,6 +9,7 @@ pub(crate) struct Stratum<'a> {
     relations: HashSet<RelationId>,
     clauses: Vec<&'a Clause>,
     is_recursive: bool,
+    is_well_founded: bool,
 }
 
 impl<'a> Stratum<'a> {
@@ -16,11 +17,13 @@ impl<'a> Stratum<'a> {
         relations: HashSet<RelationId>,
         clauses: Vec<&'a Clause>,
         is_recursive: bool,
+        is_well_founded: bool,
     ) -> Self {
         Self {
             relations,
             clauses,
             is_recursive,
+            is_well_founded,
         }
     }
 
@@ -32,6 +35,12 @@ impl<'a> Stratum<'a> {
         self.is_recursive
     }
 
+    /// Whether the stratum negates its own relations, and so must be evaluated
+    /// under the well-founded semantics.
+    pub(crate) fn is_well_founded(&self) -> bool {
+        self.is_well_founded
+    }
+
     pub(crate) fn facts(&self) -> Vec<&Fact> {
         self.clauses
             .iter()
@@ -1,87 +1,72 @@
-use anyhow::Result;
 use std::{collections::HashMap, fmt::Debug, sync::Arc};
 
-use crate::{
-    col_val::ColVal,
-    error::{error, Error},
-    id::{ColId, VarId},
-    logic::ast::{Aggregation, Declaration},
-    types::ColType,
-    var::Var,
-};
+use crate::{error::Error, id::VarId, logic::ast::Aggregation, types::ColType, var::Var};
 
 use crate::aggregation::AggregateWrapper;
 
+use super::rule_body::RuleBodyBuilder;
+
 pub(crate) struct AggregationBuilder {
     pub(super) target: Var,
     pub(super) vars: Vec<Var>,
-    pub(super) bindings: Vec<(ColId, ColVal)>,
+    pub(super) body: RuleBodyBuilder,
     pub(super) agg: Arc<dyn AggregateWrapper>,
 }
 
 impl AggregationBuilder {
-    pub(crate) fn new(target: Var, f: Arc<dyn AggregateWrapper>) -> Self {
+    pub(crate) fn new(
+        target: Var,
+        vars: Vec<Var>,
+        body: RuleBodyBuilder,
+        f: Arc<dyn AggregateWrapper>,
+    ) -> Self {
         Self {
             target,
+            vars,
+            body,
             agg: f,
-            vars: Vec::default(),
-            bindings: Vec::default(),
         }
     }
+
+    /// Finalizes the aggregation, adding any errors in it to `errors`. Its target
+    /// is bound either way, so that its errors aren't repeated by the terms that
+    /// use it.
     pub(crate) fn finalize(
         self,
-        relation: Arc<Declaration>,
         bound_vars: &mut HashMap<VarId, ColType>,
-    ) -> Result<Aggregation> {
-        if bound_vars
-            .insert(self.target.id(), self.target.typ())
-            .is_some()
-        {
-            return error(Error::AggregationBoundTarget(self.target.id()));
+        errors: &mut Vec<anyhow::Error>,
+    ) -> Option<Aggregation> {
+        if bound_vars.contains_key(&self.target.id()) {
+            errors.push(Error::AggregationBoundTarget(self.target.id()).into());
+
+            return None;
         }
 
-        let mut cols = HashMap::default();
+        bound_vars.insert(self.target.id(), self.target.typ());
 
-        for (col_id, col_val) in self.bindings {
-            let schema = relation.schema();
+        // Each combination of alternatives would only see part of the groups
+        if !self.body.disjunctions().is_empty() {
+            errors.push(Error::AggregationDisjunction.into());
 
-            let Some(col) = schema.get_col(&col_id) else {
-                return error(Error::UnrecognizedColumnBinding(relation.id(), col_id));
-            };
+            return None;
+        }
 
-            if cols.contains_key(&col_id) {
-                return error(Error::ConflictingColumnBinding(relation.id(), col_id));
-            }
+        // Variables bound in the body of the aggregation aren't visible outside of it
+        let mut body_bound_vars = bound_vars.clone();
+        let count = errors.len();
+        let body = self.body.finalize_all(&mut body_bound_vars, errors);
 
-            match &col_val {
-                ColVal::Lit(val) => {
-                    if col.col_type().check(val).is_err() {
-                        return error(Error::ColumnValueTypeConflict(
-                            relation.id(),
-                            col_id,
-                            col_val,
-                            *col.col_type(),
-                        ));
-                    }
-                }
-                ColVal::Binding(var) => {
-                    if col.col_type().unify(&var.typ()).is_err() {
-                        return error(Error::ColumnValueTypeConflict(
-                            relation.id(),
-                            col_id,
-                            ColVal::Binding(*var),
-                            *col.col_type(),
-                        ));
-                    }
-                }
+        for var in &self.vars {
+            if !body_bound_vars.contains_key(&var.id()) {
+                errors.push(Error::ClauseNotDomainIndependent(var.id()).into());
             }
-
-            cols.insert(col_id, col_val);
         }
 
-        let aggregation = Aggregation::new(self.target, self.vars, relation, cols, self.agg);
+        if errors.len() > count {
+            return None;
+        }
 
-        Ok(aggregation)
+        Some(Aggregation::new(self.target, self.vars, body, self.agg))
     }
 }
 
@@ -90,7 +75,7 @@ impl Debug for AggregationBuilder {
         f.debug_struct("AggregationBuilder")
             .field("target", &self.target)
             .field("vars", &self.vars)
-            .field("bindings", &self.bindings)
+            .field("body", &self.body)
             .finish()
     }
 }
@@ -1,4 +1,11 @@
-use crate::{col_val::ColVal, id::ColId, types::IntoColType, value::Val, var::TypedVar};
+use crate::{
+    col_val::ColVal,
+    expr::Expr,
+    id::ColId,
+    types::IntoColType,
+    value::Val,
+    var::{TypedVar, Wildcard},
+};
 
 pub trait AtomBinding {
     fn into_pair(self) -> (ColId, ColVal);
@@ -23,3 +30,30 @@ where
         (self.0.into(), ColVal::Lit(self.1.into()))
     }
 }
+
+impl<C> AtomBinding for (C, ColVal)
+where
+    C: Into<ColId>,
+{
+    fn into_pair(self) -> (ColId, ColVal) {
+        (self.0.into(), self.1)
+    }
+}
+
+impl<C> AtomBinding for (C, Expr)
+where
+    C: Into<ColId>,
+{
+    fn into_pair(self) -> (ColId, ColVal) {
+        (self.0.into(), ColVal::Expr(self.1))
+    }
+}
+
+impl<C> AtomBinding for (C, Wildcard)
+where
+    C: Into<ColId>,
+{
+    fn into_pair(self) -> (ColId, ColVal) {
+        (self.0.into(), ColVal::Wildcard)
+    }
+}
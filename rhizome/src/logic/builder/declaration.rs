@@ -5,8 +5,9 @@ use crate::{
     col::Col,
     error::{error, Error},
     id::{ColId, RelationId},
+    lattice::{ColJoin, ColLattice, LatticeJoin},
     logic::ast::{Declaration, Schema},
-    relation::{DefaultRelation, Relation, Source},
+    relation::{DefaultRelation, LatticeRelation, Relation, Source},
     types::{ColType, IntoColType},
 };
 
@@ -14,6 +15,7 @@ use crate::{
 pub struct DeclarationBuilder<R = DefaultRelation> {
     id: RelationId,
     cols: Vec<(ColId, Col)>,
+    lattices: Vec<(ColId, Arc<dyn LatticeJoin>)>,
     source: Source,
     _marker: std::marker::PhantomData<R>,
 }
@@ -26,6 +28,7 @@ where
         Self {
             id,
             cols: Vec::default(),
+            lattices: Vec::default(),
             source,
             _marker: std::marker::PhantomData,
         }
@@ -42,9 +45,20 @@ where
             cols.insert(col_id, col);
         }
 
-        let schema = Schema::new(self.id, cols);
-        let declaration =
-            Declaration::new(self.id, Arc::new(schema), self.source, Box::<R>::default());
+        let mut schema = Schema::new(self.id, cols);
+        let mut relation: Box<dyn Relation> = Box::<R>::default();
+
+        let mut lattices = self.lattices.into_iter();
+        if let Some((col_id, lattice)) = lattices.next() {
+            if let Some((duplicate, _)) = lattices.next() {
+                return error(Error::DuplicateLatticeCol(self.id, duplicate));
+            }
+
+            schema = schema.with_lattice(col_id, lattice.monotonicity());
+            relation = Box::new(LatticeRelation::new(col_id, lattice));
+        }
+
+        let declaration = Declaration::new(self.id, Arc::new(schema), self.source, relation);
 
         Ok(declaration)
     }
@@ -56,12 +70,28 @@ where
         f(Self::new(id, source)).finalize()
     }
 
-    pub fn column<C>(mut self, id: &str) -> Self
+    pub fn column<C>(self, id: &str) -> Self
     where
         C: IntoColType,
     {
+        self.column_of_type(id, ColType::new::<C>())
+    }
+
+    /// Declares a column holding values of the lattice `L`. The relation keeps a
+    /// single tuple for each combination of its other columns, whose value in
+    /// this column is the join of every value derived for it, regardless of `R`.
+    pub fn lattice<L>(mut self, id: &str) -> Self
+    where
+        L: ColLattice,
+    {
+        self.lattices
+            .push((ColId::new(id), Arc::new(ColJoin::<L>::default())));
+
+        self.column_of_type(id, ColType::new::<L::Value>())
+    }
+
+    pub(crate) fn column_of_type(mut self, id: &str, t: ColType) -> Self {
         let id = ColId::new(id);
-        let t = ColType::new::<C>();
         let col = Col::new(id, t);
 
         self.cols.push((id, col));
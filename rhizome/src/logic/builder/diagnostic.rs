@@ -0,0 +1,153 @@
+use std::fmt::{self, Display};
+
+use crate::{
+    error::Error,
+    id::{ColId, RelationId, VarId},
+};
+
+/// An error found while building a program, along with where it was found.
+/// Diagnostics are only collected once [`collect_diagnostics`] is called on
+/// the [`ProgramBuilder`], otherwise building stops at the first error.
+///
+/// [`collect_diagnostics`]: super::ProgramBuilder::collect_diagnostics
+/// [`ProgramBuilder`]: super::ProgramBuilder
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    kind: Error,
+    clause: Option<usize>,
+    relation: Option<RelationId>,
+    suggestion: Option<String>,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(
+        kind: Error,
+        clause: Option<usize>,
+        relation: Option<RelationId>,
+        suggestion: Option<String>,
+    ) -> Self {
+        Self {
+            kind,
+            clause,
+            relation,
+            suggestion,
+        }
+    }
+
+    /// The error that was found.
+    pub fn kind(&self) -> &Error {
+        &self.kind
+    }
+
+    /// The index of the rule or fact the error was found in, in the order they
+    /// were added to the program. Errors in declarations have no index.
+    pub fn clause(&self) -> Option<usize> {
+        self.clause
+    }
+
+    /// The relation at the head of the rule or fact, or being declared.
+    pub fn relation(&self) -> Option<RelationId> {
+        self.relation
+    }
+
+    /// The column the error refers to, if any.
+    pub fn col(&self) -> Option<ColId> {
+        match self.kind {
+            Error::ClauseNotRangeRestricted(col, _)
+            | Error::DuplicateDeclarationCol(_, col)
+            | Error::DuplicateLatticeCol(_, col)
+            | Error::ConflictingColumnBinding(_, col)
+            | Error::UnrecognizedColumnBinding(_, col)
+            | Error::ColumnMissing(_, col)
+            | Error::ColumnValueTypeConflict(_, col, _, _)
+            | Error::NonGroundFact(_, col, _)
+            | Error::AggregationUnboundGroupBy(_, col, _)
+            | Error::UnexpectedExpression(_, col, _)
+            | Error::UnexpectedWildcard(_, col) => Some(col),
+            _ => None,
+        }
+    }
+
+    /// The variable the error refers to, if any.
+    pub fn var(&self) -> Option<VarId> {
+        match self.kind {
+            Error::ClauseNotRangeRestricted(_, var)
+            | Error::ClauseNotDomainIndependent(var)
+            | Error::NonGroundFact(_, _, var)
+            | Error::AggregationUnboundGroupBy(var, _, _)
+            | Error::AggregationBoundTarget(var)
+            | Error::ComputationBoundTarget(var) => Some(var),
+            _ => None,
+        }
+    }
+
+    /// A suggested fix for the error, such as the name of a similar column.
+    pub fn suggestion(&self) -> Option<&str> {
+        self.suggestion.as_deref()
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.clause, self.relation) {
+            (Some(clause), Some(relation)) => write!(f, "in clause {clause}, for {relation}: ")?,
+            (None, Some(relation)) => write!(f, "in declaration of {relation}: ")?,
+            _ => (),
+        }
+
+        write!(f, "{}", self.kind)?;
+
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, "; {suggestion}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Finds the candidate closest to `name`, if any are close enough to be a typo.
+pub(crate) fn closest<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+/// The Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+
+        for (j, b) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a != *b);
+
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_closest() {
+        assert_eq!(Some("from"), closest("fro", ["from", "to"]));
+        assert_eq!(Some("to"), closest("tp", ["from", "to"]));
+        assert_eq!(None, closest("weight", ["from", "to"]));
+    }
+}
@@ -0,0 +1,43 @@
+use anyhow::Result;
+use std::cell::Cell;
+
+use super::rule_body::RuleBodyBuilder;
+
+/// Collects the alternatives of a disjunction in the body of a rule.
+///
+/// A rule is built once for each combination of alternatives, and only the
+/// selected alternative of each disjunction adds its terms to the body.
+#[derive(Debug)]
+pub struct DisjunctionBuilder<'a> {
+    body: &'a RuleBodyBuilder,
+    selected: usize,
+    branches: Cell<usize>,
+}
+
+impl<'a> DisjunctionBuilder<'a> {
+    pub(super) fn new(body: &'a RuleBodyBuilder, selected: usize) -> Self {
+        Self {
+            body,
+            selected,
+            branches: Cell::default(),
+        }
+    }
+
+    pub(super) fn branches(&self) -> usize {
+        self.branches.get()
+    }
+
+    pub fn or<F>(&self, f: F) -> Result<()>
+    where
+        F: FnOnce(&RuleBodyBuilder) -> Result<()>,
+    {
+        let branch = self.branches.get();
+        self.branches.set(branch + 1);
+
+        if branch == self.selected {
+            f(self.body)
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -61,6 +61,16 @@ impl FactBuilder {
                 ColVal::Binding(var) => {
                     return error(Error::NonGroundFact(self.relation.id(), col_id, var.id()));
                 }
+                ColVal::Expr(_) => {
+                    return error(Error::UnexpectedExpression(
+                        self.relation.id(),
+                        col_id,
+                        col_val,
+                    ));
+                }
+                ColVal::Wildcard => {
+                    return error(Error::UnexpectedWildcard(self.relation.id(), col_id));
+                }
             }
         }
 
@@ -3,19 +3,25 @@ use anyhow::Result;
 use crate::ram::Program;
 
 pub use self::{
-    atom_binding::AtomBinding, atom_bindings::AtomBindings, program::ProgramBuilder,
-    rule_body::RuleBodyBuilder, rule_vars::RuleVars,
+    atom_binding::AtomBinding, atom_bindings::AtomBindings, diagnostic::Diagnostic,
+    disjunction::DisjunctionBuilder, program::ProgramBuilder, rule_body::RuleBodyBuilder,
+    rule_vars::RuleVars,
 };
 
+pub(crate) use self::{program::PREAMBLE, query::build_query};
+
 use super::lower_to_ram;
 
 mod aggregation;
 mod atom_binding;
 mod atom_bindings;
 mod declaration;
+mod diagnostic;
+mod disjunction;
 mod fact;
 mod negation;
 mod program;
+mod query;
 mod rel_predicate;
 mod rule_body;
 mod rule_head;
@@ -26,11 +32,24 @@ where
     F: FnOnce(ProgramBuilder) -> Result<ProgramBuilder>,
 {
     let logic = ProgramBuilder::build(f)?;
+
+    for lint in logic.lint() {
+        tracing::warn!("{lint}");
+    }
+
     let ram = lower_to_ram::lower_to_ram(&logic)?;
 
     Ok(ram)
 }
 
+pub fn parse(source: &str) -> Result<Program> {
+    build(|p| {
+        p.parse(source)?;
+
+        Ok(p)
+    })
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -50,11 +69,14 @@ mod tests {
         assert_compile, assert_compile_err,
         col_val::ColVal,
         error::Error,
-        kernel::math,
+        expr::{Expr, Op},
+        kernel::{self, math},
+        lattice::{Max, Min},
+        logic::{Cycle, Diagnostic, Lint, NegativeDependency, ProgramBuilder, Semantics},
         predicate::Predicate,
         types::{Any, ColType, RhizomeType, Type},
         value::Val,
-        var::{TypedVar, Var},
+        var::{TypedVar, Var, Wildcard},
     };
 
     #[test]
@@ -202,7 +224,46 @@ mod tests {
 
     #[test]
     fn test_cyclic_negation() {
-        assert_compile_err!(&Error::ProgramUnstratifiable, |p| {
+        assert_compile_err!(
+            &Error::ProgramUnstratifiable(Cycle::new(
+                vec!["p".into(), "q".into()],
+                NegativeDependency::Negation,
+                vec![
+                    "q(q: x0) :- t(t: x0), !p(p: x0).".to_owned(),
+                    "p(p: x0) :- t(t: x0), !q(q: x0).".to_owned(),
+                ],
+            )),
+            |p| {
+                p.input("t", |h| h.column::<i32>("t"))?;
+
+                p.output("p", |h| h.column::<i32>("p"))?;
+                p.output("q", |h| h.column::<i32>("q"))?;
+
+                p.rule::<(i32,)>("p", &|h, b, (x,)| {
+                    h.bind((("p", x),))?;
+
+                    b.search("t", (("t", x),))?;
+                    b.except("q", (("q", x),))?;
+
+                    Ok(())
+                })?;
+
+                p.rule::<(i32,)>("q", &|h, b, (x,)| {
+                    h.bind((("q", x),))?;
+
+                    b.search("t", (("t", x),))?;
+                    b.except("p", (("p", x),))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            }
+        );
+
+        assert_compile!(|p| {
+            p.semantics(Semantics::WellFounded);
+
             p.input("t", |h| h.column::<i32>("t"))?;
 
             p.output("p", |h| h.column::<i32>("p"))?;
@@ -230,6 +291,133 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_recursive_aggregation() {
+        assert_compile!(|p| {
+            p.input("edge", |h| h.column::<i32>("from").column::<i32>("to"))?;
+            p.output("depth", |h| h.column::<i32>("id").lattice::<Max<i32>>("n"))?;
+
+            p.rule::<(i32, i32, i32, i32)>("depth", &|h, b, (x, y, d, n)| {
+                h.bind((("id", x), ("n", n)))?;
+
+                b.search("edge", (("from", x),))?;
+                b.aggregate(n, math::max(d), |b| {
+                    b.search("edge", (("from", x), ("to", y)))?;
+                    b.search("depth", (("id", y), ("n", d)))?;
+
+                    Ok(())
+                })?;
+
+                Ok(())
+            })?;
+
+            Ok(p)
+        });
+
+        assert_compile_err!(
+            &Error::ProgramUnstratifiable(Cycle::new(
+                vec!["degree".into()],
+                NegativeDependency::Aggregation,
+                vec!["degree(id: x0, n: x3) :- edge(from: x0), x3 = max(x2) : (edge(from: x0, to: x1), degree(id: x1, n: x2)).".to_owned()],
+            )),
+            |p| {
+                p.input("edge", |h| h.column::<i32>("from").column::<i32>("to"))?;
+                p.output("degree", |h| h.column::<i32>("id").column::<i32>("n"))?;
+
+                p.rule::<(i32, i32, i32, i32)>("degree", &|h, b, (x, y, d, n)| {
+                    h.bind((("id", x), ("n", n)))?;
+
+                    b.search("edge", (("from", x),))?;
+                    b.aggregate(n, math::max(d), |b| {
+                        b.search("edge", (("from", x), ("to", y)))?;
+                        b.search("degree", (("id", y), ("n", d)))?;
+
+                        Ok(())
+                    })?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            }
+        );
+
+        // min only ever decreases, but joining a Max lattice keeps the greatest value
+        assert_compile_err!(
+            &Error::ProgramUnstratifiable(Cycle::new(
+                vec!["depth".into()],
+                NegativeDependency::Aggregation,
+                vec!["depth(id: x0, n: x3) :- edge(from: x0), x3 = min(x2) : (edge(from: x0, to: x1), depth(id: x1, n: x2)).".to_owned()],
+            )),
+            |p| {
+                p.input("edge", |h| h.column::<i32>("from").column::<i32>("to"))?;
+                p.output("depth", |h| h.column::<i32>("id").lattice::<Max<i32>>("n"))?;
+
+                p.rule::<(i32, i32, i32, i32)>("depth", &|h, b, (x, y, d, n)| {
+                    h.bind((("id", x), ("n", n)))?;
+
+                    b.search("edge", (("from", x),))?;
+                    b.aggregate(n, math::min(d), |b| {
+                        b.search("edge", (("from", x), ("to", y)))?;
+                        b.search("depth", (("id", y), ("n", d)))?;
+
+                        Ok(())
+                    })?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            }
+        );
+
+        // The aggregate is monotone, but the negation within its body isn't
+        assert_compile_err!(
+            &Error::ProgramUnstratifiable(Cycle::new(
+                vec!["depth".into()],
+                NegativeDependency::Negation,
+                vec!["depth(id: x0, n: x3) :- edge(from: x0), x3 = max(x2) : (edge(from: x0, to: x1), depth(id: x1, n: x2), !depth(id: x0)).".to_owned()],
+            )),
+            |p| {
+                p.input("edge", |h| h.column::<i32>("from").column::<i32>("to"))?;
+                p.output("depth", |h| h.column::<i32>("id").lattice::<Max<i32>>("n"))?;
+
+                p.rule::<(i32, i32, i32, i32)>("depth", &|h, b, (x, y, d, n)| {
+                    h.bind((("id", x), ("n", n)))?;
+
+                    b.search("edge", (("from", x),))?;
+                    b.aggregate(n, math::max(d), |b| {
+                        b.search("edge", (("from", x), ("to", y)))?;
+                        b.search("depth", (("id", y), ("n", d)))?;
+                        b.except("depth", (("id", x),))?;
+
+                        Ok(())
+                    })?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            }
+        );
+    }
+
+    #[test]
+    fn test_duplicate_lattice_col() {
+        assert_compile_err!(
+            &Error::DuplicateLatticeCol("dist".into(), "hops".into()),
+            |p| {
+                p.output("dist", |h| {
+                    h.column::<u64>("to")
+                        .lattice::<Min<u64>>("dist")
+                        .lattice::<Min<u64>>("hops")
+                })?;
+
+                Ok(p)
+            }
+        );
+    }
+
     #[test]
     fn test_range_restriction() {
         assert_compile_err!(
@@ -250,6 +438,87 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_disjunction_errors() {
+        assert_compile_err!(
+            &Error::ClauseNotRangeRestricted("p0".into(), "x0".into()),
+            |p| {
+                p.output("p", |h| h.column::<i32>("p0"))?;
+                p.output("q", |h| h.column::<i32>("q0"))?;
+
+                p.rule::<(i32, i32)>("p", &|h, b, (x, y)| {
+                    h.bind((("p0", x),))?;
+                    b.any(|alt| {
+                        alt.or(|b| b.search("q", (("q0", x),)))?;
+                        alt.or(|b| b.search("q", (("q0", y),)))?;
+
+                        Ok(())
+                    })?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            }
+        );
+
+        assert_compile_err!(&Error::EmptyDisjunction, |p| {
+            p.output("p", |h| h.column::<i32>("p0"))?;
+
+            p.rule::<(i32,)>("p", &|h, b, (x,)| {
+                h.bind((("p0", x),))?;
+                b.search("p", (("p0", x),))?;
+                b.any(|_| Ok(()))?;
+
+                Ok(())
+            })?;
+
+            Ok(p)
+        });
+    }
+
+    #[test]
+    fn test_wildcard_errors() {
+        assert_compile_err!(&Error::UnexpectedWildcard("p".into(), "p0".into()), |p| {
+            p.output("p", |h| h.column::<i32>("p0"))?;
+            p.output("q", |h| h.column::<i32>("q0"))?;
+
+            p.rule::<()>("p", &|h, b, ()| {
+                h.bind((("p0", Wildcard),))?;
+                b.search("q", (("q0", Wildcard),))?;
+
+                Ok(())
+            })?;
+
+            Ok(p)
+        });
+
+        assert_compile_err!(&Error::UnexpectedWildcard("p".into(), "p0".into()), |p| {
+            p.output("p", |h| h.column::<i32>("p0"))?;
+            p.fact("p", |f| f.bind((("p0", Wildcard),)))?;
+
+            Ok(p)
+        });
+
+        assert_compile_err!(
+            &Error::UnrecognizedColumnBinding("q".into(), "q1".into()),
+            |p| {
+                p.output("p", |h| h.column::<i32>("p0"))?;
+                p.output("q", |h| h.column::<i32>("q0"))?;
+
+                p.rule::<(i32,)>("p", &|h, b, (x,)| {
+                    h.bind((("p0", x),))?;
+                    b.search("p", (("p0", x),))?;
+                    b.except("q", (("q1", Wildcard),))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            }
+        );
+    }
+
     #[test]
     fn test_domain_independence() {
         assert_compile_err!(&Error::ClauseNotDomainIndependent("x0".into()), |p| {
@@ -315,6 +584,152 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_lint() {
+        let program = ProgramBuilder::build(|p| {
+            p.input("edge", |h| h.column::<i32>("from").column::<i32>("to"))?;
+            p.input("unused", |h| h.column::<i32>("id"))?;
+
+            p.output("path", |h| h.column::<i32>("from").column::<i32>("to"))?;
+            p.output("cycle", |h| h.column::<i32>("id"))?;
+            p.output("empty", |h| h.column::<i32>("id"))?;
+            p.output("blocked", |h| h.column::<i32>("id"))?;
+            p.output("label", |h| h.column::<Any>("name"))?;
+
+            p.rule::<(i32, i32)>("path", &|h, b, (x, y)| {
+                h.bind((("from", x), ("to", y)))?;
+                b.search("edge", (("from", x), ("to", y)))?;
+
+                Ok(())
+            })?;
+
+            p.rule::<(i32,)>("cycle", &|h, b, (x,)| {
+                h.bind((("id", x),))?;
+                b.search("cycle", (("id", x),))?;
+
+                Ok(())
+            })?;
+
+            p.rule::<(i32,)>("blocked", &|h, b, (x,)| {
+                h.bind((("id", x),))?;
+                b.search("edge", (("from", x),))?;
+                b.search("empty", (("id", x),))?;
+
+                Ok(())
+            })?;
+
+            p.fact("label", |f| f.bind((("name", "a"),)))?;
+
+            Ok(p)
+        })
+        .unwrap();
+
+        assert_eq!(
+            vec![
+                Lint::UnreachableRelation("blocked".into()),
+                Lint::UnreachableRelation("cycle".into()),
+                Lint::UnusedOutput("empty".into()),
+                Lint::UnusedInput("unused".into()),
+                Lint::EmptyDependency(1, "cycle".into(), "cycle".into()),
+                Lint::EmptyDependency(2, "blocked".into(), "empty".into()),
+                Lint::InferableColType("label".into(), "name".into(), Type::String),
+            ],
+            program.lint()
+        );
+    }
+
+    #[test]
+    fn test_collect_diagnostics() {
+        assert_compile_err!(
+            &Error::InvalidProgram(vec![
+                Diagnostic::new(
+                    Error::DuplicateDeclarationCol("node".into(), "id".into()),
+                    None,
+                    Some("node".into()),
+                    None,
+                ),
+                Diagnostic::new(
+                    Error::UnrecognizedColumnBinding("edge".into(), "fro".into()),
+                    Some(0),
+                    Some("path".into()),
+                    Some("did you mean `from`?".to_owned()),
+                ),
+                Diagnostic::new(
+                    Error::UnrecognizedRelation("pat".into()),
+                    Some(1),
+                    Some("path".into()),
+                    Some("did you mean `path`?".to_owned()),
+                ),
+                Diagnostic::new(
+                    Error::ClauseNotRangeRestricted("to".into(), "x1".into()),
+                    Some(3),
+                    Some("path".into()),
+                    None,
+                ),
+                Diagnostic::new(
+                    Error::UnrecognizedRelation("pat".into()),
+                    Some(4),
+                    Some("path".into()),
+                    Some("did you mean `path`?".to_owned()),
+                ),
+                Diagnostic::new(
+                    Error::UnrecognizedColumnBinding("path".into(), "too".into()),
+                    Some(4),
+                    Some("path".into()),
+                    Some("did you mean `to`?".to_owned()),
+                ),
+                Diagnostic::new(
+                    Error::ColumnMissing("path".into(), "to".into()),
+                    Some(4),
+                    Some("path".into()),
+                    Some("bind `to` in the head".to_owned()),
+                ),
+            ]),
+            |p| {
+                p.collect_diagnostics();
+
+                p.input("edge", |h| h.column::<i32>("from").column::<i32>("to"))?;
+                p.output("path", |h| h.column::<i32>("from").column::<i32>("to"))?;
+                p.output("node", |h| h.column::<i32>("id").column::<i32>("id"))?;
+
+                p.rule::<(i32, i32)>("path", &|h, b, (x, y)| {
+                    h.bind((("from", x), ("to", y)))?;
+                    b.search("edge", (("fro", x), ("to", y)))?;
+
+                    Ok(())
+                })?;
+
+                p.rule::<(i32, i32, i32)>("path", &|h, b, (x, y, z)| {
+                    h.bind((("from", x), ("to", z)))?;
+                    b.search("pat", (("from", x), ("to", y)))?;
+                    b.search("edge", (("from", y), ("to", z)))?;
+
+                    Ok(())
+                })?;
+
+                p.fact("path", |f| f.bind((("from", 0), ("to", 0))))?;
+
+                p.rule::<(i32, i32)>("path", &|h, b, (x, y)| {
+                    h.bind((("from", x), ("to", y)))?;
+                    b.search("edge", (("from", x),))?;
+
+                    Ok(())
+                })?;
+
+                // Every error in a rule is found, not just the first
+                p.rule::<(i32, i32)>("path", &|h, b, (x, y)| {
+                    h.bind((("from", x), ("too", y)))?;
+                    b.search("edge", (("from", x), ("to", y)))?;
+                    b.except("pat", (("from", x),))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            }
+        );
+    }
+
     #[test]
     fn test_duplicate_input_declaration_column() {
         assert_compile_err!(
@@ -1472,6 +1887,99 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_aggregate_nested_body_errors() {
+        assert_compile!(|p| {
+            p.input("edge", |h| h.column::<i32>("from").column::<i32>("to"))?;
+            p.output("degree", |h| h.column::<i32>("id").column::<i32>("n"))?;
+
+            p.rule::<(i32, i32, i32)>("degree", &|h, b, (x, y, n)| {
+                h.bind((("id", x), ("n", n)))?;
+
+                b.search("edge", (("from", x),))?;
+                b.aggregate(n, math::count(), |b| {
+                    b.search("edge", (("from", x), ("to", y)))?;
+                    b.except("edge", (("from", y), ("to", x)))?;
+
+                    Ok(())
+                })?;
+
+                Ok(())
+            })?;
+
+            Ok(p)
+        });
+
+        assert_compile_err!(
+            &Error::ClauseNotRangeRestricted("y".into(), "x1".into()),
+            |p| {
+                p.input("edge", |h| h.column::<i32>("from").column::<i32>("to"))?;
+                p.output("sum", |h| h.column::<i32>("x").column::<i32>("y"))?;
+
+                p.rule::<(i32, i32, i32)>("sum", &|h, b, (x, y, n)| {
+                    h.bind((("x", x), ("y", y)))?;
+
+                    b.search("edge", (("from", x),))?;
+                    b.aggregate(n, math::sum(y), |b| {
+                        b.search("edge", (("from", x), ("to", y)))
+                    })?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            }
+        );
+
+        assert_compile_err!(&Error::AggregationDisjunction, |p| {
+            p.input("edge", |h| h.column::<i32>("from").column::<i32>("to"))?;
+            p.output("degree", |h| h.column::<i32>("id").column::<i32>("n"))?;
+
+            p.rule::<(i32, i32, i32)>("degree", &|h, b, (x, y, n)| {
+                h.bind((("id", x), ("n", n)))?;
+
+                b.search("edge", (("from", x),))?;
+                b.aggregate(n, math::count(), |b| {
+                    b.any(|alt| {
+                        alt.or(|b| b.search("edge", (("from", x), ("to", y))))?;
+                        alt.or(|b| b.search("edge", (("from", y), ("to", x))))?;
+
+                        Ok(())
+                    })
+                })?;
+
+                Ok(())
+            })?;
+
+            Ok(p)
+        });
+    }
+
+    #[test]
+    fn test_percentile_errors() {
+        for p in [-1.0, 100.5, f64::NAN] {
+            assert_compile_err!(
+                &Error::InvalidAggregateParameter(
+                    "percentile".to_owned(),
+                    format!("expected p between 0 and 100, got {p}"),
+                ),
+                |b| {
+                    b.input("num", |h| h.column::<i32>("n"))?;
+                    b.output("p", |h| h.column::<i32>("n"))?;
+
+                    b.rule::<(i32, i32)>("p", &|h, b, (x, n)| {
+                        h.bind((("n", x),))?;
+                        b.group_by(x, "num", (("n", n),), math::percentile(n, p))?;
+
+                        Ok(())
+                    })?;
+
+                    Ok(b)
+                }
+            );
+        }
+    }
+
     #[test]
     fn test_array() {
         assert_compile!(|p| {
@@ -1626,4 +2134,135 @@ mod tests {
         #[predicate = IsTriangle]
         fn is_triangle<T: RhizomeType + Add<Output = T> + Ord>(a: T, b: T, z: T) -> T;
     }
+
+    #[test]
+    fn test_head_expression_errors() {
+        assert_compile_err!(&Error::TypeMismatch(Type::S32, Type::U32), |p| {
+            p.output("p", |h| h.column::<i32>("x"))?;
+
+            p.rule::<i32>("p", &|h, b, x| {
+                h.bind((("x", x + 1_u32),))?;
+                b.search("p", (("x", x),))?;
+
+                Ok(())
+            })?;
+
+            Ok(p)
+        });
+
+        // Untyped literals only take the other operand's type if their value fits in it
+        assert_compile_err!(&Error::TypeMismatch(Type::U8, Type::S32), |p| {
+            p.output("p", |h| h.column::<u8>("x"))?;
+
+            p.rule::<u8>("p", &|h, b, x| {
+                h.bind((("x", x + 300),))?;
+                b.search("p", (("x", x),))?;
+
+                Ok(())
+            })?;
+
+            Ok(p)
+        });
+
+        assert_compile_err!(&Error::OperatorTypeMismatch(Op::Add, Type::String), |p| {
+            p.output("p", |h| h.column::<&str>("x"))?;
+
+            p.rule::<&str>("p", &|h, b, x| {
+                h.bind((("x", x + "!"),))?;
+                b.search("p", (("x", x),))?;
+
+                Ok(())
+            })?;
+
+            Ok(p)
+        });
+
+        assert_compile_err!(
+            &Error::ClauseNotRangeRestricted("x".into(), "y".into()),
+            |p| {
+                p.output("p", |h| h.column::<i32>("x"))?;
+
+                p.rule::<(i32, i32)>("p", &|h, b, (x, _)| {
+                    h.bind((("x", x + TypedVar::<i32>::new("y")),))?;
+                    b.search("p", (("x", x),))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            }
+        );
+
+        assert_compile_err!(
+            &Error::ColumnValueTypeConflict(
+                "p".into(),
+                "x".into(),
+                ColVal::Expr(Expr::concat(["a", "b"])),
+                ColType::Type(Type::S32)
+            ),
+            |p| {
+                p.output("p", |h| h.column::<i32>("x"))?;
+
+                p.rule::<()>("p", &|h, _, ()| {
+                    h.bind((("x", Expr::concat(["a", "b"])),))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            }
+        );
+
+        assert_compile_err!(
+            &Error::UnexpectedExpression(
+                "p".into(),
+                "x".into(),
+                ColVal::Expr(TypedVar::<i32>::new("x0") + 1)
+            ),
+            |p| {
+                p.output("p", |h| h.column::<i32>("x"))?;
+
+                p.rule::<i32>("p", &|h, b, x| {
+                    h.bind((("x", x),))?;
+                    b.search("p", (("x", x + 1),))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            }
+        );
+    }
+
+    #[test]
+    fn test_computation_errors() {
+        assert_compile_err!(&Error::ComputationBoundTarget("x1".into()), |p| {
+            p.output("p", |h| h.column::<i32>("x"))?;
+
+            p.rule::<(i32, i32)>("p", &|h, b, (x, y)| {
+                h.bind((("x", y),))?;
+                b.search("p", (("x", x),))?;
+                b.compute(y, kernel::func(|(x,): (i32,)| x + 1), (x,))?;
+                b.compute(y, kernel::func(|(x,): (i32,)| x - 1), (x,))?;
+
+                Ok(())
+            })?;
+
+            Ok(p)
+        });
+
+        assert_compile_err!(&Error::ClauseNotDomainIndependent("x1".into()), |p| {
+            p.output("p", |h| h.column::<i32>("x"))?;
+
+            p.rule::<(i32, i32, i32)>("p", &|h, b, (x, y, z)| {
+                h.bind((("x", z),))?;
+                b.search("p", (("x", x),))?;
+                b.compute(z, kernel::func(|(x, y): (i32, i32)| x + y), (x, y))?;
+
+                Ok(())
+            })?;
+
+            Ok(p)
+        });
+    }
 }
@@ -51,11 +51,17 @@ impl NegationBuilder {
                         ));
                     }
                 }
+                ColVal::Expr(_) => {
+                    return error(Error::UnexpectedExpression(relation.id(), col_id, col_val));
+                }
+                ColVal::Wildcard => (),
             }
 
             cols.insert(col_id, col_val);
         }
 
+        cols.retain(|_, col_val| !col_val.is_wildcard());
+
         let negation = Negation::new(relation, cols);
 
         Ok(negation)
@@ -1,21 +1,38 @@
 use anyhow::Result;
 use cid::Cid;
-use std::{cell::RefCell, collections::HashMap, rc::Rc, sync::Arc};
+use std::{
+    cell::{Cell, Ref, RefCell},
+    collections::HashMap,
+    rc::Rc,
+    sync::Arc,
+};
 
 use crate::{
     error::{error, Error},
     id::RelationId,
-    logic::ast::{Clause, Declaration, Program, Rule},
+    kernel::Registry,
+    logic::{
+        ast::{Clause, Declaration, Evaluation, Program, Rule, Semantics},
+        lint::{lint, Lint},
+        parser,
+    },
     relation::{Bistore, Hexastore, Relation, Source},
     tuple::Tuple,
     types::Any,
 };
 
 use super::{
-    declaration::DeclarationBuilder, fact::FactBuilder, rule_body::RuleBodyBuilder,
-    rule_head::RuleHeadBuilder, rule_vars::RuleVars,
+    declaration::DeclarationBuilder,
+    diagnostic::{closest, Diagnostic},
+    fact::FactBuilder,
+    rule_body::{next_choices, RuleBodyBuilder},
+    rule_head::RuleHeadBuilder,
+    rule_vars::RuleVars,
 };
 
+/// The input relations declared in every program, which input tuples are added to.
+pub(crate) const PREAMBLE: [&str; 2] = ["evac", "links"];
+
 type RuleBuilderClosure<'a, T> =
     dyn Fn(&'_ RuleHeadBuilder, &'_ RuleBodyBuilder, T) -> Result<()> + 'a;
 
@@ -23,6 +40,12 @@ type RuleBuilderClosure<'a, T> =
 pub struct ProgramBuilder {
     relations: Rc<RefCell<HashMap<String, Arc<Declaration>>>>,
     clauses: RefCell<Vec<Clause>>,
+    semantics: Cell<Semantics>,
+    evaluation: Cell<Evaluation>,
+    provenance: Cell<bool>,
+    // Only collected once requested, and otherwise None
+    diagnostics: RefCell<Option<Vec<Diagnostic>>>,
+    clause_count: Cell<usize>,
 }
 
 impl ProgramBuilder {
@@ -37,12 +60,49 @@ impl ProgramBuilder {
     }
 
     pub fn finalize(self) -> Result<Program> {
+        if let Some(diagnostics) = self.diagnostics.take() {
+            if !diagnostics.is_empty() {
+                return error(Error::InvalidProgram(diagnostics));
+            }
+        }
+
         let declarations = self.relations.borrow_mut().values().cloned().collect();
-        let program = Program::new(declarations, self.clauses.into_inner());
+        let program = Program::new(declarations, self.clauses.into_inner())
+            .with_semantics(self.semantics.get())
+            .with_evaluation(self.evaluation.get())
+            .with_provenance(self.provenance.get());
 
         Ok(program)
     }
 
+    /// Sets how the program is evaluated if it negates a relation within a
+    /// recursive cycle, which is rejected by default.
+    pub fn semantics(&self, semantics: Semantics) {
+        self.semantics.set(semantics);
+    }
+
+    /// Sets how the program's relations are kept up to date as its inputs change
+    /// between epochs, which is incrementally by default.
+    pub fn evaluation(&self, evaluation: Evaluation) {
+        self.evaluation.set(evaluation);
+    }
+
+    /// Records the rule and tuples that derived each tuple, so that it can be
+    /// explained. This slows evaluation down and uses memory that's never freed,
+    /// so is off by default.
+    pub fn record_provenance(&self) {
+        self.provenance.set(true);
+    }
+
+    /// Keeps building the program after an invalid declaration, rule, or fact,
+    /// rather than returning the error. Every error found is then returned at
+    /// once by [`finalize`](Self::finalize), as a list of [`Diagnostic`]s.
+    pub fn collect_diagnostics(&self) {
+        self.diagnostics
+            .borrow_mut()
+            .get_or_insert_with(Vec::default);
+    }
+
     pub fn input<F>(&self, id: &str, f: F) -> Result<()>
     where
         F: FnOnce(DeclarationBuilder) -> DeclarationBuilder,
@@ -55,18 +115,9 @@ impl ProgramBuilder {
         R: Relation + Default,
         F: FnOnce(DeclarationBuilder<R>) -> DeclarationBuilder<R>,
     {
-        if let Some(relation) = self.relations.borrow().get(&id.to_owned()) {
-            return error(Error::ConflictingRelationDeclaration(relation.id()));
-        }
+        let result = self.declare(id, Source::Edb, f);
 
-        let rel_id = RelationId::new(id);
-        let relation = DeclarationBuilder::<R>::build(rel_id, Source::Edb, f)?;
-
-        self.relations
-            .borrow_mut()
-            .insert(id.to_owned(), Arc::new(relation));
-
-        Ok(())
+        self.recover(None, id, result)
     }
 
     pub fn output<F>(&self, id: &str, f: F) -> Result<()>
@@ -77,6 +128,36 @@ impl ProgramBuilder {
     }
 
     pub fn indexed_output<R, F>(&self, id: &str, f: F) -> Result<()>
+    where
+        R: Relation + Default,
+        F: FnOnce(DeclarationBuilder<R>) -> DeclarationBuilder<R>,
+    {
+        let result = self.declare(id, Source::Idb, f);
+
+        self.recover(None, id, result)
+    }
+
+    pub fn fact<F>(&self, id: &str, f: F) -> Result<()>
+    where
+        F: FnOnce(FactBuilder) -> FactBuilder,
+    {
+        let index = self.next_clause_index();
+        let result = self.add_fact(id, f);
+
+        self.recover(Some(index), id, result)
+    }
+
+    pub fn rule<T>(&self, id: &str, f: &RuleBuilderClosure<'_, T::Vars>) -> Result<()>
+    where
+        T: RuleVars,
+    {
+        let index = self.next_clause_index();
+        let errors = self.add_rule::<T>(id, f);
+
+        self.recover_all(Some(index), id, errors)
+    }
+
+    fn declare<R, F>(&self, id: &str, source: Source, f: F) -> Result<()>
     where
         R: Relation + Default,
         F: FnOnce(DeclarationBuilder<R>) -> DeclarationBuilder<R>,
@@ -86,7 +167,7 @@ impl ProgramBuilder {
         }
 
         let rel_id = RelationId::new(id);
-        let relation = DeclarationBuilder::<R>::build(rel_id, Source::Idb, f)?;
+        let relation = DeclarationBuilder::<R>::build(rel_id, source, f)?;
 
         self.relations
             .borrow_mut()
@@ -95,7 +176,7 @@ impl ProgramBuilder {
         Ok(())
     }
 
-    pub fn fact<F>(&self, id: &str, f: F) -> Result<()>
+    fn add_fact<F>(&self, id: &str, f: F) -> Result<()>
     where
         F: FnOnce(FactBuilder) -> FactBuilder,
     {
@@ -111,44 +192,172 @@ impl ProgramBuilder {
         Ok(())
     }
 
-    pub fn rule<T>(&self, id: &str, f: &RuleBuilderClosure<'_, T::Vars>) -> Result<()>
+    /// Adds the rule built by `f`, returning every error found in it.
+    fn add_rule<T>(&self, id: &str, f: &RuleBuilderClosure<'_, T::Vars>) -> Vec<anyhow::Error>
     where
         T: RuleVars,
     {
         let Some(declaration) = self.relations.borrow().get(id).cloned() else {
-            return error(Error::UnrecognizedRelation(id.to_string()));
+            return vec![Error::UnrecognizedRelation(id.to_string()).into()];
         };
 
-        let mut bound_vars = HashMap::default();
-        let head_builder = RuleHeadBuilder::new(Arc::clone(&declaration));
-        let body_builder = RuleBodyBuilder::new(Rc::clone(&self.relations));
+        if declaration.source() == Source::Edb {
+            return vec![Error::ClauseHeadEDB(declaration.id()).into()];
+        }
 
-        f(&head_builder, &body_builder, T::into_vars(0))?;
+        // Each combination of alternatives in the body's disjunctions is built as
+        // a separate rule, by walking the choices like an odometer. Disjunctions
+        // nested within an alternative are only discovered once it's chosen, so
+        // the number of alternatives is re-read after every build.
+        let mut rules = Vec::default();
+        let mut choices = Vec::default();
+
+        loop {
+            let mut bound_vars = HashMap::default();
+            let head_builder = RuleHeadBuilder::new(Arc::clone(&declaration));
+            let body_builder =
+                RuleBodyBuilder::with_choices(Rc::clone(&self.relations), choices.clone());
+
+            // The rest of the rule is left unbuilt, so finalizing it would only
+            // report errors caused by the missing part
+            if let Err(err) = f(&head_builder, &body_builder, T::into_vars(0)) {
+                return vec![err];
+            }
 
-        let body = body_builder.finalize(&mut bound_vars)?;
-        let head = head_builder.finalize(&mut bound_vars)?;
+            let disjunctions = body_builder.disjunctions();
+            let mut errors = Vec::default();
+            let body = body_builder.finalize_all(&mut bound_vars, &mut errors);
+            let head = head_builder.finalize_all(&mut bound_vars, &mut errors);
 
-        match declaration.source() {
-            Source::Edb => error(Error::ClauseHeadEDB(declaration.id())),
-            Source::Idb => {
-                let rule = Rule::new(declaration.id(), head, body);
-                let clause = Clause::Rule(rule);
+            // Each combination of alternatives shares most of its terms, so only the
+            // errors in the first invalid one are reported
+            if !errors.is_empty() {
+                return errors;
+            }
 
-                self.clauses.borrow_mut().push(clause);
+            rules.push(Rule::new(declaration.id(), head, body));
 
-                Ok(())
+            if !next_choices(&mut choices, &disjunctions) {
+                break;
             }
         }
+
+        self.clauses
+            .borrow_mut()
+            .extend(rules.into_iter().map(Clause::Rule));
+
+        Vec::default()
+    }
+
+    /// Adds the declarations and clauses in `source` to the program, resolving
+    /// predicates and aggregates by name with the default [`Registry`].
+    pub fn parse(&self, source: &str) -> Result<()> {
+        self.parse_with(source, &Registry::default())
+    }
+
+    pub fn parse_with(&self, source: &str, registry: &Registry) -> Result<()> {
+        let statements = parser::parse(source)?;
+
+        parser::lower(self, source, &statements, registry)
+    }
+
+    /// Checks the program built so far for likely mistakes.
+    pub fn lint(&self) -> Vec<Lint> {
+        let declarations: Vec<_> = self.relations.borrow().values().cloned().collect();
+
+        lint(&declarations, &self.clauses.borrow())
+    }
+
+    pub(crate) fn declaration(&self, id: &str) -> Option<Arc<Declaration>> {
+        self.relations.borrow().get(id).cloned()
+    }
+
+    fn next_clause_index(&self) -> usize {
+        let index = self.clause_count.get();
+        self.clause_count.set(index + 1);
+
+        index
+    }
+
+    /// The diagnostics collected so far, which is empty unless they're being
+    /// collected.
+    pub fn diagnostics(&self) -> Ref<'_, [Diagnostic]> {
+        Ref::map(self.diagnostics.borrow(), |diagnostics| {
+            diagnostics.as_deref().unwrap_or_default()
+        })
+    }
+
+    /// Records the error in `result` as a diagnostic, when diagnostics are being
+    /// collected and the error is one of Rhizome's.
+    fn recover(&self, clause: Option<usize>, relation: &str, result: Result<()>) -> Result<()> {
+        self.recover_all(clause, relation, result.err().into_iter().collect())
+    }
+
+    /// Records each of `errors` as a diagnostic, when diagnostics are being
+    /// collected and they're all Rhizome's. Otherwise, returns the first.
+    fn recover_all(
+        &self,
+        clause: Option<usize>,
+        relation: &str,
+        errors: Vec<anyhow::Error>,
+    ) -> Result<()> {
+        let mut errors = errors.into_iter();
+        let mut diagnostics = self.diagnostics.borrow_mut();
+
+        let Some(diagnostics) = diagnostics.as_mut() else {
+            return errors.next().map_or(Ok(()), Err);
+        };
+
+        for err in errors {
+            let kind = err.downcast::<Error>()?;
+            let suggestion = self.suggest(&kind);
+
+            diagnostics.push(Diagnostic::new(
+                kind,
+                clause,
+                Some(RelationId::new(relation)),
+                suggestion,
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn suggest(&self, kind: &Error) -> Option<String> {
+        match kind {
+            Error::UnrecognizedRelation(id) => {
+                let relations = self.relations.borrow();
+                let similar = closest(id, relations.keys().map(String::as_str))?;
+
+                Some(format!("did you mean `{similar}`?"))
+            }
+            Error::UnrecognizedColumnBinding(relation, col) => {
+                let declaration = self.declaration(&relation.resolve())?;
+                let cols: Vec<String> = declaration
+                    .schema()
+                    .cols()
+                    .keys()
+                    .map(|col| col.resolve())
+                    .collect();
+                let similar = closest(&col.resolve(), cols.iter().map(String::as_str))?;
+
+                Some(format!("did you mean `{similar}`?"))
+            }
+            Error::ColumnMissing(_, col) => Some(format!("bind `{col}` in the head")),
+            _ => None,
+        }
     }
 
     fn install_preamble(self) -> Result<Self> {
-        self.indexed_input::<Hexastore<Tuple>, _>("evac", |h| {
+        let [evac, links] = PREAMBLE;
+
+        self.indexed_input::<Hexastore<Tuple>, _>(evac, |h| {
             h.column::<Any>("entity")
                 .column::<Any>("attribute")
                 .column::<Any>("value")
         })?;
 
-        self.indexed_input::<Bistore<Tuple>, _>("links", |h| {
+        self.indexed_input::<Bistore<Tuple>, _>(links, |h| {
             h.column::<Cid>("from").column::<Cid>("to")
         })?;
 
@@ -0,0 +1,49 @@
+use anyhow::Result;
+use std::{cell::RefCell, collections::HashMap, rc::Rc, sync::Arc};
+
+use crate::{
+    logic::ast::{Declaration, Query},
+    var::Var,
+};
+
+use super::rule_body::{next_choices, RuleBodyBuilder};
+
+/// Builds a query from a rule body against the given relations, as a separate
+/// query for each combination of alternatives in its disjunctions. Each query's
+/// solutions bind every variable bound by its body.
+pub(crate) fn build_query<F>(
+    relations: HashMap<String, Arc<Declaration>>,
+    f: F,
+) -> Result<Vec<Query>>
+where
+    F: Fn(&RuleBodyBuilder) -> Result<()>,
+{
+    let relations = Rc::new(RefCell::new(relations));
+    let mut queries = Vec::default();
+    let mut choices = Vec::default();
+
+    loop {
+        let mut bound_vars = HashMap::default();
+        let body_builder = RuleBodyBuilder::with_choices(Rc::clone(&relations), choices.clone());
+
+        f(&body_builder)?;
+
+        let disjunctions = body_builder.disjunctions();
+        let body = body_builder.finalize(&mut bound_vars)?;
+
+        let mut vars: Vec<_> = bound_vars
+            .into_iter()
+            .map(|(id, typ)| Var::inferred(id, typ))
+            .collect();
+
+        vars.sort_by_key(|var| var.id().resolve());
+
+        queries.push(Query::new(body, vars));
+
+        if !next_choices(&mut choices, &disjunctions) {
+            break;
+        }
+    }
+
+    Ok(queries)
+}
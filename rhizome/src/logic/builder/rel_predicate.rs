@@ -1,6 +1,10 @@
 use anyhow::Result;
 use cid::Cid;
-use std::{cell::RefCell, collections::HashMap, sync::Arc};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 use crate::{
     col_val::ColVal,
@@ -9,6 +13,7 @@ use crate::{
     logic::ast::{CidValue, Declaration, RelPredicate},
     relation::Source,
     types::ColType,
+    var::Var,
 };
 
 use super::atom_binding::AtomBinding;
@@ -78,16 +83,54 @@ impl RelPredicateBuilder {
                         ));
                     }
                 }
+                ColVal::Expr(_) => {
+                    return error(Error::UnexpectedExpression(relation.id(), col_id, col_val));
+                }
+                ColVal::Wildcard => (),
             }
 
             cols.insert(col_id, col_val);
         }
 
+        cols.retain(|_, col_val| !col_val.is_wildcard());
+
         let predicate = RelPredicate::new(relation, self.cid, cols);
 
         Ok(predicate)
     }
 
+    /// The variables bound to the columns of the relation, or to its CID.
+    pub(super) fn vars(&self) -> Vec<Var> {
+        let cid = match &self.cid {
+            Some(CidValue::Var(var)) => Some(*var),
+            _ => None,
+        };
+
+        self.bindings
+            .borrow()
+            .iter()
+            .flat_map(|(_, col_val)| col_val.vars())
+            .chain(cid)
+            .collect()
+    }
+
+    /// Whether any of `vars` is bound to a column of the relation, or to its CID.
+    pub(super) fn binds_any(&self, vars: &HashSet<VarId>) -> bool {
+        if let Some(CidValue::Var(var)) = &self.cid {
+            if vars.contains(&var.id()) {
+                return true;
+            }
+        }
+
+        self.bindings
+            .borrow()
+            .iter()
+            .any(|(_, col_val)| match col_val {
+                ColVal::Binding(var) => vars.contains(&var.id()),
+                _ => false,
+            })
+    }
+
     pub fn bind_one<T>(&self, binding: T) -> Result<()>
     where
         T: AtomBinding,
@@ -4,23 +4,28 @@ use std::{cell::RefCell, collections::HashMap, fmt::Debug, rc::Rc, sync::Arc};
 use crate::{
     aggregation::{AggAcc, AggregateGroupBy, AggregateWrapper},
     args::Args,
+    col_val::ColVal,
     error::{error, Error},
-    id::VarId,
-    logic::ast::{BodyTerm, CidValue, Declaration, VarPredicate},
+    function::{Function, FunctionWhere, FunctionWrapper},
+    id::{ColId, VarId},
+    logic::ast::{BodyTerm, CidValue, Computation, Declaration, VarPredicate},
     predicate::{PredicateWhere, PredicateWrapper},
-    types::ColType,
+    typed_vars::TypedVars,
+    types::{ColType, IntoColType},
+    value::Val,
     var::{TypedVar, Var},
 };
 
 use super::{
-    aggregation::AggregationBuilder, atom_bindings::AtomBindings, negation::NegationBuilder,
-    rel_predicate::RelPredicateBuilder,
+    aggregation::AggregationBuilder, atom_bindings::AtomBindings, disjunction::DisjunctionBuilder,
+    negation::NegationBuilder, rel_predicate::RelPredicateBuilder,
 };
 
 type RelPredicates = Vec<(String, RelPredicateBuilder)>;
 type Negations = Vec<(String, NegationBuilder)>;
-type VarPredicates = Vec<(Vec<Var>, Arc<dyn PredicateWrapper>)>;
-type Aggregations = Vec<(String, AggregationBuilder)>;
+type VarPredicates = Vec<(Vec<ColVal>, Arc<dyn PredicateWrapper>)>;
+type Aggregations = Vec<AggregationBuilder>;
+type Computations = Vec<(Var, Vec<Var>, Arc<dyn FunctionWrapper>)>;
 type Relations = HashMap<String, Arc<Declaration>>;
 
 pub struct RuleBodyBuilder {
@@ -28,6 +33,9 @@ pub struct RuleBodyBuilder {
     negations: RefCell<Negations>,
     var_predicates: RefCell<VarPredicates>,
     aggregations: RefCell<Aggregations>,
+    computations: RefCell<Computations>,
+    choices: Vec<usize>,
+    disjunctions: RefCell<Vec<usize>>,
     relations: Rc<RefCell<Relations>>,
 }
 
@@ -39,71 +47,203 @@ impl Debug for RuleBodyBuilder {
 
 impl RuleBodyBuilder {
     pub fn new(relations: Rc<RefCell<HashMap<String, Arc<Declaration>>>>) -> Self {
+        Self::with_choices(relations, Vec::default())
+    }
+
+    /// Creates a builder that takes the alternative at `choices[i]` for the i-th
+    /// disjunction in the body, or the first alternative past the end of `choices`.
+    pub(super) fn with_choices(
+        relations: Rc<RefCell<HashMap<String, Arc<Declaration>>>>,
+        choices: Vec<usize>,
+    ) -> Self {
         Self {
             rel_predicates: RefCell::default(),
             negations: RefCell::default(),
             var_predicates: RefCell::default(),
             aggregations: RefCell::default(),
+            computations: RefCell::default(),
+            choices,
+            disjunctions: RefCell::default(),
             relations,
         }
     }
 
+    /// The number of alternatives in each disjunction encountered while building
+    /// the body.
+    pub(super) fn disjunctions(&self) -> Vec<usize> {
+        self.disjunctions.borrow().clone()
+    }
+
     pub fn finalize(self, bound_vars: &mut HashMap<VarId, ColType>) -> Result<Vec<BodyTerm>> {
+        let mut errors = Vec::default();
+        let body_terms = self.finalize_all(bound_vars, &mut errors);
+
+        match errors.into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(body_terms),
+        }
+    }
+
+    /// Finalizes every term, adding the error in each invalid term to `errors`,
+    /// rather than stopping at the first. The variables an invalid term would have
+    /// bound are still treated as bound, so that its error isn't repeated by the
+    /// terms that use them.
+    pub(super) fn finalize_all(
+        self,
+        bound_vars: &mut HashMap<VarId, ColType>,
+        errors: &mut Vec<anyhow::Error>,
+    ) -> Vec<BodyTerm> {
         let mut body_terms = Vec::default();
 
-        for (id, builder) in self.rel_predicates.into_inner() {
-            let Some(declaration) = self.relations.borrow().get(&id).cloned() else {
-                return error(Error::UnrecognizedRelation(id));
+        // Computations can depend on each other, and searches can use their targets
+        // as keys, so terms are finalized in an order where each computation's
+        // arguments are bound before it, and its target before any search using it
+        let mut rel_predicates = self.rel_predicates.into_inner();
+        let mut aggregations = self.aggregations.into_inner();
+        let mut computations = self.computations.into_inner();
+
+        loop {
+            let (ready, pending): (Computations, Computations) =
+                computations.into_iter().partition(|(_, vars, _)| {
+                    vars.iter().all(|var| bound_vars.contains_key(&var.id()))
+                });
+
+            computations = pending;
+
+            if !ready.is_empty() {
+                for (target, vars, f) in ready {
+                    match Self::finalize_computation(target, vars, f, bound_vars) {
+                        Ok(computation) => body_terms.push(BodyTerm::Computation(computation)),
+                        Err(err) => {
+                            errors.push(err);
+                            bind_vars([target], bound_vars);
+                        }
+                    }
+                }
+
+                continue;
+            }
+
+            let targets = computations
+                .iter()
+                .map(|(target, _, _)| target.id())
+                .collect();
+
+            let (ready, pending): (RelPredicates, RelPredicates) = rel_predicates
+                .into_iter()
+                .partition(|(_, builder)| !builder.binds_any(&targets));
+
+            rel_predicates = pending;
+
+            // Once nothing else can be finalized, the remaining searches are too, so
+            // that a computation whose target they bind reports it as such
+            let ready = match (ready.is_empty(), aggregations.is_empty()) {
+                (false, _) => ready,
+                (true, false) => {
+                    for builder in std::mem::take(&mut aggregations) {
+                        if let Some(aggregation) = builder.finalize(bound_vars, errors) {
+                            body_terms.push(BodyTerm::Aggregation(aggregation));
+                        }
+                    }
+
+                    continue;
+                }
+                (true, true) => std::mem::take(&mut rel_predicates),
             };
 
-            let predicate = builder.finalize(declaration, bound_vars)?;
-            let term = BodyTerm::RelPredicate(predicate);
+            if ready.is_empty() {
+                break;
+            }
 
-            body_terms.push(term);
-        }
+            for (id, builder) in ready {
+                let vars = builder.vars();
 
-        for (vars, f) in self.var_predicates.into_inner() {
-            for var in &vars {
-                if !bound_vars.contains_key(&var.id()) {
-                    return error(Error::ClauseNotDomainIndependent(var.id()));
+                let result = match self.relations.borrow().get(&id).cloned() {
+                    Some(declaration) => builder.finalize(declaration, bound_vars),
+                    None => error(Error::UnrecognizedRelation(id)),
+                };
+
+                match result {
+                    Ok(predicate) => body_terms.push(BodyTerm::RelPredicate(predicate)),
+                    Err(err) => {
+                        errors.push(err);
+                        bind_vars(vars, bound_vars);
+                    }
                 }
             }
+        }
+
+        for (_, vars, _) in &computations {
+            check_bound(vars.iter().copied(), bound_vars, errors);
+        }
+
+        for (args, f) in self.var_predicates.into_inner() {
+            if args.iter().any(|arg| arg.is_expr() || arg.is_wildcard()) {
+                errors.push(
+                    Error::InternalRhizomeError(
+                        "predicates can only be applied to literals and variables".to_owned(),
+                    )
+                    .into(),
+                );
+
+                continue;
+            }
 
-            let term = BodyTerm::VarPredicate(VarPredicate::new(vars, f));
+            let vars = args.iter().flat_map(ColVal::vars);
 
-            body_terms.push(term);
+            if check_bound(vars, bound_vars, errors) {
+                body_terms.push(BodyTerm::VarPredicate(VarPredicate::new(args, f)));
+            }
         }
 
         for (id, builder) in self.negations.into_inner() {
             let Some(declaration) = self.relations.borrow().get(&id).cloned() else {
-                return error(Error::UnrecognizedRelation(id));
-            };
+                errors.push(Error::UnrecognizedRelation(id).into());
 
-            let negation = builder.finalize(declaration)?;
+                continue;
+            };
 
-            for var in negation.vars() {
-                if !bound_vars.contains_key(&var.id()) {
-                    return error(Error::ClauseNotDomainIndependent(var.id()));
+            match builder.finalize(declaration) {
+                Ok(negation) => {
+                    if check_bound(negation.vars().into_iter().copied(), bound_vars, errors) {
+                        body_terms.push(BodyTerm::Negation(negation));
+                    }
                 }
+                Err(err) => errors.push(err),
             }
+        }
+
+        body_terms
+    }
 
-            let term = BodyTerm::Negation(negation);
+    fn finalize_computation(
+        target: Var,
+        vars: Vec<Var>,
+        f: Arc<dyn FunctionWrapper>,
+        bound_vars: &mut HashMap<VarId, ColType>,
+    ) -> Result<Computation> {
+        let arg_types = f.arg_types();
 
-            body_terms.push(term);
+        if arg_types.len() != vars.len() {
+            return error(Error::FunctionArityMismatch(arg_types.len(), vars.len()));
         }
 
-        for (id, builder) in self.aggregations.into_inner() {
-            let Some(declaration) = self.relations.borrow().get(&id).cloned() else {
-                return error(Error::UnrecognizedRelation(id));
-            };
+        for (var, arg_type) in vars.iter().zip(arg_types) {
+            if let Some(bound_type) = bound_vars.get(&var.id()) {
+                let unified_type = arg_type.unify(bound_type)?.unify(&var.typ())?;
 
-            let aggregation = builder.finalize(declaration, bound_vars)?;
-            let term = BodyTerm::Aggregation(aggregation);
+                bound_vars.insert(var.id(), unified_type);
+            }
+        }
 
-            body_terms.push(term);
+        if bound_vars.contains_key(&target.id()) {
+            return error(Error::ComputationBoundTarget(target.id()));
         }
 
-        Ok(body_terms)
+        let target_type = f.output_type().unify(&target.typ())?;
+        bound_vars.insert(target.id(), target_type);
+
+        Ok(Computation::new(target, vars, f))
     }
 
     pub fn search<T>(&self, id: &str, bindings: T) -> Result<()>
@@ -184,14 +324,86 @@ impl RuleBodyBuilder {
         Pred: PredicateWhere<I>,
         Pred::Predicate: PredicateWrapper,
     {
-        let args = pred.as_args();
+        let args = pred.as_args().into_iter().map(ColVal::Binding).collect();
         let wrapper = Arc::new(pred.into_predicate());
 
-        self.var_predicates.borrow_mut().push((args, wrapper));
+        self.var_predicate(args, wrapper)
+    }
+
+    /// Adds a predicate over `args`, each a literal or a variable, such as one
+    /// looked up by name in a [`Registry`](crate::kernel::Registry).
+    pub fn var_predicate(&self, args: Vec<ColVal>, f: Arc<dyn PredicateWrapper>) -> Result<()> {
+        self.var_predicates.borrow_mut().push((args, f));
 
         Ok(())
     }
 
+    /// Binds `target` to the result of applying `f` to `args`, which must be bound
+    /// elsewhere in the body.
+    pub fn compute<F, V, I, O>(&self, target: TypedVar<O>, f: F, args: V) -> Result<()>
+    where
+        F: Function<Input = I, Output = O> + Send + Sync + 'static,
+        V: TypedVars<Args = I>,
+        I: Args,
+        O: IntoColType + Into<Val>,
+    {
+        self.computation(target.into(), args.vars(), Arc::new(f))
+    }
+
+    pub fn function<Func, I, O>(&self, target: TypedVar<O>, f: Func) -> Result<()>
+    where
+        Func: FunctionWhere<I, O>,
+        Func::Function: FunctionWrapper,
+        O: IntoColType,
+    {
+        let args = f.as_args();
+        let wrapper = Arc::new(f.into_function());
+
+        self.computation(target.into(), args, wrapper)
+    }
+
+    pub(crate) fn computation(
+        &self,
+        target: Var,
+        vars: Vec<Var>,
+        f: Arc<dyn FunctionWrapper>,
+    ) -> Result<()> {
+        self.computations.borrow_mut().push((target, vars, f));
+
+        Ok(())
+    }
+
+    /// Adds a disjunction of alternative body fragments, which share the rest of
+    /// the body. The rule is desugared into one rule per alternative.
+    pub fn any<F>(&self, f: F) -> Result<()>
+    where
+        F: FnOnce(&DisjunctionBuilder<'_>) -> Result<()>,
+    {
+        // Reserve a slot first, so that disjunctions nested within an
+        // alternative are numbered after this one
+        let index = {
+            let mut disjunctions = self.disjunctions.borrow_mut();
+            disjunctions.push(0);
+
+            disjunctions.len() - 1
+        };
+
+        let selected = self.choices.get(index).copied().unwrap_or_default();
+        let disjunction = DisjunctionBuilder::new(self, selected);
+
+        f(&disjunction)?;
+
+        if disjunction.branches() == 0 {
+            return error(Error::EmptyDisjunction);
+        }
+
+        self.disjunctions.borrow_mut()[index] = disjunction.branches();
+
+        Ok(())
+    }
+
+    /// Binds `target` to the result of aggregating over the tuples of `id` that
+    /// match `group_by`.
     pub fn group_by<GroupBy, Agg, I, O>(
         &self,
         target: TypedVar<O>,
@@ -206,19 +418,106 @@ impl RuleBodyBuilder {
         O: AggAcc,
         Agg::Aggregate: AggregateWrapper + 'static,
     {
-        let wrapper = Arc::new(Agg::Aggregate::default());
-        let mut builder = AggregationBuilder::new(target.into(), wrapper);
+        let vars = agg.as_args();
+        let wrapper = Arc::new(agg.into_aggregate()?);
+        let mut bindings = Vec::default();
 
-        for var in agg.as_args() {
-            builder.vars.push(var);
-        }
+        group_by.bind(&mut bindings);
+
+        self.aggregation(target.into(), id, vars, bindings, wrapper)
+    }
+
+    /// Binds `target` to the result of aggregating over every solution to the
+    /// body built by `f`, grouped by the variables it shares with the rest of
+    /// this body. Variables bound only within `f` aren't visible outside of it.
+    pub fn aggregate<F, Agg, I, O>(&self, target: TypedVar<O>, agg: Agg, f: F) -> Result<()>
+    where
+        F: FnOnce(&RuleBodyBuilder) -> Result<()>,
+        Agg: AggregateGroupBy<I, O>,
+        I: Args,
+        O: AggAcc,
+        Agg::Aggregate: AggregateWrapper + 'static,
+    {
+        let vars = agg.as_args();
+        let wrapper = Arc::new(agg.into_aggregate()?);
+        let body = RuleBodyBuilder::new(Rc::clone(&self.relations));
 
-        group_by.bind(&mut builder.bindings);
+        f(&body)?;
+
+        self.aggregations.borrow_mut().push(AggregationBuilder::new(
+            target.into(),
+            vars,
+            body,
+            wrapper,
+        ));
+
+        Ok(())
+    }
+
+    pub(crate) fn aggregation(
+        &self,
+        target: Var,
+        id: &str,
+        vars: Vec<Var>,
+        bindings: Vec<(ColId, ColVal)>,
+        agg: Arc<dyn AggregateWrapper>,
+    ) -> Result<()> {
+        let body = RuleBodyBuilder::new(Rc::clone(&self.relations));
+        let search = RelPredicateBuilder::new(None);
+
+        search.bindings.borrow_mut().extend(bindings);
+        body.rel_predicates
+            .borrow_mut()
+            .push((id.to_string(), search));
 
         self.aggregations
             .borrow_mut()
-            .push((id.to_string(), builder));
+            .push(AggregationBuilder::new(target, vars, body, agg));
 
         Ok(())
     }
 }
+
+/// Treats each of `vars` as bound, with the type it was declared with, unless
+/// it's already bound.
+fn bind_vars(vars: impl IntoIterator<Item = Var>, bound_vars: &mut HashMap<VarId, ColType>) {
+    for var in vars {
+        bound_vars.entry(var.id()).or_insert_with(|| var.typ());
+    }
+}
+
+/// Adds an error to `errors` for each of `vars` that isn't bound, returning
+/// whether they all are.
+fn check_bound(
+    vars: impl IntoIterator<Item = Var>,
+    bound_vars: &HashMap<VarId, ColType>,
+    errors: &mut Vec<anyhow::Error>,
+) -> bool {
+    let mut all_bound = true;
+
+    for var in vars {
+        if !bound_vars.contains_key(&var.id()) {
+            errors.push(Error::ClauseNotDomainIndependent(var.id()).into());
+            all_bound = false;
+        }
+    }
+
+    all_bound
+}
+
+/// Advances `choices` to the next combination of alternatives, given the number
+/// of alternatives in each disjunction of the body built with them. Returns
+/// false once every combination has been built.
+pub(super) fn next_choices(choices: &mut Vec<usize>, disjunctions: &[usize]) -> bool {
+    choices.resize(disjunctions.len(), 0);
+
+    while let Some(choice) = choices.pop() {
+        if choice + 1 < disjunctions[choices.len()] {
+            choices.push(choice + 1);
+
+            return true;
+        }
+    }
+
+    false
+}
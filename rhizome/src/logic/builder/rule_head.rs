@@ -1,12 +1,18 @@
 use anyhow::Result;
-use std::{cell::RefCell, collections::HashMap, fmt::Debug, sync::Arc};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    sync::Arc,
+};
 
 use crate::{
     col_val::ColVal,
     error::{error, Error},
+    expr::Expr,
     id::{ColId, VarId},
     logic::ast::Declaration,
-    types::ColType,
+    types::{ColType, Type},
     value::Val,
 };
 
@@ -30,64 +36,179 @@ impl RuleHeadBuilder {
         self,
         bound_vars: &mut HashMap<VarId, ColType>,
     ) -> Result<HashMap<ColId, ColVal>> {
-        let schema = self.relation.schema();
+        let mut errors = Vec::default();
+        let cols = self.finalize_all(bound_vars, &mut errors);
+
+        match errors.into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(cols),
+        }
+    }
+
+    /// Finalizes every column binding, adding the error in each invalid binding to
+    /// `errors`, rather than stopping at the first.
+    pub(super) fn finalize_all(
+        self,
+        bound_vars: &mut HashMap<VarId, ColType>,
+        errors: &mut Vec<anyhow::Error>,
+    ) -> HashMap<ColId, ColVal> {
         let mut cols = HashMap::default();
+        let mut bound_cols = HashSet::new();
 
-        for (col_id, col_val) in self.bindings.into_inner() {
-            let Some(col) = schema.get_col(&col_id) else {
-                return error(Error::UnrecognizedColumnBinding(self.relation.id(), col_id));
-            };
+        for (col_id, mut col_val) in self.bindings.take() {
+            if !bound_cols.insert(col_id) {
+                errors.push(Error::ConflictingColumnBinding(self.relation.id(), col_id).into());
 
-            if cols.contains_key(&col_id) {
-                return error(Error::ConflictingColumnBinding(self.relation.id(), col_id));
+                continue;
             }
 
-            match &col_val {
-                ColVal::Lit(val) => {
-                    if col.col_type().check(val).is_err() {
-                        return error(Error::ColumnValueTypeConflict(
-                            self.relation.id(),
-                            col_id,
-                            col_val,
-                            *col.col_type(),
-                        ));
-                    }
+            match self.finalize_binding(col_id, &mut col_val, bound_vars) {
+                Ok(()) => {
+                    cols.insert(col_id, col_val);
                 }
-                ColVal::Binding(var) => {
-                    if let Some(bound_type) = bound_vars.get(&var.id()) {
-                        if let Ok(unified_type) = bound_type
-                            .unify(col.col_type())
-                            .and_then(|t| t.unify(&var.typ()))
-                        {
-                            bound_vars.insert(var.id(), unified_type);
-                        } else {
-                            return error(Error::ColumnValueTypeConflict(
-                                self.relation.id(),
-                                col_id,
-                                ColVal::Binding(*var),
-                                *col.col_type(),
-                            ));
-                        }
-                    } else {
-                        return error(Error::ClauseNotRangeRestricted(col_id, var.id()));
-                    }
+                Err(err) => errors.push(err),
+            }
+        }
 
-                    if !bound_vars.contains_key(&var.id()) {
-                        return error(Error::ClauseNotRangeRestricted(col_id, var.id()));
-                    }
+        for col_id in self.relation.schema().cols().keys() {
+            if !bound_cols.contains(col_id) {
+                errors.push(Error::ColumnMissing(self.relation.id(), *col_id).into());
+            }
+        }
+
+        cols
+    }
+
+    fn finalize_binding(
+        &self,
+        col_id: ColId,
+        col_val: &mut ColVal,
+        bound_vars: &mut HashMap<VarId, ColType>,
+    ) -> Result<()> {
+        let schema = self.relation.schema();
+
+        let Some(col) = schema.get_col(&col_id) else {
+            return error(Error::UnrecognizedColumnBinding(self.relation.id(), col_id));
+        };
+
+        match col_val {
+            ColVal::Lit(val) => {
+                if col.col_type().check(val).is_err() {
+                    return error(Error::ColumnValueTypeConflict(
+                        self.relation.id(),
+                        col_id,
+                        col_val.clone(),
+                        *col.col_type(),
+                    ));
                 }
             }
+            ColVal::Binding(var) => {
+                let Some(bound_type) = bound_vars.get(&var.id()) else {
+                    return error(Error::ClauseNotRangeRestricted(col_id, var.id()));
+                };
 
-            cols.insert(col_id, col_val);
-        }
+                let Ok(unified_type) = bound_type
+                    .unify(col.col_type())
+                    .and_then(|t| t.unify(&var.typ()))
+                else {
+                    return error(Error::ColumnValueTypeConflict(
+                        self.relation.id(),
+                        col_id,
+                        ColVal::Binding(*var),
+                        *col.col_type(),
+                    ));
+                };
 
-        for col_id in self.relation.schema().cols().keys() {
-            if !cols.contains_key(col_id) {
-                return error(Error::ColumnMissing(self.relation.id(), *col_id));
+                bound_vars.insert(var.id(), unified_type);
+            }
+            ColVal::Expr(expr) => {
+                let expr_type = Self::expr_type(col_id, expr, bound_vars)?;
+
+                if expr_type.unify(col.col_type()).is_err() {
+                    return error(Error::ColumnValueTypeConflict(
+                        self.relation.id(),
+                        col_id,
+                        col_val.clone(),
+                        *col.col_type(),
+                    ));
+                }
+            }
+            ColVal::Wildcard => {
+                return error(Error::UnexpectedWildcard(self.relation.id(), col_id));
             }
         }
 
-        Ok(cols)
+        Ok(())
+    }
+
+    /// Infers the type of an expression bound to `col_id`, checking that every
+    /// variable it uses is bound in the body, and that its operands have
+    /// compatible types.
+    ///
+    /// Rust gives numeric literals without a suffix the type `i32` or `f64`, so
+    /// literals of those types are treated as untyped, and converted to the type
+    /// of the operand or argument they're used with, provided their value fits in
+    /// it. As a result, `d + 1` is a `u64` if `d` is, while `d + 1_u32` isn't.
+    fn expr_type(
+        col_id: ColId,
+        expr: &mut Expr,
+        bound_vars: &mut HashMap<VarId, ColType>,
+    ) -> Result<ColType> {
+        match expr {
+            Expr::Lit(val) => Ok(ColType::Type(val.type_of())),
+            Expr::Var(var) => {
+                let Some(bound_type) = bound_vars.get(&var.id()) else {
+                    return error(Error::ClauseNotRangeRestricted(col_id, var.id()));
+                };
+
+                let unified_type = bound_type.unify(&var.typ())?;
+                bound_vars.insert(var.id(), unified_type);
+
+                Ok(unified_type)
+            }
+            Expr::Op(op, left, right) => {
+                let left_type = Self::expr_type(col_id, left, bound_vars)?;
+                let right_type = Self::expr_type(col_id, right, bound_vars)?;
+
+                let (left_type, right_type) = (
+                    coerce_literal(left, left_type, right_type),
+                    coerce_literal(right, right_type, left_type),
+                );
+
+                let unified_type = left_type.unify(&right_type)?;
+
+                match unified_type {
+                    ColType::Type(t) if !t.is_numeric() => {
+                        error(Error::OperatorTypeMismatch(*op, t))
+                    }
+                    _ => Ok(unified_type),
+                }
+            }
+            Expr::Concat(args) => {
+                for arg in args {
+                    let arg_type = Self::expr_type(col_id, arg, bound_vars)?;
+
+                    ColType::Type(Type::String).unify(&arg_type)?;
+                }
+
+                Ok(ColType::Type(Type::String))
+            }
+            Expr::Call(f, args) => {
+                let arg_types = f.arg_types();
+
+                if arg_types.len() != args.len() {
+                    return error(Error::FunctionArityMismatch(arg_types.len(), args.len()));
+                }
+
+                for (arg_type, arg) in arg_types.iter().zip(args) {
+                    let expr_type = Self::expr_type(col_id, arg, bound_vars)?;
+
+                    arg_type.unify(&coerce_literal(arg, expr_type, *arg_type))?;
+                }
+
+                Ok(f.output_type())
+            }
+        }
     }
 
     pub fn set<S, T>(&self, id: S, value: T) -> Result<()>
@@ -123,3 +244,46 @@ impl RuleHeadBuilder {
         Ok(())
     }
 }
+
+/// Converts `expr`, of type `typ`, to the numeric type `other`, if it's an
+/// untyped literal whose value fits in it, and returns its resulting type.
+fn coerce_literal(expr: &mut Expr, typ: ColType, other: ColType) -> ColType {
+    let (Expr::Lit(val), ColType::Type(other)) = (&*expr, other) else {
+        return typ;
+    };
+
+    if !other.is_numeric() || typ == ColType::Type(other) {
+        return typ;
+    }
+
+    let coerced = match *val {
+        Val::S32(n) => match other {
+            Type::S8 => i8::try_from(n).ok().map(Val::from),
+            Type::U8 => u8::try_from(n).ok().map(Val::from),
+            Type::S16 => i16::try_from(n).ok().map(Val::from),
+            Type::U16 => u16::try_from(n).ok().map(Val::from),
+            Type::U32 => u32::try_from(n).ok().map(Val::from),
+            Type::S64 => Some(Val::from(i64::from(n))),
+            Type::U64 => u64::try_from(n).ok().map(Val::from),
+            Type::F32 => Some(n as f32).filter(|f| *f as i32 == n).map(Val::from),
+            Type::F64 => Some(Val::from(f64::from(n))),
+            _ => None,
+        },
+        Val::F64(f) => match other {
+            Type::F32 => Some(f.0 as f32)
+                .filter(|g| f64::from(*g) == f.0 || f.0.is_nan())
+                .map(Val::from),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    match coerced {
+        Some(coerced) => {
+            *expr = Expr::Lit(coerced);
+
+            ColType::Type(other)
+        }
+        None => typ,
+    }
+}
@@ -0,0 +1,249 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display},
+    sync::Arc,
+};
+
+use crate::{
+    col_val::ColVal,
+    id::{ColId, RelationId},
+    relation::Source,
+    types::{ColType, Type},
+};
+
+use super::{
+    ast::{BodyTerm, Clause, Declaration},
+    builder::PREAMBLE,
+};
+
+/// A likely mistake in a program, which doesn't prevent it from running.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Lint {
+    /// An input relation that no rule reads.
+    UnusedInput(RelationId),
+    /// An output relation without any rules or facts.
+    UnusedOutput(RelationId),
+    /// An output relation with rules, none of which can ever derive a tuple.
+    UnreachableRelation(RelationId),
+    /// A rule, at the given index among the program's clauses and with the given
+    /// head, that searches a relation which is always empty.
+    EmptyDependency(usize, RelationId, RelationId),
+    /// A column of type `Any` that's only ever bound to values of one type.
+    InferableColType(RelationId, ColId, Type),
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lint::UnusedInput(id) => write!(f, "Input {id} is never used"),
+            Lint::UnusedOutput(id) => write!(f, "Output {id} has no rules or facts"),
+            Lint::UnreachableRelation(id) => {
+                write!(
+                    f,
+                    "Output {id} is always empty, as none of its rules can derive a tuple"
+                )
+            }
+            Lint::EmptyDependency(index, head, dependency) => write!(
+                f,
+                "Rule {index} for {head} never derives a tuple, as {dependency} is always empty"
+            ),
+            Lint::InferableColType(id, col, typ) => {
+                write!(
+                    f,
+                    "Column {col} of {id} has type any, but is only bound to {typ}"
+                )
+            }
+        }
+    }
+}
+
+pub(crate) fn lint(declarations: &[Arc<Declaration>], clauses: &[Clause]) -> Vec<Lint> {
+    let mut lints = Vec::default();
+
+    let mut declarations = declarations.iter().collect::<Vec<_>>();
+    declarations.sort_by_key(|declaration| declaration.id().resolve());
+
+    let heads: HashSet<RelationId> = clauses.iter().map(|clause| clause.head()).collect();
+    let read: HashSet<RelationId> = clauses
+        .iter()
+        .filter_map(|clause| match clause {
+            Clause::Rule(rule) => Some(rule.body()),
+            Clause::Fact(_) => None,
+        })
+        .flat_map(|body| body.iter().flat_map(term_reads))
+        .collect();
+
+    let non_empty = non_empty_relations(&declarations, clauses);
+
+    for declaration in &declarations {
+        let id = declaration.id();
+
+        match declaration.source() {
+            Source::Edb if !is_preamble(id) && !read.contains(&id) => {
+                lints.push(Lint::UnusedInput(id));
+            }
+            Source::Idb if !heads.contains(&id) => {
+                lints.push(Lint::UnusedOutput(id));
+            }
+            Source::Idb if !non_empty.contains(&id) => {
+                lints.push(Lint::UnreachableRelation(id));
+            }
+            _ => (),
+        }
+    }
+
+    for (index, clause) in clauses.iter().enumerate() {
+        let Clause::Rule(rule) = clause else {
+            continue;
+        };
+
+        let mut empty = HashSet::<RelationId>::default();
+
+        for predicate in rule.rel_predicate_terms() {
+            let dependency = predicate.relation().id();
+
+            if !non_empty.contains(&dependency) && empty.insert(dependency) {
+                lints.push(Lint::EmptyDependency(index, rule.head(), dependency));
+            }
+        }
+    }
+
+    let bound_types = bound_types(clauses);
+
+    for declaration in &declarations {
+        if is_preamble(declaration.id()) {
+            continue;
+        }
+
+        let schema = declaration.schema();
+        let mut cols = schema.cols().values().collect::<Vec<_>>();
+        cols.sort_by_key(|col| col.id().resolve());
+
+        for col in cols {
+            if *col.col_type() != ColType::Any {
+                continue;
+            }
+
+            let Some(types) = bound_types.get(&(declaration.id(), col.id())) else {
+                continue;
+            };
+
+            // Columns bound to values of conflicting types can't be narrowed
+            let unified = types
+                .iter()
+                .try_fold(ColType::Any, |acc, typ| acc.unify(typ).ok());
+
+            if let Some(ColType::Type(typ)) = unified {
+                lints.push(Lint::InferableColType(declaration.id(), col.id(), typ));
+            }
+        }
+    }
+
+    lints
+}
+
+fn is_preamble(id: RelationId) -> bool {
+    PREAMBLE.contains(&id.resolve().as_str())
+}
+
+/// The relations that rules read, including those within aggregations.
+fn term_reads(term: &BodyTerm) -> Vec<RelationId> {
+    match term {
+        BodyTerm::RelPredicate(inner) => vec![inner.relation().id()],
+        BodyTerm::Negation(inner) => vec![inner.relation().id()],
+        BodyTerm::Aggregation(inner) => inner.relations().iter().map(|r| r.id()).collect(),
+        BodyTerm::VarPredicate(_) | BodyTerm::Computation(_) => vec![],
+    }
+}
+
+/// The relations that may contain a tuple: inputs, relations with facts, and
+/// the heads of rules that only search such relations.
+fn non_empty_relations(
+    declarations: &[&Arc<Declaration>],
+    clauses: &[Clause],
+) -> HashSet<RelationId> {
+    let mut non_empty: HashSet<RelationId> = declarations
+        .iter()
+        .filter(|declaration| declaration.source() == Source::Edb)
+        .map(|declaration| declaration.id())
+        .chain(clauses.iter().filter_map(|clause| match clause {
+            Clause::Fact(fact) => Some(fact.head()),
+            Clause::Rule(_) => None,
+        }))
+        .collect();
+
+    loop {
+        let mut changed = false;
+
+        for clause in clauses {
+            let Clause::Rule(rule) = clause else {
+                continue;
+            };
+
+            if !non_empty.contains(&rule.head())
+                && rule
+                    .rel_predicate_terms()
+                    .iter()
+                    .all(|predicate| non_empty.contains(&predicate.relation().id()))
+            {
+                non_empty.insert(rule.head());
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return non_empty;
+        }
+    }
+}
+
+/// The types of the values and variables bound to each column of each relation.
+fn bound_types(clauses: &[Clause]) -> HashMap<(RelationId, ColId), Vec<ColType>> {
+    let mut types = HashMap::<(RelationId, ColId), Vec<ColType>>::default();
+
+    fn bind(
+        types: &mut HashMap<(RelationId, ColId), Vec<ColType>>,
+        id: RelationId,
+        args: &HashMap<ColId, ColVal>,
+    ) {
+        for (&col, val) in args {
+            let typ = match val {
+                ColVal::Lit(val) => ColType::Type(val.type_of()),
+                ColVal::Binding(var) => var.typ(),
+                ColVal::Expr(_) | ColVal::Wildcard => continue,
+            };
+
+            types.entry((id, col)).or_default().push(typ);
+        }
+    }
+
+    fn bind_body(types: &mut HashMap<(RelationId, ColId), Vec<ColType>>, body: &[BodyTerm]) {
+        for term in body {
+            match term {
+                BodyTerm::RelPredicate(inner) => bind(types, inner.relation().id(), inner.args()),
+                BodyTerm::Negation(inner) => bind(types, inner.relation().id(), inner.args()),
+                BodyTerm::Aggregation(inner) => bind_body(types, inner.body()),
+                BodyTerm::VarPredicate(_) | BodyTerm::Computation(_) => (),
+            }
+        }
+    }
+
+    for clause in clauses {
+        match clause {
+            Clause::Fact(fact) => {
+                for (&col, val) in fact.args() {
+                    types
+                        .entry((fact.head(), col))
+                        .or_default()
+                        .push(ColType::Type(val.type_of()));
+                }
+            }
+            Clause::Rule(rule) => {
+                bind(&mut types, rule.head(), rule.args());
+                bind_body(&mut types, rule.body());
+            }
+        }
+    }
+
+    types
+}
@@ -8,19 +8,25 @@ use anyhow::Result;
 use crate::{
     col_val::ColVal,
     error::{error, Error},
+    expr::Expr,
     id::{ColId, RelationId, VarId},
+    pretty::Pretty,
     ram::{
-        self, Aggregation, AliasId, ExitBuilder, Formula, Insert, Loop, Merge, Operation, Project,
-        Purge, Search, SinksBuilder, SourcesBuilder, Statement, Swap, Term,
+        self, Aggregation, AliasId, BranchBuilder, Compute, Difference, ExitBuilder, ExprFn,
+        Formula, Insert, Loop, Merge, Operation, Project, Purge, Remove, Search, SinksBuilder,
+        SourcesBuilder, Statement, Swap, Term, Yield,
     },
     relation::{Relation, RelationKey, Source, Version},
+    tuple::Truth,
     value::Val,
+    var::Var,
 };
 
 use super::{
     ast::{
         cid_value::CidValue, declaration::Declaration, fact::Fact, program::Program, rule::Rule,
-        stratum::Stratum, Negation, RelPredicate, VarPredicate,
+        stratum::Stratum, BodyTerm, Computation, Evaluation, Negation, Query, RelPredicate,
+        VarPredicate,
     },
     stratify::stratify,
 };
@@ -32,30 +38,46 @@ pub(crate) fn lower_to_ram(program: &Program) -> Result<ram::program::Program> {
     let mut outputs: Vec<&Declaration> = Vec::default();
     let mut statements: Vec<Statement> = Vec::default();
 
-    for declaration in program.declarations() {
-        relations.insert(
-            (declaration.id(), Version::New),
-            Arc::new(RwLock::new(declaration.relation())),
-        );
+    let strata = stratify(program)?;
 
-        relations.insert(
-            (declaration.id(), Version::Delta),
-            Arc::new(RwLock::new(declaration.relation())),
-        );
+    let well_founded: HashSet<RelationId> = strata
+        .iter()
+        .filter(|stratum| stratum.is_well_founded())
+        .flat_map(|stratum| stratum.relations().iter().copied())
+        .collect();
 
-        relations.insert(
-            (declaration.id(), Version::Total),
-            Arc::new(RwLock::new(declaration.relation())),
-        );
+    for declaration in program.declarations() {
+        let mut versions = vec![
+            Version::Total,
+            Version::Delta,
+            Version::New,
+            Version::Retracted,
+        ];
 
         match declaration.source() {
             Source::Edb => {
                 inputs.push(declaration);
             }
             Source::Idb => {
+                // Only outputs are recomputed, or accumulate tuples over a loop
+                versions.extend([Version::Inserted, Version::Previous]);
+
                 outputs.push(declaration);
             }
         }
+
+        // Only the relations of well-founded strata need the versions that hold
+        // their alternating fixpoint
+        if well_founded.contains(&declaration.id()) {
+            versions.extend([Version::Assumed, Version::Known, Version::Undefined]);
+        }
+
+        for version in versions {
+            relations.insert(
+                (declaration.id(), version),
+                Arc::new(RwLock::new(declaration.relation())),
+            );
+        }
     }
 
     // Run sources for each input
@@ -64,228 +86,455 @@ pub(crate) fn lower_to_ram(program: &Program) -> Result<ram::program::Program> {
 
         for input in &inputs {
             let id = input.id();
-            let relation = Arc::clone(
-                relations
-                    .get(&(id, Version::Delta))
-                    .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?,
-            );
 
-            sources_builder.add_relation(id, relation);
+            sources_builder.add_relation(
+                id,
+                find_relation((id, Version::Total), &relations)?,
+                find_relation((id, Version::Delta), &relations)?,
+                find_relation((id, Version::Retracted), &relations)?,
+            );
         }
 
         statements.push(Statement::Sources(sources_builder.finalize()));
     }
 
-    for stratum in &stratify(program)? {
+    for stratum in &strata {
         let mut lowered = lower_stratum_to_ram(stratum, program, &relations)?;
 
         statements.append(&mut lowered);
     }
 
-    // Merge all newly received input fact into Total and then purge Delta
-    for input in &inputs {
-        let id = input.id();
+    // Purge the changes made during this epoch, as every relation's total is now up to date
+    for declaration in inputs.iter().chain(&outputs) {
+        let id = declaration.id();
 
-        let delta_relation = Arc::clone(
-            relations
-                .get(&(id, Version::Delta))
-                .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?,
-        );
+        for version in [Version::Delta, Version::Retracted] {
+            let relation = find_relation((id, version), &relations)?;
 
-        let total_relation = Arc::clone(
-            relations
-                .get(&(id, Version::Total))
-                .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?,
-        );
+            statements.push(Statement::Purge(Purge::new((id, version), relation)));
+        }
+    }
 
-        let merge = Merge::new(
-            (id, Version::Delta),
-            (id, Version::Total),
-            Arc::clone(&delta_relation),
-            total_relation,
-        );
+    let statements = statements.into_iter().map(Arc::new).collect();
 
-        statements.push(Statement::Merge(merge));
+    let declarations = program
+        .declarations()
+        .iter()
+        .map(|declaration| (declaration.id().resolve(), Arc::clone(declaration)))
+        .collect();
 
-        statements.push(Statement::Purge(Purge::new(
-            (id, Version::Delta),
-            delta_relation,
-        )));
+    Ok(ram::program::Program::new(statements, relations)
+        .with_declarations(declarations)
+        .with_provenance(program.provenance()))
+}
+
+/// Lowers a stratum so that its relations match a from-scratch evaluation at
+/// the end of each epoch, with the tuples inserted into each during the epoch
+/// in delta and those removed from it in retracted.
+///
+/// While every relation the stratum depends on only grows, its own relations
+/// only grow too, so the stratum is maintained by evaluating its rules
+/// semi-naively against the tuples inserted during the epoch. If a relation it
+/// searches has tuples retracted, the tuples derived from them are deleted and
+/// those that still hold are rederived beforehand, and the changes are reduced
+/// to the net changes afterwards.
+///
+/// Deleting and rederiving can't undo a negation or an aggregation, so if a
+/// relation the stratum negates or aggregates over changes at all, the stratum
+/// is recomputed from scratch and compared against its previous total instead.
+/// The same goes for retractions from strata that aggregate over their own
+/// relations or hold lattice columns. Programs evaluated with
+/// [`Evaluation::Recompute`] always recompute every stratum.
+pub(crate) fn lower_stratum_to_ram(
+    stratum: &Stratum<'_>,
+    program: &Program,
+    relations: &HashMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>>,
+) -> Result<Vec<Statement>> {
+    let mut statements: Vec<Statement> = Vec::default();
+    let provenance = program.provenance();
+
+    if stratum.is_well_founded() {
+        statements.append(&mut lower_well_founded_stratum_to_ram(
+            stratum, provenance, relations,
+        )?);
+    } else if program.evaluation() == Evaluation::Recompute {
+        statements.append(&mut lower_recomputed_stratum_to_ram(
+            stratum, provenance, relations,
+        )?);
+    } else {
+        let (invalidated, retracted) = lower_invalidation_to_ram(stratum, program, relations)?;
+        let mut maintain = lower_incremental_stratum_to_ram(stratum, provenance, relations)?;
+
+        if !retracted.is_empty() {
+            let delete = lower_deletion_to_ram(stratum, program, provenance, relations)?;
+            let net = lower_net_changes_to_ram(stratum, relations)?;
+
+            maintain.insert(
+                0,
+                Statement::Branch(retracted.clone().finalize(delete.into_iter().map(Arc::new))),
+            );
+
+            maintain.push(Statement::Branch(
+                retracted.finalize(net.into_iter().map(Arc::new)),
+            ));
+        }
+
+        if invalidated.is_empty() {
+            statements.append(&mut maintain);
+        } else {
+            let recompute = lower_recomputed_stratum_to_ram(stratum, provenance, relations)?;
+
+            statements.push(Statement::Branch(
+                invalidated
+                    .clone()
+                    .finalize(recompute.into_iter().map(Arc::new)),
+            ));
+
+            statements.push(Statement::Branch(
+                invalidated
+                    .negate()
+                    .finalize(maintain.into_iter().map(Arc::new)),
+            ));
+        }
     }
 
-    // Purge Delta for all outputs
-    for output in &outputs {
-        let id = output.id();
-        let relation = Arc::clone(
-            relations
-                .get(&(id, Version::Delta))
-                .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?,
-        );
+    // Run sinks for the tuples inserted into and retracted from the stratum during this epoch
+    let mut sinks_builder = SinksBuilder::default();
 
-        statements.push(Statement::Purge(Purge::new((id, Version::Delta), relation)));
+    for &id in stratum.relations() {
+        sinks_builder.add_relation(
+            id,
+            find_relation((id, Version::Delta), relations)?,
+            find_relation((id, Version::Retracted), relations)?,
+        );
     }
 
-    let statements = statements.into_iter().map(Arc::new).collect();
+    if !sinks_builder.relations.is_empty() {
+        statements.push(Statement::Sinks(sinks_builder.finalize()));
+    }
 
-    Ok(ram::program::Program::new(statements))
+    Ok(statements)
 }
 
-pub(crate) fn lower_stratum_to_ram(
+/// Builds the conditions under which a stratum can't be maintained from the
+/// tuples inserted during the epoch alone: the first, under which it's
+/// recomputed, that a relation it negates or aggregates over changed; and the
+/// second, under which its tuples are deleted and rederived, that a relation it
+/// searches had tuples retracted. Improving the value held in a lattice column
+/// replaces a tuple, so searching a relation with one is treated like negating
+/// it.
+fn lower_invalidation_to_ram(
     stratum: &Stratum<'_>,
     program: &Program,
     relations: &HashMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>>,
+) -> Result<(BranchBuilder, BranchBuilder)> {
+    let mut searched = Vec::default();
+    let mut negated = Vec::default();
+
+    for rule in stratum.rules() {
+        for predicate in rule.rel_predicate_terms() {
+            let relation = predicate.relation();
+
+            if relation.schema().lattice().is_some() {
+                negated.push(relation.id());
+            } else {
+                searched.push(relation.id());
+            }
+        }
+
+        for negation in rule.negation_terms() {
+            negated.push(negation.relation().id());
+        }
+
+        for aggregation in rule.aggregation_terms() {
+            negated.extend(aggregation.relations().iter().map(|r| r.id()));
+        }
+    }
+
+    let mut invalidated = BranchBuilder::default();
+    let mut retracted = BranchBuilder::default();
+    let rederives = can_rederive(stratum, program);
+
+    let negated = negated
+        .into_iter()
+        .flat_map(|id| [(id, Version::Delta), (id, Version::Retracted)]);
+
+    let searched = searched.into_iter().map(|id| (id, Version::Retracted));
+
+    // The stratum's own relations only change while it's evaluated
+    let is_lower = |(id, _): &RelationKey| !stratum.relations().contains(id);
+
+    for key in negated.filter(is_lower) {
+        invalidated.add_relation(key, find_relation(key, relations)?);
+    }
+
+    for key in searched.filter(is_lower) {
+        let builder = if rederives {
+            &mut retracted
+        } else {
+            &mut invalidated
+        };
+
+        builder.add_relation(key, find_relation(key, relations)?);
+    }
+
+    Ok((invalidated, retracted))
+}
+
+/// Whether the tuples derived from those retracted from the relations the
+/// stratum searches can be deleted and rederived. Aggregating over the stratum's
+/// own relations, or improving the values held in its lattice columns, replaces
+/// tuples, and deleting the replacements wouldn't bring back what they replaced.
+fn can_rederive(stratum: &Stratum<'_>, program: &Program) -> bool {
+    !stratum
+        .rules()
+        .iter()
+        .any(|rule| is_aggregating(stratum, rule))
+        && program
+            .declarations()
+            .iter()
+            .filter(|declaration| stratum.relations().contains(&declaration.id()))
+            .all(|declaration| declaration.schema().lattice().is_none())
+}
+
+/// Evaluates the stratum semi-naively, given that every relation it depends on
+/// only had tuples inserted during this epoch.
+fn lower_incremental_stratum_to_ram(
+    stratum: &Stratum<'_>,
+    provenance: bool,
+    relations: &HashMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>>,
 ) -> Result<Vec<Statement>> {
     let mut statements: Vec<Statement> = Vec::default();
 
+    // Merge facts into delta
+    for fact in stratum.facts() {
+        let lowered = lower_fact_to_ram(fact, Version::Delta, true, relations)?;
+
+        statements.push(lowered);
+    }
+
     if stratum.is_recursive() {
-        // Merge facts into delta
-        for fact in stratum.facts() {
-            let lowered = lower_fact_to_ram(fact, relations)?;
-
-            statements.push(lowered);
-        }
-
-        // Partition the stratum's rules based on whether they depend on relations
-        // that change during this stratum
-        let (dynamic_rules, static_rules): (Vec<&Rule>, Vec<&Rule>) =
-            stratum.rules().iter().partition(|r| {
-                r.rel_predicate_terms()
-                    .iter()
-                    .any(|p| match p.relation().source() {
-                        Source::Edb => false,
-                        Source::Idb => stratum.relations().contains(&p.relation().id()),
-                    })
-            });
+        let (static_rules, dynamic_rules, aggregating_rules) = partition_rules(stratum);
 
         // Evaluate static rules out of the loop
         for rule in &static_rules {
-            let mut lowered = lower_rule_to_ram(rule, stratum, program, Version::Delta, relations)?;
+            let mut lowered = lower_rule_to_ram(rule, Version::Delta, provenance, relations)?;
 
             statements.append(&mut lowered);
         }
 
         // Merge the output of the static rules into total
         for relation in HashSet::<RelationId>::from_iter(static_rules.iter().map(|r| r.head())) {
-            let from_relation = Arc::clone(
-                relations
-                    .get(&(relation, Version::Delta))
-                    .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?,
+            let merge = Merge::new(
+                (relation, Version::Delta),
+                (relation, Version::Total),
+                find_relation((relation, Version::Delta), relations)?,
+                find_relation((relation, Version::Total), relations)?,
             );
 
-            let into_relation = Arc::clone(
-                relations
-                    .get(&(relation, Version::Total))
-                    .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?,
-            );
+            statements.push(Statement::Merge(merge));
+        }
 
+        statements.append(&mut lower_loop_to_ram(
+            stratum,
+            &dynamic_rules,
+            &aggregating_rules,
+            provenance,
+            relations,
+        )?);
+    } else {
+        // Evaluate all rules, inserting into Delta
+        for rule in stratum.rules() {
+            let mut lowered = lower_rule_to_ram(rule, Version::Delta, provenance, relations)?;
+
+            statements.append(&mut lowered);
+        }
+
+        // Merge rules from Delta into Total
+        for &relation in stratum.relations() {
             let merge = Merge::new(
                 (relation, Version::Delta),
                 (relation, Version::Total),
-                Arc::clone(&from_relation),
-                into_relation,
+                find_relation((relation, Version::Delta), relations)?,
+                find_relation((relation, Version::Total), relations)?,
             );
 
             statements.push(Statement::Merge(merge));
         }
+    }
 
-        let mut loop_body: Vec<Statement> = Vec::default();
+    Ok(statements)
+}
 
-        // Purge new, computed during the last loop iteration
-        for &id in stratum.relations() {
-            let relation = Arc::clone(
-                relations
-                    .get(&(id, Version::New))
-                    .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?,
-            );
+/// Evaluates the stratum from scratch, reading every relation it depends on in
+/// total, and then diffs the result against the stratum's previous total.
+fn lower_recomputed_stratum_to_ram(
+    stratum: &Stratum<'_>,
+    provenance: bool,
+    relations: &HashMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>>,
+) -> Result<Vec<Statement>> {
+    let mut statements: Vec<Statement> = Vec::default();
 
-            let statement = Statement::Purge(Purge::new((id, Version::New), relation));
+    // Set the previous total aside, to compare the recomputed total against
+    for &id in stratum.relations() {
+        let swap = Swap::new(
+            (id, Version::Total),
+            (id, Version::Previous),
+            find_relation((id, Version::Total), relations)?,
+            find_relation((id, Version::Previous), relations)?,
+        );
 
-            loop_body.push(statement);
-        }
+        statements.push(Statement::Swap(swap));
+        statements.push(Statement::Purge(Purge::new(
+            (id, Version::Total),
+            find_relation((id, Version::Total), relations)?,
+        )));
+    }
 
-        // Evaluate dynamic rules within the loop, inserting into new
-        for rule in &dynamic_rules {
-            let mut lowered = lower_rule_to_ram(rule, stratum, program, Version::New, relations)?;
+    // Facts are inserted again, as total is purged beforehand
+    for fact in stratum.facts() {
+        statements.push(lower_fact_to_ram(fact, Version::Delta, false, relations)?);
+    }
 
-            loop_body.append(&mut lowered);
-        }
+    let (static_rules, dynamic_rules, aggregating_rules) = partition_rules(stratum);
 
-        // Run sinks for the stratum
-        let mut sinks_builder = SinksBuilder::default();
+    // Rules that don't depend on the stratum's own relations only need evaluating
+    // once, against the total of every relation they search
+    for rule in &static_rules {
+        let mut lowered = lower_naive_rule_to_ram(rule, Version::Delta, provenance, relations)?;
+
+        statements.append(&mut lowered);
+    }
+
+    for &id in stratum.relations() {
+        let merge = Merge::new(
+            (id, Version::Delta),
+            (id, Version::Total),
+            find_relation((id, Version::Delta), relations)?,
+            find_relation((id, Version::Total), relations)?,
+        );
+
+        statements.push(Statement::Merge(merge));
+    }
+
+    if stratum.is_recursive() {
+        statements.append(&mut lower_loop_to_ram(
+            stratum,
+            &dynamic_rules,
+            &aggregating_rules,
+            provenance,
+            relations,
+        )?);
+    }
+
+    statements.append(&mut lower_difference_to_ram(
+        stratum,
+        Version::Total,
+        Version::Previous,
+        Version::Delta,
+        relations,
+    )?);
+
+    statements.append(&mut lower_difference_to_ram(
+        stratum,
+        Version::Previous,
+        Version::Total,
+        Version::Retracted,
+        relations,
+    )?);
+
+    statements.append(&mut lower_rebuild_total_to_ram(stratum, relations)?);
+
+    for &id in stratum.relations() {
+        let relation = find_relation((id, Version::Previous), relations)?;
+
+        statements.push(Statement::Purge(Purge::new(
+            (id, Version::Previous),
+            relation,
+        )));
+    }
+
+    Ok(statements)
+}
+
+/// Deletes the tuples of the stratum derived from those retracted from the
+/// relations it searches, and rederives those that still hold some other way.
+///
+/// Every tuple with a derivation that used a retracted tuple is deleted, along
+/// with every tuple derived from those in turn, collecting them in retracted.
+/// They're moved out of total into delta, and each rule is evaluated against
+/// what remains of total to find those that still hold. Afterwards, previous
+/// holds the deleted tuples, and delta the rederived ones, which seed the
+/// stratum's incremental evaluation so that it derives whatever follows from
+/// them.
+fn lower_deletion_to_ram(
+    stratum: &Stratum<'_>,
+    program: &Program,
+    provenance: bool,
+    relations: &HashMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>>,
+) -> Result<Vec<Statement>> {
+    let mut statements: Vec<Statement> = Vec::default();
+
+    // Delete the tuples derived from those retracted, into delta
+    for rule in stratum.rules() {
+        let rewrites = changed_rewrites(rule, Version::Retracted, |relation| {
+            !stratum.relations().contains(&relation.id())
+        });
+
+        statements.append(&mut lower_deletion_rewrites_to_ram(
+            rule,
+            rewrites,
+            Version::Delta,
+            relations,
+        )?);
+    }
+
+    if stratum.is_recursive() {
+        let mut loop_body: Vec<Statement> = Vec::default();
 
         for &id in stratum.relations() {
-            let relation = Arc::clone(
-                relations
-                    .get(&(id, Version::Delta))
-                    .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?,
-            );
+            let relation = find_relation((id, Version::New), relations)?;
 
-            sinks_builder.add_relation(id, relation);
-        }
+            loop_body.push(Statement::Purge(Purge::new((id, Version::New), relation)));
 
-        if !sinks_builder.relations.is_empty() {
-            loop_body.push(Statement::Sinks(sinks_builder.finalize()));
+            loop_body.push(Statement::Merge(Merge::new(
+                (id, Version::Delta),
+                (id, Version::Retracted),
+                find_relation((id, Version::Delta), relations)?,
+                find_relation((id, Version::Retracted), relations)?,
+            )));
         }
-        // Merge delta into total
-        for &relation in stratum.relations() {
-            // Merge the output of the static rules into total
-            let from_relation = Arc::clone(
-                relations
-                    .get(&(relation, Version::Delta))
-                    .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?,
-            );
-
-            let into_relation = Arc::clone(
-                relations
-                    .get(&(relation, Version::Total))
-                    .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?,
-            );
 
-            let merge = Merge::new(
-                (relation, Version::Delta),
-                (relation, Version::Total),
-                Arc::clone(&from_relation),
-                into_relation,
-            );
+        // Delete the tuples derived from those deleted in the last iteration, into new
+        for rule in stratum.rules() {
+            let rewrites = changed_rewrites(rule, Version::Delta, |relation| {
+                stratum.relations().contains(&relation.id())
+            });
 
-            loop_body.push(Statement::Merge(merge));
+            loop_body.append(&mut lower_deletion_rewrites_to_ram(
+                rule,
+                rewrites,
+                Version::New,
+                relations,
+            )?);
         }
 
-        // Exit the loop if all of the dynamic relations have reached a fixed point
         let mut exit_builder = ExitBuilder::default();
 
         for &id in stratum.relations() {
-            let relation = Arc::clone(
-                relations
-                    .get(&(id, Version::New))
-                    .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?,
-            );
+            let relation = find_relation((id, Version::New), relations)?;
 
             exit_builder.add_relation((id, Version::New), relation);
         }
 
         loop_body.push(Statement::Exit(exit_builder.finalize()));
 
-        //Swap new and delta
-        for &relation in stratum.relations() {
-            // Swap new and delta
-            let left_relation = Arc::clone(
-                relations
-                    .get(&(relation, Version::New))
-                    .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?,
-            );
-
-            let right_relation = Arc::clone(
-                relations
-                    .get(&(relation, Version::Delta))
-                    .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?,
-            );
-
+        for &id in stratum.relations() {
             let swap = Swap::new(
-                (relation, Version::New),
-                (relation, Version::Delta),
-                left_relation,
-                right_relation,
+                (id, Version::New),
+                (id, Version::Delta),
+                find_relation((id, Version::New), relations)?,
+                find_relation((id, Version::Delta), relations)?,
             );
 
             loop_body.push(Statement::Swap(swap));
@@ -294,156 +543,776 @@ pub(crate) fn lower_stratum_to_ram(
         let loop_body: Vec<Arc<Statement>> = loop_body.into_iter().map(Arc::new).collect();
 
         statements.push(Statement::Loop(Loop::new(loop_body)));
+    } else {
+        for &id in stratum.relations() {
+            statements.push(Statement::Merge(Merge::new(
+                (id, Version::Delta),
+                (id, Version::Retracted),
+                find_relation((id, Version::Delta), relations)?,
+                find_relation((id, Version::Retracted), relations)?,
+            )));
+        }
+    }
 
-        // Merge total into delta for subsequent strata
-        // TODO: this seems wrong and will lead to duplicate work across epochs. Will likely need to
-        // use the lattice based timestamps to resolve that.
-        for &relation in stratum.relations() {
-            let from_relation = Arc::clone(
-                relations
-                    .get(&(relation, Version::Total))
-                    .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?,
-            );
+    // Move the deleted tuples out of total, into delta
+    for &id in stratum.relations() {
+        statements.push(Statement::Purge(Purge::new(
+            (id, Version::Delta),
+            find_relation((id, Version::Delta), relations)?,
+        )));
 
-            let into_relation = Arc::clone(
-                relations
-                    .get(&(relation, Version::Delta))
-                    .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?,
-            );
+        statements.push(Statement::Remove(
+            Remove::new(
+                (
+                    (id, Version::Retracted),
+                    find_relation((id, Version::Retracted), relations)?,
+                ),
+                (
+                    (id, Version::Total),
+                    find_relation((id, Version::Total), relations)?,
+                ),
+            )
+            .moving_into((
+                (id, Version::Delta),
+                find_relation((id, Version::Delta), relations)?,
+            )),
+        ));
 
-            let merge = Merge::new(
-                (relation, Version::Total),
-                (relation, Version::Delta),
-                Arc::clone(&from_relation),
-                into_relation,
-            );
+        statements.push(Statement::Purge(Purge::new(
+            (id, Version::Retracted),
+            find_relation((id, Version::Retracted), relations)?,
+        )));
+    }
 
-            statements.push(Statement::Merge(merge));
-        }
-    } else {
-        // Merge facts into delta
-        for fact in stratum.facts() {
-            let lowered = lower_fact_to_ram(fact, relations)?;
+    // Rederive the deleted tuples that still hold, into new
+    for fact in stratum.facts() {
+        statements.push(lower_missing_fact_to_ram(fact, Version::New, relations)?);
+    }
 
-            statements.push(lowered);
-        }
+    for rule in stratum.rules() {
+        let head = find_declaration(rule.head(), program)?;
 
-        // Evaluate all rules, inserting into Delta
-        for rule in stratum.rules() {
-            let mut lowered = lower_rule_to_ram(rule, stratum, program, Version::Delta, relations)?;
+        statements.append(&mut lower_rewrites_to_ram(
+            rule,
+            vec![rederive_rewrite(rule, head, Version::Delta)],
+            Version::New,
+            provenance,
+            relations,
+        )?);
+    }
 
-            statements.append(&mut lowered);
+    // Set the deleted tuples aside, and seed the incremental evaluation with the
+    // rederived ones
+    for &id in stratum.relations() {
+        statements.push(Statement::Swap(Swap::new(
+            (id, Version::Delta),
+            (id, Version::Previous),
+            find_relation((id, Version::Delta), relations)?,
+            find_relation((id, Version::Previous), relations)?,
+        )));
+
+        statements.push(Statement::Swap(Swap::new(
+            (id, Version::New),
+            (id, Version::Delta),
+            find_relation((id, Version::New), relations)?,
+            find_relation((id, Version::Delta), relations)?,
+        )));
+    }
+
+    Ok(statements)
+}
+
+/// Reduces the changes made to each of the stratum's relations by deleting and
+/// rederiving its tuples, followed by its incremental evaluation, to the net
+/// changes. Deleted tuples that were derived again are neither inserted nor
+/// retracted, and keep the timestamp they were first derived at.
+fn lower_net_changes_to_ram(
+    stratum: &Stratum<'_>,
+    relations: &HashMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>>,
+) -> Result<Vec<Statement>> {
+    let mut statements: Vec<Statement> = Vec::default();
+
+    statements.append(&mut lower_difference_to_ram(
+        stratum,
+        Version::Previous,
+        Version::Delta,
+        Version::Retracted,
+        relations,
+    )?);
+
+    statements.append(&mut lower_difference_to_ram(
+        stratum,
+        Version::Delta,
+        Version::Previous,
+        Version::New,
+        relations,
+    )?);
+
+    for &id in stratum.relations() {
+        // Only the deleted tuples that were derived again remain in previous
+        statements.push(Statement::Remove(
+            Remove::new(
+                (
+                    (id, Version::Retracted),
+                    find_relation((id, Version::Retracted), relations)?,
+                ),
+                (
+                    (id, Version::Previous),
+                    find_relation((id, Version::Previous), relations)?,
+                ),
+            )
+            .forgetting(),
+        ));
+
+        // Restore them to total as they were first derived
+        statements.push(Statement::Remove(Remove::new(
+            (
+                (id, Version::Previous),
+                find_relation((id, Version::Previous), relations)?,
+            ),
+            (
+                (id, Version::Total),
+                find_relation((id, Version::Total), relations)?,
+            ),
+        )));
+
+        statements.push(Statement::Merge(Merge::new(
+            (id, Version::Previous),
+            (id, Version::Total),
+            find_relation((id, Version::Previous), relations)?,
+            find_relation((id, Version::Total), relations)?,
+        )));
+
+        statements.push(Statement::Swap(Swap::new(
+            (id, Version::New),
+            (id, Version::Delta),
+            find_relation((id, Version::New), relations)?,
+            find_relation((id, Version::Delta), relations)?,
+        )));
+
+        for version in [Version::New, Version::Previous] {
+            let relation = find_relation((id, version), relations)?;
+
+            statements.push(Statement::Purge(Purge::new((id, version), relation)));
         }
+    }
 
-        // Merge rules from Delta into Total
-        for &relation in stratum.relations() {
-            let from_relation = Arc::clone(
-                relations
-                    .get(&(relation, Version::Delta))
-                    .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?,
-            );
+    Ok(statements)
+}
 
-            let into_relation = Arc::clone(
-                relations
-                    .get(&(relation, Version::Total))
-                    .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?,
-            );
+/// Partitions the stratum's rules into those that only depend on relations
+/// which don't change during the stratum, those that do, and those that
+/// aggregate over them.
+fn partition_rules<'a>(stratum: &'a Stratum<'_>) -> (Vec<&'a Rule>, Vec<&'a Rule>, Vec<&'a Rule>) {
+    let (dynamic_rules, static_rules): (Vec<&Rule>, Vec<&Rule>) =
+        stratum.rules().iter().partition(|r| {
+            r.rel_predicate_terms()
+                .iter()
+                .any(|p| is_dynamic(stratum, &p.relation()))
+                || is_aggregating(stratum, r)
+        });
+
+    // Aggregations see all of each relation rather than its delta, so the rules that
+    // aggregate over the stratum's relations are evaluated once delta is merged into total
+    let (aggregating_rules, dynamic_rules): (Vec<&Rule>, Vec<&Rule>) = dynamic_rules
+        .into_iter()
+        .partition(|r| is_aggregating(stratum, r));
+
+    (static_rules, dynamic_rules, aggregating_rules)
+}
 
+/// Lowers the loop that evaluates a recursive stratum's dynamic rules to a fixed
+/// point, starting from the tuples in delta. Once the loop exits, delta holds
+/// every tuple inserted over its iterations.
+fn lower_loop_to_ram(
+    stratum: &Stratum<'_>,
+    dynamic_rules: &[&Rule],
+    aggregating_rules: &[&Rule],
+    provenance: bool,
+    relations: &HashMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>>,
+) -> Result<Vec<Statement>> {
+    let mut statements: Vec<Statement> = Vec::default();
+    let mut loop_body: Vec<Statement> = Vec::default();
+
+    // Purge new, computed during the last loop iteration
+    for &id in stratum.relations() {
+        let relation = find_relation((id, Version::New), relations)?;
+
+        loop_body.push(Statement::Purge(Purge::new((id, Version::New), relation)));
+    }
+
+    // Evaluate dynamic rules within the loop, inserting into new
+    for rule in dynamic_rules {
+        let mut lowered = lower_rule_to_ram(rule, Version::New, provenance, relations)?;
+
+        loop_body.append(&mut lowered);
+    }
+
+    // Merge delta into total, and into the tuples inserted over the whole loop
+    for &relation in stratum.relations() {
+        for version in [Version::Total, Version::Inserted] {
             let merge = Merge::new(
                 (relation, Version::Delta),
-                (relation, Version::Total),
-                Arc::clone(&from_relation),
-                into_relation,
+                (relation, version),
+                find_relation((relation, Version::Delta), relations)?,
+                find_relation((relation, version), relations)?,
             );
 
-            statements.push(Statement::Merge(merge));
+            loop_body.push(Statement::Merge(merge));
+        }
+    }
+
+    // Evaluate aggregating rules, inserting into new
+    for rule in aggregating_rules {
+        let mut lowered = lower_rule_to_ram(rule, Version::New, provenance, relations)?;
+
+        loop_body.append(&mut lowered);
+    }
+
+    // Exit the loop if all of the dynamic relations have reached a fixed point
+    let mut exit_builder = ExitBuilder::default();
+
+    for &id in stratum.relations() {
+        let relation = find_relation((id, Version::New), relations)?;
+
+        exit_builder.add_relation((id, Version::New), relation);
+    }
+
+    loop_body.push(Statement::Exit(exit_builder.finalize()));
+
+    // Swap new and delta
+    for &relation in stratum.relations() {
+        let swap = Swap::new(
+            (relation, Version::New),
+            (relation, Version::Delta),
+            find_relation((relation, Version::New), relations)?,
+            find_relation((relation, Version::Delta), relations)?,
+        );
+
+        loop_body.push(Statement::Swap(swap));
+    }
+
+    let loop_body: Vec<Arc<Statement>> = loop_body.into_iter().map(Arc::new).collect();
+
+    statements.push(Statement::Loop(Loop::new(loop_body)));
+
+    // Delta only holds the last iteration's tuples, so replace it with those
+    // inserted over the whole loop
+    for &relation in stratum.relations() {
+        let swap = Swap::new(
+            (relation, Version::Inserted),
+            (relation, Version::Delta),
+            find_relation((relation, Version::Inserted), relations)?,
+            find_relation((relation, Version::Delta), relations)?,
+        );
+
+        statements.push(Statement::Swap(swap));
+        statements.push(Statement::Purge(Purge::new(
+            (relation, Version::Inserted),
+            find_relation((relation, Version::Inserted), relations)?,
+        )));
+    }
+
+    Ok(statements)
+}
+
+/// Lowers a stratum that negates its own relations to an alternating fixpoint,
+/// which computes its well-founded model.
+///
+/// Each iteration evaluates the stratum twice, with negations of its relations
+/// checked against an assumed interpretation. Assuming only the tuples known to
+/// be true over-estimates the model, and assuming that over-estimate in turn
+/// under-estimates it, so the tuples known to be true grow until they reach a
+/// fixed point. Tuples in the final over-estimate that aren't known to be true
+/// are undefined.
+///
+/// The model is recomputed from scratch each epoch. Subsequent strata only see
+/// the tuples that are known to be true, which are diffed against the previous
+/// model like any other recomputed stratum, while the undefined tuples are
+/// diffed against those of the previous epoch and sent to the sinks marked as
+/// [`Truth::Undefined`].
+fn lower_well_founded_stratum_to_ram(
+    stratum: &Stratum<'_>,
+    provenance: bool,
+    relations: &HashMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>>,
+) -> Result<Vec<Statement>> {
+    let mut statements: Vec<Statement> = Vec::default();
+
+    // Set the previous model aside, to compare the recomputed model against
+    statements.append(&mut lower_copy_to_ram(
+        stratum,
+        Version::Total,
+        Version::Previous,
+        relations,
+    )?);
+
+    // Nothing is known to be true before the first iteration
+    for &id in stratum.relations() {
+        let relation = find_relation((id, Version::Known), relations)?;
+
+        statements.push(Statement::Purge(Purge::new((id, Version::Known), relation)));
+    }
+
+    let mut loop_body: Vec<Statement> = Vec::default();
+
+    // Over-estimate the model, by assuming only the tuples known to be true
+    loop_body.append(&mut lower_copy_to_ram(
+        stratum,
+        Version::Known,
+        Version::Assumed,
+        relations,
+    )?);
+    loop_body.append(&mut lower_assumed_fixpoint_to_ram(
+        stratum, provenance, relations,
+    )?);
+
+    // Under-estimate the model, by assuming the over-estimate
+    loop_body.append(&mut lower_copy_to_ram(
+        stratum,
+        Version::Total,
+        Version::Assumed,
+        relations,
+    )?);
+    loop_body.append(&mut lower_assumed_fixpoint_to_ram(
+        stratum, provenance, relations,
+    )?);
+
+    // Collect the tuples that are newly known to be true
+    loop_body.append(&mut lower_difference_to_ram(
+        stratum,
+        Version::Total,
+        Version::Known,
+        Version::New,
+        relations,
+    )?);
+
+    for &id in stratum.relations() {
+        let merge = Merge::new(
+            (id, Version::Total),
+            (id, Version::Known),
+            find_relation((id, Version::Total), relations)?,
+            find_relation((id, Version::Known), relations)?,
+        );
+
+        loop_body.push(Statement::Merge(merge));
+    }
+
+    // Exit the loop once no more tuples are known to be true
+    let mut exit_builder = ExitBuilder::default();
+
+    for &id in stratum.relations() {
+        let relation = find_relation((id, Version::New), relations)?;
+
+        exit_builder.add_relation((id, Version::New), relation);
+    }
+
+    loop_body.push(Statement::Exit(exit_builder.finalize()));
+
+    let loop_body: Vec<Arc<Statement>> = loop_body.into_iter().map(Arc::new).collect();
+
+    statements.push(Statement::Loop(Loop::new(loop_body)));
+
+    // The tuples of the last over-estimate that aren't known to be true are undefined
+    statements.append(&mut lower_difference_to_ram(
+        stratum,
+        Version::Assumed,
+        Version::Known,
+        Version::New,
+        relations,
+    )?);
+
+    // Diff the undefined tuples against those of the previous epoch, reusing the
+    // versions that are done with for this epoch
+    statements.append(&mut lower_difference_to_ram(
+        stratum,
+        Version::New,
+        Version::Undefined,
+        Version::Inserted,
+        relations,
+    )?);
+
+    statements.append(&mut lower_difference_to_ram(
+        stratum,
+        Version::Undefined,
+        Version::New,
+        Version::Assumed,
+        relations,
+    )?);
+
+    let mut sinks_builder = SinksBuilder::default().truth(Truth::Undefined);
+
+    for &id in stratum.relations() {
+        sinks_builder.add_relation(
+            id,
+            find_relation((id, Version::Inserted), relations)?,
+            find_relation((id, Version::Assumed), relations)?,
+        );
+    }
+
+    statements.push(Statement::Sinks(sinks_builder.finalize()));
+
+    for &id in stratum.relations() {
+        let swap = Swap::new(
+            (id, Version::New),
+            (id, Version::Undefined),
+            find_relation((id, Version::New), relations)?,
+            find_relation((id, Version::Undefined), relations)?,
+        );
+
+        statements.push(Statement::Swap(swap));
+    }
+
+    // Subsequent strata only see the tuples that are known to be true
+    statements.append(&mut lower_copy_to_ram(
+        stratum,
+        Version::Known,
+        Version::Total,
+        relations,
+    )?);
+
+    statements.append(&mut lower_difference_to_ram(
+        stratum,
+        Version::Total,
+        Version::Previous,
+        Version::Delta,
+        relations,
+    )?);
+
+    statements.append(&mut lower_difference_to_ram(
+        stratum,
+        Version::Previous,
+        Version::Total,
+        Version::Retracted,
+        relations,
+    )?);
+
+    statements.append(&mut lower_rebuild_total_to_ram(stratum, relations)?);
+
+    for &id in stratum.relations() {
+        for version in [
+            Version::New,
+            Version::Previous,
+            Version::Inserted,
+            Version::Assumed,
+        ] {
+            let relation = find_relation((id, version), relations)?;
+
+            statements.push(Statement::Purge(Purge::new((id, version), relation)));
         }
+    }
+
+    Ok(statements)
+}
+
+/// Evaluates the stratum to a fixed point in total, given the interpretation
+/// in the assumed version of each relation.
+fn lower_assumed_fixpoint_to_ram(
+    stratum: &Stratum<'_>,
+    provenance: bool,
+    relations: &HashMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>>,
+) -> Result<Vec<Statement>> {
+    let mut statements: Vec<Statement> = Vec::default();
+
+    for &id in stratum.relations() {
+        let relation = find_relation((id, Version::Total), relations)?;
+
+        statements.push(Statement::Purge(Purge::new((id, Version::Total), relation)));
+    }
+
+    // Facts are inserted on every evaluation, as total is purged beforehand
+    for fact in stratum.facts() {
+        statements.push(lower_fact_to_ram(fact, Version::Total, false, relations)?);
+    }
+
+    let mut loop_body: Vec<Statement> = Vec::default();
+
+    for &id in stratum.relations() {
+        let relation = find_relation((id, Version::New), relations)?;
+
+        loop_body.push(Statement::Purge(Purge::new((id, Version::New), relation)));
+    }
+
+    // Evaluate every rule naively, as the stratum's relations are only read in total
+    for rule in stratum.rules() {
+        let rewrite = well_founded_rewrite(stratum, rule);
+
+        loop_body.append(&mut lower_rewrites_to_ram(
+            rule,
+            vec![rewrite],
+            Version::New,
+            provenance,
+            relations,
+        )?);
+    }
+
+    let mut exit_builder = ExitBuilder::default();
+
+    for &id in stratum.relations() {
+        let relation = find_relation((id, Version::New), relations)?;
+
+        exit_builder.add_relation((id, Version::New), relation);
+    }
+
+    loop_body.push(Statement::Exit(exit_builder.finalize()));
+
+    for &id in stratum.relations() {
+        let merge = Merge::new(
+            (id, Version::New),
+            (id, Version::Total),
+            find_relation((id, Version::New), relations)?,
+            find_relation((id, Version::Total), relations)?,
+        );
+
+        loop_body.push(Statement::Merge(merge));
+    }
+
+    let loop_body: Vec<Arc<Statement>> = loop_body.into_iter().map(Arc::new).collect();
+
+    statements.push(Statement::Loop(Loop::new(loop_body)));
+
+    Ok(statements)
+}
+
+/// Replaces the contents of the `to` version of each of the stratum's relations
+/// with the `from` version.
+fn lower_copy_to_ram(
+    stratum: &Stratum<'_>,
+    from: Version,
+    to: Version,
+    relations: &HashMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>>,
+) -> Result<Vec<Statement>> {
+    let mut statements: Vec<Statement> = Vec::default();
+
+    for &id in stratum.relations() {
+        let from_relation = find_relation((id, from), relations)?;
+        let into_relation = find_relation((id, to), relations)?;
+
+        statements.push(Statement::Purge(Purge::new(
+            (id, to),
+            Arc::clone(&into_relation),
+        )));
+
+        statements.push(Statement::Merge(Merge::new(
+            (id, from),
+            (id, to),
+            from_relation,
+            into_relation,
+        )));
+    }
+
+    Ok(statements)
+}
+
+/// Rebuilds the total of each of the stratum's relations from its previous total
+/// and the changes made to it during this epoch, so that recomputed tuples keep
+/// the timestamp they were first derived at. The retracted tuples are removed
+/// from the previous total in place, rather than copying the rest of it.
+fn lower_rebuild_total_to_ram(
+    stratum: &Stratum<'_>,
+    relations: &HashMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>>,
+) -> Result<Vec<Statement>> {
+    let mut statements: Vec<Statement> = Vec::default();
 
-        // Run sinks for the stratum
-        let mut sinks_builder = SinksBuilder::default();
+    for &id in stratum.relations() {
+        statements.push(Statement::Remove(
+            Remove::new(
+                (
+                    (id, Version::Retracted),
+                    find_relation((id, Version::Retracted), relations)?,
+                ),
+                (
+                    (id, Version::Previous),
+                    find_relation((id, Version::Previous), relations)?,
+                ),
+            )
+            .forgetting(),
+        ));
 
-        for &id in stratum.relations() {
-            let relation = Arc::clone(
-                relations
-                    .get(&(id, Version::Delta))
-                    .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?,
-            );
+        statements.push(Statement::Swap(Swap::new(
+            (id, Version::Previous),
+            (id, Version::Total),
+            find_relation((id, Version::Previous), relations)?,
+            find_relation((id, Version::Total), relations)?,
+        )));
 
-            sinks_builder.add_relation(id, relation);
-        }
+        statements.push(Statement::Merge(Merge::new(
+            (id, Version::Delta),
+            (id, Version::Total),
+            find_relation((id, Version::Delta), relations)?,
+            find_relation((id, Version::Total), relations)?,
+        )));
+    }
 
-        if !sinks_builder.relations.is_empty() {
-            statements.push(Statement::Sinks(sinks_builder.finalize()));
-        }
+    Ok(statements)
+}
 
-        // Merge total into delta for subsequent strata
-        // TODO: this seems wrong and will lead to duplicate work across epochs. Will likely need to
-        // use the lattice based timestamps to resolve that.
-        for &relation in stratum.relations() {
-            let from_relation = Arc::clone(
-                relations
-                    .get(&(relation, Version::Total))
-                    .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?,
-            );
+/// Replaces the contents of the `into` version of each of the stratum's relations
+/// with the tuples of the `from` version that aren't in the `without` version.
+fn lower_difference_to_ram(
+    stratum: &Stratum<'_>,
+    from: Version,
+    without: Version,
+    into: Version,
+    relations: &HashMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>>,
+) -> Result<Vec<Statement>> {
+    let mut statements: Vec<Statement> = Vec::default();
 
-            let into_relation = Arc::clone(
-                relations
-                    .get(&(relation, Version::Delta))
-                    .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?,
-            );
+    for &id in stratum.relations() {
+        let into_relation = find_relation((id, into), relations)?;
 
-            let merge = Merge::new(
-                (relation, Version::Total),
-                (relation, Version::Delta),
-                Arc::clone(&from_relation),
-                into_relation,
-            );
+        statements.push(Statement::Purge(Purge::new(
+            (id, into),
+            Arc::clone(&into_relation),
+        )));
 
-            statements.push(Statement::Merge(merge));
-        }
-    };
+        statements.push(Statement::Difference(Difference::new(
+            ((id, from), find_relation((id, from), relations)?),
+            ((id, without), find_relation((id, without), relations)?),
+            ((id, into), into_relation),
+        )));
+    }
 
     Ok(statements)
 }
 
+fn find_relation(
+    key: RelationKey,
+    relations: &HashMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>>,
+) -> Result<Arc<RwLock<Box<dyn Relation>>>> {
+    relations
+        .get(&key)
+        .cloned()
+        .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()).into())
+}
+
+fn find_declaration(id: RelationId, program: &Program) -> Result<Arc<Declaration>> {
+    program
+        .declarations()
+        .iter()
+        .find(|declaration| declaration.id() == id)
+        .cloned()
+        .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()).into())
+}
+
+/// Whether `relation` changes during the evaluation of `stratum`.
+fn is_dynamic(stratum: &Stratum<'_>, relation: &Declaration) -> bool {
+    match relation.source() {
+        Source::Edb => false,
+        Source::Idb => stratum.relations().contains(&relation.id()),
+    }
+}
+
+/// Whether `rule` aggregates over a relation that changes during the evaluation
+/// of `stratum`, which is only permitted for monotone aggregations.
+fn is_aggregating(stratum: &Stratum<'_>, rule: &Rule) -> bool {
+    rule.aggregation_terms()
+        .iter()
+        .flat_map(|a| a.relations())
+        .any(|relation| is_dynamic(stratum, &relation))
+}
+
+/// Lowers `fact` to an insert into the given version of its relation. Ground
+/// inserts only run on the first clock cycle.
 pub(crate) fn lower_fact_to_ram(
     fact: &Fact,
+    version: Version,
+    is_ground: bool,
     relations: &HashMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>>,
 ) -> Result<Statement> {
     let cols = fact.args().iter().map(|(k, v)| (*k, Term::Lit(v.clone())));
 
     let relation = Arc::clone(
         relations
-            .get(&(fact.head(), Version::Delta))
+            .get(&(fact.head(), version))
             .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?,
     );
 
+    Ok(Statement::Insert(Insert::new(
+        Operation::Project(Project::new((fact.head(), version), cols, vec![], relation)),
+        is_ground,
+    )))
+}
+
+/// Lowers `fact` to an insert into the given version of its relation, unless
+/// it's already in the relation's total.
+fn lower_missing_fact_to_ram(
+    fact: &Fact,
+    version: Version,
+    relations: &HashMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>>,
+) -> Result<Statement> {
+    let cols: Vec<(ColId, Term)> = fact
+        .args()
+        .iter()
+        .map(|(k, v)| (*k, Term::Lit(v.clone())))
+        .collect();
+
+    let formula = Formula::not_in(
+        fact.head(),
+        Version::Total,
+        cols.clone(),
+        find_relation((fact.head(), Version::Total), relations)?,
+    );
+
     Ok(Statement::Insert(Insert::new(
         Operation::Project(Project::new(
-            (fact.head(), Version::Delta),
+            (fact.head(), version),
             cols,
-            vec![],
-            relation,
+            vec![formula],
+            find_relation((fact.head(), version), relations)?,
         )),
-        true,
+        false,
     )))
 }
 
 pub(crate) fn lower_rule_to_ram(
     rule: &Rule,
-    _stratum: &Stratum<'_>,
-    _program: &Program,
     version: Version,
+    provenance: bool,
+    relations: &HashMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>>,
+) -> Result<Vec<Statement>> {
+    lower_rewrites_to_ram(
+        rule,
+        semi_naive_rewrites(rule),
+        version,
+        provenance,
+        relations,
+    )
+}
+
+/// Lowers `rule` to be evaluated naively, against the total of every relation.
+pub(crate) fn lower_naive_rule_to_ram(
+    rule: &Rule,
+    version: Version,
+    provenance: bool,
+    relations: &HashMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>>,
+) -> Result<Vec<Statement>> {
+    lower_rewrites_to_ram(
+        rule,
+        vec![naive_rewrite(rule)],
+        version,
+        provenance,
+        relations,
+    )
+}
+
+fn lower_rewrites_to_ram(
+    rule: &Rule,
+    rewrites: Vec<Vec<SemiNaiveTerm>>,
+    version: Version,
+    provenance: bool,
     relations: &HashMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>>,
 ) -> Result<Vec<Statement>> {
     let mut statements: Vec<Statement> = Vec::default();
 
-    for rewrite in semi_naive_rewrites(rule) {
-        let ordered = order_terms(rewrite);
+    // Render the rule on a single line, to explain the tuples it derives
+    let rendered: Option<Arc<str>> =
+        provenance.then(|| format!("{}", rule.to_doc().pretty(usize::MAX)).into());
+
+    for rewrite in rewrites {
+        let ordered = order_terms(rewrite, HashSet::default());
 
         let operation = lower_rule_body_to_ram(
-            rule,
-            version,
+            BodyOutput::Head(rule, version, rendered.as_ref()),
             Default::default(),
             Default::default(),
             ordered.into_iter().rev().collect(),
@@ -457,10 +1326,67 @@ pub(crate) fn lower_rule_to_ram(
     Ok(statements)
 }
 
-#[allow(clippy::too_many_arguments)]
-pub(crate) fn lower_rule_body_to_ram(
+/// Lowers the rewrites of `rule` to project the tuples they derive into the
+/// given version of its head, as tuples to delete.
+fn lower_deletion_rewrites_to_ram(
     rule: &Rule,
+    rewrites: Vec<Vec<SemiNaiveTerm>>,
     version: Version,
+    relations: &HashMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>>,
+) -> Result<Vec<Statement>> {
+    let mut statements: Vec<Statement> = Vec::default();
+
+    for rewrite in rewrites {
+        let ordered = order_terms(rewrite, HashSet::default());
+
+        let operation = lower_rule_body_to_ram(
+            BodyOutput::Delete(rule, version),
+            Default::default(),
+            Default::default(),
+            ordered.into_iter().rev().collect(),
+            vec![],
+            relations,
+        )?;
+
+        statements.push(Statement::Insert(Insert::new(operation, false)));
+    }
+
+    Ok(statements)
+}
+
+/// Lowers `query` to yield the values of its variables for each solution to its
+/// body, against the total of every relation.
+pub(crate) fn lower_query_to_ram(
+    query: &Query,
+    relations: &HashMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>>,
+) -> Result<Operation> {
+    let ordered = order_terms(total_terms(query.body()), HashSet::default());
+
+    lower_rule_body_to_ram(
+        BodyOutput::Yield(query.vars()),
+        Default::default(),
+        Default::default(),
+        ordered.into_iter().rev().collect(),
+        vec![],
+        relations,
+    )
+}
+
+/// Where the solutions to a rule body go, once every term has been lowered.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum BodyOutput<'a> {
+    /// Projected into the head of the rule, along with the rendered rule if the
+    /// program records provenance.
+    Head(&'a Rule, Version, Option<&'a Arc<str>>),
+    /// Projected into the head of the rule as tuples to delete, unless they're
+    /// already in its retracted version, which collects those found so far.
+    Delete(&'a Rule, Version),
+    /// Yielded to the enclosing aggregation, as the values of the given variables.
+    Yield(&'a [Var]),
+}
+
+pub(crate) fn lower_rule_body_to_ram(
+    output: BodyOutput<'_>,
     bindings: im::HashMap<VarId, Term>,
     mut next_alias: im::HashMap<RelationId, AliasId>,
     mut terms: Vec<SemiNaiveTerm>,
@@ -498,45 +1424,8 @@ pub(crate) fn lower_rule_body_to_ram(
                 };
             }
 
-            if rule
-                .args()
-                .clone()
-                .into_values()
-                .filter_map(|v| match v {
-                    ColVal::Binding(v) => Some(v),
-                    _ => None,
-                })
-                .all(|v| next_bindings.contains_key(&v.id()))
-            {
-                let relation = relations
-                    .get(&(rule.head(), Version::Total))
-                    .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?
-                    .clone();
-
-                let mut cols = im::HashMap::<ColId, Term>::default();
-                for (&k, v) in rule.args() {
-                    let term = match v {
-                        ColVal::Lit(c) => Term::Lit(c.clone()),
-                        ColVal::Binding(v) => next_bindings
-                            .get(&v.id())
-                            .ok_or_else(|| {
-                                Error::InternalRhizomeError(format!(
-                                    "binding not found: {}",
-                                    v.id()
-                                ))
-                            })?
-                            .clone(),
-                    };
-
-                    cols.insert(k, term);
-                }
-
-                formulae.push(Formula::not_in(
-                    rule.head(),
-                    Version::Total,
-                    Vec::from_iter(cols.clone()),
-                    relation,
-                ))
+            if let Some(formula) = lower_head_not_in_to_ram(output, &next_bindings, relations)? {
+                formulae.push(formula);
             }
 
             if let Some(cid_val) = inner.cid() {
@@ -573,6 +1462,11 @@ pub(crate) fn lower_rule_body_to_ram(
                             rel_bindings.push((col_id, bound.clone()));
                         }
                     }
+                    ColVal::Expr(_) | ColVal::Wildcard => {
+                        return error(Error::InternalRhizomeError(
+                            "unexpected expression in rule body".to_owned(),
+                        ));
+                    }
                 }
             }
 
@@ -589,8 +1483,7 @@ pub(crate) fn lower_rule_body_to_ram(
                 rel_bindings,
                 formulae,
                 lower_rule_body_to_ram(
-                    rule,
-                    version,
+                    output,
                     next_bindings,
                     next_alias,
                     terms,
@@ -604,122 +1497,48 @@ pub(crate) fn lower_rule_body_to_ram(
 
             formulae.push(formula);
 
-            lower_rule_body_to_ram(
-                rule, version, bindings, next_alias, terms, formulae, relations,
-            )
+            lower_rule_body_to_ram(output, bindings, next_alias, terms, formulae, relations)
         }
-        Some(SemiNaiveTerm::Negation(inner)) => {
-            let formula_delta =
-                lower_negation_to_ram(&inner, &bindings, Version::Delta, relations)?;
-            let formula_total =
-                lower_negation_to_ram(&inner, &bindings, Version::Total, relations)?;
+        Some(SemiNaiveTerm::Negation(inner, versions)) => {
+            for version in versions {
+                let formula = lower_negation_to_ram(&inner, &bindings, version, relations)?;
 
-            formulae.push(formula_delta);
-            formulae.push(formula_total);
+                formulae.push(formula);
+            }
 
-            lower_rule_body_to_ram(
-                rule, version, bindings, next_alias, terms, formulae, relations,
-            )
+            lower_rule_body_to_ram(output, bindings, next_alias, terms, formulae, relations)
         }
         Some(SemiNaiveTerm::Aggregation(inner)) => {
-            let mut next_bindings = bindings.clone();
-            let alias = next_alias.get(&inner.relation().id()).copied();
-
-            next_alias =
-                next_alias.update_with(inner.relation().id(), AliasId::default(), |old, _| {
-                    old.next()
-                });
-
-            let mut args = Vec::default();
-            let mut group_by_cols = HashMap::default();
-
-            for (col_id, col_val) in inner.group_by_cols() {
-                if let Some(term) = match col_val {
-                    ColVal::Lit(lit) => Some(Term::Lit(lit.clone())),
-                    ColVal::Binding(var) => {
-                        if let Some(term) = bindings.get(&var.id()) {
-                            if inner.vars().contains(var) {
-                                args.push(term.clone());
-                            }
-
-                            Some(term.clone())
-                        } else if inner.vars().contains(var) {
-                            args.push(Term::Col(inner.relation().id(), alias, *col_id));
-
-                            None
-                        } else {
-                            None
-                        }
-                    }
-                } {
-                    group_by_cols.insert(*col_id, term);
-                }
-            }
-
-            let aggregation_relation = Arc::clone(
-                relations
-                    .get(&(inner.relation().id(), Version::Total))
-                    .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?,
+            // The body of the aggregation is joined against the total relations, with
+            // the variables bound so far acting as the keys it's grouped by
+            let ordered = order_terms(
+                total_terms(inner.body()),
+                bindings.keys().copied().collect(),
             );
 
-            next_bindings.insert(
-                inner.target().id(),
-                Term::Agg(inner.relation().id(), alias, *inner.target()),
-            );
-
-            if rule
-                .args()
-                .clone()
-                .into_values()
-                .filter_map(|v| match v {
-                    ColVal::Binding(v) => Some(v),
-                    _ => None,
-                })
-                .all(|v| next_bindings.contains_key(&v.id()))
-            {
-                let relation = relations
-                    .get(&(rule.head(), Version::Total))
-                    .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?
-                    .clone();
+            let source = lower_rule_body_to_ram(
+                BodyOutput::Yield(inner.vars()),
+                bindings.clone(),
+                next_alias.clone(),
+                ordered.into_iter().rev().collect(),
+                vec![],
+                relations,
+            )?;
 
-                let mut cols = im::HashMap::<ColId, Term>::default();
-                for (&k, v) in rule.args() {
-                    let term = match v {
-                        ColVal::Lit(c) => Term::Lit(c.clone()),
-                        ColVal::Binding(v) => next_bindings
-                            .get(&v.id())
-                            .ok_or_else(|| {
-                                Error::InternalRhizomeError(format!(
-                                    "binding not found: {}",
-                                    v.id()
-                                ))
-                            })?
-                            .clone(),
-                    };
-
-                    cols.insert(k, term);
-                }
+            let mut next_bindings = bindings.clone();
+            next_bindings.insert(inner.target().id(), Term::Agg(*inner.target()));
 
-                formulae.push(Formula::not_in(
-                    rule.head(),
-                    Version::Total,
-                    Vec::from_iter(cols.clone()),
-                    relation,
-                ));
+            if let Some(formula) = lower_head_not_in_to_ram(output, &next_bindings, relations)? {
+                formulae.push(formula);
             }
 
             Ok(Operation::Aggregation(Aggregation::new(
-                args,
                 inner.agg(),
                 *inner.target(),
-                group_by_cols,
-                inner.relation().id(),
-                alias,
-                aggregation_relation,
+                source,
                 formulae,
                 lower_rule_body_to_ram(
-                    rule,
-                    version,
+                    output,
                     next_bindings,
                     next_alias,
                     terms,
@@ -728,37 +1547,167 @@ pub(crate) fn lower_rule_body_to_ram(
                 )?,
             )))
         }
-        None => {
-            let relation = relations
-                .get(&(rule.head(), version))
-                .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?
-                .clone();
-
-            let mut cols = im::HashMap::<ColId, Term>::default();
-            for (&k, v) in rule.args() {
-                let term = match v {
-                    ColVal::Lit(c) => Term::Lit(c.clone()),
-                    ColVal::Binding(v) => bindings
-                        .get(&v.id())
-                        .ok_or_else(|| {
-                            Error::InternalRhizomeError(format!("binding not found: {}", v.id()))
-                        })?
-                        .clone(),
-                };
+        Some(SemiNaiveTerm::Computation(inner)) => {
+            let mut next_bindings = bindings.clone();
+
+            let mut args = Vec::default();
+            for var in inner.vars() {
+                args.push(lower_var_to_ram(var, &bindings)?);
+            }
+
+            next_bindings.insert(inner.target().id(), Term::Computed(*inner.target()));
 
-                cols.insert(k, term);
+            // The head can only be checked against once the computed value is bound
+            let mut next_formulae = vec![];
+            if let Some(formula) = lower_head_not_in_to_ram(output, &next_bindings, relations)? {
+                next_formulae.push(formula);
             }
 
-            Ok(Operation::Project(Project::new(
-                (rule.head(), version),
-                cols,
+            Ok(Operation::Compute(Compute::new(
+                args,
+                inner.f(),
+                *inner.target(),
                 formulae,
-                relation,
+                lower_rule_body_to_ram(
+                    output,
+                    next_bindings,
+                    next_alias,
+                    terms,
+                    next_formulae,
+                    relations,
+                )?,
             )))
         }
+        None => match output {
+            BodyOutput::Head(rule, version, rendered) => {
+                let relation = relations
+                    .get(&(rule.head(), version))
+                    .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?
+                    .clone();
+
+                let mut cols = im::HashMap::<ColId, Term>::default();
+                for (&k, v) in rule.args() {
+                    cols.insert(k, lower_col_val_to_ram(v, &bindings)?);
+                }
+
+                let project = Project::new((rule.head(), version), cols, formulae, relation);
+
+                Ok(Operation::Project(match rendered {
+                    Some(rule) => project.with_rule(Arc::clone(rule), bindings),
+                    None => project,
+                }))
+            }
+            BodyOutput::Delete(rule, version) => {
+                let relation = find_relation((rule.head(), version), relations)?;
+
+                let mut cols = im::HashMap::<ColId, Term>::default();
+                for (&k, v) in rule.args() {
+                    cols.insert(k, lower_col_val_to_ram(v, &bindings)?);
+                }
+
+                let project = Project::new((rule.head(), version), cols, formulae, relation);
+
+                Ok(Operation::Project(project.deleting()))
+            }
+            BodyOutput::Yield(vars) => {
+                let mut args = Vec::default();
+                for var in vars {
+                    args.push(lower_var_to_ram(var, &bindings)?);
+                }
+
+                Ok(Operation::Yield(Yield::new(args, formulae)))
+            }
+        },
+    }
+}
+
+/// When every variable in the head of the rule is bound, produces a formula
+/// that skips tuples which have already been derived, or already found to be
+/// deleted.
+fn lower_head_not_in_to_ram(
+    output: BodyOutput<'_>,
+    bindings: &im::HashMap<VarId, Term>,
+    relations: &HashMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>>,
+) -> Result<Option<Formula>> {
+    let (rule, version) = match output {
+        BodyOutput::Head(rule, ..) => (rule, Version::Total),
+        BodyOutput::Delete(rule, _) => (rule, Version::Retracted),
+        BodyOutput::Yield(_) => return Ok(None),
+    };
+
+    if !rule
+        .args()
+        .values()
+        .flat_map(|v| v.vars())
+        .all(|v| bindings.contains_key(&v.id()))
+    {
+        return Ok(None);
+    }
+
+    let relation = relations
+        .get(&(rule.head(), version))
+        .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?
+        .clone();
+
+    let mut cols = im::HashMap::<ColId, Term>::default();
+    for (&k, v) in rule.args() {
+        cols.insert(k, lower_col_val_to_ram(v, bindings)?);
+    }
+
+    Ok(Some(Formula::not_in(
+        rule.head(),
+        version,
+        Vec::from_iter(cols),
+        relation,
+    )))
+}
+
+fn lower_col_val_to_ram(col_val: &ColVal, bindings: &im::HashMap<VarId, Term>) -> Result<Term> {
+    match col_val {
+        ColVal::Lit(val) => Ok(Term::Lit(val.clone())),
+        ColVal::Binding(var) => lower_var_to_ram(var, bindings),
+        ColVal::Expr(expr) => lower_expr_to_ram(expr, bindings),
+        ColVal::Wildcard => error(Error::InternalRhizomeError(
+            "unexpected wildcard in rule head".to_owned(),
+        )),
     }
 }
 
+fn lower_expr_to_ram(expr: &Expr, bindings: &im::HashMap<VarId, Term>) -> Result<Term> {
+    let (f, args) = match expr {
+        Expr::Lit(val) => return Ok(Term::Lit(val.clone())),
+        Expr::Var(var) => return lower_var_to_ram(var, bindings),
+        Expr::Op(op, left, right) => (
+            ExprFn::Op(*op),
+            vec![
+                lower_expr_to_ram(left, bindings)?,
+                lower_expr_to_ram(right, bindings)?,
+            ],
+        ),
+        Expr::Concat(args) => (
+            ExprFn::Concat,
+            args.iter()
+                .map(|arg| lower_expr_to_ram(arg, bindings))
+                .collect::<Result<_>>()?,
+        ),
+        Expr::Call(f, args) => (
+            ExprFn::Call(Arc::clone(f)),
+            args.iter()
+                .map(|arg| lower_expr_to_ram(arg, bindings))
+                .collect::<Result<_>>()?,
+        ),
+    };
+
+    Ok(Term::Expr(Box::new(ram::Expr::new(f, args))))
+}
+
+fn lower_var_to_ram(var: &Var, bindings: &im::HashMap<VarId, Term>) -> Result<Term> {
+    Ok(bindings
+        .get(&var.id())
+        .ok_or_else(|| Error::InternalRhizomeError(format!("binding not found: {}", var.id())))?
+        .clone())
+}
+
 pub(crate) fn lower_negation_to_ram(
     negation: &Negation,
     bindings: &im::HashMap<VarId, Term>,
@@ -775,6 +1724,11 @@ pub(crate) fn lower_negation_to_ram(
                     Error::InternalRhizomeError(format!("binding not found: {}", var.id()))
                 })?
                 .clone(),
+            ColVal::Expr(_) | ColVal::Wildcard => {
+                return error(Error::InternalRhizomeError(
+                    "unexpected expression in negation".to_owned(),
+                ));
+            }
         };
 
         cols.insert(*k, term);
@@ -796,66 +1750,96 @@ pub(crate) fn lower_var_predicate_to_ram(
     var_predicate: &VarPredicate,
     bindings: &im::HashMap<VarId, Term>,
 ) -> Result<Formula> {
-    let var_terms = var_predicate
-        .vars()
+    let terms = var_predicate
+        .args()
         .iter()
-        .map(|var| {
-            Ok(bindings
-                .get(&var.id())
-                .ok_or_else(|| {
-                    Error::InternalRhizomeError(format!("binding not found: {}", var.id()))
-                })?
-                .clone())
-        })
+        .map(|arg| lower_col_val_to_ram(arg, bindings))
         .collect::<Result<Vec<Term>>>()?;
 
-    Ok(Formula::predicate(var_terms, var_predicate.f()))
+    Ok(Formula::predicate(terms, var_predicate.f()))
 }
 
 #[derive(Debug, Clone)]
 pub(crate) enum SemiNaiveTerm {
     RelPredicate(RelPredicate, Version),
     VarPredicate(VarPredicate),
-    Negation(Negation),
+    /// Negated against each of the given versions of the relation.
+    Negation(Negation, Vec<Version>),
     Aggregation(super::ast::body_term::Aggregation),
+    Computation(Computation),
 }
 
-pub(crate) fn semi_naive_rewrites(rule: &Rule) -> Vec<Vec<SemiNaiveTerm>> {
-    let mut non_relational_terms = vec![];
+/// The terms of `rule` other than the relations it searches. Negated relations
+/// are read in total, which includes their delta.
+fn non_relational_terms(rule: &Rule) -> Vec<SemiNaiveTerm> {
+    let mut terms = vec![];
 
     for var_predicate in rule.var_predicate_terms() {
-        non_relational_terms.push(SemiNaiveTerm::VarPredicate(var_predicate.clone()));
+        terms.push(SemiNaiveTerm::VarPredicate(var_predicate.clone()));
     }
 
     for negation in rule.negation_terms() {
-        non_relational_terms.push(SemiNaiveTerm::Negation(negation.clone()));
+        terms.push(SemiNaiveTerm::Negation(
+            negation.clone(),
+            vec![Version::Total],
+        ));
     }
 
     for aggregation in rule.aggregation_terms() {
-        non_relational_terms.push(SemiNaiveTerm::Aggregation(aggregation.clone()));
+        terms.push(SemiNaiveTerm::Aggregation(aggregation.clone()));
     }
 
+    for computation in rule.computation_terms() {
+        terms.push(SemiNaiveTerm::Computation(computation.clone()));
+    }
+
+    terms
+}
+
+pub(crate) fn semi_naive_rewrites(rule: &Rule) -> Vec<Vec<SemiNaiveTerm>> {
     if rule.rel_predicate_terms().is_empty() {
-        return vec![non_relational_terms];
+        return vec![non_relational_terms(rule)];
     }
 
+    changed_rewrites(rule, Version::Delta, |_| true)
+}
+
+/// Rewrites `rule` for each non-empty subset of the relations it searches that
+/// `is_changed` selects, searching that subset in the `changed` version and
+/// every other relation in total.
+fn changed_rewrites(
+    rule: &Rule,
+    changed: Version,
+    is_changed: impl Fn(&Declaration) -> bool,
+) -> Vec<Vec<SemiNaiveTerm>> {
+    let non_relational_terms = non_relational_terms(rule);
+
+    let (changing, unchanged): (Vec<&RelPredicate>, Vec<&RelPredicate>) = rule
+        .rel_predicate_terms()
+        .into_iter()
+        .partition(|term| is_changed(&term.relation()));
+
     let mut rewrites: Vec<Vec<SemiNaiveTerm>> = vec![];
 
     // Use a bitmask to represent all of the possible rewrites of the rule,
-    // where each rel_predicate searches against either the delta or total
-    // relation. The valid semi-naive rewrites will then be the non-zero
-    // bitmasks, where a 0 bit corresponds to a search against a total
-    // relation, and a 1 against a delta relation.
-    let rewrite_count = 1 << rule.rel_predicate_terms().len();
+    // where each changing rel_predicate searches against either the changed or
+    // total relation. The valid rewrites will then be the non-zero bitmasks,
+    // where a 0 bit corresponds to a search against a total relation, and a 1
+    // against a changed relation.
+    let rewrite_count = 1 << changing.len();
 
     for offset in 1..rewrite_count {
         let mut rewrite = non_relational_terms.clone();
 
-        for (i, &term) in rule.rel_predicate_terms().iter().enumerate() {
+        for &term in &unchanged {
+            rewrite.push(SemiNaiveTerm::RelPredicate(term.clone(), Version::Total));
+        }
+
+        for (i, &term) in changing.iter().enumerate() {
             if offset & (1 << i) == 0 {
                 rewrite.push(SemiNaiveTerm::RelPredicate(term.clone(), Version::Total))
             } else {
-                rewrite.push(SemiNaiveTerm::RelPredicate(term.clone(), Version::Delta))
+                rewrite.push(SemiNaiveTerm::RelPredicate(term.clone(), changed))
             }
         }
 
@@ -865,9 +1849,77 @@ pub(crate) fn semi_naive_rewrites(rule: &Rule) -> Vec<Vec<SemiNaiveTerm>> {
     rewrites
 }
 
-fn order_terms(mut terms: Vec<SemiNaiveTerm>) -> Vec<SemiNaiveTerm> {
+/// The terms of a body, searching and negating the total of every relation.
+fn total_terms(body: &[BodyTerm]) -> Vec<SemiNaiveTerm> {
+    body.iter()
+        .map(|term| match term {
+            BodyTerm::RelPredicate(inner) => {
+                SemiNaiveTerm::RelPredicate(inner.clone(), Version::Total)
+            }
+            BodyTerm::VarPredicate(inner) => SemiNaiveTerm::VarPredicate(inner.clone()),
+            BodyTerm::Negation(inner) => {
+                SemiNaiveTerm::Negation(inner.clone(), vec![Version::Total])
+            }
+            BodyTerm::Aggregation(inner) => SemiNaiveTerm::Aggregation(inner.clone()),
+            BodyTerm::Computation(inner) => SemiNaiveTerm::Computation(inner.clone()),
+        })
+        .collect()
+}
+
+/// Rewrites `rule` to search the total of every relation.
+pub(crate) fn naive_rewrite(rule: &Rule) -> Vec<SemiNaiveTerm> {
+    let mut rewrite = non_relational_terms(rule);
+
+    for &term in rule.rel_predicate_terms().iter() {
+        rewrite.push(SemiNaiveTerm::RelPredicate(term.clone(), Version::Total));
+    }
+
+    rewrite
+}
+
+/// Rewrites `rule` to rederive the tuples of its head in the given version, by
+/// searching that version for them before searching the total of every relation
+/// in its body. Rules that compute a column of their head, or aggregate, can't
+/// search for it, so are evaluated naively instead.
+fn rederive_rewrite(rule: &Rule, head: Arc<Declaration>, version: Version) -> Vec<SemiNaiveTerm> {
+    let mut rewrite = naive_rewrite(rule);
+
+    let is_searchable = rule.aggregation_terms().is_empty()
+        && rule
+            .args()
+            .values()
+            .all(|v| matches!(v, ColVal::Lit(_) | ColVal::Binding(_)));
+
+    if is_searchable {
+        let predicate = RelPredicate::new(head, None, rule.args().clone());
+
+        rewrite.push(SemiNaiveTerm::RelPredicate(predicate, version));
+    }
+
+    rewrite
+}
+
+/// Rewrites `rule` to be evaluated naively within a well-founded stratum, where
+/// every relation is read in total and negations of the stratum's relations are
+/// checked against the assumed interpretation.
+pub(crate) fn well_founded_rewrite(stratum: &Stratum<'_>, rule: &Rule) -> Vec<SemiNaiveTerm> {
+    naive_rewrite(rule)
+        .into_iter()
+        .map(|term| match term {
+            SemiNaiveTerm::Negation(negation, _)
+                if stratum.relations().contains(&negation.relation().id()) =>
+            {
+                SemiNaiveTerm::Negation(negation, vec![Version::Assumed])
+            }
+            term => term,
+        })
+        .collect()
+}
+
+/// Orders `terms` so that each is evaluated once the variables it depends on are
+/// bound, given that `bindings` are bound beforehand.
+fn order_terms(mut terms: Vec<SemiNaiveTerm>, mut bindings: HashSet<VarId>) -> Vec<SemiNaiveTerm> {
     let mut ordered_terms = Vec::new();
-    let mut bindings: HashSet<VarId> = HashSet::new();
 
     while !terms.is_empty() {
         // Select a term based on the current bindings
@@ -888,25 +1940,46 @@ fn select_term(
     available_terms: &mut Vec<SemiNaiveTerm>,
     bindings: &HashSet<VarId>,
 ) -> Option<SemiNaiveTerm> {
+    // A search that uses a computed variable as a key waits for it to be computed
+    let computed: HashSet<VarId> = available_terms
+        .iter()
+        .filter_map(|term| match term {
+            SemiNaiveTerm::Computation(inner) => Some(inner.target().id()),
+            _ => None,
+        })
+        .collect();
+
     let select_index = available_terms
         .iter()
         .enumerate()
         .filter(|(_, term)| match term {
-            SemiNaiveTerm::RelPredicate(_, _) => true,
+            SemiNaiveTerm::RelPredicate(inner, _) => {
+                inner.vars().iter().all(|var| !computed.contains(&var.id()))
+            }
             SemiNaiveTerm::VarPredicate(inner) => inner.is_vars_bound(bindings),
-            SemiNaiveTerm::Negation(inner) => inner.is_vars_bound(bindings),
+            SemiNaiveTerm::Negation(inner, _) => inner.is_vars_bound(bindings),
             SemiNaiveTerm::Aggregation(_) => true,
+            SemiNaiveTerm::Computation(inner) => inner.is_vars_bound(bindings),
         })
         .max_by_key(|(_, term)| match term {
-            SemiNaiveTerm::Negation(inner) => (4, inner.vars().len()),
+            SemiNaiveTerm::Computation(inner) => (5, inner.vars().len()),
+            SemiNaiveTerm::Negation(inner, _) => (4, inner.vars().len()),
             SemiNaiveTerm::VarPredicate(inner) => (3, inner.vars().len()),
-            SemiNaiveTerm::RelPredicate(inner, Version::Delta) => {
+            SemiNaiveTerm::RelPredicate(inner, Version::Delta | Version::Retracted) => {
                 (2, inner.bound_vars(bindings).len())
             }
             SemiNaiveTerm::RelPredicate(inner, Version::Total) => {
                 (1, inner.bound_vars(bindings).len())
             }
-            SemiNaiveTerm::RelPredicate(_, Version::New) => {
+            SemiNaiveTerm::RelPredicate(
+                _,
+                Version::New
+                | Version::Assumed
+                | Version::Known
+                | Version::Undefined
+                | Version::Inserted
+                | Version::Previous,
+            ) => {
                 panic!("New relation in semi-naive rule");
             }
             SemiNaiveTerm::Aggregation(inner) => (0, inner.bound_vars(bindings).len()),
@@ -921,6 +1994,9 @@ fn update_bindings(bindings: &mut HashSet<VarId>, term: &SemiNaiveTerm) {
         SemiNaiveTerm::Aggregation(inner) => {
             bindings.insert(inner.target().id());
         }
+        SemiNaiveTerm::Computation(inner) => {
+            bindings.insert(inner.target().id());
+        }
         SemiNaiveTerm::RelPredicate(inner, _) => {
             if let Some(CidValue::Var(var)) = inner.cid() {
                 bindings.insert(var.id());
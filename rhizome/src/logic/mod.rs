@@ -1,7 +1,18 @@
 mod ast;
 mod builder;
+mod parser;
 
+pub(crate) mod lint;
 pub(crate) mod lower_to_ram;
 pub(crate) mod stratify;
 
-pub use builder::{build, AtomBinding, AtomBindings, ProgramBuilder, RuleBodyBuilder, RuleVars};
+pub(crate) use ast::declaration::Declaration;
+pub use ast::{Evaluation, Semantics};
+pub(crate) use builder::build_query;
+pub use builder::{
+    build, parse, AtomBinding, AtomBindings, Diagnostic, DisjunctionBuilder, ProgramBuilder,
+    RuleBodyBuilder, RuleVars,
+};
+pub use lint::Lint;
+pub use parser::{Position, Span};
+pub use stratify::{Cycle, NegativeDependency};
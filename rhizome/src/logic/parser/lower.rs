@@ -0,0 +1,316 @@
+use anyhow::{Context, Result};
+use std::sync::Arc;
+
+use crate::{
+    col_val::ColVal,
+    error::{error, Error},
+    id::ColId,
+    kernel::Registry,
+    logic::{
+        ast::{CidValue, Declaration},
+        ProgramBuilder,
+    },
+    relation::Source,
+    types::{Any, ColType, Type},
+    value::Val,
+    var::{TypedVar, Var},
+};
+
+use super::{
+    syntax::{self, Atom, BodyTerm, Clause, Literal, Statement, Term},
+    Span,
+};
+
+/// Adds the declarations and clauses parsed from `source` to `builder`, which
+/// validates them exactly as it would a program built in Rust.
+pub(crate) fn lower(
+    builder: &ProgramBuilder,
+    source: &str,
+    statements: &[Statement<'_>],
+    registry: &Registry,
+) -> Result<()> {
+    for statement in statements {
+        match statement {
+            Statement::Declaration(declaration) => lower_declaration(builder, declaration)
+                .with_context(|| {
+                    format!("in declaration at {}", Span::of(source, declaration.text))
+                })?,
+            Statement::Clause(clause) => lower_clause(builder, source, clause, registry)
+                .with_context(|| format!("in clause at {}", Span::of(source, clause.text)))?,
+        }
+    }
+
+    Ok(())
+}
+
+fn lower_declaration(
+    builder: &ProgramBuilder,
+    declaration: &syntax::Declaration<'_>,
+) -> Result<()> {
+    let cols = &declaration.cols;
+
+    match declaration.source {
+        Source::Edb => builder.input(declaration.id, |h| {
+            cols.iter().fold(h, |h, (id, t)| h.column_of_type(id, *t))
+        }),
+        Source::Idb => builder.output(declaration.id, |h| {
+            cols.iter().fold(h, |h, (id, t)| h.column_of_type(id, *t))
+        }),
+    }
+}
+
+fn lower_clause(
+    builder: &ProgramBuilder,
+    source: &str,
+    clause: &Clause<'_>,
+    registry: &Registry,
+) -> Result<()> {
+    let head = &clause.head;
+    let declaration = find_declaration(builder, head.id)?;
+
+    let Some(body) = &clause.body else {
+        let bindings = atom_bindings(source, &declaration, head)?;
+
+        return builder.fact(head.id, |f| {
+            bindings
+                .into_iter()
+                .fold(f, |f, (col, col_val)| f.bind_one((col, col_val)))
+        });
+    };
+
+    builder.rule::<()>(head.id, &|h, b, ()| {
+        for (col, col_val) in atom_bindings(source, &declaration, head)? {
+            h.bind_one((col, col_val))?;
+        }
+
+        for term in body {
+            match term {
+                BodyTerm::Search(atom) => {
+                    let relation = find_declaration(builder, atom.id)?;
+
+                    let cid = match &atom.cid {
+                        None => None,
+                        Some(Term::Var(var)) => Some(CidValue::from(TypedVar::<Any>::new(var))),
+                        Some(Term::Lit(_, Literal::Cid(cid))) => Some(CidValue::Cid(*cid)),
+                        Some(Term::Lit(text, _) | Term::Wildcard(text)) => {
+                            return error(Error::ParseError(
+                                Span::of(source, text),
+                                "expected a CID".to_owned(),
+                            ))
+                        }
+                    };
+
+                    let bindings = atom_bindings(source, &relation, atom)?;
+
+                    b.build_search(atom.id, cid, |s| {
+                        for (col, col_val) in &bindings {
+                            s.bind_one((*col, col_val.clone()))?;
+                        }
+
+                        Ok(())
+                    })?;
+                }
+                BodyTerm::Negation(atom) => {
+                    let relation = find_declaration(builder, atom.id)?;
+                    let bindings = atom_bindings(source, &relation, atom)?;
+
+                    b.build_except(atom.id, |s| {
+                        for (col, col_val) in &bindings {
+                            s.bind_one((*col, col_val.clone()))?;
+                        }
+
+                        Ok(())
+                    })?;
+                }
+                BodyTerm::Predicate(call)
+                    if call.args.is_empty() && builder.declaration(call.id).is_some() =>
+                {
+                    b.build_search(call.id, None, |_| Ok(()))?;
+                }
+                BodyTerm::Predicate(call) => {
+                    let Some(f) = registry.predicate(call.id) else {
+                        return error(Error::UnrecognizedFunction(call.id.to_owned()));
+                    };
+
+                    let mut args = Vec::default();
+
+                    for arg in &call.args {
+                        match arg {
+                            Term::Var(var) => args.push(ColVal::Binding(Var::new::<Any>(var))),
+                            Term::Lit(text, lit) => args.push(ColVal::Lit(literal_val(
+                                source,
+                                text,
+                                lit,
+                                ColType::Any,
+                            )?)),
+                            Term::Wildcard(text) => {
+                                return error(Error::ParseError(
+                                    Span::of(source, text),
+                                    "expected a variable or literal".to_owned(),
+                                ))
+                            }
+                        }
+                    }
+
+                    b.var_predicate(args, f)?;
+                }
+                BodyTerm::Aggregation(aggregation) => {
+                    let atom = &aggregation.atom;
+                    let relation = find_declaration(builder, atom.id)?;
+
+                    let arg_types = aggregation
+                        .args
+                        .iter()
+                        .map(|arg| bound_type(&relation, atom, arg))
+                        .collect::<Vec<_>>();
+
+                    let target_type = bound_type(&declaration, head, aggregation.target);
+
+                    let f = match registry.aggregate(aggregation.function, &arg_types, target_type)
+                    {
+                        None => {
+                            return error(Error::UnrecognizedFunction(
+                                aggregation.function.to_owned(),
+                            ))
+                        }
+                        Some(None) => {
+                            let types = arg_types
+                                .iter()
+                                .map(ToString::to_string)
+                                .collect::<Vec<_>>()
+                                .join(", ");
+
+                            return error(Error::FunctionSignatureMismatch(
+                                aggregation.function.to_owned(),
+                                format!("({types})"),
+                            ));
+                        }
+                        Some(Some(f)) => f,
+                    };
+
+                    let vars = aggregation
+                        .args
+                        .iter()
+                        .map(|arg| Var::new::<Any>(arg))
+                        .collect();
+
+                    let bindings = atom_bindings(source, &relation, atom)?
+                        .into_iter()
+                        .map(|(col, col_val)| (ColId::new(col), col_val))
+                        .collect();
+
+                    b.aggregation(
+                        Var::new::<Any>(aggregation.target),
+                        atom.id,
+                        vars,
+                        bindings,
+                        f,
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    })
+}
+
+type Binding<'a> = (&'a str, ColVal);
+
+fn atom_bindings<'a>(
+    source: &str,
+    relation: &Declaration,
+    atom: &Atom<'a>,
+) -> Result<Vec<Binding<'a>>> {
+    let mut bindings = Vec::default();
+
+    for (col, term) in &atom.args {
+        let binding = match term {
+            Term::Var(var) => ColVal::Binding(Var::new::<Any>(var)),
+            Term::Lit(text, lit) => {
+                ColVal::Lit(literal_val(source, text, lit, col_type(relation, col))?)
+            }
+            Term::Wildcard(_) => ColVal::Wildcard,
+        };
+
+        bindings.push((*col, binding));
+    }
+
+    Ok(bindings)
+}
+
+fn find_declaration(builder: &ProgramBuilder, id: &str) -> Result<Arc<Declaration>> {
+    match builder.declaration(id) {
+        Some(declaration) => Ok(declaration),
+        None => error(Error::UnrecognizedRelation(id.to_owned())),
+    }
+}
+
+fn col_type(relation: &Declaration, col: &str) -> ColType {
+    relation
+        .schema()
+        .get_col(&ColId::new(col))
+        .map_or(ColType::Any, |col| *col.col_type())
+}
+
+/// The type of the column that `var` is bound to in `atom`, if any.
+fn bound_type(relation: &Declaration, atom: &Atom<'_>, var: &str) -> ColType {
+    atom.args
+        .iter()
+        .find(|(_, term)| *term == Term::Var(var))
+        .map_or(ColType::Any, |(col, _)| col_type(relation, col))
+}
+
+/// Integer and float literals take the type of the column they're bound to, or
+/// default to the types Rust would give them.
+fn literal_val(source: &str, text: &str, lit: &Literal, col_type: ColType) -> Result<Val> {
+    let val = match lit {
+        Literal::Bool(b) => Val::Bool(*b),
+        Literal::Char(c) => Val::Char(*c),
+        Literal::String(s) => Val::String(s.as_str().into()),
+        Literal::Cid(cid) => Val::Cid(*cid),
+        Literal::Float(f) => match col_type {
+            ColType::Type(Type::F32) => Val::from(*f as f32),
+            _ => Val::from(*f),
+        },
+        Literal::Int(n) => {
+            let n = *n;
+
+            let val = match col_type {
+                ColType::Type(Type::F32) => Ok(Val::from(n as f32)),
+                ColType::Type(Type::F64) => Ok(Val::from(n as f64)),
+                ColType::Type(Type::S8) => i8::try_from(n).map(Val::from),
+                ColType::Type(Type::U8) => u8::try_from(n).map(Val::from),
+                ColType::Type(Type::S16) => i16::try_from(n).map(Val::from),
+                ColType::Type(Type::U16) => u16::try_from(n).map(Val::from),
+                ColType::Type(Type::U32) => u32::try_from(n).map(Val::from),
+                ColType::Type(Type::S64) => i64::try_from(n).map(Val::from),
+                ColType::Type(Type::U64) => u64::try_from(n).map(Val::from),
+                _ => i32::try_from(n).map(Val::from),
+            };
+
+            match val {
+                Ok(val) => val,
+                Err(_) => {
+                    let typ = match col_type {
+                        ColType::Type(t) if is_integer(t) => t,
+                        _ => Type::S32,
+                    };
+
+                    return error(Error::ParseError(
+                        Span::of(source, text),
+                        format!("integer literal out of range for {typ}"),
+                    ));
+                }
+            }
+        }
+    };
+
+    Ok(val)
+}
+
+fn is_integer(t: Type) -> bool {
+    matches!(
+        t,
+        Type::S8 | Type::U8 | Type::S16 | Type::U16 | Type::S32 | Type::U32 | Type::S64 | Type::U64
+    )
+}
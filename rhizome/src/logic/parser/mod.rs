@@ -0,0 +1,677 @@
+//! A Soufflé-like surface syntax for programs.
+//!
+//! ```text
+//! // Declarations name their columns, and give each a type.
+//! .input edge(from: s32, to: s32)
+//! .output path(from: s32, to: s32)
+//!
+//! path(from: x, to: y) :- edge(from: x, to: y).
+//! path(from: x, to: z) :- edge(from: x, to: y), path(from: y, to: z).
+//! ```
+//!
+//! Body terms are searches (`edge(from: x)`), searches that also bind the CID of
+//! an input fact (`evac@c(attribute: "name")`), negations (`!path(from: x)`),
+//! predicates with positional arguments (`is_even(x)`), and aggregations
+//! (`n = count() : edge(from: x)`). A clause without a body is a fact. Predicates
+//! and aggregates are resolved by name through a [`Registry`](crate::kernel::Registry).
+
+use anyhow::Result;
+use cid::Cid;
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_until, take_while},
+    character::complete::{alpha1, alphanumeric1, char, digit1, multispace1, none_of, satisfy},
+    combinator::{consumed, cut, map, not, opt, recognize, value, verify},
+    error::{ErrorKind, ParseError},
+    multi::{many0_count, separated_list0, separated_list1},
+    sequence::{delimited, pair, preceded, separated_pair, terminated, tuple},
+    Err,
+};
+
+use crate::{
+    error::{error, Error},
+    relation::Source,
+    types::{ColType, Type},
+};
+
+use self::syntax::{
+    Aggregation, Atom, BodyTerm, Call, Clause, Declaration, Literal, Statement, Term,
+};
+
+pub(crate) use lower::lower;
+pub use span::{Position, Span};
+
+mod lower;
+mod span;
+mod syntax;
+
+type IResult<'a, O> = nom::IResult<&'a str, O, SyntaxError<'a>>;
+
+pub(crate) fn parse(source: &str) -> Result<Vec<Statement<'_>>> {
+    let mut statements = Vec::default();
+    let mut input = source;
+
+    loop {
+        let Ok((rest, _)) = skip(input) else {
+            return error(Error::InternalRhizomeError(
+                "failed to skip whitespace".to_owned(),
+            ));
+        };
+
+        if rest.is_empty() {
+            return Ok(statements);
+        }
+
+        match statement(rest) {
+            Ok((rest, statement)) => {
+                statements.push(statement);
+                input = rest;
+            }
+            Err(Err::Error(e) | Err::Failure(e)) => {
+                return error(e.into_error(source));
+            }
+            Err(Err::Incomplete(_)) => {
+                return error(Error::InternalRhizomeError(
+                    "streaming parser used for program source".to_owned(),
+                ));
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SyntaxError<'a> {
+    input: &'a str,
+    expected: Option<String>,
+    /// The length of the offending fragment, when known. Otherwise, the error
+    /// points at the next token.
+    len: Option<usize>,
+}
+
+impl<'a> SyntaxError<'a> {
+    fn expected(input: &'a str, expected: impl Into<String>) -> Self {
+        Self {
+            input,
+            expected: Some(expected.into()),
+            len: None,
+        }
+    }
+
+    fn into_error(self, source: &str) -> Error {
+        let token = if let Some(len) = self.len {
+            &self.input[..len]
+        } else if self.input.is_empty() {
+            self.input
+        } else {
+            let len = self
+                .input
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(self.input.len());
+
+            let len = if len == 0 {
+                self.input.chars().next().map_or(0, char::len_utf8)
+            } else {
+                len
+            };
+
+            &self.input[..len]
+        };
+
+        let found = if token.is_empty() {
+            "end of input".to_owned()
+        } else {
+            format!("`{token}`")
+        };
+
+        let message = match self.expected {
+            Some(expected) => format!("expected {expected}, found {found}"),
+            None => format!("unexpected {found}"),
+        };
+
+        Error::ParseError(Span::of(source, token), message)
+    }
+}
+
+impl<'a> ParseError<&'a str> for SyntaxError<'a> {
+    fn from_error_kind(input: &'a str, _kind: ErrorKind) -> Self {
+        Self {
+            input,
+            expected: None,
+            len: None,
+        }
+    }
+
+    fn append(_input: &'a str, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn or(self, other: Self) -> Self {
+        // Report whichever alternative made it furthest through the input
+        match self.input.len().cmp(&other.input.len()) {
+            std::cmp::Ordering::Less => self,
+            std::cmp::Ordering::Greater => other,
+            std::cmp::Ordering::Equal if self.expected.is_some() => self,
+            std::cmp::Ordering::Equal => other,
+        }
+    }
+}
+
+fn expected<'a, O, F>(description: &'static str, mut f: F) -> impl FnMut(&'a str) -> IResult<'a, O>
+where
+    F: FnMut(&'a str) -> IResult<'a, O>,
+{
+    move |i| {
+        let start = skip(i).map_or(i, |(rest, _)| rest);
+
+        // Only describe errors that occurred before any input was consumed, since
+        // those further along are more specific.
+        f(i).map_err(|e| match e {
+            Err::Error(e) if e.input.len() >= start.len() => {
+                Err::Error(SyntaxError::expected(start, description))
+            }
+            e => e,
+        })
+    }
+}
+
+fn failure<O>(fragment: &str, expected: impl Into<String>) -> IResult<'_, O> {
+    Err(Err::Failure(SyntaxError {
+        input: fragment,
+        expected: Some(expected.into()),
+        len: Some(fragment.len()),
+    }))
+}
+
+fn line_comment(i: &str) -> IResult<'_, &str> {
+    recognize(pair(tag("//"), take_while(|c| c != '\n')))(i)
+}
+
+fn block_comment(i: &str) -> IResult<'_, &str> {
+    recognize(tuple((tag("/*"), take_until("*/"), tag("*/"))))(i)
+}
+
+fn skip(i: &str) -> IResult<'_, ()> {
+    value(
+        (),
+        many0_count(alt((multispace1, line_comment, block_comment))),
+    )(i)
+}
+
+fn symbol<'a>(s: &'static str) -> impl FnMut(&'a str) -> IResult<'a, &'a str> {
+    move |i| {
+        let (i, _) = skip(i)?;
+
+        tag(s)(i).map_err(|e| {
+            e.map(|e: SyntaxError<'_>| SyntaxError::expected(e.input, format!("`{s}`")))
+        })
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn keyword<'a>(s: &'static str) -> impl FnMut(&'a str) -> IResult<'a, &'a str> {
+    terminated(tag(s), not(satisfy(is_ident_char)))
+}
+
+fn identifier(i: &str) -> IResult<'_, &str> {
+    let (i, _) = skip(i)?;
+
+    expected(
+        "an identifier",
+        recognize(pair(
+            alt((alpha1, tag("_"))),
+            many0_count(alt((alphanumeric1, tag("_")))),
+        )),
+    )(i)
+}
+
+fn variable(i: &str) -> IResult<'_, &str> {
+    expected(
+        "a variable",
+        verify(identifier, |id: &str| {
+            id != "_" && id != "true" && id != "false"
+        }),
+    )(i)
+}
+
+fn escape(i: &str) -> IResult<'_, char> {
+    cut(expected(
+        "an escape sequence",
+        alt((
+            value('\\', char('\\')),
+            value('"', char('"')),
+            value('\'', char('\'')),
+            value('\n', char('n')),
+            value('\r', char('r')),
+            value('\t', char('t')),
+            value('\0', char('0')),
+        )),
+    ))(i)
+}
+
+fn string_literal(i: &str) -> IResult<'_, String> {
+    let (mut rest, _) = char('"')(i)?;
+    let mut result = String::default();
+
+    loop {
+        let mut chars = rest.chars();
+
+        match chars.next() {
+            None => return failure(rest, "`\"`"),
+            Some('"') => return Ok((chars.as_str(), result)),
+            Some('\\') => {
+                let (next, c) = escape(chars.as_str())?;
+
+                result.push(c);
+                rest = next;
+            }
+            Some(c) => {
+                result.push(c);
+                rest = chars.as_str();
+            }
+        }
+    }
+}
+
+fn char_literal(i: &str) -> IResult<'_, char> {
+    delimited(
+        char('\''),
+        alt((preceded(char('\\'), escape), none_of("\\'"))),
+        cut(expected("`'`", char('\''))),
+    )(i)
+}
+
+fn cid_literal(i: &str) -> IResult<'_, Cid> {
+    let (rest, (text, s)) = preceded(tag("cid"), consumed(string_literal))(i)?;
+
+    match Cid::try_from(s.as_str()) {
+        Ok(cid) => Ok((rest, cid)),
+        Err(_) => failure(text, "a valid CID"),
+    }
+}
+
+/// A float without a literal of its own, quoted like a CID.
+fn float_literal(i: &str) -> IResult<'_, f64> {
+    let (rest, (text, s)) = preceded(tag("float"), consumed(string_literal))(i)?;
+
+    match s.as_str() {
+        "nan" => Ok((rest, f64::NAN)),
+        "inf" => Ok((rest, f64::INFINITY)),
+        "-inf" => Ok((rest, f64::NEG_INFINITY)),
+        _ => failure(text, "`nan`, `inf` or `-inf`"),
+    }
+}
+
+fn number_literal(i: &str) -> IResult<'_, Literal> {
+    let (rest, text) = recognize(tuple((
+        opt(char('-')),
+        digit1,
+        opt(pair(char('.'), digit1)),
+    )))(i)?;
+
+    if text.contains('.') {
+        match text.parse() {
+            Ok(f) => Ok((rest, Literal::Float(f))),
+            Err(_) => failure(text, "a float literal"),
+        }
+    } else {
+        match text.parse() {
+            Ok(n) => Ok((rest, Literal::Int(n))),
+            Err(_) => failure(text, "an integer literal"),
+        }
+    }
+}
+
+fn literal(i: &str) -> IResult<'_, (&str, Literal)> {
+    let (i, _) = skip(i)?;
+
+    consumed(alt((
+        value(Literal::Bool(true), keyword("true")),
+        value(Literal::Bool(false), keyword("false")),
+        map(cid_literal, Literal::Cid),
+        map(float_literal, Literal::Float),
+        number_literal,
+        map(string_literal, Literal::String),
+        map(char_literal, Literal::Char),
+    )))(i)
+}
+
+fn term(i: &str) -> IResult<'_, Term<'_>> {
+    expected(
+        "a variable or literal",
+        alt((
+            map(literal, |(text, lit)| Term::Lit(text, lit)),
+            map(preceded(skip, keyword("_")), Term::Wildcard),
+            map(variable, Term::Var),
+        )),
+    )(i)
+}
+
+fn col_type(i: &str) -> IResult<'_, ColType> {
+    let (rest, id) = identifier(i)?;
+
+    let t = match id {
+        "any" => ColType::Any,
+        "bool" => ColType::Type(Type::Bool),
+        "s8" => ColType::Type(Type::S8),
+        "u8" => ColType::Type(Type::U8),
+        "s16" => ColType::Type(Type::S16),
+        "u16" => ColType::Type(Type::U16),
+        "s32" => ColType::Type(Type::S32),
+        "u32" => ColType::Type(Type::U32),
+        "f32" => ColType::Type(Type::F32),
+        "s64" => ColType::Type(Type::S64),
+        "u64" => ColType::Type(Type::U64),
+        "f64" => ColType::Type(Type::F64),
+        "char" => ColType::Type(Type::Char),
+        "string" => ColType::Type(Type::String),
+        "cid" => ColType::Type(Type::Cid),
+        "list" => ColType::Type(Type::List),
+        _ => return failure(id, "a column type"),
+    };
+
+    Ok((rest, t))
+}
+
+fn declaration(i: &str) -> IResult<'_, Declaration<'_>> {
+    let (i, _) = skip(i)?;
+
+    let (rest, (text, (source, id, cols))) = consumed(tuple((
+        preceded(
+            char('.'),
+            cut(expected(
+                "`input` or `output`",
+                alt((
+                    value(Source::Edb, keyword("input")),
+                    value(Source::Idb, keyword("output")),
+                )),
+            )),
+        ),
+        cut(identifier),
+        cut(delimited(
+            symbol("("),
+            separated_list0(
+                symbol(","),
+                separated_pair(identifier, symbol(":"), cut(col_type)),
+            ),
+            symbol(")"),
+        )),
+    )))(i)?;
+
+    let declaration = Declaration {
+        text,
+        source,
+        id,
+        cols,
+    };
+
+    Ok((rest, declaration))
+}
+
+enum Arg<'a> {
+    Named(&'a str, Term<'a>),
+    Positional(Term<'a>),
+}
+
+/// Parses `id(args)` or `id@cid(args)`, which is either a search or a predicate,
+/// depending on whether its arguments are named.
+fn call(i: &str) -> IResult<'_, BodyTerm<'_>> {
+    let (i, _) = skip(i)?;
+
+    let (rest, (text, (id, cid, args))) = consumed(tuple((
+        identifier,
+        opt(preceded(symbol("@"), cut(term))),
+        preceded(
+            symbol("("),
+            cut(terminated(
+                separated_list0(
+                    symbol(","),
+                    alt((
+                        map(
+                            separated_pair(identifier, symbol(":"), cut(term)),
+                            |(col, term)| Arg::Named(col, term),
+                        ),
+                        map(term, Arg::Positional),
+                    )),
+                ),
+                symbol(")"),
+            )),
+        ),
+    )))(i)?;
+
+    if args.iter().all(|arg| matches!(arg, Arg::Named(..))) && (!args.is_empty() || cid.is_some()) {
+        let args = args
+            .into_iter()
+            .filter_map(|arg| match arg {
+                Arg::Named(col, term) => Some((col, term)),
+                Arg::Positional(_) => None,
+            })
+            .collect();
+
+        Ok((
+            rest,
+            BodyTerm::Search(Atom {
+                text,
+                id,
+                cid,
+                args,
+            }),
+        ))
+    } else if args.iter().all(|arg| matches!(arg, Arg::Positional(..))) && cid.is_none() {
+        let args = args
+            .into_iter()
+            .filter_map(|arg| match arg {
+                Arg::Named(..) => None,
+                Arg::Positional(term) => Some(term),
+            })
+            .collect();
+
+        Ok((rest, BodyTerm::Predicate(Call { text, id, args })))
+    } else {
+        failure(
+            text,
+            "arguments that are either all named or all positional",
+        )
+    }
+}
+
+/// Parses an atom with named arguments, which may not bind a CID.
+fn atom(i: &str) -> IResult<'_, Atom<'_>> {
+    let (rest, term) = call(i)?;
+
+    match term {
+        BodyTerm::Search(atom) if atom.cid.is_some() => {
+            failure(atom.text, "an atom that does not bind a CID")
+        }
+        BodyTerm::Search(atom) => Ok((rest, atom)),
+        BodyTerm::Predicate(call) if call.args.is_empty() => {
+            let atom = Atom {
+                text: call.text,
+                id: call.id,
+                cid: None,
+                args: Vec::default(),
+            };
+
+            Ok((rest, atom))
+        }
+        BodyTerm::Predicate(call) => failure(call.text, "named arguments"),
+        _ => Err(Err::Failure(SyntaxError::expected(i, "an atom"))),
+    }
+}
+
+fn aggregation(i: &str) -> IResult<'_, Aggregation<'_>> {
+    let (i, _) = skip(i)?;
+
+    let (rest, (text, (target, (function, args, atom)))) = consumed(pair(
+        variable,
+        preceded(
+            symbol("="),
+            cut(tuple((
+                identifier,
+                delimited(
+                    symbol("("),
+                    separated_list0(symbol(","), variable),
+                    symbol(")"),
+                ),
+                preceded(symbol(":"), atom),
+            ))),
+        ),
+    ))(i)?;
+
+    let aggregation = Aggregation {
+        text,
+        target,
+        function,
+        args,
+        atom,
+    };
+
+    Ok((rest, aggregation))
+}
+
+fn body_term(i: &str) -> IResult<'_, BodyTerm<'_>> {
+    expected(
+        "a body term",
+        alt((
+            map(preceded(symbol("!"), cut(atom)), BodyTerm::Negation),
+            map(aggregation, BodyTerm::Aggregation),
+            call,
+        )),
+    )(i)
+}
+
+fn clause(i: &str) -> IResult<'_, Clause<'_>> {
+    let (i, _) = skip(i)?;
+
+    let (rest, (text, (head, body))) = consumed(terminated(
+        pair(
+            atom,
+            opt(preceded(
+                symbol(":-"),
+                cut(separated_list1(symbol(","), body_term)),
+            )),
+        ),
+        cut(symbol(".")),
+    ))(i)?;
+
+    Ok((rest, Clause { text, head, body }))
+}
+
+fn statement(i: &str) -> IResult<'_, Statement<'_>> {
+    expected(
+        "a declaration or clause",
+        alt((
+            map(declaration, Statement::Declaration),
+            map(clause, Statement::Clause),
+        )),
+    )(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use crate::{
+        assert_compile_err,
+        error::Error,
+        id::{ColId, RelationId, VarId},
+    };
+
+    use super::*;
+
+    fn parse_err(source: &str) -> Error {
+        match parse(source) {
+            Ok(_) => panic!("Expected a parse error"),
+            Err(e) => e.downcast().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_err(".output p(x: s32)\np(x: 1) :- q(x: 1)"),
+            Error::ParseError(
+                Span::new(Position::new(2, 19), Position::new(2, 19)),
+                "expected `.`, found end of input".to_owned()
+            )
+        );
+
+        assert_eq!(
+            parse_err(".output p(x: int)"),
+            Error::ParseError(
+                Span::new(Position::new(1, 14), Position::new(1, 17)),
+                "expected a column type, found `int`".to_owned()
+            )
+        );
+
+        assert_eq!(
+            parse_err(".output p(x: s32)\np(x: y) :- q(x: @)."),
+            Error::ParseError(
+                Span::new(Position::new(2, 17), Position::new(2, 18)),
+                "expected a variable or literal, found `@`".to_owned()
+            )
+        );
+
+        assert_eq!(
+            parse_err(".output p(x: s32)\np(x: y) :- q(x: y, 1)."),
+            Error::ParseError(
+                Span::new(Position::new(2, 12), Position::new(2, 22)),
+                "expected arguments that are either all named or all positional, found `q(x: y, 1)`"
+                    .to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_semantic_errors() {
+        assert_compile_err!(&Error::UnrecognizedRelation("q".to_owned()), |p| {
+            p.parse(".output p(x: s32)\np(x: x) :- q(x: x).")?;
+
+            Ok(p)
+        });
+
+        assert_compile_err!(&Error::UnrecognizedFunction("even".to_owned()), |p| {
+            p.parse(".output p(x: s32)\np(x: x) :- p(x: x), even(x).")?;
+
+            Ok(p)
+        });
+
+        assert_compile_err!(
+            &Error::UnexpectedWildcard(RelationId::new("p"), ColId::new("x")),
+            |p| {
+                p.parse(".output p(x: s32)\np(x: _) :- p(x: x).")?;
+
+                Ok(p)
+            }
+        );
+
+        assert_compile_err!(
+            &Error::ClauseNotRangeRestricted(ColId::new("x"), VarId::new("y")),
+            |p| {
+                p.parse(".output p(x: s32)\np(x: y) :- p(x: x).")?;
+
+                Ok(p)
+            }
+        );
+
+        assert_compile_err!(
+            &Error::ParseError(
+                Span::new(Position::new(2, 6), Position::new(2, 9)),
+                "integer literal out of range for u8".to_owned()
+            ),
+            |p| {
+                p.parse(".output p(x: u8)\np(x: 256).")?;
+
+                Ok(p)
+            }
+        );
+
+        assert_compile_err!(&Error::ClauseHeadEDB(RelationId::new("p")), |p| {
+            p.parse(".input p(x: s32)\np(x: 1).")?;
+
+            Ok(p)
+        });
+    }
+}
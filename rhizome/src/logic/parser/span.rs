@@ -0,0 +1,79 @@
+use std::fmt::{self, Display};
+
+/// A line and column in program source, both starting at 1.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Position {
+    line: usize,
+    col: usize,
+}
+
+impl Position {
+    pub fn new(line: usize, col: usize) -> Self {
+        Self { line, col }
+    }
+
+    pub(crate) fn from_offset(source: &str, offset: usize) -> Self {
+        let prefix = &source[..offset];
+        let line = prefix.matches('\n').count() + 1;
+        let col = prefix
+            .rfind('\n')
+            .map_or(prefix, |idx| &prefix[idx + 1..])
+            .chars()
+            .count()
+            + 1;
+
+        Self { line, col }
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn col(&self) -> usize {
+        self.col
+    }
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("{}:{}", self.line, self.col))
+    }
+}
+
+/// A range of program source, from `start` up to but not including `end`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Span {
+    start: Position,
+    end: Position,
+}
+
+impl Span {
+    pub fn new(start: Position, end: Position) -> Self {
+        Self { start, end }
+    }
+
+    /// The span of `fragment`, which must be a subslice of `source`.
+    pub(crate) fn of(source: &str, fragment: &str) -> Self {
+        let start = fragment.as_ptr() as usize - source.as_ptr() as usize;
+        let end = start + fragment.len();
+
+        Self {
+            start: Position::from_offset(source, start),
+            end: Position::from_offset(source, end),
+        }
+    }
+
+    pub fn start(&self) -> Position {
+        self.start
+    }
+
+    pub fn end(&self) -> Position {
+        self.end
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("{}-{}", self.start, self.end))
+    }
+}
@@ -0,0 +1,78 @@
+use cid::Cid;
+
+use crate::{relation::Source, types::ColType};
+
+/// A top-level item in program source. Every node keeps the fragment of
+/// source it was parsed from, so later errors can be reported with a span.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Statement<'a> {
+    Declaration(Declaration<'a>),
+    Clause(Clause<'a>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Declaration<'a> {
+    pub(crate) text: &'a str,
+    pub(crate) source: Source,
+    pub(crate) id: &'a str,
+    pub(crate) cols: Vec<(&'a str, ColType)>,
+}
+
+/// A fact, when `body` is `None`, or a rule otherwise.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Clause<'a> {
+    pub(crate) text: &'a str,
+    pub(crate) head: Atom<'a>,
+    pub(crate) body: Option<Vec<BodyTerm<'a>>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Atom<'a> {
+    pub(crate) text: &'a str,
+    pub(crate) id: &'a str,
+    pub(crate) cid: Option<Term<'a>>,
+    pub(crate) args: Vec<(&'a str, Term<'a>)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum BodyTerm<'a> {
+    Search(Atom<'a>),
+    Negation(Atom<'a>),
+    /// A call with positional arguments. A call without arguments is a search
+    /// if a relation with that name has been declared.
+    Predicate(Call<'a>),
+    Aggregation(Aggregation<'a>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Call<'a> {
+    pub(crate) text: &'a str,
+    pub(crate) id: &'a str,
+    pub(crate) args: Vec<Term<'a>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Aggregation<'a> {
+    pub(crate) text: &'a str,
+    pub(crate) target: &'a str,
+    pub(crate) function: &'a str,
+    pub(crate) args: Vec<&'a str>,
+    pub(crate) atom: Atom<'a>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Term<'a> {
+    Var(&'a str),
+    Lit(&'a str, Literal),
+    Wildcard(&'a str),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Literal {
+    Bool(bool),
+    Int(i128),
+    Float(f64),
+    Char(char),
+    String(String),
+    Cid(Cid),
+}
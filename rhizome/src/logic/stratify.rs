@@ -1,19 +1,109 @@
-use std::{collections::HashSet, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::{self, Display},
+    sync::Arc,
+};
 
 use anyhow::Result;
 use petgraph::{
-    graph::{DiGraph, NodeIndex},
+    graph::{DiGraph, EdgeIndex, NodeIndex},
     visit::EdgeRef,
     Direction,
 };
 
 use crate::{
+    col_val::ColVal,
     error::{error, Error},
     id::RelationId,
+    pretty::Pretty,
     relation::Source,
 };
 
-use super::ast::{clause::Clause, program::Program, stratum::Stratum, BodyTerm, Declaration, Rule};
+use super::ast::{
+    clause::Clause, program::Program, stratum::Stratum, Aggregation, BodyTerm, Declaration, Rule,
+    Schema, Semantics,
+};
+
+/// A cycle of dependencies between relations that passes through a negation or
+/// a non-monotone aggregation, which prevents a program from being stratified.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Cycle {
+    relations: Vec<RelationId>,
+    dependency: NegativeDependency,
+    rules: Vec<String>,
+}
+
+impl Cycle {
+    pub(crate) fn new(
+        relations: Vec<RelationId>,
+        dependency: NegativeDependency,
+        rules: Vec<String>,
+    ) -> Self {
+        Self {
+            relations,
+            dependency,
+            rules,
+        }
+    }
+
+    /// The relations in the cycle, in the order they depend on one another,
+    /// where the second relation depends negatively on the first.
+    pub fn relations(&self) -> &[RelationId] {
+        &self.relations
+    }
+
+    /// How the second relation of the cycle depends on the first.
+    pub fn dependency(&self) -> NegativeDependency {
+        self.dependency
+    }
+
+    /// The rules that make each relation in the cycle depend on the previous one.
+    pub fn rules(&self) -> &[String] {
+        &self.rules
+    }
+}
+
+impl Display for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (Some(from), Some(to)) = (self.relations.first(), self.relations.get(1)) else {
+            return Ok(());
+        };
+
+        write!(
+            f,
+            "{to} depends on {from} through {}, in the cycle ",
+            self.dependency
+        )?;
+
+        for relation in &self.relations {
+            write!(f, "{relation} -> ")?;
+        }
+
+        write!(f, "{from}, formed by the rules:")?;
+
+        for rule in &self.rules {
+            write!(f, "\n    {rule}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The kind of body term that makes one relation depend negatively on another.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum NegativeDependency {
+    Negation,
+    Aggregation,
+}
+
+impl Display for NegativeDependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NegativeDependency::Negation => f.write_str("negation"),
+            NegativeDependency::Aggregation => f.write_str("aggregation"),
+        }
+    }
+}
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 enum Node {
@@ -53,19 +143,7 @@ impl Edge {
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 enum Polarity {
     Positive,
-    Negative,
-}
-
-impl Polarity {
-    #[allow(dead_code)]
-    fn is_positive(&self) -> bool {
-        matches!(self, Polarity::Positive)
-    }
-
-    #[allow(dead_code)]
-    fn is_negative(&self) -> bool {
-        matches!(self, Polarity::Negative)
-    }
+    Negative(NegativeDependency),
 }
 
 pub(crate) fn stratify(program: &Program) -> Result<Vec<Stratum<'_>>> {
@@ -86,15 +164,16 @@ pub(crate) fn stratify(program: &Program) -> Result<Vec<Stratum<'_>>> {
         );
     }
 
-    let mut edg = DiGraph::<Node, Polarity>::default();
+    // Each edge is weighted by its polarity, and the index of the clause it comes from
+    let mut edg = DiGraph::<Node, (Polarity, usize)>::default();
     let mut nodes = im::HashMap::<Node, NodeIndex>::default();
 
-    for clause in program.clauses() {
+    for (index, clause) in program.clauses().iter().enumerate() {
         nodes
             .entry(Node::Idb(clause.head()))
             .or_insert_with(|| edg.add_node(Node::Idb(clause.head())));
 
-        for dependency in clause_depends_on(clause) {
+        for dependency in clause_depends_on(program, clause) {
             nodes
                 .entry(dependency.to())
                 .or_insert_with(|| edg.add_node(dependency.to()));
@@ -111,17 +190,33 @@ pub(crate) fn stratify(program: &Program) -> Result<Vec<Stratum<'_>>> {
                 .get(&dependency.from())
                 .ok_or_else(|| Error::InternalRhizomeError("dependency not found".to_owned()))?;
 
-            edg.add_edge(*from, *to, dependency.polarity());
+            edg.add_edge(*from, *to, (dependency.polarity(), index));
         }
     }
 
     let sccs = petgraph::algo::kosaraju_scc(&edg);
 
-    for scc in &sccs {
+    // Under the well-founded semantics, a cycle through a negation is permitted,
+    // but marks its stratum to be evaluated to the well-founded model
+    let mut well_founded = vec![false; sccs.len()];
+
+    for (index, scc) in sccs.iter().enumerate() {
         for node in scc {
             for edge in edg.edges_directed(*node, Direction::Outgoing) {
-                if edge.weight().is_negative() && scc.contains(&edge.target()) {
-                    return error(Error::ProgramUnstratifiable);
+                if let (Polarity::Negative(dependency), _) = edge.weight() {
+                    if scc.contains(&edge.target()) {
+                        if *dependency == NegativeDependency::Negation
+                            && program.semantics() == Semantics::WellFounded
+                        {
+                            well_founded[index] = true;
+
+                            continue;
+                        }
+
+                        let cycle = find_cycle(program, &edg, scc, edge.id(), *dependency)?;
+
+                        return error(Error::ProgramUnstratifiable(cycle));
+                    }
                 }
             }
         }
@@ -129,7 +224,8 @@ pub(crate) fn stratify(program: &Program) -> Result<Vec<Stratum<'_>>> {
 
     Ok(sccs
         .iter()
-        .map(|nodes| {
+        .zip(well_founded)
+        .map(|(nodes, is_well_founded)| {
             let mut relations: HashSet<RelationId> = HashSet::default();
             let mut clauses: Vec<&Clause> = Vec::default();
 
@@ -149,52 +245,167 @@ pub(crate) fn stratify(program: &Program) -> Result<Vec<Stratum<'_>>> {
                 relations,
                 clauses,
                 nodes.len() > 1 || edg.contains_edge(nodes[0], nodes[0]),
+                is_well_founded,
             )
         })
         .rev()
         .collect())
 }
 
-fn clause_depends_on(clause: &Clause) -> Vec<Edge> {
-    match clause {
-        Clause::Fact(_) => vec![],
-        Clause::Rule(rule) => rule_depends_on(rule),
-    }
-}
-
-fn rule_depends_on(rule: &Rule) -> Vec<Edge> {
-    let mut edges = Vec::default();
+/// Finds the shortest cycle within `scc` that starts with the negative edge `start`.
+fn find_cycle(
+    program: &Program,
+    edg: &DiGraph<Node, (Polarity, usize)>,
+    scc: &[NodeIndex],
+    start: EdgeIndex,
+    dependency: NegativeDependency,
+) -> Result<Cycle> {
+    let (from, to) = edg
+        .edge_endpoints(start)
+        .ok_or_else(|| Error::InternalRhizomeError("edge not found".to_owned()))?;
+
+    // Search breadth-first for the shortest path from the end of the negative edge back to its start
+    let mut reached_by = HashMap::<NodeIndex, EdgeIndex>::default();
+    let mut queue = VecDeque::from([to]);
+
+    while let Some(node) = queue.pop_front() {
+        if node == from {
+            break;
+        }
 
-    for term in rule.body() {
-        if let Some(polarity) = term_polarity(term) {
-            for dependency in term_depends_on(term) {
-                let edge = match dependency.source() {
-                    Source::Edb => Edge::FromEDB(dependency.id(), rule.head(), polarity),
-                    Source::Idb => Edge::FromIDB(dependency.id(), rule.head(), polarity),
-                };
+        for edge in edg.edges_directed(node, Direction::Outgoing) {
+            let target = edge.target();
 
-                edges.push(edge);
+            if scc.contains(&target) && target != to && !reached_by.contains_key(&target) {
+                reached_by.insert(target, edge.id());
+                queue.push_back(target);
             }
         }
     }
 
-    edges
+    let mut edges = vec![];
+    let mut node = from;
+
+    while node != to {
+        let edge = reached_by
+            .get(&node)
+            .ok_or_else(|| Error::InternalRhizomeError("cycle not found".to_owned()))?;
+
+        edges.push(*edge);
+        node = edg
+            .edge_endpoints(*edge)
+            .ok_or_else(|| Error::InternalRhizomeError("edge not found".to_owned()))?
+            .0;
+    }
+
+    edges.push(start);
+    edges.reverse();
+
+    let mut relations = vec![];
+    let mut rules = vec![];
+
+    for edge in edges {
+        let (source, _) = edg
+            .edge_endpoints(edge)
+            .ok_or_else(|| Error::InternalRhizomeError("edge not found".to_owned()))?;
+
+        let relation = match edg[source] {
+            Node::Edb(id) | Node::Idb(id) => id,
+        };
+
+        let (_, index) = edg[edge];
+        let clause = program
+            .clauses()
+            .get(index)
+            .ok_or_else(|| Error::InternalRhizomeError("clause not found".to_owned()))?;
+
+        relations.push(relation);
+        // Render each rule on a single line, to keep them apart in the error message
+        rules.push(format!("{}", clause.to_doc().pretty(usize::MAX)));
+    }
+
+    Ok(Cycle::new(relations, dependency, rules))
 }
 
-fn term_polarity(term: &BodyTerm) -> Option<Polarity> {
-    match term {
-        BodyTerm::RelPredicate(_) => Some(Polarity::Positive),
-        BodyTerm::Negation(_) => Some(Polarity::Negative),
-        BodyTerm::VarPredicate(_) => None,
-        BodyTerm::Aggregation(_) => Some(Polarity::Negative),
+fn clause_depends_on(program: &Program, clause: &Clause) -> Vec<Edge> {
+    match clause {
+        Clause::Fact(_) => vec![],
+        Clause::Rule(rule) => {
+            let schema = program
+                .declarations()
+                .iter()
+                .find(|declaration| declaration.id() == rule.head())
+                .map(|declaration| declaration.schema());
+
+            rule_depends_on(rule, schema.as_deref())
+        }
     }
 }
 
-fn term_depends_on(term: &BodyTerm) -> Vec<Arc<Declaration>> {
+fn rule_depends_on(rule: &Rule, schema: Option<&Schema>) -> Vec<Edge> {
+    rule.body()
+        .iter()
+        .flat_map(|term| term_depends_on(rule, schema, term))
+        .map(|(dependency, polarity)| match dependency.source() {
+            Source::Edb => Edge::FromEDB(dependency.id(), rule.head(), polarity),
+            Source::Idb => Edge::FromIDB(dependency.id(), rule.head(), polarity),
+        })
+        .collect()
+}
+
+/// The relations `term` depends on, and how. The relations searched in the body
+/// of an aggregation depend on whether it's monotone, but those it negates are
+/// always negative dependencies.
+fn term_depends_on(
+    rule: &Rule,
+    schema: Option<&Schema>,
+    term: &BodyTerm,
+) -> Vec<(Arc<Declaration>, Polarity)> {
     match term {
-        BodyTerm::RelPredicate(inner) => vec![inner.relation()],
-        BodyTerm::Negation(inner) => vec![inner.relation()],
-        BodyTerm::VarPredicate(_) => vec![],
-        BodyTerm::Aggregation(inner) => vec![inner.relation()],
+        BodyTerm::RelPredicate(inner) => vec![(inner.relation(), Polarity::Positive)],
+        BodyTerm::Negation(inner) => vec![(
+            inner.relation(),
+            Polarity::Negative(NegativeDependency::Negation),
+        )],
+        BodyTerm::VarPredicate(_) | BodyTerm::Computation(_) => vec![],
+        BodyTerm::Aggregation(inner) => {
+            let polarity = if is_monotone(rule, schema, inner) {
+                Polarity::Positive
+            } else {
+                Polarity::Negative(NegativeDependency::Aggregation)
+            };
+
+            inner
+                .body()
+                .iter()
+                .flat_map(|term| term_depends_on(rule, schema, term))
+                .map(|(dependency, inner_polarity)| match inner_polarity {
+                    Polarity::Positive => (dependency, polarity),
+                    Polarity::Negative(_) => (dependency, inner_polarity),
+                })
+                .collect()
+        }
     }
 }
+
+/// An aggregation whose result is bound directly to the lattice column of the
+/// head is monotone, if the aggregate moves its result in the same direction as
+/// the lattice's join: the result of aggregating over part of a group is joined
+/// with the result over the whole group, once it's derived, so it can be
+/// evaluated within the recursion.
+fn is_monotone(rule: &Rule, schema: Option<&Schema>, aggregation: &Aggregation) -> bool {
+    let Some(schema) = schema else {
+        return false;
+    };
+
+    let bound_to_lattice = schema.lattice().map_or(false, |col| {
+        matches!(
+            rule.args().get(&col),
+            Some(ColVal::Binding(var)) if var.id() == aggregation.target().id()
+        )
+    });
+
+    bound_to_lattice
+        && schema.lattice_monotonicity().is_some()
+        && aggregation.agg().monotonicity() == schema.lattice_monotonicity()
+}
@@ -5,13 +5,21 @@ pub trait PredicateWhere<I> {
 
     fn into_predicate(self) -> Self::Predicate;
 
-    // TODO: support arbitrary expressions here, instead of just vars
+    /// The variables the predicate is applied to. To apply it to an expression, bind the
+    /// expression to a variable with [`RuleBodyBuilder::compute`].
+    ///
+    /// [`RuleBodyBuilder::compute`]: crate::RuleBodyBuilder::compute
     fn as_args(&self) -> Vec<Var>;
 }
 
 pub trait Predicate: Sized {
     type Input;
 
+    /// The name of a built-in predicate, which programs can refer to it by.
+    fn name(&self) -> Option<&str> {
+        None
+    }
+
     // TODO: Make this Result<bool, E> with generic E?
     fn apply(&self, args: Self::Input) -> Option<bool>;
 }
@@ -22,12 +30,17 @@ where
 {
     type Input = T::Input;
 
+    fn name(&self) -> Option<&str> {
+        (**self).name()
+    }
+
     fn apply(&self, args: Self::Input) -> Option<bool> {
         (**self).apply(args)
     }
 }
 
 pub trait PredicateWrapper: Send + Sync + 'static {
+    fn name(&self) -> Option<&str>;
     fn apply(&self, args: Vec<Val>) -> Option<bool>;
 }
 
@@ -36,8 +49,12 @@ where
     T: Predicate<Input = I> + Send + Sync + 'static,
     I: Args,
 {
+    fn name(&self) -> Option<&str> {
+        T::name(self)
+    }
+
     fn apply(&self, args: Vec<Val>) -> Option<bool> {
-        let args = <T::Input as Args>::instantiate(args).unwrap();
+        let args = <T::Input as Args>::instantiate(&args).ok()?;
 
         T::apply(self, args)
     }
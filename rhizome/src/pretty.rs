@@ -1,5 +1,9 @@
 use pretty::RcDoc;
 
+/// Printed in place of the name of an anonymous predicate, function or aggregate,
+/// such as a closure. It can't be parsed, since source can only refer to them by name.
+pub(crate) const ANONYMOUS: &str = "<anonymous>";
+
 pub trait Pretty {
     fn to_doc(&self) -> RcDoc<'_, ()>;
 }
@@ -15,16 +19,22 @@ mod tests {
     use std::sync::{Arc, RwLock};
 
     use anyhow::Result;
+    use cid::Cid;
     use im::hashmap;
     use pretty_assertions::assert_eq;
 
     use crate::{
+        expr::Expr,
+        function::Function,
+        kernel,
+        logic::{lower_to_ram::lower_to_ram, ProgramBuilder},
         ram::{
             formula::Formula,
             operation::{project::Project, search::Search, Operation},
             term::Term,
         },
         relation::{DefaultRelation, Version},
+        types::Any,
         value::Val,
     };
 
@@ -67,4 +77,196 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_pretty_logic() -> Result<()> {
+        let program = ProgramBuilder::build(|p| {
+            p.parse(
+                r#"
+                .input edge(from: s32, to: s32)
+                .input label(node: s32, name: string)
+                .output path(from: s32, to: s32)
+                .output unlabeled(node: s32)
+                .output degree(node: s32, n: s32)
+                .output root(node: s32)
+                .output forward(from: s32, to: s32)
+
+                root(node: 0).
+                path(from: x, to: y) :- edge(from: x, to: y).
+                path(from: x, to: z) :- edge(from: x, to: y), path(from: y, to: z).
+                unlabeled(node: x) :- path(from: x, to: _x), !label(node: x, name: "root").
+                degree(node: x, n: n) :- edge(from: x), n = count() : edge(from: x).
+                forward(from: x, to: y) :- edge(from: x, to: y), lt(x, y).
+                "#,
+            )?;
+
+            Ok(p)
+        })?;
+
+        let mut w = Vec::new();
+        program.to_doc().render(80, &mut w)?;
+
+        assert_eq!(
+            r#".output degree(n: s32, node: s32)
+.input edge(from: s32, to: s32)
+.input evac(attribute: any, entity: any, value: any)
+.output forward(from: s32, to: s32)
+.input label(name: string, node: s32)
+.input links(from: cid, to: cid)
+.output path(from: s32, to: s32)
+.output root(node: s32)
+.output unlabeled(node: s32)
+
+root(node: 0).
+path(from: x, to: y) :- edge(from: x, to: y).
+path(from: x, to: z) :- edge(from: x, to: y), path(from: y, to: z).
+unlabeled(node: x) :- path(from: x, to: _x), !label(name: "root", node: x).
+degree(n: n, node: x) :- edge(from: x), n = count() : edge(from: x).
+forward(from: x, to: y) :- edge(from: x, to: y), lt(x, y)."#,
+            String::from_utf8(w)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pretty_logic_function_names() -> Result<()> {
+        struct Succ;
+
+        impl Function for Succ {
+            type Input = (i32,);
+            type Output = i32;
+
+            fn name(&self) -> Option<&str> {
+                Some("succ")
+            }
+
+            fn apply(&self, (x,): Self::Input) -> Option<i32> {
+                Some(x + 1)
+            }
+        }
+
+        let program = ProgramBuilder::build(|p| {
+            p.input("num", |h| h.column::<i32>("n"))?;
+            p.output("next", |h| h.column::<i32>("n").column::<i32>("m"))?;
+
+            p.rule::<(i32, i32)>("next", &|h, b, (x, y)| {
+                h.bind((("n", y), ("m", Expr::call(Succ, [y]))))?;
+                b.search("num", (("n", x),))?;
+                b.compute(y, kernel::func(|(x,): (i32,)| x * 2), (x,))?;
+
+                Ok(())
+            })?;
+
+            Ok(p)
+        })?;
+
+        let mut w = Vec::new();
+        program.to_doc().render(80, &mut w)?;
+        let printed = String::from_utf8(w)?;
+
+        // Closures have no name that source could refer to them by
+        assert_eq!(
+            Some("next(m: succ(x1), n: x1) :- num(n: x0), x1 = <anonymous>(x0)."),
+            printed.lines().last()
+        );
+
+        // The lowered program names them the same way
+        let mut w = Vec::new();
+        lower_to_ram(&program)?.to_doc().render(80, &mut w)?;
+        let lowered = String::from_utf8(w)?;
+
+        assert!(lowered.contains("succ("), "{lowered}");
+        assert!(lowered.contains(" = <anonymous>("), "{lowered}");
+        assert!(!lowered.contains("UDF"), "{lowered}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pretty_logic_round_trip() -> Result<()> {
+        let program = ProgramBuilder::build(|p| {
+            p.output("value", |h| {
+                h.column::<f64>("f")
+                    .column::<f32>("g")
+                    .column::<Arc<str>>("s")
+                    .column::<char>("c")
+            })?;
+            p.output("copy", |h| h.column::<f64>("f").column::<f32>("g"))?;
+            p.input("reading", |h| {
+                h.column::<Cid>("source")
+                    .column::<Any>("raw")
+                    .column::<u64>("at")
+            })?;
+
+            p.fact("value", |h| {
+                h.bind((
+                    ("f", 1e20),
+                    ("g", 0.1_f32),
+                    ("s", "\"\\\n\u{7f}é"),
+                    ("c", '\''),
+                ))
+            })?;
+            p.fact("value", |h| {
+                h.bind((
+                    ("f", f64::NAN),
+                    ("g", f32::NEG_INFINITY),
+                    ("s", "'"),
+                    ("c", '"'),
+                ))
+            })?;
+            p.fact("value", |h| {
+                h.bind((("f", 1e-7), ("g", f32::INFINITY), ("s", ""), ("c", '\t')))
+            })?;
+
+            // Variables may share their names with non-finite floats
+            p.parse("copy(f: nan, g: inf) :- value(f: nan, g: inf).")?;
+
+            Ok(p)
+        })?;
+
+        let mut w = Vec::new();
+        program.to_doc().render(80, &mut w)?;
+        let printed = String::from_utf8(w)?;
+
+        // Characters that Debug would escape, such as DEL, are printed as they are
+        assert_eq!(
+            format!(
+                r#".output copy(f: f64, g: f32)
+.input evac(attribute: any, entity: any, value: any)
+.input links(from: cid, to: cid)
+.input reading(at: u64, raw: any, source: cid)
+.output value(c: char, f: f64, g: f32, s: string)
+
+value(c: '\'', f: 100000000000000000000.0, g: 0.1, s: "\"\\\n{}é").
+value(c: '"', f: float"nan", g: float"-inf", s: "'").
+value(c: '\t', f: 0.0000001, g: float"inf", s: "").
+copy(f: nan, g: inf) :- value(f: nan, g: inf)."#,
+                '\u{7f}'
+            ),
+            printed
+        );
+
+        let reparsed = ProgramBuilder::build(|p| {
+            // The preamble relations are already declared by the builder
+            let source = printed
+                .lines()
+                .filter(|line| {
+                    !line.starts_with(".input evac(") && !line.starts_with(".input links(")
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            p.parse(&source)?;
+
+            Ok(p)
+        })?;
+
+        let mut w = Vec::new();
+        reparsed.to_doc().render(80, &mut w)?;
+
+        assert_eq!(printed, String::from_utf8(w)?);
+
+        Ok(())
+    }
 }
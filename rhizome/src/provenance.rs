@@ -0,0 +1,344 @@
+//! Records why each tuple was derived, for programs built with
+//! [`record_provenance`](crate::ProgramBuilder::record_provenance).
+
+use anyhow::Result;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+use crate::{tuple::Tuple, value::Val};
+
+/// Why a tuple is in a relation: either it was inserted, or a rule derived it
+/// from the tuples its body matched, each of which has a derivation of its own.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Derivation {
+    tuple: Tuple,
+    rule: Option<Arc<str>>,
+    bindings: BTreeMap<String, Val>,
+    premises: Vec<Derivation>,
+}
+
+impl Derivation {
+    /// The tuple that was derived, with its CID if it's an input.
+    pub fn tuple(&self) -> &Tuple {
+        &self.tuple
+    }
+
+    /// The rule that derived the tuple, or None if it was inserted as an input or
+    /// a fact.
+    pub fn rule(&self) -> Option<&str> {
+        self.rule.as_deref()
+    }
+
+    /// The values bound to each variable of the rule when it derived the tuple.
+    pub fn bindings(&self) -> &BTreeMap<String, Val> {
+        &self.bindings
+    }
+
+    /// The derivations of the tuples matched by the body of the rule.
+    pub fn premises(&self) -> &[Derivation] {
+        &self.premises
+    }
+}
+
+/// A single application of a rule, as recorded when it derives a tuple, or the
+/// insertion of one of the program's facts, which has no rule.
+#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub(crate) struct Step {
+    rule: Option<Arc<str>>,
+    bindings: BTreeMap<String, Val>,
+    premises: Vec<Tuple>,
+}
+
+impl Step {
+    pub(crate) fn new(
+        rule: Option<Arc<str>>,
+        bindings: BTreeMap<String, Val>,
+        premises: impl IntoIterator<Item = Tuple>,
+    ) -> Self {
+        Self {
+            rule,
+            bindings,
+            premises: premises.into_iter().collect(),
+        }
+    }
+}
+
+/// Every step that derived each tuple, along with the tuples derived by a step
+/// that matched each premise. Steps are forgotten once the tuple they derived,
+/// or any of their premises, is retracted.
+#[derive(Debug, Default)]
+pub(crate) struct Provenance {
+    steps: BTreeMap<Tuple, BTreeSet<Step>>,
+    dependents: BTreeMap<Tuple, BTreeSet<Tuple>>,
+}
+
+impl Provenance {
+    pub(crate) fn record(&mut self, tuple: Tuple, step: Step) {
+        for premise in &step.premises {
+            self.dependents
+                .entry(premise.clone())
+                .or_default()
+                .insert(tuple.clone());
+        }
+
+        self.steps.entry(tuple).or_default().insert(step);
+    }
+
+    /// Forgets every step that derived `tuple`, and every step that matched it.
+    pub(crate) fn forget(&mut self, tuple: &Tuple) {
+        for step in self.steps.remove(tuple).into_iter().flatten() {
+            for premise in &step.premises {
+                self.forget_dependent(premise, tuple);
+            }
+        }
+
+        for dependent in self.dependents.remove(tuple).into_iter().flatten() {
+            let Some(steps) = self.steps.get_mut(&dependent) else {
+                continue;
+            };
+
+            let (forgotten, kept): (BTreeSet<_>, BTreeSet<_>) = std::mem::take(steps)
+                .into_iter()
+                .partition(|step| step.premises.contains(tuple));
+
+            // A premise shared with a kept step still has the dependent
+            for step in forgotten {
+                for premise in &step.premises {
+                    if !kept.iter().any(|other| other.premises.contains(premise)) {
+                        self.forget_dependent(premise, &dependent);
+                    }
+                }
+            }
+
+            // A tuple no step derived any longer has nothing to explain it by,
+            // rather than being taken for an input
+            if kept.is_empty() {
+                self.steps.remove(&dependent);
+            } else {
+                self.steps.insert(dependent, kept);
+            }
+        }
+    }
+
+    fn forget_dependent(&mut self, premise: &Tuple, dependent: &Tuple) {
+        if let Some(dependents) = self.dependents.get_mut(premise) {
+            dependents.remove(dependent);
+
+            if dependents.is_empty() {
+                self.dependents.remove(premise);
+            }
+        }
+    }
+
+    /// Explains `tuple` by a step whose premises can each be explained in turn,
+    /// down to inputs, which have no steps. `find` returns the stored tuple equal
+    /// to the given one, if it's present. Returns None if `tuple` isn't present,
+    /// or if every step that derived it depends on a premise that isn't.
+    ///
+    /// Rather than backtracking through the steps, which can take exponential
+    /// time when tuples are derived from each other, the tuples that can be
+    /// explained are found bottom-up, from the inputs reachable from `tuple`.
+    /// Each is explained by the first step found whose premises were explained
+    /// before it, so no derivation depends on itself.
+    pub(crate) fn explain(
+        &self,
+        tuple: &Tuple,
+        find: &dyn Fn(&Tuple) -> Result<Option<Tuple>>,
+    ) -> Result<Option<Derivation>> {
+        // Every tuple reachable from `tuple`, as stored, if it's present
+        let mut stored = BTreeMap::default();
+        let mut pending = vec![tuple.clone()];
+
+        while let Some(next) = pending.pop() {
+            if stored.contains_key(&next) {
+                continue;
+            }
+
+            let found = find(&next)?;
+
+            if found.is_some() {
+                for step in self.steps.get(&next).into_iter().flatten() {
+                    pending.extend(step.premises.iter().cloned());
+                }
+            }
+
+            stored.insert(next, found);
+        }
+
+        let mut explained: BTreeMap<&Tuple, Option<&Step>> = BTreeMap::default();
+
+        loop {
+            let mut changed = false;
+
+            for (tuple, found) in &stored {
+                if found.is_none() || explained.contains_key(tuple) {
+                    continue;
+                }
+
+                // Tuples that were never derived were inserted as inputs
+                let Some(steps) = self.steps.get(tuple) else {
+                    explained.insert(tuple, None);
+                    changed = true;
+
+                    continue;
+                };
+
+                if let Some(step) = steps.iter().find(|step| {
+                    step.premises
+                        .iter()
+                        .all(|premise| explained.contains_key(premise))
+                }) {
+                    explained.insert(tuple, Some(step));
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                break;
+            }
+        }
+
+        Ok(Self::derivation(tuple, &stored, &explained))
+    }
+
+    fn derivation(
+        tuple: &Tuple,
+        stored: &BTreeMap<Tuple, Option<Tuple>>,
+        explained: &BTreeMap<&Tuple, Option<&Step>>,
+    ) -> Option<Derivation> {
+        let step = explained.get(tuple)?;
+        let found = stored.get(tuple)?.clone()?;
+
+        let Some(step) = step else {
+            return Some(Derivation {
+                tuple: found,
+                rule: None,
+                bindings: BTreeMap::default(),
+                premises: Vec::default(),
+            });
+        };
+
+        let premises = step
+            .premises
+            .iter()
+            .map(|premise| Self::derivation(premise, stored, explained))
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(Derivation {
+            tuple: found,
+            rule: step.rule.clone(),
+            bindings: step.bindings.clone(),
+            premises,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain_skips_cycles() -> Result<()> {
+        let tuple = |id: &str, n: i32| Tuple::new(id, [("n", n)], None);
+        let step = |rule: &str, premises: Vec<Tuple>| {
+            Step::new(Some(rule.into()), BTreeMap::default(), premises)
+        };
+
+        let mut provenance = Provenance::default();
+
+        // a and b were each only derived from the other, and c from either a or e
+        provenance.record(tuple("a", 0), step("r0", vec![tuple("b", 0)]));
+        provenance.record(tuple("b", 0), step("r1", vec![tuple("a", 0)]));
+        provenance.record(tuple("c", 0), step("r2", vec![tuple("a", 0)]));
+        provenance.record(tuple("c", 0), step("r3", vec![tuple("e", 0)]));
+
+        let find = |tuple: &Tuple| Ok(Some(tuple.clone()));
+
+        assert_eq!(provenance.explain(&tuple("a", 0), &find)?, None);
+        assert_eq!(provenance.explain(&tuple("b", 0), &find)?, None);
+
+        let derivation = provenance.explain(&tuple("c", 0), &find)?.unwrap();
+
+        assert_eq!(derivation.rule(), Some("r3"));
+        assert_eq!(derivation.premises().len(), 1);
+        assert_eq!(derivation.premises()[0].tuple(), &tuple("e", 0));
+        assert_eq!(derivation.premises()[0].rule(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain_dense_cycles() -> Result<()> {
+        let tuple = |n: i32| Tuple::new("t", [("n", n)], None);
+
+        let mut provenance = Provenance::default();
+
+        // Each tuple was derived from every pair of the others, and only the last
+        // from an input
+        for n in 0..16 {
+            for m in 0..16 {
+                for k in 0..16 {
+                    if n != m && n != k && m != k {
+                        provenance.record(
+                            tuple(n),
+                            Step::new(Some("r0".into()), BTreeMap::default(), [tuple(m), tuple(k)]),
+                        );
+                    }
+                }
+            }
+        }
+
+        provenance.record(
+            tuple(15),
+            Step::new(Some("r1".into()), BTreeMap::default(), [tuple(16)]),
+        );
+
+        let find = |tuple: &Tuple| Ok(Some(tuple.clone()));
+
+        assert_eq!(provenance.explain(&tuple(0), &find)?, None);
+
+        let derivation = provenance.explain(&tuple(15), &find)?.unwrap();
+
+        assert_eq!(derivation.rule(), Some("r1"));
+        assert_eq!(derivation.premises()[0].tuple(), &tuple(16));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_forget() -> Result<()> {
+        let tuple = |id: &str, n: i32| Tuple::new(id, [("n", n)], None);
+        let step = |rule: &str, premises: Vec<Tuple>| {
+            Step::new(Some(rule.into()), BTreeMap::default(), premises)
+        };
+
+        let mut provenance = Provenance::default();
+
+        // c was derived from a and b, or from d alone, and e from c
+        provenance.record(
+            tuple("c", 0),
+            step("r0", vec![tuple("a", 0), tuple("b", 0)]),
+        );
+        provenance.record(tuple("c", 0), step("r1", vec![tuple("d", 0)]));
+        provenance.record(tuple("e", 0), step("r2", vec![tuple("c", 0)]));
+
+        provenance.forget(&tuple("a", 0));
+
+        assert_eq!(
+            provenance.steps[&tuple("c", 0)],
+            BTreeSet::from_iter([step("r1", vec![tuple("d", 0)])])
+        );
+        assert!(!provenance.dependents.contains_key(&tuple("a", 0)));
+        assert!(!provenance.dependents.contains_key(&tuple("b", 0)));
+
+        // Forgetting c forgets the steps that derived it, and those it matched
+        provenance.forget(&tuple("c", 0));
+
+        assert!(provenance.steps.is_empty());
+        assert!(provenance.dependents.is_empty());
+
+        Ok(())
+    }
+}
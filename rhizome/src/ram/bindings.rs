@@ -3,7 +3,7 @@ use anyhow::Result;
 use crate::{
     error::Error,
     id::{ColId, RelationId},
-    storage::blockstore::Blockstore,
+    tuple::Tuple,
     value::Val,
     var::Var,
 };
@@ -11,7 +11,11 @@ use crate::{
 use super::{AliasId, Formula, Term};
 
 #[derive(Debug, Clone, Default)]
-pub(crate) struct Bindings(im::HashMap<BindingKey, Val>);
+pub(crate) struct Bindings {
+    vals: im::HashMap<BindingKey, Val>,
+    // The tuples matched so far, which are only kept while recording provenance
+    matched: Option<im::Vector<Tuple>>,
+}
 
 // TODO: Put Links in here as they're resolved,
 // so that we can memoize their resolution; see https://github.com/RhizomeDB/rs-rhizome/issues/23
@@ -19,57 +23,80 @@ pub(crate) struct Bindings(im::HashMap<BindingKey, Val>);
 pub(crate) enum BindingKey {
     Relation(RelationId, Option<AliasId>, ColId),
     Cid(RelationId, Option<AliasId>),
-    Agg(RelationId, Option<AliasId>, Var),
+    Agg(Var),
+    Computed(Var),
 }
 
 impl Bindings {
+    /// Bindings that keep track of the tuples matched by each search.
+    pub(crate) fn with_provenance() -> Self {
+        Self {
+            vals: im::HashMap::default(),
+            matched: Some(im::Vector::default()),
+        }
+    }
+
     pub(crate) fn insert(&mut self, key: BindingKey, term: Val) {
-        self.0.insert(key, term);
+        self.vals.insert(key, term);
+    }
+
+    pub(crate) fn record_match(&mut self, tuple: &Tuple) {
+        if let Some(matched) = &mut self.matched {
+            matched.push_back(tuple.clone());
+        }
     }
 
-    pub(crate) fn resolve<BS>(&self, term: &Term, _blockstore: &BS) -> Result<Option<Val>>
-    where
-        BS: Blockstore,
-    {
+    pub(crate) fn matched(&self) -> impl Iterator<Item = &Tuple> {
+        self.matched.iter().flatten()
+    }
+
+    pub(crate) fn resolve(&self, term: &Term) -> Result<Option<Val>> {
         match term {
             Term::Col(relation_id, alias, col_id) => Ok(self
-                .0
+                .vals
                 .get(&BindingKey::Relation(*relation_id, *alias, *col_id))
                 .cloned()),
 
-            Term::Cid(relation_id, alias) => {
-                Ok(self.0.get(&BindingKey::Cid(*relation_id, *alias)).cloned())
-            }
+            Term::Cid(relation_id, alias) => Ok(self
+                .vals
+                .get(&BindingKey::Cid(*relation_id, *alias))
+                .cloned()),
 
             Term::Lit(val) => Ok(Some(val).cloned()),
 
-            Term::Agg(relation_id, alias, var) => Ok(self
-                .0
-                .get(&BindingKey::Agg(*relation_id, *alias, *var))
-                .cloned()),
+            Term::Agg(var) => Ok(self.vals.get(&BindingKey::Agg(*var)).cloned()),
+
+            Term::Computed(var) => Ok(self.vals.get(&BindingKey::Computed(*var)).cloned()),
+
+            Term::Expr(expr) => {
+                let mut args = Vec::default();
+
+                for term in expr.args() {
+                    let Some(val) = self.resolve(term)? else {
+                        return Ok(None);
+                    };
+
+                    args.push(val);
+                }
+
+                Ok(expr.apply(args))
+            }
         }
     }
 
-    pub(crate) fn is_formula_satisfied<BS>(
-        &self,
-        formula: &Formula,
-        blockstore: &BS,
-    ) -> Result<bool>
-    where
-        BS: Blockstore,
-    {
+    pub(crate) fn is_formula_satisfied(&self, formula: &Formula) -> Result<bool> {
         match formula {
             Formula::Equality(inner) => {
-                let left = self.resolve::<BS>(inner.left(), blockstore)?;
-                let right = self.resolve::<BS>(inner.right(), blockstore)?;
+                let left = self.resolve(inner.left())?;
+                let right = self.resolve(inner.right())?;
 
                 Ok(left == right)
             }
-            Formula::NotIn(inner) => inner.is_satisfied(blockstore, self),
+            Formula::NotIn(inner) => inner.is_satisfied(self),
             Formula::Predicate(inner) => {
                 let mut args = Vec::default();
                 for term in inner.args() {
-                    let resolved = self.resolve::<BS>(term, blockstore)?.ok_or_else(|| {
+                    let resolved = self.resolve(term)?.ok_or_else(|| {
                         Error::InternalRhizomeError(
                             "argument to predicate failed to resolve".to_owned(),
                         )
@@ -78,7 +105,7 @@ impl Bindings {
                     args.push(resolved);
                 }
 
-                inner.is_satisfied(args)
+                Ok(inner.is_satisfied(args))
             }
         }
     }
@@ -0,0 +1,104 @@
+use pretty::RcDoc;
+use std::{
+    fmt::{self, Debug},
+    sync::Arc,
+};
+
+use crate::{
+    expr::{self, Op},
+    function::FunctionWrapper,
+    pretty::{Pretty, ANONYMOUS},
+    value::Val,
+};
+
+use super::Term;
+
+#[derive(Clone)]
+pub struct Expr {
+    f: ExprFn,
+    args: Vec<Term>,
+}
+
+#[derive(Clone)]
+pub(crate) enum ExprFn {
+    Op(Op),
+    Concat,
+    Call(Arc<dyn FunctionWrapper>),
+}
+
+impl Expr {
+    pub(crate) fn new(f: ExprFn, args: Vec<Term>) -> Self {
+        Self { f, args }
+    }
+
+    pub(crate) fn args(&self) -> &Vec<Term> {
+        &self.args
+    }
+
+    /// Applies the expression to its resolved arguments, producing no value if
+    /// they're of the wrong type, or if the computation fails.
+    pub(crate) fn apply(&self, mut args: Vec<Val>) -> Option<Val> {
+        match &self.f {
+            ExprFn::Op(op) => {
+                let right = args.pop()?;
+                let left = args.pop()?;
+
+                op.apply(left, right)
+            }
+            ExprFn::Concat => expr::concat(args),
+            ExprFn::Call(f) => f.apply(args),
+        }
+    }
+}
+
+impl Pretty for Expr {
+    fn to_doc(&self) -> RcDoc<'_, ()> {
+        match (&self.f, self.args.as_slice()) {
+            (ExprFn::Op(op), [left, right]) => RcDoc::concat([
+                RcDoc::text("("),
+                left.to_doc(),
+                RcDoc::text(" "),
+                RcDoc::as_string(op),
+                RcDoc::text(" "),
+                right.to_doc(),
+                RcDoc::text(")"),
+            ]),
+            (f, args) => {
+                let name = match f {
+                    ExprFn::Op(op) => op.to_string(),
+                    ExprFn::Concat => "concat".to_owned(),
+                    ExprFn::Call(f) => f.name().unwrap_or(ANONYMOUS).to_owned(),
+                };
+
+                let args_doc = RcDoc::intersperse(
+                    args.iter().map(|arg| arg.to_doc()),
+                    RcDoc::text(",").append(RcDoc::line()),
+                )
+                .nest(1)
+                .group();
+
+                RcDoc::concat([
+                    RcDoc::text(name),
+                    RcDoc::text("("),
+                    args_doc,
+                    RcDoc::text(")"),
+                ])
+            }
+        }
+    }
+}
+
+impl Debug for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match &self.f {
+            ExprFn::Op(op) => op.to_string(),
+            ExprFn::Concat => "concat".to_owned(),
+            ExprFn::Call(f) => f.name().unwrap_or(ANONYMOUS).to_owned(),
+        };
+
+        f.debug_struct("Expr")
+            .field("f", &name)
+            .field("args", &self.args)
+            .finish()
+    }
+}
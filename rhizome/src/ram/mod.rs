@@ -1,6 +1,7 @@
 pub(crate) mod alias_id;
 pub(crate) mod bindings;
 pub(crate) mod equality;
+pub(crate) mod expr;
 pub(crate) mod formula;
 pub(crate) mod not_in;
 pub(crate) mod operation;
@@ -12,6 +13,7 @@ pub(crate) mod term;
 pub(crate) use alias_id::*;
 pub(crate) use bindings::*;
 pub(crate) use equality::*;
+pub(crate) use expr::*;
 pub(crate) use formula::*;
 pub(crate) use not_in::*;
 pub(crate) use operation::*;
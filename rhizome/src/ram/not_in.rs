@@ -11,7 +11,6 @@ use crate::{
     id::ColId,
     pretty::Pretty,
     relation::{Relation, RelationKey},
-    storage::blockstore::Blockstore,
     value::Val,
 };
 
@@ -50,15 +49,15 @@ impl NotIn {
         &self.cols
     }
 
-    pub(crate) fn is_satisfied<BS>(&self, blockstore: &BS, bindings: &Bindings) -> Result<bool>
-    where
-        BS: Blockstore,
-    {
+    pub(crate) fn is_satisfied(&self, bindings: &Bindings) -> Result<bool> {
         let mut bound: Vec<(ColId, Val)> = Vec::default();
 
         for (id, term) in self.cols() {
-            if let Some(val) = bindings.resolve::<BS>(term, blockstore)? {
+            if let Some(val) = bindings.resolve(term)? {
                 bound.push((*id, <Val>::clone(&val)));
+            } else if term.is_expr() {
+                // The expression can't be computed, so there's nothing to project
+                return Ok(false);
             } else {
                 return error(Error::InternalRhizomeError(format!(
                     "failed to resolve term for column: {}",
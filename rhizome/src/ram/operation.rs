@@ -4,10 +4,12 @@ use pretty::RcDoc;
 use crate::pretty::Pretty;
 
 pub(crate) mod aggregation;
+pub(crate) mod compute;
 pub(crate) mod project;
 pub(crate) mod search;
 
 pub(crate) use aggregation::*;
+pub(crate) use compute::*;
 pub(crate) use project::*;
 pub(crate) use search::*;
 
@@ -16,6 +18,8 @@ pub(crate) enum Operation {
     Search(Search),
     Project(Project),
     Aggregation(Aggregation),
+    Compute(Compute),
+    Yield(Yield),
 }
 
 impl Pretty for Operation {
@@ -24,6 +28,8 @@ impl Pretty for Operation {
             Operation::Search(inner) => inner.to_doc(),
             Operation::Project(inner) => inner.to_doc(),
             Operation::Aggregation(inner) => inner.to_doc(),
+            Operation::Compute(inner) => inner.to_doc(),
+            Operation::Yield(inner) => inner.to_doc(),
         }
     }
 }
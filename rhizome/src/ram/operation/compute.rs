@@ -0,0 +1,133 @@
+use anyhow::Result;
+use std::{
+    fmt::{self, Debug},
+    sync::Arc,
+};
+
+use pretty::RcDoc;
+
+use crate::{
+    error::Error,
+    function::FunctionWrapper,
+    pretty::{Pretty, ANONYMOUS},
+    ram::{BindingKey, Bindings, Formula, Term},
+    var::Var,
+};
+
+use super::Operation;
+
+/// Binds `target` to the result of applying `f` to `args`, before running the
+/// nested operation.
+pub(crate) struct Compute {
+    args: Vec<Term>,
+    f: Arc<dyn FunctionWrapper>,
+    target: Var,
+    when: Vec<Formula>,
+    operation: Box<Operation>,
+}
+
+impl Compute {
+    pub(crate) fn new(
+        args: Vec<Term>,
+        f: Arc<dyn FunctionWrapper>,
+        target: Var,
+        when: impl IntoIterator<Item = Formula>,
+        operation: Operation,
+    ) -> Self {
+        let when = when.into_iter().collect();
+
+        Self {
+            args,
+            f,
+            target,
+            when,
+            operation: Box::new(operation),
+        }
+    }
+
+    pub(crate) fn operation(&self) -> &Operation {
+        &self.operation
+    }
+
+    pub(crate) fn apply(&self, bindings: &Bindings) -> Result<Option<Bindings>> {
+        for formula in &self.when {
+            if !bindings.is_formula_satisfied(formula)? {
+                return Ok(None);
+            }
+        }
+
+        let mut args = Vec::default();
+        for term in &self.args {
+            let resolved = bindings.resolve(term)?.ok_or_else(|| {
+                Error::InternalRhizomeError("argument to function failed to resolve".to_owned())
+            })?;
+
+            args.push(resolved);
+        }
+
+        if let Some(result) = self.f.apply(args) {
+            let mut next_bindings = bindings.clone();
+            next_bindings.insert(BindingKey::Computed(self.target), result);
+
+            Ok(Some(next_bindings))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl Debug for Compute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Compute")
+            .field("args", &self.args)
+            .field("target", &self.target)
+            .field("when", &self.when)
+            .finish()
+    }
+}
+
+impl Pretty for Compute {
+    fn to_doc(&self) -> RcDoc<'_, ()> {
+        let args_doc = RcDoc::intersperse(
+            self.args.iter().map(|arg| arg.to_doc()),
+            RcDoc::text(",").append(RcDoc::line()),
+        )
+        .nest(1)
+        .group();
+
+        let when_doc = if self.when.is_empty() {
+            RcDoc::nil()
+        } else {
+            RcDoc::text(" where")
+                .append(RcDoc::hardline())
+                .append(RcDoc::text("("))
+                .append(
+                    RcDoc::intersperse(
+                        self.when.iter().map(|formula| formula.to_doc()),
+                        RcDoc::text(" and "),
+                    )
+                    .nest(1)
+                    .group(),
+                )
+                .append(RcDoc::text(")"))
+        };
+
+        RcDoc::concat([
+            RcDoc::text("compute "),
+            RcDoc::as_string(self.target.id()),
+            RcDoc::text(" = "),
+            RcDoc::text(self.f.name().unwrap_or(ANONYMOUS)),
+            RcDoc::text("("),
+            args_doc,
+            RcDoc::text(")"),
+            when_doc,
+            RcDoc::text(" do"),
+        ])
+        .append(
+            RcDoc::hardline()
+                .append(self.operation().to_doc())
+                .nest(2)
+                .group(),
+        )
+    }
+}
@@ -1,18 +1,21 @@
 use anyhow::Result;
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     sync::{Arc, RwLock},
 };
 
 use pretty::RcDoc;
 
 use crate::{
+    col_val::ColVal,
     error::{error, Error},
-    id::ColId,
+    id::{ColId, VarId},
+    logic::Declaration,
     pretty::Pretty,
+    provenance::{Provenance, Step},
     ram::{term::Term, Bindings, Formula},
     relation::{Relation, RelationKey},
-    storage::blockstore::Blockstore,
+    timestamp::PairTimestamp,
     tuple::Tuple,
     value::Val,
 };
@@ -23,6 +26,12 @@ pub(crate) struct Project {
     cols: HashMap<ColId, Term>,
     relation: Arc<RwLock<Box<dyn Relation>>>,
     formulae: Vec<Formula>,
+    // The rendered rule that the projection belongs to, and the terms bound to
+    // each of its variables, for recording provenance. Facts have neither
+    rule: Option<Arc<str>>,
+    vars: Vec<(VarId, Term)>,
+    // Tuples projected to be deleted aren't derived, so no step is recorded
+    derives: bool,
 }
 
 impl Project {
@@ -46,30 +55,86 @@ impl Project {
             cols,
             formulae,
             relation,
+            rule: None,
+            vars: Vec::default(),
+            derives: true,
         }
     }
 
-    pub(crate) fn apply<BS>(&self, blockstore: &BS, bindings: &Bindings) -> Result<()>
-    where
-        BS: Blockstore,
-    {
+    pub(crate) fn relation_key(&self) -> RelationKey {
+        self.relation_key
+    }
+
+    pub(crate) fn with_rule(
+        mut self,
+        rule: Arc<str>,
+        vars: impl IntoIterator<Item = (VarId, Term)>,
+    ) -> Self {
+        self.rule = Some(rule);
+        self.vars = vars.into_iter().collect();
+        self
+    }
+
+    /// Projects tuples that are to be deleted, rather than derived, so records
+    /// no provenance for them.
+    pub(crate) fn deleting(mut self) -> Self {
+        self.derives = false;
+        self
+    }
+
+    pub(crate) fn apply(
+        &self,
+        bindings: &Bindings,
+        declaration: &Declaration,
+        timestamp: Option<PairTimestamp>,
+        provenance: Option<&mut Provenance>,
+    ) -> Result<()> {
         for formula in self.formulae.iter() {
-            if !bindings.is_formula_satisfied::<BS>(formula, blockstore)? {
+            if !bindings.is_formula_satisfied(formula)? {
                 return Ok(());
             }
         }
 
+        let schema = declaration.schema();
         let mut bound: Vec<(ColId, Val)> = Vec::default();
 
         for (id, term) in &self.cols {
-            if let Some(val) = bindings.resolve::<BS>(term, blockstore)? {
-                bound.push((*id, <Val>::clone(&val)));
-            } else {
+            let Some(val) = bindings.resolve(term)? else {
                 return Ok(());
+            };
+
+            // Variables of any type can be bound to a typed column, so values are
+            // checked as they're projected, before a relation has to handle them
+            if let Some(col) = schema.get_col(id) {
+                if col.col_type().check(&val).is_err() {
+                    return error(Error::ColumnValueTypeConflict(
+                        self.relation_key.0,
+                        *id,
+                        ColVal::Lit(<Val>::clone(&val)),
+                        *col.col_type(),
+                    ));
+                }
             }
+
+            bound.push((*id, <Val>::clone(&val)));
         }
 
-        let fact = Tuple::new(self.relation_key.0, bound.clone(), None);
+        let fact = Tuple::new(self.relation_key.0, bound.clone(), None).with_timestamp(timestamp);
+
+        // Facts are recorded without a rule, as they may also be derived by one
+        if let Some(provenance) = provenance.filter(|_| self.derives) {
+            let mut resolved = BTreeMap::default();
+
+            for (var, term) in &self.vars {
+                if let Some(val) = bindings.resolve(term)? {
+                    resolved.insert(var.resolve(), val);
+                }
+            }
+
+            let step = Step::new(self.rule.clone(), resolved, bindings.matched().cloned());
+
+            provenance.record(fact.clone(), step);
+        }
 
         self.relation
             .write()
@@ -9,7 +9,6 @@ use crate::{
     pretty::Pretty,
     ram::{alias_id::AliasId, formula::Formula, BindingKey, Bindings, Term},
     relation::{Relation, RelationKey},
-    storage::blockstore::Blockstore,
     value::Val,
 };
 
@@ -50,14 +49,13 @@ impl Search {
         &self.operation
     }
 
-    pub(crate) fn apply<BS, F>(&self, blockstore: &BS, bindings: &Bindings, f: F) -> Result<bool>
+    pub(crate) fn apply<F>(&self, bindings: &Bindings, f: F) -> Result<bool>
     where
-        BS: Blockstore,
         F: Fn(Bindings) -> Result<bool>,
     {
         let mut bound_cols = vec![];
         for (col_id, term) in self.bindings.iter() {
-            let resolved = bindings.resolve::<BS>(term, blockstore)?.ok_or_else(|| {
+            let resolved = bindings.resolve(term)?.ok_or_else(|| {
                 Error::InternalRhizomeError("expected binding not found".to_owned())
             })?;
 
@@ -76,6 +74,8 @@ impl Search {
         {
             let mut next_bindings = bindings.clone();
 
+            next_bindings.record_match(fact);
+
             // TODO: Only add the CID to the bindings if it's required by
             // a later operation.
             if let Some(cid) = fact.cid() {
@@ -98,7 +98,7 @@ impl Search {
 
             let mut satisfied = true;
             for formula in self.when.iter() {
-                if !next_bindings.is_formula_satisfied::<BS>(formula, blockstore)? {
+                if !next_bindings.is_formula_satisfied(formula)? {
                     satisfied = false;
                 }
             }
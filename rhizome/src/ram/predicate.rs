@@ -1,11 +1,14 @@
-use anyhow::Result;
 use pretty::RcDoc;
 use std::{
     fmt::{self, Debug},
     sync::Arc,
 };
 
-use crate::{error::Error, predicate::PredicateWrapper, pretty::Pretty, value::Val};
+use crate::{
+    predicate::PredicateWrapper,
+    pretty::{Pretty, ANONYMOUS},
+    value::Val,
+};
 
 use super::Term;
 
@@ -24,11 +27,10 @@ impl Predicate {
         &self.args
     }
 
-    pub(crate) fn is_satisfied(&self, args: Vec<Val>) -> Result<bool> {
-        Ok(self
-            .f
-            .apply(args)
-            .ok_or_else(|| Error::InternalRhizomeError("failed to apply predicate".to_owned()))?)
+    /// A predicate that can't be applied to its arguments, such as a comparison
+    /// between values of different types, isn't satisfied by them.
+    pub(crate) fn is_satisfied(&self, args: Vec<Val>) -> bool {
+        self.f.apply(args).unwrap_or(false)
     }
 }
 
@@ -41,7 +43,12 @@ impl Pretty for Predicate {
         .nest(1)
         .group();
 
-        RcDoc::concat([RcDoc::text("UDF("), args_doc, RcDoc::text(")")])
+        RcDoc::concat([
+            RcDoc::text(self.f.name().unwrap_or(ANONYMOUS)),
+            RcDoc::text("("),
+            args_doc,
+            RcDoc::text(")"),
+        ])
     }
 }
 
@@ -1,24 +1,72 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
 
 use pretty::RcDoc;
 
-use crate::pretty::Pretty;
+use crate::{
+    logic::Declaration,
+    pretty::Pretty,
+    relation::{Relation, RelationKey},
+};
 
 use super::Statement;
 
 #[derive(Debug)]
 pub struct Program {
     statements: Vec<Arc<Statement>>,
+    relations: HashMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>>,
+    // Kept to build queries against the relations, by name
+    declarations: HashMap<String, Arc<Declaration>>,
+    provenance: bool,
 }
 
 impl Program {
-    pub(crate) fn new(statements: Vec<Arc<Statement>>) -> Self {
-        Self { statements }
+    pub(crate) fn new(
+        statements: Vec<Arc<Statement>>,
+        relations: HashMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>>,
+    ) -> Self {
+        Self {
+            statements,
+            relations,
+            declarations: HashMap::default(),
+            provenance: false,
+        }
+    }
+
+    pub(crate) fn with_declarations(
+        mut self,
+        declarations: HashMap<String, Arc<Declaration>>,
+    ) -> Self {
+        self.declarations = declarations;
+        self
+    }
+
+    pub(crate) fn declarations(&self) -> &HashMap<String, Arc<Declaration>> {
+        &self.declarations
+    }
+
+    pub(crate) fn with_provenance(mut self, provenance: bool) -> Self {
+        self.provenance = provenance;
+        self
+    }
+
+    pub(crate) fn provenance(&self) -> bool {
+        self.provenance
     }
 
     pub(crate) fn statements(&self) -> &[Arc<Statement>] {
         &self.statements
     }
+
+    pub(crate) fn relation(&self, key: RelationKey) -> Option<&Arc<RwLock<Box<dyn Relation>>>> {
+        self.relations.get(&key)
+    }
+
+    pub(crate) fn relations(&self) -> &HashMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>> {
+        &self.relations
+    }
 }
 
 impl Pretty for Program {
@@ -3,20 +3,26 @@ use pretty::RcDoc;
 
 use crate::pretty::Pretty;
 
+pub(crate) mod branch;
+pub(crate) mod difference;
 pub(crate) mod exit;
 pub(crate) mod insert;
 pub(crate) mod merge;
 pub(crate) mod purge;
 pub(crate) mod recursive;
+pub(crate) mod remove;
 pub(crate) mod sinks;
 pub(crate) mod sources;
 pub(crate) mod swap;
 
+pub(crate) use branch::*;
+pub(crate) use difference::*;
 pub(crate) use exit::*;
 pub(crate) use insert::*;
 pub(crate) use merge::*;
 pub(crate) use purge::*;
 pub(crate) use recursive::*;
+pub(crate) use remove::*;
 pub(crate) use sinks::*;
 pub(crate) use sources::*;
 pub(crate) use swap::*;
@@ -29,7 +35,10 @@ pub(crate) enum Statement {
     Merge(Merge),
     Swap(Swap),
     Purge(Purge),
+    Difference(Difference),
+    Remove(Remove),
     Loop(Loop),
+    Branch(Branch),
     Exit(Exit),
     Sources(Sources),
     Sinks(Sinks),
@@ -42,7 +51,10 @@ impl Pretty for Statement {
             Statement::Merge(inner) => inner.to_doc(),
             Statement::Swap(inner) => inner.to_doc(),
             Statement::Purge(inner) => inner.to_doc(),
+            Statement::Difference(inner) => inner.to_doc(),
+            Statement::Remove(inner) => inner.to_doc(),
             Statement::Loop(inner) => inner.to_doc(),
+            Statement::Branch(inner) => inner.to_doc(),
             Statement::Exit(inner) => inner.to_doc(),
             Statement::Sources(inner) => inner.to_doc(),
             Statement::Sinks(inner) => inner.to_doc(),
@@ -0,0 +1,123 @@
+use anyhow::Result;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+};
+
+use pretty::RcDoc;
+
+use crate::{
+    error::{error, Error},
+    pretty::Pretty,
+    relation::{Relation, RelationKey},
+};
+
+use super::Statement;
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct BranchBuilder {
+    relations: BTreeMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>>,
+    negated: bool,
+}
+
+impl BranchBuilder {
+    pub(crate) fn add_relation(
+        &mut self,
+        relation_key: RelationKey,
+        relation: Arc<RwLock<Box<dyn Relation>>>,
+    ) {
+        self.relations.insert(relation_key, relation);
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.relations.is_empty()
+    }
+
+    /// Takes the branch when every relation is empty, rather than when any isn't.
+    pub(crate) fn negate(mut self) -> Self {
+        self.negated = true;
+        self
+    }
+
+    pub(crate) fn finalize(self, body: impl IntoIterator<Item = Arc<Statement>>) -> Branch {
+        Branch {
+            relations: self.relations,
+            negated: self.negated,
+            body: body.into_iter().collect(),
+        }
+    }
+}
+
+/// Runs its body once, if any of the relations it checks aren't empty.
+#[derive(Debug)]
+pub(crate) struct Branch {
+    relations: BTreeMap<RelationKey, Arc<RwLock<Box<dyn Relation>>>>,
+    negated: bool,
+    body: Vec<Arc<Statement>>,
+}
+
+impl Branch {
+    pub(crate) fn body(&self) -> &[Arc<Statement>] {
+        &self.body
+    }
+
+    pub(crate) fn apply(&self) -> Result<bool> {
+        for relation in self.relations.values() {
+            let is_empty = relation
+                .read()
+                .or_else(|_| {
+                    error(Error::InternalRhizomeError(
+                        "relation lock poisoned".to_owned(),
+                    ))
+                })?
+                .is_empty();
+
+            if !is_empty {
+                return Ok(!self.negated);
+            }
+        }
+
+        Ok(self.negated)
+    }
+}
+
+impl Pretty for Branch {
+    fn to_doc(&self) -> RcDoc<'_, ()> {
+        let relations_doc = RcDoc::intersperse(
+            self.relations.keys().map(|relation_key| {
+                RcDoc::concat([
+                    RcDoc::text("count("),
+                    relation_key.to_doc(),
+                    RcDoc::text(") > 0"),
+                ])
+            }),
+            RcDoc::text(" or "),
+        )
+        .nest(1)
+        .group();
+
+        let body_doc = RcDoc::hardline()
+            .append(RcDoc::intersperse(
+                self.body().iter().map(|statement| statement.to_doc()),
+                RcDoc::text(";")
+                    .append(RcDoc::hardline())
+                    .append(RcDoc::hardline()),
+            ))
+            .nest(2)
+            .group();
+
+        let condition_doc = if self.negated {
+            RcDoc::text("unless ")
+        } else {
+            RcDoc::text("if ")
+        };
+
+        condition_doc
+            .append(relations_doc)
+            .append(RcDoc::text(" do"))
+            .append(body_doc)
+            .append(RcDoc::text(";"))
+            .append(RcDoc::hardline())
+            .append(RcDoc::text("end"))
+    }
+}
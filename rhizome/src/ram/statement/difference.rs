@@ -0,0 +1,89 @@
+use anyhow::Result;
+use std::sync::{Arc, RwLock};
+
+use pretty::RcDoc;
+
+use crate::{
+    error::{error, Error},
+    id::ColId,
+    pretty::Pretty,
+    relation::{Relation, RelationKey},
+    value::Val,
+};
+
+/// Inserts the tuples of one relation that aren't in another into a third.
+/// Tuples are compared exactly, so a lattice tuple whose value is subsumed by
+/// another still counts as different.
+#[derive(Clone, Debug)]
+pub(crate) struct Difference {
+    from_key: RelationKey,
+    without_key: RelationKey,
+    into_key: RelationKey,
+    from_relation: Arc<RwLock<Box<dyn Relation>>>,
+    without_relation: Arc<RwLock<Box<dyn Relation>>>,
+    into_relation: Arc<RwLock<Box<dyn Relation>>>,
+}
+
+impl Difference {
+    pub(crate) fn new(
+        from: (RelationKey, Arc<RwLock<Box<dyn Relation>>>),
+        without: (RelationKey, Arc<RwLock<Box<dyn Relation>>>),
+        into: (RelationKey, Arc<RwLock<Box<dyn Relation>>>),
+    ) -> Self {
+        Self {
+            from_key: from.0,
+            without_key: without.0,
+            into_key: into.0,
+            from_relation: from.1,
+            without_relation: without.1,
+            into_relation: into.1,
+        }
+    }
+
+    pub(crate) fn apply(&self) -> Result<()> {
+        let mut into = self.into_relation.write().or_else(|_| {
+            error(Error::InternalRhizomeError(
+                "relation lock poisoned".to_owned(),
+            ))
+        })?;
+
+        let from = self.from_relation.read().or_else(|_| {
+            error(Error::InternalRhizomeError(
+                "relation lock poisoned".to_owned(),
+            ))
+        })?;
+
+        let without = self.without_relation.read().or_else(|_| {
+            error(Error::InternalRhizomeError(
+                "relation lock poisoned".to_owned(),
+            ))
+        })?;
+
+        for tuple in from.search(vec![]) {
+            let bindings: Vec<(ColId, Val)> = tuple
+                .cols()
+                .into_iter()
+                .filter_map(|id| tuple.col(&id).map(|val| (id, val)))
+                .collect();
+
+            if !without.search(bindings.clone()).any(|other| other == tuple) {
+                into.insert(bindings, tuple.clone());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Pretty for Difference {
+    fn to_doc(&self) -> RcDoc<'_, ()> {
+        RcDoc::concat([
+            RcDoc::text("merge "),
+            self.from_key.to_doc(),
+            RcDoc::text(" without "),
+            self.without_key.to_doc(),
+            RcDoc::text(" into "),
+            self.into_key.to_doc(),
+        ])
+    }
+}
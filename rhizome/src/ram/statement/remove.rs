@@ -0,0 +1,131 @@
+use anyhow::Result;
+use std::sync::{Arc, RwLock};
+
+use pretty::RcDoc;
+
+use crate::{
+    error::{error, Error},
+    id::ColId,
+    pretty::Pretty,
+    provenance::Provenance,
+    relation::{Relation, RelationKey},
+    value::Val,
+};
+
+/// Removes the tuples of one relation from another, in place. Tuples are compared
+/// exactly, like [`super::Difference`].
+#[derive(Clone, Debug)]
+pub(crate) struct Remove {
+    from_key: RelationKey,
+    relation_key: RelationKey,
+    from_relation: Arc<RwLock<Box<dyn Relation>>>,
+    relation: Arc<RwLock<Box<dyn Relation>>>,
+    // Where the removed tuples are moved to, as stored, if they're kept
+    into_key: Option<RelationKey>,
+    into_relation: Option<Arc<RwLock<Box<dyn Relation>>>>,
+    // Whether the removed tuples are retracted for good, so their provenance is
+    // forgotten
+    forgets: bool,
+}
+
+impl Remove {
+    pub(crate) fn new(
+        from: (RelationKey, Arc<RwLock<Box<dyn Relation>>>),
+        relation: (RelationKey, Arc<RwLock<Box<dyn Relation>>>),
+    ) -> Self {
+        Self {
+            from_key: from.0,
+            relation_key: relation.0,
+            from_relation: from.1,
+            relation: relation.1,
+            into_key: None,
+            into_relation: None,
+            forgets: false,
+        }
+    }
+
+    /// Moves the removed tuples into another relation, keeping the timestamps
+    /// they're stored with.
+    pub(crate) fn moving_into(
+        mut self,
+        into: (RelationKey, Arc<RwLock<Box<dyn Relation>>>),
+    ) -> Self {
+        self.into_key = Some(into.0);
+        self.into_relation = Some(into.1);
+        self
+    }
+
+    /// Forgets the provenance of the removed tuples, which are being retracted.
+    pub(crate) fn forgetting(mut self) -> Self {
+        self.forgets = true;
+        self
+    }
+
+    pub(crate) fn apply(&self, provenance: Option<&mut Provenance>) -> Result<()> {
+        let mut provenance = provenance.filter(|_| self.forgets);
+
+        let from = self.from_relation.read().or_else(|_| {
+            error(Error::InternalRhizomeError(
+                "relation lock poisoned".to_owned(),
+            ))
+        })?;
+
+        let mut relation = self.relation.write().or_else(|_| {
+            error(Error::InternalRhizomeError(
+                "relation lock poisoned".to_owned(),
+            ))
+        })?;
+
+        let mut into = match &self.into_relation {
+            Some(into) => Some(into.write().or_else(|_| {
+                error(Error::InternalRhizomeError(
+                    "relation lock poisoned".to_owned(),
+                ))
+            })?),
+            None => None,
+        };
+
+        for tuple in from.search(vec![]) {
+            let bindings: Vec<(ColId, Val)> = tuple
+                .cols()
+                .into_iter()
+                .filter_map(|id| tuple.col(&id).map(|val| (id, val)))
+                .collect();
+
+            let Some(stored) = relation
+                .search(bindings.clone())
+                .find(|other| *other == tuple)
+                .cloned()
+            else {
+                continue;
+            };
+
+            relation.remove(bindings.clone(), &stored);
+
+            if let Some(provenance) = &mut provenance {
+                provenance.forget(tuple);
+            }
+
+            if let Some(into) = &mut into {
+                into.insert(bindings, stored);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Pretty for Remove {
+    fn to_doc(&self) -> RcDoc<'_, ()> {
+        RcDoc::concat([
+            RcDoc::text("remove "),
+            self.from_key.to_doc(),
+            RcDoc::text(" from "),
+            self.relation_key.to_doc(),
+            match &self.into_key {
+                Some(key) => RcDoc::text(" into ").append(key.to_doc()),
+                None => RcDoc::nil(),
+            },
+        ])
+    }
+}
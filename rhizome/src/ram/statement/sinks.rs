@@ -1,6 +1,6 @@
 use anyhow::Result;
 use std::{
-    collections::{HashMap, VecDeque},
+    collections::HashMap,
     sync::{Arc, RwLock},
 };
 
@@ -11,48 +11,76 @@ use crate::{
     id::RelationId,
     pretty::Pretty,
     relation::Relation,
-    tuple::Tuple,
+    tuple::{Truth, Tuple},
 };
 
 #[derive(Debug, Default)]
 pub(crate) struct SinksBuilder {
-    pub(crate) relations: HashMap<RelationId, Arc<RwLock<Box<dyn Relation>>>>,
+    pub(crate) relations: HashMap<RelationId, SinkRelations>,
+    truth: Truth,
 }
 
 impl SinksBuilder {
     pub(crate) fn add_relation(
         &mut self,
         id: RelationId,
-        relation: Arc<RwLock<Box<dyn Relation>>>,
+        inserted: Arc<RwLock<Box<dyn Relation>>>,
+        retracted: Arc<RwLock<Box<dyn Relation>>>,
     ) {
-        self.relations.insert(id, relation);
+        self.relations.insert(
+            id,
+            SinkRelations {
+                inserted,
+                retracted,
+            },
+        );
+    }
+
+    /// Marks the tuples sent to the sinks with `truth`, rather than as true.
+    pub(crate) fn truth(mut self, truth: Truth) -> Self {
+        self.truth = truth;
+        self
     }
 
     pub(crate) fn finalize(self) -> Sinks {
         Sinks {
             relations: self.relations,
+            truth: self.truth,
         }
     }
 }
 
+/// The relations holding the tuples inserted into and retracted from a sink's
+/// relation.
+#[derive(Clone, Debug)]
+pub(crate) struct SinkRelations {
+    inserted: Arc<RwLock<Box<dyn Relation>>>,
+    retracted: Arc<RwLock<Box<dyn Relation>>>,
+}
+
+/// Sends the changes to its relations to their sinks, as a diff of +1 for each
+/// inserted tuple and -1 for each retracted tuple.
 #[derive(Clone, Debug, Default)]
 pub(crate) struct Sinks {
-    relations: HashMap<RelationId, Arc<RwLock<Box<dyn Relation>>>>,
+    relations: HashMap<RelationId, SinkRelations>,
+    truth: Truth,
 }
 
 impl Sinks {
-    pub(crate) fn apply(&self, output: &mut VecDeque<Tuple>) -> Result<()> {
-        for relation in self.relations.values() {
-            for fact in relation
-                .read()
-                .or_else(|_| {
-                    error(Error::InternalRhizomeError(
-                        "relation lock poisoned".to_owned(),
-                    ))
-                })?
-                .search(vec![])
-            {
-                output.push_back(fact.clone());
+    pub(crate) fn apply(&self, mut output: impl FnMut(Tuple, isize)) -> Result<()> {
+        for relations in self.relations.values() {
+            for (relation, diff) in [(&relations.retracted, -1), (&relations.inserted, 1)] {
+                for fact in relation
+                    .read()
+                    .or_else(|_| {
+                        error(Error::InternalRhizomeError(
+                            "relation lock poisoned".to_owned(),
+                        ))
+                    })?
+                    .search(vec![])
+                {
+                    output(fact.clone().with_truth(self.truth), diff);
+                }
             }
         }
 
@@ -67,6 +95,9 @@ impl Pretty for Sinks {
             RcDoc::text(", "),
         );
 
-        RcDoc::text("sinks ").append(relations_doc)
+        match self.truth {
+            Truth::True => RcDoc::text("sinks ").append(relations_doc),
+            Truth::Undefined => RcDoc::text("sinks undefined ").append(relations_doc),
+        }
     }
 }
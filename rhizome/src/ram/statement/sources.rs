@@ -1,7 +1,7 @@
 use anyhow::Result;
 use std::{
     collections::{HashMap, VecDeque},
-    sync::{Arc, RwLock},
+    sync::{Arc, RwLock, RwLockWriteGuard},
 };
 
 use pretty::RcDoc;
@@ -11,22 +11,46 @@ use crate::{
     id::{ColId, RelationId},
     pretty::Pretty,
     relation::Relation,
+    timestamp::PairTimestamp,
     tuple::Tuple,
     value::Val,
 };
 
+/// A change to an input relation, received from outside of the program.
+#[derive(Clone, Debug)]
+pub(crate) enum Change {
+    Insert(Tuple),
+    Retract(Tuple),
+}
+
+#[derive(Clone, Debug)]
+struct SourceRelations {
+    total: Arc<RwLock<Box<dyn Relation>>>,
+    delta: Arc<RwLock<Box<dyn Relation>>>,
+    retracted: Arc<RwLock<Box<dyn Relation>>>,
+}
+
 #[derive(Debug, Default)]
 pub(crate) struct SourcesBuilder {
-    relations: HashMap<RelationId, Arc<RwLock<Box<dyn Relation>>>>,
+    relations: HashMap<RelationId, SourceRelations>,
 }
 
 impl SourcesBuilder {
     pub(crate) fn add_relation(
         &mut self,
         id: RelationId,
-        relation: Arc<RwLock<Box<dyn Relation>>>,
+        total: Arc<RwLock<Box<dyn Relation>>>,
+        delta: Arc<RwLock<Box<dyn Relation>>>,
+        retracted: Arc<RwLock<Box<dyn Relation>>>,
     ) {
-        self.relations.insert(id, relation);
+        self.relations.insert(
+            id,
+            SourceRelations {
+                total,
+                delta,
+                retracted,
+            },
+        );
     }
 
     pub(crate) fn finalize(self) -> Sources {
@@ -36,43 +60,90 @@ impl SourcesBuilder {
     }
 }
 
+/// Applies the changes received since the last epoch to the total of each input,
+/// while keeping track of the tuples inserted into delta and those retracted.
 #[derive(Clone, Debug, Default)]
 pub(crate) struct Sources {
-    relations: HashMap<RelationId, Arc<RwLock<Box<dyn Relation>>>>,
+    relations: HashMap<RelationId, SourceRelations>,
 }
 
 impl Sources {
-    pub(crate) fn apply(&self, input: &mut VecDeque<Tuple>) -> Result<bool> {
-        let mut has_new_facts = false;
+    pub(crate) fn apply(
+        &self,
+        input: &mut VecDeque<Change>,
+        timestamp: Option<PairTimestamp>,
+    ) -> Result<bool> {
+        let mut has_changes = false;
+
+        while let Some(change) = input.pop_front() {
+            let (Change::Insert(fact) | Change::Retract(fact)) = &change;
 
-        while let Some(fact) = input.pop_front() {
             let mut bindings: Vec<(ColId, Val)> = Vec::default();
             for col_id in fact.cols() {
                 bindings.push((col_id, <Val>::clone(&fact.col(&col_id).unwrap())));
             }
 
-            let id = fact.id();
-            let relation = self
+            let relations = self
                 .relations
-                .get(&id)
+                .get(&fact.id())
                 .ok_or_else(|| Error::InternalRhizomeError("relation not found".to_owned()))?;
 
-            relation
-                .write()
-                .or_else(|_| {
-                    error(Error::InternalRhizomeError(
-                        "relation lock poisoned".to_owned(),
-                    ))
-                })?
-                .insert(bindings, fact);
+            let mut total = write(&relations.total)?;
+            let mut delta = write(&relations.delta)?;
+            let mut retracted = write(&relations.retracted)?;
+
+            match change {
+                Change::Insert(fact) => {
+                    let previous = retracted
+                        .search(bindings.clone())
+                        .find(|other| **other == fact)
+                        .cloned();
 
-            has_new_facts = true;
+                    // Reinserting a tuple retracted during this epoch cancels out the retraction,
+                    // restoring it as it was
+                    if let Some(previous) = previous {
+                        retracted.remove(bindings.clone(), &previous);
+                        total.insert(bindings, previous);
+                    } else if !total.search(bindings.clone()).any(|other| *other == fact) {
+                        let fact = fact.with_timestamp(timestamp);
+
+                        total.insert(bindings.clone(), fact.clone());
+                        delta.insert(bindings, fact);
+                    }
+                }
+                Change::Retract(fact) => {
+                    let Some(previous) = total
+                        .search(bindings.clone())
+                        .find(|other| **other == fact)
+                        .cloned()
+                    else {
+                        continue;
+                    };
+
+                    total.remove(bindings.clone(), &previous);
+
+                    // Likewise, retracting a tuple inserted during this epoch cancels out the insertion
+                    if !delta.remove(bindings.clone(), &previous) {
+                        retracted.insert(bindings, previous);
+                    }
+                }
+            }
+
+            has_changes = true;
         }
 
-        Ok(has_new_facts)
+        Ok(has_changes)
     }
 }
 
+fn write(relation: &RwLock<Box<dyn Relation>>) -> Result<RwLockWriteGuard<'_, Box<dyn Relation>>> {
+    relation.write().or_else(|_| {
+        error(Error::InternalRhizomeError(
+            "relation lock poisoned".to_owned(),
+        ))
+    })
+}
+
 impl Pretty for Sources {
     fn to_doc(&self) -> RcDoc<'_, ()> {
         let relations_doc = RcDoc::intersperse(
@@ -8,14 +8,17 @@ use crate::{
     var::Var,
 };
 
-use super::AliasId;
+use super::{AliasId, Expr};
 
 #[derive(Clone, Debug, From, IsVariant, TryInto)]
 pub enum Term {
     Lit(Val),
     Col(RelationId, Option<AliasId>, ColId),
     Cid(RelationId, Option<AliasId>),
-    Agg(RelationId, Option<AliasId>, Var),
+    #[from(ignore)]
+    Agg(Var),
+    Computed(Var),
+    Expr(Box<Expr>),
 }
 
 impl Pretty for Term {
@@ -45,14 +48,10 @@ impl Pretty for Term {
                 relation_binding(relation_id, alias_id),
                 RcDoc::text(")"),
             ]),
-            Term::Agg(relation_id, alias_id, var) => RcDoc::concat([
-                RcDoc::text("("),
-                relation_binding(relation_id, alias_id),
-                RcDoc::text("."),
-                RcDoc::as_string(var),
-                RcDoc::text(")"),
-            ]),
+            Term::Agg(var) => RcDoc::as_string(var.id()),
+            Term::Computed(var) => RcDoc::as_string(var.id()),
             Term::Lit(value) => RcDoc::as_string(value),
+            Term::Expr(expr) => expr.to_doc(),
         }
     }
 }
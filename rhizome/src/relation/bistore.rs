@@ -103,6 +103,15 @@ where
         Ok(())
     }
 
+    pub(crate) fn remove(&mut self, bindings: Vec<(ColId, Val)>, val: &T) -> bool {
+        let (Some(f), Some(t)) = Self::bindings_to_cols(bindings) else {
+            return false;
+        };
+
+        Self::index_remove(&mut self.tf, (t.clone(), f.clone()), val);
+        Self::index_remove(&mut self.ft, (f, t), val)
+    }
+
     pub(crate) fn search(&self, bindings: Vec<(ColId, Val)>) -> BTreeSet<&T> {
         let (f, t) = Self::bindings_to_cols(bindings);
 
@@ -175,6 +184,35 @@ where
         };
     }
 
+    fn index_remove<K1, K2>(index: &mut Index<K1, K2, T>, keys: (K1, K2), val: &T) -> bool
+    where
+        K1: Key,
+        K2: Key,
+    {
+        let (k1, k2) = keys;
+
+        let Some(v1) = index.get_mut(&k1) else {
+            return false;
+        };
+
+        let Some(v2) = v1.get_mut(&k2) else {
+            return false;
+        };
+
+        let removed = v2.remove(val);
+
+        // Prune emptied layers, as emptiness is checked against the outermost layer
+        if v2.is_empty() {
+            v1.remove(&k2);
+
+            if v1.is_empty() {
+                index.remove(&k1);
+            }
+        }
+
+        removed
+    }
+
     fn index_search_0<K1, K2>(index: &Index<K1, K2, T>) -> BTreeSet<&T>
     where
         K1: Key,
@@ -299,6 +337,10 @@ impl Relation for Bistore<Tuple> {
         self.insert(bindings, val).unwrap()
     }
 
+    fn remove(&mut self, bindings: Vec<(ColId, Val)>, val: &Tuple) -> bool {
+        self.remove(bindings, val)
+    }
+
     fn merge(&mut self, rhs: &dyn Relation) {
         if let Some(rhs) = rhs.downcast_ref::<Self>() {
             self.merge(rhs)
@@ -638,4 +680,47 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_remove() -> Result<()> {
+        let mut bistore = Bistore::<usize>::default();
+
+        bistore.insert(
+            vec![(ColId::new("from"), 0.into()), (ColId::new("to"), 1.into())],
+            0,
+        )?;
+
+        bistore.insert(
+            vec![(ColId::new("from"), 0.into()), (ColId::new("to"), 1.into())],
+            1,
+        )?;
+
+        assert_eq!(
+            bistore.remove(
+                vec![(ColId::new("from"), 0.into()), (ColId::new("to"), 1.into())],
+                &0,
+            ),
+            true
+        );
+
+        assert_eq!(
+            bistore.remove(
+                vec![(ColId::new("from"), 0.into()), (ColId::new("to"), 1.into())],
+                &0,
+            ),
+            false
+        );
+
+        assert_eq!(bistore.search(vec![]), BTreeSet::from_iter(&[1]));
+
+        bistore.remove(
+            vec![(ColId::new("from"), 0.into()), (ColId::new("to"), 1.into())],
+            &1,
+        );
+
+        assert_eq!(bistore.is_empty(), true);
+        assert_eq!(bistore.contains(vec![(ColId::new("to"), 1.into())]), false);
+
+        Ok(())
+    }
 }
@@ -43,12 +43,22 @@ impl Relation for ImmutableOrdSetRelation {
     }
 
     fn insert(&mut self, _bindings: Vec<(ColId, Val)>, val: Tuple) {
-        self.inner = self.inner.update(val);
+        if !self.inner.contains(&val) {
+            self.inner = self.inner.update(val);
+        }
+    }
+
+    fn remove(&mut self, _bindings: Vec<(ColId, Val)>, val: &Tuple) -> bool {
+        self.inner.remove(val).is_some()
     }
 
     fn merge(&mut self, rhs: &dyn Relation) {
         if let Some(rhs) = rhs.downcast_ref::<Self>() {
-            self.inner.extend(rhs.inner.iter().cloned());
+            for tuple in rhs.inner.iter() {
+                if !self.inner.contains(tuple) {
+                    self.inner.insert(tuple.clone());
+                }
+            }
         } else {
             panic!("Attempted to merge incompatible relations");
         }
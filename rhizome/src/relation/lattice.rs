@@ -0,0 +1,166 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use as_any::Downcast;
+
+use crate::{id::ColId, lattice::LatticeJoin, tuple::Tuple, value::Val};
+
+use super::Relation;
+
+/// Keeps a single tuple per key, where the key is every column other than the
+/// lattice column, and the lattice column holds the join of every value
+/// inserted under that key.
+#[derive(Clone, Debug)]
+pub(crate) struct LatticeRelation {
+    col: ColId,
+    lattice: Arc<dyn LatticeJoin>,
+    inner: BTreeMap<Vec<(ColId, Val)>, Tuple>,
+}
+
+impl LatticeRelation {
+    pub(crate) fn new(col: ColId, lattice: Arc<dyn LatticeJoin>) -> Self {
+        Self {
+            col,
+            lattice,
+            inner: BTreeMap::default(),
+        }
+    }
+
+    fn key(&self, tuple: &Tuple) -> Vec<(ColId, Val)> {
+        tuple
+            .cols()
+            .into_iter()
+            .filter(|id| *id != self.col)
+            .filter_map(|id| tuple.col(&id).map(|val| (id, val)))
+            .collect()
+    }
+
+    // Values are checked against the column's type as they're projected, so
+    // this is only None for tuples that were never stored
+    fn join(&self, left: &Val, right: &Val) -> Option<Val> {
+        self.lattice.join(left, right)
+    }
+}
+
+impl Relation for LatticeRelation {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    // A tuple is contained if its value is subsumed by the value already held for its key
+    fn contains(&self, bindings: Vec<(ColId, Val)>) -> bool {
+        let (value, key): (Vec<_>, Vec<_>) =
+            bindings.into_iter().partition(|(k, _)| *k == self.col);
+
+        self.search(key)
+            .any(|f| match (value.first(), f.col(&self.col)) {
+                (Some((_, value)), Some(existing)) => {
+                    self.join(&existing, value).as_ref() == Some(&existing)
+                }
+                _ => true,
+            })
+    }
+
+    fn search(&self, bindings: Vec<(ColId, Val)>) -> Box<dyn Iterator<Item = &'_ Tuple> + '_> {
+        Box::new(self.inner.values().filter(move |f| {
+            bindings
+                .iter()
+                .all(|(k, v)| f.col(k).map_or(false, |b| b == *v))
+        }))
+    }
+
+    fn purge(&mut self) {
+        self.inner = BTreeMap::default();
+    }
+
+    fn insert(&mut self, _bindings: Vec<(ColId, Val)>, val: Tuple) {
+        let key = self.key(&val);
+
+        let joined = match (self.inner.get(&key), val.col(&self.col)) {
+            (Some(existing), Some(value)) => {
+                let Some(existing) = existing.col(&self.col) else {
+                    return;
+                };
+
+                let Some(joined) = self.join(&existing, &value) else {
+                    return;
+                };
+
+                if joined == existing {
+                    return;
+                }
+
+                let cols = key.into_iter().chain([(self.col, joined)]);
+
+                Tuple::new(val.id(), cols, val.cid()).with_timestamp(val.timestamp())
+            }
+            _ => val,
+        };
+
+        self.inner.insert(self.key(&joined), joined);
+    }
+
+    // Only the joined tuple can be removed, as the values joined into it aren't kept
+    fn remove(&mut self, _bindings: Vec<(ColId, Val)>, val: &Tuple) -> bool {
+        let key = self.key(val);
+
+        if self.inner.get(&key) == Some(val) {
+            self.inner.remove(&key);
+
+            true
+        } else {
+            false
+        }
+    }
+
+    fn merge(&mut self, rhs: &dyn Relation) {
+        if let Some(rhs) = rhs.downcast_ref::<Self>() {
+            for tuple in rhs.inner.values() {
+                self.insert(vec![], tuple.clone());
+            }
+        } else {
+            panic!("Attempted to merge incompatible relations");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lattice::{ColJoin, Min};
+
+    use super::*;
+
+    #[test]
+    fn test_lattice_relation() {
+        let mut relation =
+            LatticeRelation::new(ColId::new("dist"), Arc::new(ColJoin::<Min<u64>>::default()));
+
+        let tuple = |to: u64, dist: u64| Tuple::new("dist", [("to", to), ("dist", dist)], None);
+
+        relation.insert(vec![], tuple(1, 5));
+        relation.insert(vec![], tuple(1, 7));
+        relation.insert(vec![], tuple(2, 4));
+
+        assert_eq!(2, relation.len());
+        assert!(relation.contains(vec![
+            ("to".into(), 1u64.into()),
+            ("dist".into(), 6u64.into())
+        ]));
+        assert!(!relation.contains(vec![
+            ("to".into(), 1u64.into()),
+            ("dist".into(), 4u64.into())
+        ]));
+
+        relation.insert(vec![], tuple(1, 3));
+
+        assert_eq!(
+            vec![&tuple(1, 3)],
+            relation
+                .search(vec![("to".into(), 1u64.into())])
+                .collect::<Vec<_>>()
+        );
+    }
+}
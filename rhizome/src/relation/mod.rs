@@ -12,11 +12,13 @@ use std::{fmt::Debug, hash::Hash};
 pub(crate) mod bistore;
 pub(crate) mod hexastore;
 pub(crate) mod immutable_ord_set;
+pub(crate) mod lattice;
 pub(crate) mod ord_set;
 
 pub use bistore::Bistore;
 pub use hexastore::Hexastore;
 pub use immutable_ord_set::ImmutableOrdSetRelation;
+pub(crate) use lattice::LatticeRelation;
 pub use ord_set::OrdSetRelation;
 
 pub(crate) type DefaultRelation = OrdSetRelation;
@@ -36,6 +38,21 @@ pub(crate) enum Version {
     Total,
     Delta,
     New,
+    /// The interpretation that negations are evaluated against, while computing
+    /// the well-founded model of a stratum.
+    Assumed,
+    /// The tuples known to be true in the well-founded model of a stratum.
+    Known,
+    /// The tuples that were undefined in the well-founded model of a stratum,
+    /// as of the previous epoch.
+    Undefined,
+    /// The tuples removed from a relation during the current epoch.
+    Retracted,
+    /// The tuples inserted into a recursive relation during the current epoch,
+    /// accumulated over each iteration of its loop.
+    Inserted,
+    /// A relation's total as of the previous epoch, while it's recomputed.
+    Previous,
 }
 
 impl Display for Version {
@@ -44,11 +61,18 @@ impl Display for Version {
             Version::Total => f.write_str("total"),
             Version::Delta => f.write_str("delta"),
             Version::New => f.write_str("new"),
+            Version::Assumed => f.write_str("assumed"),
+            Version::Known => f.write_str("known"),
+            Version::Undefined => f.write_str("undefined"),
+            Version::Retracted => f.write_str("retracted"),
+            Version::Inserted => f.write_str("inserted"),
+            Version::Previous => f.write_str("previous"),
         }
     }
 }
 
-// TODO: Keep track of the timestamp a fact was derived at?
+/// A set of tuples. Inserting a tuple that's already present keeps the one
+/// already stored, along with the timestamp it was first derived at.
 pub trait Relation: Debug + DynClone + Send + Sync + AsAny + 'static {
     fn len(&self) -> usize;
     fn is_empty(&self) -> bool;
@@ -58,6 +82,8 @@ pub trait Relation: Debug + DynClone + Send + Sync + AsAny + 'static {
 
     fn purge(&mut self);
     fn insert(&mut self, bindings: Vec<(ColId, Val)>, val: Tuple);
+    /// Removes `val`, returning whether it was present.
+    fn remove(&mut self, bindings: Vec<(ColId, Val)>, val: &Tuple) -> bool;
     fn merge(&mut self, rhs: &dyn Relation);
 }
 
@@ -88,6 +114,10 @@ impl Relation for Box<dyn Relation> {
         (**self).insert(bindings, val)
     }
 
+    fn remove(&mut self, bindings: Vec<(ColId, Val)>, val: &Tuple) -> bool {
+        (**self).remove(bindings, val)
+    }
+
     fn merge(&mut self, rhs: &dyn Relation) {
         (**self).merge(rhs)
     }
@@ -1,14 +1,26 @@
 use anyhow::Result;
 use std::fmt::Debug;
 
+use cid::Cid;
 use futures::{
     channel::{mpsc, oneshot},
     SinkExt,
 };
+use rhizome_runtime::MaybeSend;
 
-use crate::{id::RelationId, timestamp::DefaultTimestamp, tuple::InputTuple};
+use crate::{
+    id::{ColId, RelationId},
+    logic::{RuleBodyBuilder, RuleVars},
+    provenance::Derivation,
+    timestamp::DefaultTimestamp,
+    tuple::{InputTuple, Tuple},
+    value::Val,
+};
 
-use super::{reactor::Reactor, ClientCommand, ClientEvent, CreateSink, CreateStream};
+use super::{
+    reactor::Reactor, ClientCommand, ClientEvent, CreateDiffSink, CreateSink, CreateStream,
+    CreateTupleStream, Query, StreamErrors, SubscriptionId,
+};
 
 #[derive(Debug)]
 pub struct Client {
@@ -49,12 +61,41 @@ impl Client {
         Ok(())
     }
 
-    pub async fn register_stream(&mut self, id: &str, f: Box<dyn CreateStream>) -> Result<()> {
+    /// Inserts a tuple into an input relation declared by the program, once the
+    /// next epoch is evaluated. The tuple must have a value of the declared type
+    /// for every column of the relation, and no others.
+    pub async fn insert(&mut self, id: &str, tuple: Tuple) -> Result<()> {
         let id = RelationId::new(id);
         let (tx, rx) = oneshot::channel();
 
         self.command_tx
-            .send(ClientCommand::RegisterStream(id, f, tx))
+            .send(ClientCommand::Insert(id, Box::new(tuple), tx))
+            .await?;
+
+        rx.await?
+    }
+
+    /// Inserts a batch of facts, which are evaluated in the same epoch. If any fact
+    /// is invalid or can't be stored, none are inserted. Returns the start of the
+    /// epoch the batch is evaluated in, as marked complete to sinks once it is.
+    /// An empty batch is rejected, since no epoch is evaluated for it.
+    pub async fn transact(&mut self, facts: Vec<InputTuple>) -> Result<DefaultTimestamp> {
+        let (tx, rx) = oneshot::channel();
+
+        self.command_tx
+            .send(ClientCommand::Transact(facts, tx))
+            .await?;
+
+        rx.await?
+    }
+
+    /// Retracts the fact with the given CID, along with everything derived from
+    /// it, once the next epoch is evaluated.
+    pub async fn retract_fact(&mut self, cid: Cid) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+
+        self.command_tx
+            .send(ClientCommand::RetractFact(cid, tx))
             .await?;
 
         rx.await?;
@@ -62,6 +103,39 @@ impl Client {
         Ok(())
     }
 
+    /// Registers a stream of facts, each of which is inserted into the `evac` and
+    /// `links` relations.
+    pub async fn register_stream(&mut self, f: Box<dyn CreateStream>) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+
+        self.command_tx
+            .send(ClientCommand::RegisterStream(f, tx))
+            .await?;
+
+        rx.await?;
+
+        Ok(())
+    }
+
+    /// Registers a stream of tuples to insert into an input relation declared by
+    /// the program. Tuples that don't match the relation's declaration are skipped,
+    /// and the error each was rejected with is sent to the returned receiver.
+    pub async fn register_tuple_stream(
+        &mut self,
+        id: &str,
+        f: Box<dyn CreateTupleStream>,
+    ) -> Result<StreamErrors> {
+        let id = RelationId::new(id);
+        let (tx, rx) = oneshot::channel();
+
+        self.command_tx
+            .send(ClientCommand::RegisterTupleStream(id, f, tx))
+            .await?;
+
+        rx.await?
+    }
+
+    /// Registers a sink that receives the tuples inserted into a relation.
     pub async fn register_sink(&mut self, id: &str, f: Box<dyn CreateSink>) -> Result<()> {
         let id = RelationId::new(id);
         let (tx, rx) = oneshot::channel();
@@ -74,4 +148,127 @@ impl Client {
 
         Ok(())
     }
+
+    /// Registers a sink that receives every change to a relation as a diff,
+    /// followed by a marker at the end of each epoch.
+    pub async fn register_diff_sink(&mut self, id: &str, f: Box<dyn CreateDiffSink>) -> Result<()> {
+        let id = RelationId::new(id);
+        let (tx, rx) = oneshot::channel();
+
+        self.command_tx
+            .send(ClientCommand::RegisterDiffSink(id, f, tx))
+            .await?;
+
+        rx.await?;
+
+        Ok(())
+    }
+
+    /// Subscribes a sink to the tuples of a relation whose columns have the given
+    /// values. The sink is first sent the matching tuples as of the end of the
+    /// last epoch, as the diffs of that epoch, and then the changes to them in each
+    /// epoch after, until it's unsubscribed.
+    pub async fn subscribe<A, D>(
+        &mut self,
+        id: &str,
+        filter: impl IntoIterator<Item = (A, D)>,
+        f: Box<dyn CreateDiffSink>,
+    ) -> Result<SubscriptionId>
+    where
+        A: Into<ColId>,
+        D: Into<Val>,
+    {
+        let id = RelationId::new(id);
+        let filter = filter
+            .into_iter()
+            .map(|(k, v)| (k.into(), v.into()))
+            .collect();
+        let (tx, rx) = oneshot::channel();
+
+        self.command_tx
+            .send(ClientCommand::Subscribe(id, filter, f, tx))
+            .await?;
+
+        rx.await?
+    }
+
+    /// Stops sending changes to a subscribed sink, once it's been sent those of
+    /// the epochs already evaluated.
+    pub async fn unsubscribe(&mut self, subscription: SubscriptionId) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+
+        self.command_tx
+            .send(ClientCommand::Unsubscribe(subscription, tx))
+            .await?;
+
+        rx.await?;
+
+        Ok(())
+    }
+
+    /// Explains why `tuple` is in the given relation, as of the end of the last
+    /// epoch, by the rule that derived it and the derivations of the tuples that
+    /// rule matched, down to the inputs. Returns None if the tuple isn't in the
+    /// relation, and an error unless the program records provenance.
+    pub async fn explain(&mut self, id: &str, tuple: Tuple) -> Result<Option<Derivation>> {
+        let id = RelationId::new(id);
+        let (tx, rx) = oneshot::channel();
+
+        self.command_tx
+            .send(ClientCommand::Explain(id, Box::new(tuple), tx))
+            .await?;
+
+        rx.await?
+    }
+
+    /// Returns the tuples of a relation whose columns have the given values, as of
+    /// the end of the last epoch. The relation's indexes are used where they cover
+    /// the bound columns.
+    pub async fn lookup<A, D>(
+        &mut self,
+        id: &str,
+        bindings: impl IntoIterator<Item = (A, D)>,
+    ) -> Result<Vec<Tuple>>
+    where
+        A: Into<ColId>,
+        D: Into<Val>,
+    {
+        let id = RelationId::new(id);
+        let bindings = bindings
+            .into_iter()
+            .map(|(k, v)| (k.into(), v.into()))
+            .collect();
+        let (tx, rx) = oneshot::channel();
+
+        self.command_tx
+            .send(ClientCommand::Query(Query::Relation(id, bindings), tx))
+            .await?;
+
+        rx.await?
+    }
+
+    /// Returns the solutions to a rule body, built as in [`ProgramBuilder::rule`],
+    /// against the relations as of the end of the last epoch. Each solution is a
+    /// tuple of the `query` relation, with a column named after every variable
+    /// bound by the body.
+    ///
+    /// [`ProgramBuilder::rule`]: crate::ProgramBuilder::rule
+    pub async fn query<T>(
+        &mut self,
+        f: impl Fn(&RuleBodyBuilder, T::Vars) -> Result<()> + MaybeSend + 'static,
+    ) -> Result<Vec<Tuple>>
+    where
+        T: RuleVars,
+    {
+        let query = Query::Body(Box::new(move |body: &RuleBodyBuilder| {
+            f(body, T::into_vars(0))
+        }));
+        let (tx, rx) = oneshot::channel();
+
+        self.command_tx
+            .send(ClientCommand::Query(query, tx))
+            .await?;
+
+        rx.await?
+    }
 }
@@ -1,13 +1,21 @@
+use anyhow::Result;
 use std::{fmt, fmt::Debug};
 
-use futures::{channel::oneshot, Sink, Stream};
+use cid::Cid;
+use futures::{
+    channel::{mpsc, oneshot},
+    Sink, Stream,
+};
 use rhizome_runtime::MaybeSend;
 
 use crate::{
     error::Error,
-    id::RelationId,
-    timestamp::Timestamp,
+    id::{ColId, RelationId},
+    logic::RuleBodyBuilder,
+    provenance::Derivation,
+    timestamp::{DefaultTimestamp, Timestamp},
     tuple::{InputTuple, Tuple},
+    value::Val,
 };
 
 pub mod client;
@@ -15,24 +23,78 @@ pub mod reactor;
 mod vm;
 
 pub type FactStream = Box<dyn Stream<Item = InputTuple>>;
+pub type TupleStream = Box<dyn Stream<Item = Tuple>>;
 pub type FactSink = Box<dyn Sink<Tuple, Error = Error>>;
+pub type DiffSink<T = DefaultTimestamp> = Box<dyn Sink<SinkEvent<T>, Error = Error>>;
+/// The errors of the tuples a stream yielded that couldn't be inserted, which
+/// are skipped without ending the stream.
+pub type StreamErrors = mpsc::UnboundedReceiver<anyhow::Error>;
 
 pub trait CreateStream: (FnOnce() -> FactStream) + MaybeSend {}
+pub trait CreateTupleStream: (FnOnce() -> TupleStream) + MaybeSend {}
 pub trait CreateSink: (FnOnce() -> FactSink) + MaybeSend {}
+pub trait CreateDiffSink<T = DefaultTimestamp>: (FnOnce() -> DiffSink<T>) + MaybeSend {}
+pub trait BuildQuery: (Fn(&RuleBodyBuilder) -> Result<()>) + MaybeSend {}
 
 impl<F> CreateStream for F where F: FnOnce() -> FactStream + MaybeSend {}
 
+impl<F> CreateTupleStream for F where F: FnOnce() -> TupleStream + MaybeSend {}
+
 impl<F> CreateSink for F where F: FnOnce() -> FactSink + MaybeSend {}
 
+impl<F, T> CreateDiffSink<T> for F where F: FnOnce() -> DiffSink<T> + MaybeSend {}
+
+impl<F> BuildQuery for F where F: Fn(&RuleBodyBuilder) -> Result<()> + MaybeSend {}
+
+/// A change to the contents of a relation, as sent to the sinks registered on it.
+///
+/// Each epoch, a sink receives a diff for every tuple inserted into or retracted
+/// from its relation, followed by a marker once the epoch is complete. Applying
+/// the diffs of each epoch in turn mirrors the relation exactly.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SinkEvent<T = DefaultTimestamp> {
+    /// A tuple, the time it changed, and +1 if it was inserted or -1 if it was
+    /// retracted.
+    Diff(Tuple, T, isize),
+    /// Every diff of the epoch starting at the given time has been sent.
+    EpochComplete(T),
+}
+
+/// A query against the relations of a running program, answered as of the end
+/// of the last epoch without changing them.
+pub enum Query {
+    /// The tuples of a relation whose columns have the given values.
+    Relation(RelationId, Vec<(ColId, Val)>),
+    /// The solutions to a rule body, each as a tuple of the `query` relation with
+    /// a column named after every variable bound by the body.
+    Body(Box<dyn BuildQuery>),
+}
+
+impl Debug for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Query::Relation(id, bindings) => {
+                f.debug_tuple("Relation").field(id).field(bindings).finish()
+            }
+            Query::Body(_) => f.debug_tuple("Body").finish(),
+        }
+    }
+}
+
+/// Identifies a sink subscribed to a relation, so that it can be unsubscribed.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct SubscriptionId(usize);
+
 #[derive(Debug)]
 pub enum StreamEvent {
     Fact(InputTuple),
+    Tuple(RelationId, Tuple, mpsc::UnboundedSender<anyhow::Error>),
 }
 
 #[derive(Debug)]
-pub enum SinkCommand {
+pub enum SinkCommand<T = DefaultTimestamp> {
     Flush(oneshot::Sender<()>),
-    ProcessFact(Tuple),
+    ProcessEvent(SinkEvent<T>),
 }
 
 #[derive(Debug)]
@@ -43,20 +105,61 @@ where
     ReachedFixedpoint(T),
 }
 
-pub enum ClientCommand {
+pub enum ClientCommand<T = DefaultTimestamp> {
     Flush(oneshot::Sender<()>),
     InsertFact(Box<InputTuple>, oneshot::Sender<()>),
-    RegisterStream(RelationId, Box<dyn CreateStream>, oneshot::Sender<()>),
+    Transact(Vec<InputTuple>, oneshot::Sender<Result<T>>),
+    Insert(RelationId, Box<Tuple>, oneshot::Sender<Result<()>>),
+    RetractFact(Cid, oneshot::Sender<()>),
+    RegisterStream(Box<dyn CreateStream>, oneshot::Sender<()>),
+    RegisterTupleStream(
+        RelationId,
+        Box<dyn CreateTupleStream>,
+        oneshot::Sender<Result<StreamErrors>>,
+    ),
     RegisterSink(RelationId, Box<dyn CreateSink>, oneshot::Sender<()>),
+    RegisterDiffSink(RelationId, Box<dyn CreateDiffSink<T>>, oneshot::Sender<()>),
+    Explain(
+        RelationId,
+        Box<Tuple>,
+        oneshot::Sender<Result<Option<Derivation>>>,
+    ),
+    Query(Query, oneshot::Sender<Result<Vec<Tuple>>>),
+    Subscribe(
+        RelationId,
+        Vec<(ColId, Val)>,
+        Box<dyn CreateDiffSink<T>>,
+        oneshot::Sender<Result<SubscriptionId>>,
+    ),
+    Unsubscribe(SubscriptionId, oneshot::Sender<()>),
 }
 
-impl Debug for ClientCommand {
+impl<T> Debug for ClientCommand<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             ClientCommand::Flush(_) => f.debug_tuple("Flush").finish(),
             ClientCommand::InsertFact(fact, _) => f.debug_tuple("InsertFact").field(fact).finish(),
-            ClientCommand::RegisterStream(_, _, _) => f.debug_tuple("RegisterStream").finish(),
+            ClientCommand::Transact(facts, _) => f.debug_tuple("Transact").field(facts).finish(),
+            ClientCommand::Insert(id, tuple, _) => {
+                f.debug_tuple("Insert").field(id).field(tuple).finish()
+            }
+            ClientCommand::RetractFact(cid, _) => f.debug_tuple("RetractFact").field(cid).finish(),
+            ClientCommand::RegisterStream(_, _) => f.debug_tuple("RegisterStream").finish(),
+            ClientCommand::RegisterTupleStream(id, _, _) => {
+                f.debug_tuple("RegisterTupleStream").field(id).finish()
+            }
             ClientCommand::RegisterSink(_, _, _) => f.debug_tuple("RegisterSink").finish(),
+            ClientCommand::RegisterDiffSink(_, _, _) => f.debug_tuple("RegisterDiffSink").finish(),
+            ClientCommand::Explain(id, tuple, _) => {
+                f.debug_tuple("Explain").field(id).field(tuple).finish()
+            }
+            ClientCommand::Query(query, _) => f.debug_tuple("Query").field(query).finish(),
+            ClientCommand::Subscribe(id, filter, _, _) => {
+                f.debug_tuple("Subscribe").field(id).field(filter).finish()
+            }
+            ClientCommand::Unsubscribe(subscription, _) => {
+                f.debug_tuple("Unsubscribe").field(subscription).finish()
+            }
         }
     }
 }
@@ -64,8 +167,10 @@ impl Debug for ClientCommand {
 #[cfg(test)]
 mod tests {
     use std::{
+        collections::BTreeSet,
         marker::PhantomData,
         ops::{Add, AddAssign},
+        sync::Arc,
     };
 
     use anyhow::Result;
@@ -76,13 +181,25 @@ mod tests {
     use crate::{
         aggregation::Aggregate,
         assert_derives,
-        kernel::{self, math},
+        expr::Expr,
+        function::Function,
+        id::{ColId, RelationId},
+        kernel::{self, cmp, math, string, Registry},
+        lattice::{Max, Min},
+        logic::{Evaluation, ProgramBuilder, Semantics},
         predicate::Predicate,
-        types::RhizomeType,
+        provenance::Derivation,
+        storage::{
+            blockstore::Blockstore, memory::MemoryBlockstore, DefaultCodec, DEFAULT_MULTIHASH,
+        },
+        timestamp::PairTimestamp,
+        tuple::Truth,
+        types::{Any, RhizomeType},
         value::Val,
+        var::{TypedVar, Wildcard},
     };
 
-    use super::*;
+    use super::{vm::VM, *};
 
     #[test]
     fn test_step_epoch_transitive_closure() {
@@ -755,6 +872,248 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_count_distinct() {
+        assert_derives!(
+            |p| {
+                p.output("pair", |h| h.column::<i32>("x").column::<i32>("y"))?;
+                p.output("count", |h| h.column::<i32>("n"))?;
+
+                p.fact("pair", |f| f.bind((("x", 1), ("y", 1))))?;
+                p.fact("pair", |f| f.bind((("x", 2), ("y", 1))))?;
+                p.fact("pair", |f| f.bind((("x", 3), ("y", 2))))?;
+
+                p.rule::<(i32, i32)>("count", &|h, b, (count, y)| {
+                    h.bind((("n", count),))?;
+                    b.group_by(count, "pair", (("y", y),), math::count_distinct(y))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            [("count", [Tuple::new("count", [("n", 2)], None)])]
+        );
+    }
+
+    #[test]
+    fn test_median_and_percentile() {
+        assert_derives!(
+            |p| {
+                p.output("num", |h| h.column::<i32>("n"))?;
+                p.output("median", |h| h.column::<i32>("n"))?;
+                p.output("p90", |h| h.column::<i32>("n"))?;
+
+                for n in [7, 1, 4, 9, 3, 10] {
+                    p.fact("num", |f| f.bind((("n", n),)))?;
+                }
+
+                p.rule::<(i32, i32)>("median", &|h, b, (median, n)| {
+                    h.bind((("n", median),))?;
+                    b.group_by(median, "num", (("n", n),), math::median(n))?;
+
+                    Ok(())
+                })?;
+
+                p.rule::<(i32, i32)>("p90", &|h, b, (p90, n)| {
+                    h.bind((("n", p90),))?;
+                    b.group_by(p90, "num", (("n", n),), math::percentile(n, 90.0))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            [
+                ("median", [Tuple::new("median", [("n", 4)], None)]),
+                ("p90", [Tuple::new("p90", [("n", 10)], None)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_join() {
+        assert_derives!(
+            |p| {
+                p.output("tag", |h| h.column::<i32>("id").column::<Arc<str>>("name"))?;
+                p.output("tags", |h| {
+                    h.column::<i32>("id").column::<Arc<str>>("names")
+                })?;
+
+                p.fact("tag", |f| f.bind((("id", 1), ("name", "b"))))?;
+                p.fact("tag", |f| f.bind((("id", 1), ("name", "a"))))?;
+                p.fact("tag", |f| f.bind((("id", 2), ("name", "c"))))?;
+
+                p.rule::<(i32, Arc<str>, Arc<str>)>("tags", &|h, b, (id, names, name)| {
+                    h.bind((("id", id), ("names", names)))?;
+                    b.search("tag", (("id", id),))?;
+                    b.group_by(
+                        names,
+                        "tag",
+                        (("id", id), ("name", name)),
+                        math::join(name, ", "),
+                    )?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            [(
+                "tags",
+                [
+                    Tuple::new(
+                        "tags",
+                        [("id", Val::from(1)), ("names", Val::from("a, b"))],
+                        None
+                    ),
+                    Tuple::new(
+                        "tags",
+                        [("id", Val::from(2)), ("names", Val::from("c"))],
+                        None
+                    ),
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_arg_max_and_arg_min() {
+        assert_derives!(
+            |p| {
+                p.output("score", |h| {
+                    h.column::<Arc<str>>("name").column::<i32>("points")
+                })?;
+                p.output("best", |h| h.column::<Arc<str>>("name"))?;
+                p.output("worst", |h| h.column::<Arc<str>>("name"))?;
+
+                p.fact("score", |f| f.bind((("name", "a"), ("points", 3))))?;
+                p.fact("score", |f| f.bind((("name", "b"), ("points", 8))))?;
+                p.fact("score", |f| f.bind((("name", "c"), ("points", 1))))?;
+
+                p.rule::<(Arc<str>, Arc<str>, i32)>("best", &|h, b, (best, name, points)| {
+                    h.bind((("name", best),))?;
+                    b.group_by(
+                        best,
+                        "score",
+                        (("name", name), ("points", points)),
+                        math::arg_max(points, name),
+                    )?;
+
+                    Ok(())
+                })?;
+
+                p.rule::<(Arc<str>, Arc<str>, i32)>("worst", &|h, b, (worst, name, points)| {
+                    h.bind((("name", worst),))?;
+                    b.group_by(
+                        worst,
+                        "score",
+                        (("name", name), ("points", points)),
+                        math::arg_min(points, name),
+                    )?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            [
+                ("best", [Tuple::new("best", [("name", "b")], None)]),
+                ("worst", [Tuple::new("worst", [("name", "c")], None)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collect() {
+        assert_derives!(
+            |p| {
+                p.output("edge", |h| h.column::<i32>("from").column::<i32>("to"))?;
+                p.output("adjacent", |h| {
+                    h.column::<i32>("from").column::<Vec<i32>>("to")
+                })?;
+
+                p.fact("edge", |f| f.bind((("from", 1), ("to", 3))))?;
+                p.fact("edge", |f| f.bind((("from", 1), ("to", 2))))?;
+                p.fact("edge", |f| f.bind((("from", 2), ("to", 3))))?;
+
+                p.rule::<(i32, Vec<i32>, i32)>("adjacent", &|h, b, (from, adjacent, to)| {
+                    h.bind((("from", from), ("to", adjacent)))?;
+                    b.search("edge", (("from", from),))?;
+                    b.group_by(
+                        adjacent,
+                        "edge",
+                        (("from", from), ("to", to)),
+                        math::collect(to),
+                    )?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            [(
+                "adjacent",
+                [
+                    Tuple::new(
+                        "adjacent",
+                        [("from", Val::from(1)), ("to", Val::from(vec![2, 3]))],
+                        None
+                    ),
+                    Tuple::new(
+                        "adjacent",
+                        [("from", Val::from(2)), ("to", Val::from(vec![3]))],
+                        None
+                    ),
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_fold() {
+        assert_derives!(
+            |p| {
+                p.output("flag", |h| h.column::<i32>("id").column::<u32>("bits"))?;
+                p.output("flags", |h| h.column::<i32>("id").column::<u32>("bits"))?;
+
+                p.fact("flag", |f| f.bind((("id", 1), ("bits", 0b001u32))))?;
+                p.fact("flag", |f| f.bind((("id", 1), ("bits", 0b100u32))))?;
+                p.fact("flag", |f| f.bind((("id", 2), ("bits", 0b010u32))))?;
+
+                p.rule::<(i32, u32, u32)>("flags", &|h, b, (id, flags, bits)| {
+                    h.bind((("id", id), ("bits", flags)))?;
+                    b.search("flag", (("id", id),))?;
+                    b.group_by(
+                        flags,
+                        "flag",
+                        (("id", id), ("bits", bits)),
+                        kernel::fold((bits,), 0, |acc, (bits,)| acc | bits, |acc| Some(*acc)),
+                    )?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            [(
+                "flags",
+                [
+                    Tuple::new(
+                        "flags",
+                        [("id", Val::from(1)), ("bits", Val::from(0b101u32))],
+                        None
+                    ),
+                    Tuple::new(
+                        "flags",
+                        [("id", Val::from(2)), ("bits", Val::from(0b010u32))],
+                        None
+                    ),
+                ]
+            )]
+        );
+    }
+
     #[test]
     fn test_multi_arity_reduce() {
         assert_derives!(
@@ -862,28 +1221,87 @@ mod tests {
     }
 
     #[test]
-    fn test_self_join_str() {
+    fn test_aggregate_nested_body() {
         assert_derives!(
             |p| {
-                p.output("pair1", |h| {
-                    h.column::<&str>("id")
-                        .column::<&str>("x")
-                        .column::<&str>("y")
+                p.output("node", |h| h.column::<i32>("id"))?;
+                p.output("edge", |h| h.column::<i32>("from").column::<i32>("to"))?;
+                p.output("blocked", |h| h.column::<i32>("id"))?;
+                p.output("degree", |h| h.column::<i32>("id").column::<i32>("n"))?;
+
+                for id in 1..=4 {
+                    p.fact("node", |f| f.bind((("id", id),)))?;
+                }
+
+                for (from, to) in [(1, 2), (1, 3), (1, 4), (2, 3), (3, 4)] {
+                    p.fact("edge", |f| f.bind((("from", from), ("to", to))))?;
+                }
+
+                p.fact("blocked", |f| f.bind((("id", 4),)))?;
+
+                p.rule::<(i32, i32, i32)>("degree", &|h, b, (x, y, n)| {
+                    h.bind((("id", x), ("n", n)))?;
+
+                    b.search("node", (("id", x),))?;
+                    b.aggregate(n, math::count(), |b| {
+                        b.search("edge", (("from", x), ("to", y)))?;
+                        b.search("node", (("id", y),))?;
+                        b.except("blocked", (("id", y),))?;
+
+                        Ok(())
+                    })?;
+
+                    Ok(())
                 })?;
-                p.output("pair2", |h| {
-                    h.column::<&str>("id")
-                        .column::<&str>("x")
-                        .column::<&str>("y")
+
+                Ok(p)
+            },
+            [(
+                "degree",
+                [
+                    Tuple::new("degree", [("id", 1), ("n", 2)], None),
+                    Tuple::new("degree", [("id", 2), ("n", 1)], None),
+                    Tuple::new("degree", [("id", 3), ("n", 0)], None),
+                    Tuple::new("degree", [("id", 4), ("n", 0)], None),
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_lattice_shortest_path() {
+        // Values superseded during the fixpoint are joined away before being streamed
+        assert_derives!(
+            |p| {
+                p.output("edge", |h| {
+                    h.column::<u64>("from")
+                        .column::<u64>("to")
+                        .column::<u64>("weight")
+                })?;
+                p.output("dist", |h| {
+                    h.column::<u64>("to").lattice::<Min<u64>>("dist")
                 })?;
 
-                p.fact("pair1", |f| f.bind((("id", "a"), ("x", "1"), ("y", "2"))))?;
-                p.fact("pair1", |f| f.bind((("id", "b"), ("x", "3"), ("y", "4"))))?;
-                p.fact("pair1", |f| f.bind((("id", "a"), ("x", "5"), ("y", "6"))))?;
+                for (from, to, weight) in [
+                    (1u64, 2u64, 4u64),
+                    (1, 3, 1),
+                    (3, 2, 1),
+                    (2, 4, 1),
+                    (4, 1, 1),
+                ] {
+                    p.fact("edge", |f| {
+                        f.bind((("from", from), ("to", to), ("weight", weight)))
+                    })?;
+                }
 
-                p.rule::<(&str, &str, &str)>("pair2", &|h, b, (id, x, y)| {
-                    h.bind((("id", id), ("x", x), ("y", y)))?;
-                    b.search("pair1", (("id", id), ("x", x)))?;
-                    b.search("pair1", (("id", id), ("y", y)))?;
+                p.fact("dist", |f| f.bind((("to", 1u64), ("dist", 0u64))))?;
+
+                p.rule::<(u64, u64, u64, u64, u64)>("dist", &|h, b, (x, y, dx, w, d)| {
+                    h.bind((("to", y), ("dist", d)))?;
+
+                    b.search("dist", (("to", x), ("dist", dx)))?;
+                    b.search("edge", (("from", x), ("to", y), ("weight", w)))?;
+                    b.compute(d, kernel::func(|(dx, w): (u64, u64)| dx + w), (dx, w))?;
 
                     Ok(())
                 })?;
@@ -891,66 +1309,2150 @@ mod tests {
                 Ok(p)
             },
             [(
-                "pair2",
+                "dist",
                 [
-                    Tuple::new("pair2", [("id", "a"), ("x", "1"), ("y", "2")], None),
-                    Tuple::new("pair2", [("id", "a"), ("x", "1"), ("y", "6")], None),
-                    Tuple::new("pair2", [("id", "a"), ("x", "5"), ("y", "2")], None),
-                    Tuple::new("pair2", [("id", "a"), ("x", "5"), ("y", "6")], None),
-                    Tuple::new("pair2", [("id", "b"), ("x", "3"), ("y", "4")], None),
+                    Tuple::new("dist", [("to", 1u64), ("dist", 0u64)], None),
+                    Tuple::new("dist", [("to", 2u64), ("dist", 2u64)], None),
+                    Tuple::new("dist", [("to", 3u64), ("dist", 1u64)], None),
+                    Tuple::new("dist", [("to", 4u64), ("dist", 3u64)], None),
                 ]
             )]
         );
     }
 
-    #[derive(Debug)]
-    #[allow(unreachable_pub)]
-    pub struct Product<T: RhizomeType + AddAssign + WrappingMul + Zero>(T);
+    #[test]
+    fn test_lattice_from_evac() -> Result<()> {
+        let mut bs = MemoryBlockstore::default();
+        let mut vm = <VM>::new(crate::build(|p| {
+            p.output("dist", |h| {
+                h.column::<i32>("to").lattice::<Min<u64>>("dist")
+            })?;
+
+            p.rule::<[Any; 2]>("dist", &|h, b, [x, d]| {
+                h.bind((("to", x), ("dist", d)))?;
+                b.search("evac", (("entity", x), ("attribute", "dist"), ("value", d)))?;
+
+                Ok(())
+            })?;
+
+            Ok(p)
+        })?);
+
+        let mut step = |facts: Vec<InputTuple>| -> Result<()> {
+            for input_fact in facts {
+                bs.put_serializable(
+                    &input_fact,
+                    #[allow(unknown_lints, clippy::default_constructed_unit_structs)]
+                    DefaultCodec::default(),
+                    DEFAULT_MULTIHASH,
+                )?;
+
+                for fact in input_fact.tuples()? {
+                    vm.push(fact)?;
+                }
+            }
+
+            vm.step_epoch(&bs)
+        };
+
+        step(vec![
+            InputTuple::new(0, "dist", 3u64, []),
+            InputTuple::new(0, "dist", 2u64, []),
+        ])?;
+
+        // Values that don't fit the lattice column are rejected, rather than joined
+        let Err(err) = step(vec![InputTuple::new(0, "dist", 1, [])]) else {
+            panic!("value of the wrong type was joined");
+        };
+
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(&Error::ColumnValueTypeConflict(..))
+        ));
+
+        assert_eq!(
+            vm.search(RelationId::new("dist"), vec![])?,
+            vec![Tuple::new(
+                "dist",
+                [("to", Val::S32(0)), ("dist", Val::U64(2))],
+                None
+            )]
+        );
 
-    impl<T: RhizomeType + AddAssign + WrappingMul + Zero> Default for Product<T> {
-        fn default() -> Self {
-            Self(Zero::zero())
-        }
+        Ok(())
     }
 
-    impl<T: RhizomeType + AddAssign + WrappingMul + Zero> Aggregate for Product<T> {
-        type Input = (T, T);
-        type Output = T;
+    #[test]
+    fn test_recursive_monotone_aggregation() {
+        // The height of each node is one more than the greatest height of its children
+        assert_derives!(
+            |p| {
+                p.output("node", |h| h.column::<u64>("id"))?;
+                p.output("edge", |h| h.column::<u64>("from").column::<u64>("to"))?;
+                p.output("height", |h| {
+                    h.column::<u64>("id").lattice::<Max<u64>>("height")
+                })?;
 
-        fn step(&mut self, (a, b): (T, T)) {
-            self.0 += a * b;
-        }
+                for id in 1u64..=5 {
+                    p.fact("node", |f| f.bind((("id", id),)))?;
+                }
 
-        fn finalize(&self) -> Option<Self::Output> {
-            Some(self.0.clone())
-        }
-    }
+                for (from, to) in [(1u64, 2u64), (1, 3), (2, 4), (3, 4), (4, 5), (1, 5)] {
+                    p.fact("edge", |f| f.bind((("from", from), ("to", to))))?;
+                }
 
-    rhizome_fn! {
-        #[aggregate = Product]
-        fn product<T: RhizomeType + AddAssign + WrappingMul + Zero>(a: T, b: T) -> T;
-    }
+                p.fact("height", |f| f.bind((("id", 5u64), ("height", 0u64))))?;
 
-    #[derive(Debug)]
-    #[allow(unreachable_pub)]
-    pub struct IsTriangle<T>(PhantomData<T>);
+                p.rule::<(u64, u64, u64, u64, u64)>("height", &|h, b, (x, y, hy, hx, n)| {
+                    h.bind((("id", x), ("height", n)))?;
 
-    impl<T> Default for IsTriangle<T> {
-        fn default() -> Self {
-            Self(Default::default())
-        }
-    }
+                    b.search("node", (("id", x),))?;
+                    b.aggregate(n, math::max(hx), |b| {
+                        b.search("edge", (("from", x), ("to", y)))?;
+                        b.search("height", (("id", y), ("height", hy)))?;
+                        b.compute(hx, kernel::func(|(hy,): (u64,)| hy + 1), (hy,))?;
 
-    impl<T: RhizomeType + Add<Output = T> + Ord> Predicate for IsTriangle<(T, T, T)> {
-        type Input = (T, T, T);
+                        Ok(())
+                    })?;
 
-        fn apply(&self, (a, b, c): Self::Input) -> Option<bool> {
-            Some(a + b < c)
-        }
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            [(
+                "height",
+                [
+                    Tuple::new("height", [("id", 1u64), ("height", 3u64)], None),
+                    Tuple::new("height", [("id", 2u64), ("height", 2u64)], None),
+                    Tuple::new("height", [("id", 3u64), ("height", 2u64)], None),
+                    Tuple::new("height", [("id", 4u64), ("height", 1u64)], None),
+                    Tuple::new("height", [("id", 5u64), ("height", 0u64)], None),
+                ]
+            )]
+        );
     }
 
-    rhizome_fn! {
-        #[predicate = IsTriangle]
-        fn is_triangle<T: RhizomeType + Add<Output = T> + Ord>(a: T, b: T, z: T) -> T;
+    #[test]
+    fn test_well_founded_negation() {
+        // a and b can each only move to the other, so neither's win is decided
+        assert_derives!(
+            |p| {
+                p.semantics(Semantics::WellFounded);
+
+                p.output("move", |h| h.column::<&str>("from").column::<&str>("to"))?;
+                p.output("win", |h| h.column::<&str>("position"))?;
+
+                for (from, to) in [("a", "b"), ("b", "a"), ("b", "c"), ("c", "d")] {
+                    p.fact("move", |f| f.bind((("from", from), ("to", to))))?;
+                }
+
+                p.rule::<(&str, &str)>("win", &|h, b, (x, y)| {
+                    h.bind((("position", x),))?;
+
+                    b.search("move", (("from", x), ("to", y)))?;
+                    b.except("win", (("position", y),))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            [(
+                "win",
+                [
+                    Tuple::new("win", [("position", "a")], None).with_truth(Truth::Undefined),
+                    Tuple::new("win", [("position", "b")], None).with_truth(Truth::Undefined),
+                    Tuple::new("win", [("position", "c")], None),
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_well_founded_input() {
+        assert_derives!(
+            |p| {
+                p.semantics(Semantics::WellFounded);
+
+                p.output("p", |h| h.column::<i32>("id"))?;
+                p.output("q", |h| h.column::<i32>("id"))?;
+
+                p.rule::<(i32,)>("p", &|h, b, (x,)| {
+                    h.bind((("id", x),))?;
+
+                    b.search("evac", (("entity", x), ("attribute", "p")))?;
+                    b.except("q", (("id", x),))?;
+
+                    Ok(())
+                })?;
+
+                p.rule::<(i32,)>("q", &|h, b, (x,)| {
+                    h.bind((("id", x),))?;
+
+                    b.search("evac", (("entity", x), ("attribute", "q")))?;
+                    b.except("p", (("id", x),))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            [
+                InputTuple::new(0, "p", 0, []),
+                InputTuple::new(1, "p", 0, []),
+                InputTuple::new(1, "q", 0, []),
+            ],
+            [
+                (
+                    "p",
+                    vec![
+                        Tuple::new("p", [("id", 0)], None),
+                        Tuple::new("p", [("id", 1)], None).with_truth(Truth::Undefined),
+                    ]
+                ),
+                (
+                    "q",
+                    vec![Tuple::new("q", [("id", 1)], None).with_truth(Truth::Undefined)]
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn test_retract_transitive_closure() -> Result<()> {
+        let derived = derive_epochs(
+            |p| {
+                p.output("path", |h| h.column::<i32>("from").column::<i32>("to"))?;
+
+                p.rule::<(i32, i32)>("path", &|h, b, (x, y)| {
+                    h.bind((("from", x), ("to", y)))?;
+                    b.search("evac", (("entity", x), ("attribute", "to"), ("value", y)))?;
+
+                    Ok(())
+                })?;
+
+                p.rule::<(i32, i32, i32)>("path", &|h, b, (x, y, z)| {
+                    h.bind((("from", x), ("to", z)))?;
+
+                    b.search("evac", (("entity", x), ("attribute", "to"), ("value", y)))?;
+                    b.search("path", (("from", y), ("to", z)))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            "path",
+            [
+                (
+                    vec![
+                        InputTuple::new(0, "to", 1, []),
+                        InputTuple::new(1, "to", 2, []),
+                        InputTuple::new(2, "to", 3, []),
+                    ],
+                    vec![],
+                ),
+                (vec![], vec![InputTuple::new(1, "to", 2, [])]),
+                (vec![InputTuple::new(1, "to", 2, [])], vec![]),
+            ],
+        )?;
+
+        let path = |from: i32, to: i32| Tuple::new("path", [("from", from), ("to", to)], None);
+
+        assert_eq!(
+            derived,
+            vec![
+                BTreeSet::from_iter([
+                    (path(0, 1), 1),
+                    (path(0, 2), 1),
+                    (path(0, 3), 1),
+                    (path(1, 2), 1),
+                    (path(1, 3), 1),
+                    (path(2, 3), 1),
+                ]),
+                BTreeSet::from_iter([
+                    (path(0, 2), -1),
+                    (path(0, 3), -1),
+                    (path(1, 2), -1),
+                    (path(1, 3), -1),
+                ]),
+                // Every path through the retracted edge was removed, so they're derived again
+                BTreeSet::from_iter([
+                    (path(0, 2), 1),
+                    (path(0, 3), 1),
+                    (path(1, 2), 1),
+                    (path(1, 3), 1),
+                ]),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_retract_cycle() -> Result<()> {
+        let program = |evaluation| {
+            move |p: ProgramBuilder| {
+                p.evaluation(evaluation);
+
+                p.output("path", |h| h.column::<i32>("from").column::<i32>("to"))?;
+
+                p.rule::<(i32, i32)>("path", &|h, b, (x, y)| {
+                    h.bind((("from", x), ("to", y)))?;
+                    b.search("evac", (("entity", x), ("attribute", "to"), ("value", y)))?;
+
+                    Ok(())
+                })?;
+
+                p.rule::<(i32, i32, i32)>("path", &|h, b, (x, y, z)| {
+                    h.bind((("from", x), ("to", z)))?;
+
+                    b.search("path", (("from", x), ("to", y)))?;
+                    b.search("path", (("from", y), ("to", z)))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            }
+        };
+
+        let epochs = || {
+            [
+                (
+                    vec![
+                        InputTuple::new(0, "to", 1, []),
+                        InputTuple::new(1, "to", 2, []),
+                        InputTuple::new(2, "to", 0, []),
+                    ],
+                    vec![],
+                ),
+                (vec![], vec![InputTuple::new(0, "to", 1, [])]),
+                (vec![InputTuple::new(0, "to", 1, [])], vec![]),
+            ]
+        };
+
+        let derived = derive_epochs(program(Evaluation::Incremental), "path", epochs())?;
+
+        let path = |from: i32, to: i32| Tuple::new("path", [("from", from), ("to", to)], None);
+
+        // The paths around the cycle support each other, but are deleted along
+        // with the edge they were all derived from
+        assert_eq!(
+            derived[1],
+            BTreeSet::from_iter([
+                (path(0, 0), -1),
+                (path(0, 1), -1),
+                (path(0, 2), -1),
+                (path(1, 1), -1),
+                (path(2, 1), -1),
+                (path(2, 2), -1),
+            ])
+        );
+
+        assert_eq!(
+            derived,
+            derive_epochs(program(Evaluation::Recompute), "path", epochs())?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_retract_rederive() -> Result<()> {
+        let derived = derive_epochs(
+            |p| {
+                p.output("reachable", |h| h.column::<i32>("id"))?;
+
+                p.fact("reachable", |f| f.bind((("id", 0),)))?;
+
+                p.rule::<(i32, i32)>("reachable", &|h, b, (x, y)| {
+                    h.bind((("id", y),))?;
+                    b.search("evac", (("entity", x), ("attribute", "to"), ("value", y)))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            "reachable",
+            [
+                (
+                    vec![
+                        InputTuple::new(0, "to", 1, []),
+                        InputTuple::new(2, "to", 1, []),
+                        InputTuple::new(3, "to", 0, []),
+                    ],
+                    vec![],
+                ),
+                (
+                    vec![],
+                    vec![
+                        InputTuple::new(0, "to", 1, []),
+                        InputTuple::new(3, "to", 0, []),
+                    ],
+                ),
+                (vec![], vec![InputTuple::new(2, "to", 1, [])]),
+            ],
+        )?;
+
+        let reachable = |id: i32| Tuple::new("reachable", [("id", id)], None);
+
+        assert_eq!(
+            derived,
+            vec![
+                BTreeSet::from_iter([(reachable(0), 1), (reachable(1), 1)]),
+                // Both are deleted and rederived, from the other edge and the fact
+                BTreeSet::default(),
+                BTreeSet::from_iter([(reachable(1), -1)]),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_retract_negation() -> Result<()> {
+        let derived = derive_epochs(
+            |p| {
+                p.output("blocked", |h| h.column::<i32>("id"))?;
+                p.output("unblocked", |h| h.column::<i32>("id"))?;
+
+                p.rule::<(i32,)>("blocked", &|h, b, (x,)| {
+                    h.bind((("id", x),))?;
+                    b.search("evac", (("entity", x), ("attribute", "blocked")))?;
+
+                    Ok(())
+                })?;
+
+                p.rule::<(i32,)>("unblocked", &|h, b, (x,)| {
+                    h.bind((("id", x),))?;
+
+                    b.search("evac", (("entity", x), ("attribute", "node")))?;
+                    b.except("blocked", (("id", x),))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            "unblocked",
+            [
+                (
+                    vec![
+                        InputTuple::new(1, "node", true, []),
+                        InputTuple::new(2, "node", true, []),
+                        InputTuple::new(2, "blocked", true, []),
+                    ],
+                    vec![],
+                ),
+                (vec![InputTuple::new(1, "blocked", true, [])], vec![]),
+                (vec![], vec![InputTuple::new(2, "blocked", true, [])]),
+                (vec![], vec![InputTuple::new(1, "blocked", true, [])]),
+            ],
+        )?;
+
+        let unblocked = |id: i32| Tuple::new("unblocked", [("id", id)], None);
+
+        assert_eq!(
+            derived,
+            vec![
+                BTreeSet::from_iter([(unblocked(1), 1)]),
+                BTreeSet::from_iter([(unblocked(1), -1)]),
+                BTreeSet::from_iter([(unblocked(2), 1)]),
+                BTreeSet::from_iter([(unblocked(1), 1)]),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_retract_aggregation() -> Result<()> {
+        let derived = derive_epochs(
+            |p| {
+                p.output("degree", |h| h.column::<i32>("id").column::<i32>("n"))?;
+
+                p.rule::<(i32, i32, i32)>("degree", &|h, b, (x, y, n)| {
+                    h.bind((("id", x), ("n", n)))?;
+
+                    b.search("evac", (("entity", x), ("attribute", "node")))?;
+                    b.aggregate(n, math::count(), |b| {
+                        b.search("evac", (("entity", x), ("attribute", "to"), ("value", y)))?;
+
+                        Ok(())
+                    })?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            "degree",
+            [
+                (
+                    vec![
+                        InputTuple::new(0, "node", true, []),
+                        InputTuple::new(0, "to", 1, []),
+                        InputTuple::new(0, "to", 2, []),
+                    ],
+                    vec![],
+                ),
+                (vec![], vec![InputTuple::new(0, "to", 2, [])]),
+                (vec![InputTuple::new(0, "to", 2, [])], vec![]),
+            ],
+        )?;
+
+        let degree = |id: i32, n: i32| Tuple::new("degree", [("id", id), ("n", n)], None);
+
+        assert_eq!(
+            derived,
+            vec![
+                BTreeSet::from_iter([(degree(0, 2), 1)]),
+                BTreeSet::from_iter([(degree(0, 2), -1), (degree(0, 1), 1)]),
+                BTreeSet::from_iter([(degree(0, 1), -1), (degree(0, 2), 1)]),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_retract_lattice() -> Result<()> {
+        let derived = derive_epochs(
+            |p| {
+                p.output("dist", |h| {
+                    h.column::<u64>("to").lattice::<Min<u64>>("dist")
+                })?;
+
+                p.fact("dist", |f| f.bind((("to", 1u64), ("dist", 0u64))))?;
+
+                // Edges are inserted with the destination as their attribute, and the weight
+                // as their value
+                p.rule::<(u64, u64, u64, u64, u64)>("dist", &|h, b, (x, y, dx, w, d)| {
+                    h.bind((("to", y), ("dist", d)))?;
+
+                    b.search("dist", (("to", x), ("dist", dx)))?;
+                    b.search("evac", (("entity", x), ("attribute", y), ("value", w)))?;
+                    b.compute(d, kernel::func(|(dx, w): (u64, u64)| dx + w), (dx, w))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            "dist",
+            [
+                (
+                    vec![
+                        InputTuple::new(1u64, 2u64, 4u64, []),
+                        InputTuple::new(1u64, 3u64, 1u64, []),
+                        InputTuple::new(3u64, 2u64, 1u64, []),
+                    ],
+                    vec![],
+                ),
+                (vec![], vec![InputTuple::new(3u64, 2u64, 1u64, [])]),
+            ],
+        )?;
+
+        let dist = |to: u64, dist: u64| Tuple::new("dist", [("to", to), ("dist", dist)], None);
+
+        assert_eq!(
+            derived,
+            vec![
+                BTreeSet::from_iter([(dist(1, 0), 1), (dist(2, 2), 1), (dist(3, 1), 1)]),
+                // The shortest path was retracted, so the next shortest replaces it
+                BTreeSet::from_iter([(dist(2, 2), -1), (dist(2, 4), 1)]),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_retract_well_founded_negation() -> Result<()> {
+        let derived = derive_epochs(
+            |p| {
+                p.semantics(Semantics::WellFounded);
+
+                p.output("win", |h| h.column::<i32>("position"))?;
+
+                p.rule::<(i32, i32)>("win", &|h, b, (x, y)| {
+                    h.bind((("position", x),))?;
+
+                    b.search("evac", (("entity", x), ("attribute", "move"), ("value", y)))?;
+                    b.except("win", (("position", y),))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            "win",
+            [
+                (
+                    vec![
+                        InputTuple::new(1, "move", 2, []),
+                        InputTuple::new(2, "move", 1, []),
+                    ],
+                    vec![],
+                ),
+                (vec![InputTuple::new(2, "move", 3, [])], vec![]),
+                (vec![], vec![InputTuple::new(2, "move", 3, [])]),
+            ],
+        )?;
+
+        let win = |position: i32| Tuple::new("win", [("position", position)], None);
+        let undefined = |position: i32| win(position).with_truth(Truth::Undefined);
+
+        assert_eq!(
+            derived,
+            vec![
+                BTreeSet::from_iter([(undefined(1), 1), (undefined(2), 1)]),
+                // Moving to a losing position decides both wins
+                BTreeSet::from_iter([(undefined(1), -1), (undefined(2), -1), (win(2), 1)]),
+                BTreeSet::from_iter([(win(2), -1), (undefined(1), 1), (undefined(2), 1)]),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recompute_matches_incremental() -> Result<()> {
+        let program = |evaluation| {
+            move |p: ProgramBuilder| {
+                p.evaluation(evaluation);
+
+                p.output("node", |h| h.column::<i32>("id"))?;
+                p.output("path", |h| h.column::<i32>("from").column::<i32>("to"))?;
+                p.output("unreachable", |h| {
+                    h.column::<i32>("from").column::<i32>("to")
+                })?;
+
+                p.rule::<(i32,)>("node", &|h, b, (x,)| {
+                    h.bind((("id", x),))?;
+                    b.search("evac", (("entity", x), ("attribute", "node")))?;
+
+                    Ok(())
+                })?;
+
+                p.rule::<(i32, i32)>("path", &|h, b, (x, y)| {
+                    h.bind((("from", x), ("to", y)))?;
+                    b.search("evac", (("entity", x), ("attribute", "to"), ("value", y)))?;
+
+                    Ok(())
+                })?;
+
+                p.rule::<(i32, i32, i32)>("path", &|h, b, (x, y, z)| {
+                    h.bind((("from", x), ("to", z)))?;
+
+                    b.search("evac", (("entity", x), ("attribute", "to"), ("value", y)))?;
+                    b.search("path", (("from", y), ("to", z)))?;
+
+                    Ok(())
+                })?;
+
+                p.rule::<(i32, i32)>("unreachable", &|h, b, (x, y)| {
+                    h.bind((("from", x), ("to", y)))?;
+
+                    b.search("node", (("id", x),))?;
+                    b.search("node", (("id", y),))?;
+                    b.except("path", (("from", x), ("to", y)))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            }
+        };
+
+        let epochs = || {
+            [
+                (
+                    vec![
+                        InputTuple::new(0, "node", true, []),
+                        InputTuple::new(1, "node", true, []),
+                        InputTuple::new(2, "node", true, []),
+                        InputTuple::new(0, "to", 1, []),
+                        InputTuple::new(1, "to", 2, []),
+                    ],
+                    vec![],
+                ),
+                (
+                    vec![InputTuple::new(2, "to", 0, [])],
+                    vec![InputTuple::new(0, "to", 1, [])],
+                ),
+                (vec![InputTuple::new(0, "to", 1, [])], vec![]),
+            ]
+        };
+
+        for relation in ["path", "unreachable"] {
+            assert_eq!(
+                derive_epochs(program(Evaluation::Recompute), relation, epochs())?,
+                derive_epochs(program(Evaluation::Incremental), relation, epochs())?,
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_derivation_timestamps() -> Result<()> {
+        for evaluation in [Evaluation::Incremental, Evaluation::Recompute] {
+            let bs = MemoryBlockstore::default();
+            let mut vm = <VM>::new(crate::build(|p| {
+                p.evaluation(evaluation);
+
+                p.output("path", |h| h.column::<i32>("from").column::<i32>("to"))?;
+
+                p.rule::<(i32, i32)>("path", &|h, b, (x, y)| {
+                    h.bind((("from", x), ("to", y)))?;
+                    b.search("evac", (("entity", x), ("attribute", "to"), ("value", y)))?;
+
+                    Ok(())
+                })?;
+
+                p.rule::<(i32, i32, i32)>("path", &|h, b, (x, y, z)| {
+                    h.bind((("from", x), ("to", z)))?;
+
+                    b.search("evac", (("entity", x), ("attribute", "to"), ("value", y)))?;
+                    b.search("path", (("from", y), ("to", z)))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            })?);
+
+            for (inserted, retracted) in [
+                (vec![(0, 1), (1, 2)], vec![]),
+                (vec![(2, 3)], vec![]),
+                (vec![(0, 2)], vec![]),
+                (vec![], vec![(0, 2)]),
+            ] {
+                for (from, to) in inserted {
+                    for fact in InputTuple::new(from, "to", to, []).tuples()? {
+                        vm.push(fact)?;
+                    }
+                }
+
+                for (from, to) in retracted {
+                    for fact in InputTuple::new(from, "to", to, []).tuples()? {
+                        vm.retract(fact)?;
+                    }
+                }
+
+                vm.step_epoch(&bs)?;
+
+                // Each inserted tuple is sent to the sinks with the time it was derived at
+                while let Some(event) = vm.pop()? {
+                    if let SinkEvent::Diff(fact, timestamp, _) = event {
+                        assert_eq!(fact.timestamp().map(|t| t.epoch()), Some(timestamp.epoch()));
+                    }
+                }
+            }
+
+            let path = |from: i32, to: i32, epoch: u32, iteration: u32| {
+                (
+                    Tuple::new("path", [("from", from), ("to", to)], None),
+                    Some(PairTimestamp(epoch, iteration)),
+                )
+            };
+
+            let derived: BTreeSet<_> = vm
+                .search(RelationId::new("path"), vec![])?
+                .into_iter()
+                .map(|fact| {
+                    let timestamp = fact.timestamp();
+
+                    (fact, timestamp)
+                })
+                .collect();
+
+            // Tuples derived in an earlier epoch keep their timestamp, even if they
+            // were deleted and rederived, or the stratum recomputed, since
+            assert_eq!(
+                derived,
+                BTreeSet::from_iter([
+                    path(0, 1, 0, 0),
+                    path(1, 2, 0, 0),
+                    path(0, 2, 0, 1),
+                    path(2, 3, 1, 0),
+                    path(1, 3, 1, 1),
+                    path(0, 3, 1, 2),
+                ]),
+                "{evaluation:?}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain() -> Result<()> {
+        let mut bs = MemoryBlockstore::default();
+        let mut vm = <VM>::new(crate::build(|p| {
+            p.record_provenance();
+
+            p.output("path", |h| h.column::<i32>("from").column::<i32>("to"))?;
+
+            p.rule::<(i32, i32)>("path", &|h, b, (x, y)| {
+                h.bind((("from", x), ("to", y)))?;
+                b.search("evac", (("entity", x), ("attribute", "to"), ("value", y)))?;
+
+                Ok(())
+            })?;
+
+            p.rule::<(i32, i32, i32)>("path", &|h, b, (x, y, z)| {
+                h.bind((("from", x), ("to", z)))?;
+
+                b.search("evac", (("entity", x), ("attribute", "to"), ("value", y)))?;
+                b.search("path", (("from", y), ("to", z)))?;
+
+                Ok(())
+            })?;
+
+            Ok(p)
+        })?);
+
+        let edge = |from: i32, to: i32| -> Result<Tuple> {
+            let mut tuples = InputTuple::new(from, "to", to, []).tuples()?;
+
+            Ok(tuples.remove(0))
+        };
+
+        let path = |from: i32, to: i32| Tuple::new("path", [("from", from), ("to", to)], None);
+
+        // Every tuple a derivation depends on, directly or not
+        fn premises(derivation: &Derivation) -> BTreeSet<Tuple> {
+            derivation
+                .premises()
+                .iter()
+                .flat_map(|premise| {
+                    let mut tuples = premises(premise);
+                    tuples.insert(premise.tuple().clone());
+                    tuples
+                })
+                .collect()
+        }
+
+        for (from, to) in [(0, 1), (1, 2), (0, 2)] {
+            for fact in InputTuple::new(from, "to", to, []).tuples()? {
+                vm.push(fact)?;
+            }
+        }
+
+        vm.step_epoch(&bs)?;
+
+        let derivation = vm.explain(&path(0, 2))?.unwrap();
+
+        assert_eq!(derivation.tuple(), &path(0, 2));
+        assert!(derivation.rule().is_some());
+        assert_eq!(derivation.bindings()["x0"], Val::S32(0));
+        assert!(
+            premises(&derivation) == BTreeSet::from_iter([edge(0, 2)?])
+                || premises(&derivation)
+                    == BTreeSet::from_iter([edge(0, 1)?, edge(1, 2)?, path(1, 2)])
+        );
+
+        // Inputs aren't derived by any rule
+        let derivation = vm.explain(&edge(0, 1)?)?.unwrap();
+
+        assert_eq!(derivation.rule(), None);
+        assert_eq!(derivation.tuple().cid(), edge(0, 1)?.cid());
+
+        // Once an input is retracted, tuples are only explained by the derivations
+        // that remain
+        for fact in InputTuple::new(1, "to", 2, []).tuples()? {
+            vm.retract(fact)?;
+        }
+
+        bs.put_serializable(
+            &InputTuple::new(1, "to", 2, []),
+            #[allow(unknown_lints, clippy::default_constructed_unit_structs)]
+            DefaultCodec::default(),
+            DEFAULT_MULTIHASH,
+        )?;
+
+        vm.step_epoch(&bs)?;
+
+        let derivation = vm.explain(&path(0, 2))?.unwrap();
+
+        assert_eq!(premises(&derivation), BTreeSet::from_iter([edge(0, 2)?]));
+        assert_eq!(vm.explain(&path(1, 2))?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain_without_provenance() -> Result<()> {
+        let vm = <VM>::new(crate::build(|p| {
+            p.output("path", |h| h.column::<i32>("from").column::<i32>("to"))?;
+
+            Ok(p)
+        })?);
+
+        let path = Tuple::new("path", [("from", 0), ("to", 1)], None);
+
+        assert_eq!(
+            vm.explain(&path).unwrap_err().downcast_ref::<Error>(),
+            Some(&Error::ProvenanceNotRecorded)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_query() -> Result<()> {
+        let bs = MemoryBlockstore::default();
+        let mut vm = <VM>::new(crate::build(|p| {
+            p.output("path", |h| h.column::<i32>("from").column::<i32>("to"))?;
+
+            p.rule::<(i32, i32)>("path", &|h, b, (x, y)| {
+                h.bind((("from", x), ("to", y)))?;
+                b.search("evac", (("entity", x), ("attribute", "to"), ("value", y)))?;
+
+                Ok(())
+            })?;
+
+            p.rule::<(i32, i32, i32)>("path", &|h, b, (x, y, z)| {
+                h.bind((("from", x), ("to", z)))?;
+
+                b.search("path", (("from", x), ("to", y)))?;
+                b.search("path", (("from", y), ("to", z)))?;
+
+                Ok(())
+            })?;
+
+            Ok(p)
+        })?);
+
+        for (from, to) in [(0, 1), (1, 2), (2, 3), (4, 3)] {
+            for fact in InputTuple::new(from, "to", to, []).tuples()? {
+                vm.push(fact)?;
+            }
+        }
+
+        vm.step_epoch(&bs)?;
+
+        while vm.pop()?.is_some() {}
+
+        let path = |from: i32, to: i32| Tuple::new("path", [("from", from), ("to", to)], None);
+        let solution = |to: i32| Tuple::new("query", [("to", to)], None);
+
+        let from_zero = |body: &RuleBodyBuilder| {
+            body.search("path", (("from", 0), ("to", TypedVar::<i32>::new("to"))))
+        };
+
+        assert_eq!(
+            vm.query(&Query::Body(Box::new(from_zero)), &bs)?,
+            vec![solution(1), solution(2), solution(3)]
+        );
+
+        // Negated relations are read in total too
+        let unreachable_from_four = |body: &RuleBodyBuilder| {
+            let to = TypedVar::<i32>::new("to");
+
+            body.search("path", (("from", 0), ("to", to)))?;
+            body.except("path", (("from", 4), ("to", to)))?;
+
+            Ok(())
+        };
+
+        assert_eq!(
+            vm.query(&Query::Body(Box::new(unreachable_from_four)), &bs)?,
+            vec![solution(1), solution(2)]
+        );
+
+        assert_eq!(
+            vm.query(
+                &Query::Relation(
+                    RelationId::new("path"),
+                    vec![(ColId::new("to"), Val::S32(3))]
+                ),
+                &bs
+            )?,
+            vec![path(0, 3), path(1, 3), path(2, 3), path(4, 3)]
+        );
+
+        // Queries are checked against the declarations of the relations they search
+        let unknown =
+            |body: &RuleBodyBuilder| body.search("node", (("id", TypedVar::<i32>::new("id")),));
+
+        assert_eq!(
+            vm.query(&Query::Body(Box::new(unknown)), &bs)
+                .unwrap_err()
+                .downcast_ref::<Error>(),
+            Some(&Error::UnrecognizedRelation("node".to_owned()))
+        );
+
+        // Answering queries leaves the relations as they were
+        for fact in InputTuple::new(3, "to", 5, []).tuples()? {
+            vm.push(fact)?;
+        }
+
+        vm.step_epoch(&bs)?;
+
+        let mut derived = BTreeSet::default();
+        while let Some(event) = vm.pop()? {
+            if let SinkEvent::Diff(fact, _, diff) = event {
+                if fact.id() == RelationId::new("path") {
+                    derived.insert((fact, diff));
+                }
+            }
+        }
+
+        assert_eq!(
+            derived,
+            BTreeSet::from_iter([0, 1, 2, 3, 4].map(|from| (path(from, 5), 1)))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lookup() -> Result<()> {
+        let bs = MemoryBlockstore::default();
+        let mut vm = <VM>::new(crate::build(|p| {
+            p.output("edge", |h| h.column::<i32>("from").column::<i32>("to"))?;
+
+            p.rule::<(i32, i32)>("edge", &|h, b, (x, y)| {
+                h.bind((("from", x), ("to", y)))?;
+                b.search("evac", (("entity", x), ("attribute", "to"), ("value", y)))?;
+
+                Ok(())
+            })?;
+
+            Ok(p)
+        })?);
+
+        let lookup = |vm: &VM, id: &str, bindings: Vec<(&str, Val)>| {
+            let bindings = bindings
+                .into_iter()
+                .map(|(col, val)| (ColId::new(col), val))
+                .collect();
+
+            vm.query(&Query::Relation(RelationId::new(id), bindings), &bs)
+        };
+
+        let edge = |from: i32, to: i32| Tuple::new("edge", [("from", from), ("to", to)], None);
+
+        for (from, to) in [(0, 1), (0, 2), (1, 2)] {
+            for fact in InputTuple::new(from, "to", to, []).tuples()? {
+                vm.push(fact)?;
+            }
+        }
+
+        vm.step_epoch(&bs)?;
+
+        // Tuples that haven't been evaluated yet aren't found
+        for fact in InputTuple::new(0, "to", 3, []).tuples()? {
+            vm.push(fact)?;
+        }
+
+        assert_eq!(
+            lookup(&vm, "edge", vec![("from", Val::S32(0))])?,
+            vec![edge(0, 1), edge(0, 2)]
+        );
+
+        // Input relations are searched by their indexes
+        assert_eq!(lookup(&vm, "evac", vec![("value", Val::S32(2))])?.len(), 2);
+
+        vm.step_epoch(&bs)?;
+
+        assert_eq!(
+            lookup(&vm, "edge", vec![("from", Val::S32(0))])?,
+            vec![edge(0, 1), edge(0, 2), edge(0, 3)]
+        );
+
+        assert_eq!(
+            lookup(&vm, "edge", vec![("form", Val::S32(0))])
+                .unwrap_err()
+                .downcast_ref::<Error>(),
+            Some(&Error::UnrecognizedColumnBinding(
+                RelationId::new("edge"),
+                ColId::new("form")
+            ))
+        );
+
+        assert!(matches!(
+            lookup(&vm, "edge", vec![("from", Val::Bool(true))])
+                .unwrap_err()
+                .downcast_ref::<Error>(),
+            Some(&Error::ColumnValueTypeConflict(..))
+        ));
+
+        assert_eq!(
+            lookup(&vm, "node", vec![])
+                .unwrap_err()
+                .downcast_ref::<Error>(),
+            Some(&Error::UnrecognizedRelation("node".to_owned()))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_insert() -> Result<()> {
+        let bs = MemoryBlockstore::default();
+        let mut vm = <VM>::new(crate::build(|p| {
+            p.input("edge", |h| h.column::<i32>("from").column::<i32>("to"))?;
+            p.output("path", |h| h.column::<i32>("from").column::<i32>("to"))?;
+
+            p.rule::<(i32, i32)>("path", &|h, b, (x, y)| {
+                h.bind((("from", x), ("to", y)))?;
+                b.search("edge", (("from", x), ("to", y)))?;
+
+                Ok(())
+            })?;
+
+            p.rule::<(i32, i32, i32)>("path", &|h, b, (x, y, z)| {
+                h.bind((("from", x), ("to", z)))?;
+
+                b.search("edge", (("from", x), ("to", y)))?;
+                b.search("path", (("from", y), ("to", z)))?;
+
+                Ok(())
+            })?;
+
+            Ok(p)
+        })?);
+
+        let edge = |from: i32, to: i32| Tuple::new("edge", [("from", from), ("to", to)], None);
+        let path = |from: i32, to: i32| Tuple::new("path", [("from", from), ("to", to)], None);
+
+        for (from, to) in [(0, 1), (1, 2), (2, 3)] {
+            vm.insert(RelationId::new("edge"), edge(from, to))?;
+        }
+
+        vm.step_epoch(&bs)?;
+
+        assert_eq!(
+            vm.query(&Query::Relation(RelationId::new("path"), vec![]), &bs)?,
+            vec![
+                path(0, 1),
+                path(0, 2),
+                path(0, 3),
+                path(1, 2),
+                path(1, 3),
+                path(2, 3),
+            ]
+        );
+
+        let insert = |vm: &mut VM, id: &str, tuple: Tuple| {
+            vm.insert(RelationId::new(id), tuple)
+                .unwrap_err()
+                .downcast::<Error>()
+                .unwrap()
+        };
+
+        assert_eq!(
+            insert(&mut vm, "path", path(3, 4)),
+            Error::InsertIDB(RelationId::new("path"))
+        );
+
+        assert_eq!(
+            insert(&mut vm, "edge", path(3, 4)),
+            Error::TupleRelationMismatch(RelationId::new("edge"), RelationId::new("path"))
+        );
+
+        assert_eq!(
+            insert(&mut vm, "node", Tuple::new("node", [("id", 0)], None)),
+            Error::UnrecognizedRelation("node".to_owned())
+        );
+
+        assert_eq!(
+            insert(&mut vm, "edge", Tuple::new("edge", [("from", 3)], None)),
+            Error::ColumnMissing(RelationId::new("edge"), ColId::new("to"))
+        );
+
+        assert_eq!(
+            insert(
+                &mut vm,
+                "edge",
+                Tuple::new("edge", [("from", 3), ("to", 4), ("weight", 1)], None)
+            ),
+            Error::UnrecognizedColumnBinding(RelationId::new("edge"), ColId::new("weight"))
+        );
+
+        assert!(matches!(
+            insert(
+                &mut vm,
+                "edge",
+                Tuple::new(
+                    "edge",
+                    [("from", Val::S32(3)), ("to", Val::Bool(true))],
+                    None
+                )
+            ),
+            Error::ColumnValueTypeConflict(..)
+        ));
+
+        Ok(())
+    }
+
+    /// Runs a program over several epochs, each of which inserts and then retracts
+    /// the given facts, and collects the diffs sent to the sinks of `relation` during
+    /// each.
+    fn derive_epochs<F>(
+        f: F,
+        relation: &str,
+        epochs: impl IntoIterator<Item = (Vec<InputTuple>, Vec<InputTuple>)>,
+    ) -> Result<Vec<BTreeSet<(Tuple, isize)>>>
+    where
+        F: FnOnce(ProgramBuilder) -> Result<ProgramBuilder>,
+    {
+        let mut bs = MemoryBlockstore::default();
+        let mut vm = <VM>::new(crate::build(f)?);
+        let mut derived = Vec::default();
+
+        for (inserted, retracted) in epochs {
+            for input_fact in inserted {
+                bs.put_serializable(
+                    &input_fact,
+                    #[allow(unknown_lints, clippy::default_constructed_unit_structs)]
+                    DefaultCodec::default(),
+                    DEFAULT_MULTIHASH,
+                )?;
+
+                for fact in input_fact.tuples()? {
+                    vm.push(fact)?;
+                }
+            }
+
+            for input_fact in retracted {
+                for fact in input_fact.tuples()? {
+                    vm.retract(fact)?;
+                }
+            }
+
+            let epoch = *vm.timestamp();
+
+            vm.step_epoch(&bs)?;
+
+            let mut diffs = BTreeSet::default();
+
+            loop {
+                match vm.pop()? {
+                    Some(SinkEvent::Diff(fact, timestamp, diff)) => {
+                        assert_eq!(timestamp.epoch(), epoch.epoch());
+
+                        if fact.id() == RelationId::new(relation) {
+                            diffs.insert((fact, diff));
+                        }
+                    }
+                    Some(SinkEvent::EpochComplete(timestamp)) => {
+                        assert_eq!(timestamp, epoch);
+                        assert_eq!(vm.pop()?, None);
+
+                        break;
+                    }
+                    None => panic!("epoch wasn't completed"),
+                }
+            }
+
+            derived.push(diffs);
+        }
+
+        Ok(derived)
+    }
+
+    #[test]
+    fn test_self_join_str() {
+        assert_derives!(
+            |p| {
+                p.output("pair1", |h| {
+                    h.column::<&str>("id")
+                        .column::<&str>("x")
+                        .column::<&str>("y")
+                })?;
+                p.output("pair2", |h| {
+                    h.column::<&str>("id")
+                        .column::<&str>("x")
+                        .column::<&str>("y")
+                })?;
+
+                p.fact("pair1", |f| f.bind((("id", "a"), ("x", "1"), ("y", "2"))))?;
+                p.fact("pair1", |f| f.bind((("id", "b"), ("x", "3"), ("y", "4"))))?;
+                p.fact("pair1", |f| f.bind((("id", "a"), ("x", "5"), ("y", "6"))))?;
+
+                p.rule::<(&str, &str, &str)>("pair2", &|h, b, (id, x, y)| {
+                    h.bind((("id", id), ("x", x), ("y", y)))?;
+                    b.search("pair1", (("id", id), ("x", x)))?;
+                    b.search("pair1", (("id", id), ("y", y)))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            [(
+                "pair2",
+                [
+                    Tuple::new("pair2", [("id", "a"), ("x", "1"), ("y", "2")], None),
+                    Tuple::new("pair2", [("id", "a"), ("x", "1"), ("y", "6")], None),
+                    Tuple::new("pair2", [("id", "a"), ("x", "5"), ("y", "2")], None),
+                    Tuple::new("pair2", [("id", "a"), ("x", "5"), ("y", "6")], None),
+                    Tuple::new("pair2", [("id", "b"), ("x", "3"), ("y", "4")], None),
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_head_arithmetic() {
+        assert_derives!(
+            |p| {
+                p.output("edge", |h| h.column::<i32>("from").column::<i32>("to"))?;
+                p.output("hops", |h| {
+                    h.column::<i32>("from")
+                        .column::<i32>("to")
+                        .column::<i32>("n")
+                })?;
+
+                p.fact("edge", |f| f.bind((("from", 0), ("to", 1))))?;
+                p.fact("edge", |f| f.bind((("from", 1), ("to", 2))))?;
+                p.fact("edge", |f| f.bind((("from", 2), ("to", 3))))?;
+
+                p.rule::<(i32, i32)>("hops", &|h, b, (x, y)| {
+                    h.bind((("from", x), ("to", y), ("n", 1)))?;
+                    b.search("edge", (("from", x), ("to", y)))?;
+
+                    Ok(())
+                })?;
+
+                p.rule::<(i32, i32, i32, i32)>("hops", &|h, b, (x, y, z, n)| {
+                    h.bind((("from", x), ("to", z), ("n", n + 1)))?;
+
+                    b.search("edge", (("from", x), ("to", y)))?;
+                    b.search("hops", (("from", y), ("to", z), ("n", n)))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            [(
+                "hops",
+                [
+                    Tuple::new("hops", [("from", 0), ("to", 1), ("n", 1)], None),
+                    Tuple::new("hops", [("from", 0), ("to", 2), ("n", 2)], None),
+                    Tuple::new("hops", [("from", 0), ("to", 3), ("n", 3)], None),
+                    Tuple::new("hops", [("from", 1), ("to", 2), ("n", 1)], None),
+                    Tuple::new("hops", [("from", 1), ("to", 3), ("n", 2)], None),
+                    Tuple::new("hops", [("from", 2), ("to", 3), ("n", 1)], None),
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_head_untyped_literals() {
+        // Literals without a suffix take the type of the operand they're used with
+        assert_derives!(
+            |p| {
+                p.output("reading", |h| h.column::<u64>("d").column::<f32>("g"))?;
+                p.output("next", |h| h.column::<u64>("d").column::<f32>("g"))?;
+
+                p.fact("reading", |f| f.bind((("d", 1u64), ("g", 0.5f32))))?;
+
+                p.rule::<(u64, f32)>("next", &|h, b, (d, g)| {
+                    h.bind((("d", d + 1), ("g", g * 2.0)))?;
+                    b.search("reading", (("d", d), ("g", g)))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            [(
+                "next",
+                [Tuple::new(
+                    "next",
+                    [("d", Val::U64(2)), ("g", Val::from(1.0f32))],
+                    None
+                )]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_head_concat_and_function() {
+        assert_derives!(
+            |p| {
+                p.output("person", |h| {
+                    h.column::<&str>("first").column::<&str>("last")
+                })?;
+                p.output("full_name", |h| {
+                    h.column::<&str>("name").column::<u64>("len")
+                })?;
+
+                p.fact("person", |f| {
+                    f.bind((("first", "Ada"), ("last", "Lovelace")))
+                })?;
+                p.fact("person", |f| {
+                    f.bind((("first", "Alan"), ("last", "Turing")))
+                })?;
+
+                p.rule::<(&str, &str)>("full_name", &|h, b, (first, last)| {
+                    let len = kernel::func(|(first, last): (Arc<str>, Arc<str>)| {
+                        (first.len() + last.len()) as u64
+                    });
+
+                    h.bind((
+                        (
+                            "name",
+                            Expr::concat([first.into(), Expr::from(" "), last.into()]),
+                        ),
+                        ("len", Expr::call(len, [first, last])),
+                    ))?;
+                    b.search("person", (("first", first), ("last", last)))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            [(
+                "full_name",
+                [
+                    Tuple::new(
+                        "full_name",
+                        [("name", Val::from("Ada Lovelace")), ("len", Val::U64(11))],
+                        None
+                    ),
+                    Tuple::new(
+                        "full_name",
+                        [("name", Val::from("Alan Turing")), ("len", Val::U64(10))],
+                        None
+                    ),
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_head_expression_without_value() {
+        assert_derives!(
+            |p| {
+                p.output("pair", |h| h.column::<i32>("x").column::<i32>("y"))?;
+                p.output("ratio", |h| h.column::<i32>("r"))?;
+
+                p.fact("pair", |f| f.bind((("x", 6), ("y", 3))))?;
+                p.fact("pair", |f| f.bind((("x", 1), ("y", 0))))?;
+
+                p.rule::<(i32, i32)>("ratio", &|h, b, (x, y)| {
+                    h.bind((("r", x / y),))?;
+                    b.search("pair", (("x", x), ("y", y)))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            [("ratio", [Tuple::new("ratio", [("r", 2)], None)])]
+        );
+    }
+
+    #[test]
+    fn test_body_compute() {
+        assert_derives!(
+            |p| {
+                p.output("edge", |h| h.column::<i32>("from").column::<i32>("to"))?;
+                p.output("hops", |h| {
+                    h.column::<i32>("from")
+                        .column::<i32>("to")
+                        .column::<i32>("n")
+                })?;
+
+                p.fact("edge", |f| f.bind((("from", 0), ("to", 1))))?;
+                p.fact("edge", |f| f.bind((("from", 1), ("to", 2))))?;
+                p.fact("edge", |f| f.bind((("from", 2), ("to", 3))))?;
+
+                p.rule::<(i32, i32)>("hops", &|h, b, (x, y)| {
+                    h.bind((("from", x), ("to", y), ("n", 1)))?;
+                    b.search("edge", (("from", x), ("to", y)))?;
+
+                    Ok(())
+                })?;
+
+                p.rule::<(i32, i32, i32, i32, i32)>("hops", &|h, b, (x, y, z, n, m)| {
+                    h.bind((("from", x), ("to", z), ("n", m)))?;
+
+                    b.search("edge", (("from", x), ("to", y)))?;
+                    b.search("hops", (("from", y), ("to", z), ("n", n)))?;
+                    b.compute(m, kernel::func(|(n,): (i32,)| n + 1), (n,))?;
+                    b.predicate(kernel::when((m,), |(m,)| m <= 2))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            [(
+                "hops",
+                [
+                    Tuple::new("hops", [("from", 0), ("to", 1), ("n", 1)], None),
+                    Tuple::new("hops", [("from", 0), ("to", 2), ("n", 2)], None),
+                    Tuple::new("hops", [("from", 1), ("to", 2), ("n", 1)], None),
+                    Tuple::new("hops", [("from", 1), ("to", 3), ("n", 2)], None),
+                    Tuple::new("hops", [("from", 2), ("to", 3), ("n", 1)], None),
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_builtin_comparisons() {
+        assert_derives!(
+            |p| {
+                p.output("num", |h| h.column::<i32>("n"))?;
+                p.output("lt", |h| h.column::<i32>("a").column::<i32>("b"))?;
+                p.output("gt", |h| h.column::<i32>("a").column::<i32>("b"))?;
+
+                p.fact("num", |f| f.bind((("n", 1),)))?;
+                p.fact("num", |f| f.bind((("n", 2),)))?;
+                p.fact("num", |f| f.bind((("n", 3),)))?;
+
+                p.rule::<(i32, i32)>("lt", &|h, b, (x, y)| {
+                    h.bind((("a", x), ("b", y)))?;
+                    b.search("num", (("n", x),))?;
+                    b.search("num", (("n", y),))?;
+                    b.predicate(cmp::lt(x, y))?;
+
+                    Ok(())
+                })?;
+
+                p.rule::<(i32, i32)>("gt", &|h, b, (x, y)| {
+                    h.bind((("a", x), ("b", y)))?;
+                    b.search("num", (("n", x),))?;
+                    b.search("num", (("n", y),))?;
+                    b.predicate(cmp::ge(x, y))?;
+                    b.predicate(cmp::ne(x, y))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            [
+                (
+                    "lt",
+                    [
+                        Tuple::new("lt", [("a", 1), ("b", 2)], None),
+                        Tuple::new("lt", [("a", 1), ("b", 3)], None),
+                        Tuple::new("lt", [("a", 2), ("b", 3)], None),
+                    ]
+                ),
+                (
+                    "gt",
+                    [
+                        Tuple::new("gt", [("a", 2), ("b", 1)], None),
+                        Tuple::new("gt", [("a", 3), ("b", 1)], None),
+                        Tuple::new("gt", [("a", 3), ("b", 2)], None),
+                    ]
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn test_builtin_string_predicates() {
+        assert_derives!(
+            |p| {
+                p.output("person", |h| h.column::<&str>("name"))?;
+                p.output("pattern", |h| {
+                    h.column::<&str>("prefix")
+                        .column::<&str>("regex")
+                        .column::<u64>("len")
+                })?;
+                p.output("match", |h| h.column::<&str>("name"))?;
+
+                p.fact("person", |f| f.bind((("name", "Ada Lovelace"),)))?;
+                p.fact("person", |f| f.bind((("name", "Alan Turing"),)))?;
+                p.fact("person", |f| f.bind((("name", "Alonzo Church"),)))?;
+                p.fact("person", |f| f.bind((("name", "Grace Hopper"),)))?;
+                p.fact("pattern", |f| {
+                    f.bind((("prefix", "A"), ("regex", "[ae]$"), ("len", 12_u64)))
+                })?;
+
+                p.rule::<(Arc<str>, Arc<str>, Arc<str>, u64)>("match", &|h,
+                                                                         b,
+                                                                         (
+                    name,
+                    prefix,
+                    regex,
+                    len,
+                )| {
+                    h.bind((("name", name),))?;
+                    b.search("person", (("name", name),))?;
+                    b.search(
+                        "pattern",
+                        (("prefix", prefix), ("regex", regex), ("len", len)),
+                    )?;
+                    b.predicate(string::starts_with(name, prefix))?;
+                    b.predicate(string::matches(name, regex))?;
+                    b.predicate(string::len(name, len))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            [(
+                "match",
+                [Tuple::new("match", [("name", "Ada Lovelace")], None)]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_builtin_cid_predicates() -> Result<()> {
+        let e0 = InputTuple::new(0, "name", "a", []);
+        let e1 = InputTuple::new(1, "name", "b", []);
+
+        assert_derives!(
+            |p| {
+                p.output("hash", |h| h.column::<u64>("code"))?;
+                p.output("cbor", |h| h.column::<Cid>("cid"))?;
+
+                p.fact("hash", |f| f.bind((("code", 0x16_u64),)))?;
+
+                p.rule::<(Cid, u64)>("cbor", &|h, b, (cid, code)| {
+                    h.bind((("cid", cid),))?;
+                    b.search_cid("evac", cid, ())?;
+                    b.search("hash", (("code", code),))?;
+                    b.predicate(kernel::cid::is_dag_cbor(cid))?;
+                    b.predicate(kernel::cid::has_multihash(cid, code))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            [e0.clone(), e1.clone()],
+            [(
+                "cbor",
+                [
+                    Tuple::new("cbor", [("cid", e0.cid()?)], None),
+                    Tuple::new("cbor", [("cid", e1.cid()?)], None),
+                ]
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_disjunction() {
+        assert_derives!(
+            |p| {
+                p.output("road", |h| h.column::<i32>("from").column::<i32>("to"))?;
+                p.output("rail", |h| h.column::<i32>("from").column::<i32>("to"))?;
+                p.output("ferry", |h| h.column::<i32>("from").column::<i32>("to"))?;
+                p.output("reachable", |h| h.column::<i32>("from").column::<i32>("to"))?;
+
+                p.fact("road", |f| f.bind((("from", 0), ("to", 1))))?;
+                p.fact("rail", |f| f.bind((("from", 1), ("to", 2))))?;
+                p.fact("ferry", |f| f.bind((("from", 2), ("to", 3))))?;
+
+                p.rule::<(i32, i32)>("reachable", &|h, b, (x, y)| {
+                    h.bind((("from", x), ("to", y)))?;
+                    b.any(|alt| {
+                        alt.or(|b| b.search("road", (("from", x), ("to", y))))?;
+                        alt.or(|b| {
+                            b.any(|alt| {
+                                alt.or(|b| b.search("rail", (("from", x), ("to", y))))?;
+                                alt.or(|b| b.search("ferry", (("from", x), ("to", y))))?;
+
+                                Ok(())
+                            })
+                        })?;
+
+                        Ok(())
+                    })?;
+
+                    Ok(())
+                })?;
+
+                p.rule::<(i32, i32, i32)>("reachable", &|h, b, (x, y, z)| {
+                    h.bind((("from", x), ("to", z)))?;
+                    b.search("reachable", (("from", x), ("to", y)))?;
+                    b.any(|alt| {
+                        alt.or(|b| b.search("road", (("from", y), ("to", z))))?;
+                        alt.or(|b| b.search("rail", (("from", y), ("to", z))))?;
+
+                        Ok(())
+                    })?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            [(
+                "reachable",
+                [
+                    Tuple::new("reachable", [("from", 0), ("to", 1)], None),
+                    Tuple::new("reachable", [("from", 0), ("to", 2)], None),
+                    Tuple::new("reachable", [("from", 1), ("to", 2)], None),
+                    Tuple::new("reachable", [("from", 2), ("to", 3)], None),
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_wildcard() {
+        assert_derives!(
+            |p| {
+                p.output("node", |h| h.column::<i32>("id"))?;
+                p.output("edge", |h| {
+                    h.column::<i32>("from")
+                        .column::<i32>("to")
+                        .column::<&str>("label")
+                })?;
+                p.output("leaf", |h| h.column::<i32>("id"))?;
+                p.output("source", |h| h.column::<i32>("id"))?;
+
+                p.fact("node", |f| f.bind((("id", 0),)))?;
+                p.fact("node", |f| f.bind((("id", 1),)))?;
+                p.fact("node", |f| f.bind((("id", 2),)))?;
+                p.fact("edge", |f| f.bind((("from", 0), ("to", 1), ("label", "a"))))?;
+                p.fact("edge", |f| f.bind((("from", 0), ("to", 2), ("label", "b"))))?;
+
+                p.rule::<(i32,)>("leaf", &|h, b, (x,)| {
+                    h.bind((("id", x),))?;
+                    b.search("node", (("id", x),))?;
+                    b.except("edge", (("from", x), ("to", Wildcard), ("label", Wildcard)))?;
+
+                    Ok(())
+                })?;
+
+                p.rule::<(i32,)>("source", &|h, b, (x,)| {
+                    h.bind((("id", x),))?;
+                    b.search("edge", (("from", x), ("to", Wildcard), ("label", Wildcard)))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            [
+                (
+                    "leaf",
+                    vec![
+                        Tuple::new("leaf", [("id", 1)], None),
+                        Tuple::new("leaf", [("id", 2)], None),
+                    ]
+                ),
+                ("source", vec![Tuple::new("source", [("id", 0)], None)])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_body_user_defined_function() {
+        assert_derives!(
+            |p| {
+                p.output("num", |h| h.column::<i32>("n"))?;
+                p.output("quadrupled", |h| h.column::<i32>("n"))?;
+
+                p.fact("num", |f| f.bind((("n", 1),)))?;
+                p.fact("num", |f| f.bind((("n", 5),)))?;
+
+                p.rule::<(i32, i32, i32)>("quadrupled", &|h, b, (x, y, z)| {
+                    h.bind((("n", z),))?;
+
+                    b.search("num", (("n", x),))?;
+                    b.function(z, double(y))?;
+                    b.function(y, double(x))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            [(
+                "quadrupled",
+                [
+                    Tuple::new("quadrupled", [("n", 4)], None),
+                    Tuple::new("quadrupled", [("n", 20)], None),
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_body_compute_search_key() {
+        assert_derives!(
+            |p| {
+                p.output("num", |h| h.column::<i32>("n"))?;
+                p.output("successor", |h| h.column::<i32>("n").column::<i32>("m"))?;
+
+                p.fact("num", |f| f.bind((("n", 1),)))?;
+                p.fact("num", |f| f.bind((("n", 2),)))?;
+                p.fact("num", |f| f.bind((("n", 3),)))?;
+                p.fact("num", |f| f.bind((("n", 5),)))?;
+
+                p.rule::<(i32, i32)>("successor", &|h, b, (x, y)| {
+                    h.bind((("n", x), ("m", y)))?;
+
+                    b.search("num", (("n", y),))?;
+                    b.search("num", (("n", x),))?;
+                    b.compute(y, kernel::func(|(x,): (i32,)| x + 1), (x,))?;
+
+                    Ok(())
+                })?;
+
+                Ok(p)
+            },
+            [(
+                "successor",
+                [
+                    Tuple::new("successor", [("n", 1), ("m", 2)], None),
+                    Tuple::new("successor", [("n", 2), ("m", 3)], None),
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_parse_transitive_closure() {
+        assert_derives!(
+            |p| {
+                p.parse(
+                    r#"
+                    .output edge(from: s32, to: s32)
+                    .output path(from: s32, to: s32)
+
+                    edge(from: 0, to: 1).
+                    edge(from: 1, to: 2).
+                    edge(from: 2, to: 3).
+
+                    path(from: x, to: y) :- edge(from: x, to: y).
+                    path(from: x, to: z) :- edge(from: x, to: y), path(from: y, to: z).
+                    "#,
+                )?;
+
+                Ok(p)
+            },
+            [(
+                "path",
+                [
+                    Tuple::new("path", [("from", 0), ("to", 1)], None),
+                    Tuple::new("path", [("from", 0), ("to", 2)], None),
+                    Tuple::new("path", [("from", 0), ("to", 3)], None),
+                    Tuple::new("path", [("from", 1), ("to", 2)], None),
+                    Tuple::new("path", [("from", 1), ("to", 3)], None),
+                    Tuple::new("path", [("from", 2), ("to", 3)], None),
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_parse_float_columns() {
+        assert_derives!(
+            |p| {
+                p.parse(
+                    r#"
+                    .output point(x: f64, y: f32)
+
+                    point(x: 1, y: -2).
+                    point(x: 0.5, y: 3).
+                    "#,
+                )?;
+
+                Ok(p)
+            },
+            [(
+                "point",
+                [
+                    Tuple::new(
+                        "point",
+                        [("x", Val::from(0.5_f64)), ("y", Val::from(3.0_f32))],
+                        None
+                    ),
+                    Tuple::new(
+                        "point",
+                        [("x", Val::from(1.0_f64)), ("y", Val::from(-2.0_f32))],
+                        None
+                    ),
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_parse_search_cid_and_negation() -> Result<()> {
+        let e0 = InputTuple::new(0, "name", "a", []);
+        let e1 = InputTuple::new(1, "name", "b", [e0.cid()?]);
+
+        assert_derives!(
+            |p| {
+                p.parse(
+                    r#"
+                    /* Facts that nothing links to */
+                    .output root(cid: cid, name: string)
+
+                    root(cid: c, name: n) :-
+                        evac@c(attribute: "name", value: n),
+                        !links(to: c).
+                    "#,
+                )?;
+
+                Ok(p)
+            },
+            [e0.clone(), e1.clone()],
+            [(
+                "root",
+                [Tuple::new(
+                    "root",
+                    [("cid", Val::Cid(e1.cid()?)), ("name", Val::from("b"))],
+                    None
+                )]
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_wildcard() {
+        assert_derives!(
+            |p| {
+                p.parse(
+                    r#"
+                    .output edge(from: s32, to: s32)
+                    .output leaf(id: s32)
+
+                    edge(from: 0, to: 1).
+                    edge(from: 1, to: 2).
+
+                    leaf(id: y) :- edge(from: _, to: y), !edge(from: y, to: _).
+                    "#,
+                )?;
+
+                Ok(p)
+            },
+            [("leaf", [Tuple::new("leaf", [("id", 2)], None)])]
+        );
+    }
+
+    #[test]
+    fn test_parse_builtin_predicates() {
+        assert_derives!(
+            |p| {
+                p.parse(
+                    r#"
+                    .output edge(from: s32, to: s32)
+                    .output label(node: s32, name: string)
+                    .output forward(from: s32, to: s32)
+                    .output named(node: s32)
+
+                    edge(from: 0, to: 1).
+                    edge(from: 2, to: 1).
+                    label(node: 0, name: "start").
+                    label(node: 1, name: "end").
+
+                    forward(from: x, to: y) :- edge(from: x, to: y), lt(x, y).
+                    named(node: n) :- label(node: n, name: s), starts_with(s, "st").
+                    "#,
+                )?;
+
+                Ok(p)
+            },
+            [
+                (
+                    "forward",
+                    vec![Tuple::new("forward", [("from", 0), ("to", 1)], None)]
+                ),
+                ("named", vec![Tuple::new("named", [("node", 0)], None)])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_predicate() {
+        let mut registry = Registry::default();
+        registry.register_predicate("is_triangle", IsTriangle::<(i32, i32, i32)>::default());
+
+        assert_derives!(
+            |p| {
+                p.parse_with(
+                    r#"
+                    .output num(n: s32)
+                    .output triangle(a: s32, b: s32, c: s32)
+
+                    num(n: 1). num(n: 2). num(n: 3).
+
+                    triangle(a: x, b: y, c: z) :-
+                        num(n: x), num(n: y), num(n: z), is_triangle(x, y, z).
+                    "#,
+                    &registry,
+                )?;
+
+                Ok(p)
+            },
+            [(
+                "triangle",
+                [Tuple::new("triangle", [("a", 1), ("b", 1), ("c", 3)], None)]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_parse_group_by() {
+        assert_derives!(
+            |p| {
+                p.parse(
+                    r#"
+                    .output pair(x: s32, y: s32)
+                    .output total(x: s32, n: s64, sum: s32)
+
+                    pair(x: 1, y: 2). pair(x: 1, y: 3). pair(x: 2, y: 3).
+
+                    total(x: x, n: n, sum: s) :-
+                        pair(x: x),
+                        n = count() : pair(x: x),
+                        s = sum(y) : pair(x: x, y: y).
+                    "#,
+                )?;
+
+                Ok(p)
+            },
+            [(
+                "total",
+                [
+                    Tuple::new(
+                        "total",
+                        [("x", Val::S32(1)), ("n", Val::S64(2)), ("sum", Val::S32(5))],
+                        None
+                    ),
+                    Tuple::new(
+                        "total",
+                        [("x", Val::S32(2)), ("n", Val::S64(1)), ("sum", Val::S32(3))],
+                        None
+                    ),
+                ]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_parse_group_by_library() {
+        assert_derives!(
+            |p| {
+                p.parse(
+                    r#"
+                    .output score(team: s32, name: string, points: s32)
+                    .output summary(team: s32, best: string, median: s32, names: list)
+
+                    score(team: 1, name: "a", points: 3).
+                    score(team: 1, name: "b", points: 8).
+                    score(team: 1, name: "c", points: 5).
+
+                    summary(team: t, best: b, median: m, names: ns) :-
+                        score(team: t),
+                        b = arg_max(p, n) : score(team: t, name: n, points: p),
+                        m = median(p) : score(team: t, points: p),
+                        ns = collect(n) : score(team: t, name: n).
+                    "#,
+                )?;
+
+                Ok(p)
+            },
+            [(
+                "summary",
+                [Tuple::new(
+                    "summary",
+                    [
+                        ("team", Val::S32(1)),
+                        ("best", Val::from("b")),
+                        ("median", Val::S32(5)),
+                        ("names", Val::from(vec!["a", "b", "c"])),
+                    ],
+                    None
+                )]
+            )]
+        );
+    }
+
+    #[derive(Debug)]
+    #[allow(unreachable_pub)]
+    pub struct Product<T: RhizomeType + AddAssign + WrappingMul + Zero>(T);
+
+    impl<T: RhizomeType + AddAssign + WrappingMul + Zero> Default for Product<T> {
+        fn default() -> Self {
+            Self(Zero::zero())
+        }
+    }
+
+    impl<T: RhizomeType + AddAssign + WrappingMul + Zero> Aggregate for Product<T> {
+        type Input = (T, T);
+        type Output = T;
+
+        fn step(&mut self, (a, b): (T, T)) {
+            self.0 += a * b;
+        }
+
+        fn finalize(&self) -> Option<Self::Output> {
+            Some(self.0.clone())
+        }
+    }
+
+    rhizome_fn! {
+        #[aggregate = Product]
+        fn product<T: RhizomeType + AddAssign + WrappingMul + Zero>(a: T, b: T) -> T;
+    }
+
+    #[derive(Debug)]
+    #[allow(unreachable_pub)]
+    pub struct IsTriangle<T>(PhantomData<T>);
+
+    impl<T> Default for IsTriangle<T> {
+        fn default() -> Self {
+            Self(Default::default())
+        }
+    }
+
+    impl<T: RhizomeType + Add<Output = T> + Ord> Predicate for IsTriangle<(T, T, T)> {
+        type Input = (T, T, T);
+
+        fn apply(&self, (a, b, c): Self::Input) -> Option<bool> {
+            Some(a + b < c)
+        }
+    }
+
+    rhizome_fn! {
+        #[predicate = IsTriangle]
+        fn is_triangle<T: RhizomeType + Add<Output = T> + Ord>(a: T, b: T, z: T) -> T;
+    }
+
+    #[derive(Debug)]
+    #[allow(unreachable_pub)]
+    pub struct Double<T>(PhantomData<T>);
+
+    impl<T> Default for Double<T> {
+        fn default() -> Self {
+            Self(Default::default())
+        }
+    }
+
+    impl<T: RhizomeType + Add<Output = T>> Function for Double<(T,)> {
+        type Input = (T,);
+        type Output = T;
+
+        fn apply(&self, (a,): Self::Input) -> Option<T> {
+            Some(a.clone() + a)
+        }
+    }
+
+    rhizome_fn! {
+        #[function = Double]
+        fn double<T: RhizomeType + Add<Output = T>>(a: T) -> T;
     }
 }
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use rhizome_runtime::Runtime;
+use rhizome_runtime::{MaybeSend, Runtime};
 use std::{collections::HashMap, fmt::Debug};
 
 use futures::{
@@ -7,20 +7,43 @@ use futures::{
         mpsc::{self, Receiver, Sender},
         oneshot,
     },
-    select, SinkExt, StreamExt,
+    select, stream, SinkExt, StreamExt,
 };
 
 use crate::{
     build,
-    error::Error,
-    id::RelationId,
+    error::{error, Error},
+    id::{ColId, RelationId},
     logic::ProgramBuilder,
-    storage::{blockstore::Blockstore, memory::MemoryBlockstore, DefaultCodec, DEFAULT_MULTIHASH},
+    storage::{
+        block::Block, blockstore::Blockstore, codec::Codec, memory::MemoryBlockstore, DefaultCodec,
+        DEFAULT_MULTIHASH,
+    },
     timestamp::{DefaultTimestamp, Timestamp},
-    tuple::Tuple,
+    tuple::{InputTuple, Tuple},
+    value::Val,
+};
+
+use super::{
+    vm::VM, ClientCommand, ClientEvent, CreateDiffSink, CreateSink, CreateTupleStream, DiffSink,
+    Query, SinkCommand, SinkEvent, StreamErrors, StreamEvent, SubscriptionId,
 };
 
-use super::{vm::VM, ClientCommand, ClientEvent, SinkCommand, StreamEvent};
+/// A sink registered on a relation, which is only sent the diffs of the tuples
+/// that match its filter.
+struct Subscriber<T> {
+    id: SubscriptionId,
+    filter: Vec<(ColId, Val)>,
+    tx: mpsc::Sender<SinkCommand<T>>,
+}
+
+impl<T> Subscriber<T> {
+    fn matches(&self, fact: &Tuple) -> bool {
+        self.filter
+            .iter()
+            .all(|(col, val)| fact.col(col).as_ref() == Some(val))
+    }
+}
 
 pub struct Reactor<T = DefaultTimestamp, BS = MemoryBlockstore>
 where
@@ -28,8 +51,9 @@ where
 {
     runtime: Runtime,
     blockstore: BS,
-    sinks: HashMap<RelationId, Vec<mpsc::Sender<SinkCommand>>>,
-    command_rx: mpsc::Receiver<ClientCommand>,
+    sinks: HashMap<RelationId, Vec<Subscriber<T>>>,
+    next_subscription: usize,
+    command_rx: mpsc::Receiver<ClientCommand<T>>,
     event_tx: mpsc::Sender<ClientEvent<T>>,
     stream_rx: mpsc::Receiver<StreamEvent>,
     stream_tx: mpsc::Sender<StreamEvent>,
@@ -37,10 +61,10 @@ where
 
 impl<T, BS> Reactor<T, BS>
 where
-    T: Timestamp,
+    T: Timestamp + MaybeSend + 'static,
     BS: Blockstore,
 {
-    pub fn new(command_rx: Receiver<ClientCommand>, event_tx: Sender<ClientEvent<T>>) -> Self
+    pub fn new(command_rx: Receiver<ClientCommand<T>>, event_tx: Sender<ClientEvent<T>>) -> Self
 where {
         let (stream_tx, stream_rx) = mpsc::channel(10);
 
@@ -48,6 +72,7 @@ where {
             runtime: Default::default(),
             blockstore: Default::default(),
             sinks: Default::default(),
+            next_subscription: Default::default(),
             command_rx,
             event_tx,
             stream_tx,
@@ -88,10 +113,23 @@ where {
             // TODO: use a buffered blockstore; see https://github.com/RhizomeDB/rs-rhizome/issues/24
             vm.step_epoch(&self.blockstore)?;
 
-            while let Ok(Some(fact)) = vm.pop() {
-                if let Some(sinks) = self.sinks.get_mut(&fact.id()) {
-                    for sink in sinks {
-                        sink.send(SinkCommand::ProcessFact(fact.clone())).await?;
+            while let Ok(Some(event)) = vm.pop() {
+                match &event {
+                    SinkEvent::Diff(fact, _, _) => {
+                        if let Some(sinks) = self.sinks.get_mut(&fact.id()) {
+                            for sink in sinks.iter_mut().filter(|sink| sink.matches(fact)) {
+                                sink.tx
+                                    .send(SinkCommand::ProcessEvent(event.clone()))
+                                    .await?;
+                            }
+                        }
+                    }
+                    SinkEvent::EpochComplete(_) => {
+                        for sink in self.sinks.values_mut().flatten() {
+                            sink.tx
+                                .send(SinkCommand::ProcessEvent(event.clone()))
+                                .await?;
+                        }
                     }
                 }
             }
@@ -102,7 +140,7 @@ where {
         }
     }
 
-    async fn handle_command(&mut self, vm: &mut VM<T>, command: ClientCommand) -> Result<()> {
+    async fn handle_command(&mut self, vm: &mut VM<T>, command: ClientCommand<T>) -> Result<()> {
         match command {
             ClientCommand::Flush(sender) => {
                 let mut handles = Vec::default();
@@ -110,7 +148,7 @@ where {
                 for sinks in self.sinks.values_mut() {
                     for sink in sinks.iter_mut() {
                         let (tx, rx) = oneshot::channel();
-                        sink.send(SinkCommand::Flush(tx)).await?;
+                        sink.tx.send(SinkCommand::Flush(tx)).await?;
 
                         handles.push(rx);
                     }
@@ -125,37 +163,47 @@ where {
                     .map_err(|_| Error::InternalRhizomeError("client channel closed".to_owned()))?;
             }
             ClientCommand::InsertFact(input_fact, sender) => {
-                self.blockstore.put_serializable(
-                    &input_fact,
-                    #[allow(unknown_lints, clippy::default_constructed_unit_structs)]
-                    DefaultCodec::default(),
-                    DEFAULT_MULTIHASH,
-                )?;
-
-                let cid = input_fact.cid()?;
-                let fact = Tuple::new(
-                    "evac",
-                    [
-                        ("entity", input_fact.entity()),
-                        ("attribute", input_fact.attr()),
-                        ("value", input_fact.val()),
-                    ],
-                    Some(cid),
-                );
-
-                vm.push(fact)?;
+                self.insert_facts(vm, &[*input_fact])?;
 
-                for link in input_fact.links() {
-                    let fact = Tuple::new("links", [("from", cid), ("to", *link)], None);
+                sender
+                    .send(())
+                    .map_err(|_| Error::InternalRhizomeError("client channel closed".to_owned()))?;
+            }
+            ClientCommand::Transact(input_facts, sender) => {
+                // Every fact is inserted before the epoch is next stepped, so the
+                // batch is evaluated in the epoch starting now. An empty batch has
+                // no epoch to wait for, since nothing triggers one.
+                let timestamp = if input_facts.is_empty() {
+                    error(Error::EmptyTransaction)
+                } else {
+                    self.insert_facts(vm, &input_facts).map(|_| *vm.timestamp())
+                };
 
-                    vm.push(fact)?;
+                sender
+                    .send(timestamp)
+                    .map_err(|_| Error::InternalRhizomeError("client channel closed".to_owned()))?;
+            }
+            ClientCommand::Insert(id, fact, sender) => {
+                sender
+                    .send(vm.insert(id, *fact))
+                    .map_err(|_| Error::InternalRhizomeError("client channel closed".to_owned()))?;
+            }
+            ClientCommand::RetractFact(cid, sender) => {
+                // Facts that were never inserted have nothing to retract
+                if let Some(input_fact) = self
+                    .blockstore
+                    .get_serializable::<DefaultCodec, InputTuple>(&cid)?
+                {
+                    for fact in input_fact.tuples()? {
+                        vm.retract(fact)?;
+                    }
                 }
 
                 sender
                     .send(())
                     .map_err(|_| Error::InternalRhizomeError("client channel closed".to_owned()))?;
             }
-            ClientCommand::RegisterStream(_, create_stream, sender) => {
+            ClientCommand::RegisterStream(create_stream, sender) => {
                 let mut tx = self.stream_tx.clone();
                 let create_task = move || async move {
                     let mut stream = Box::into_pin(create_stream());
@@ -173,26 +221,66 @@ where {
                     .send(())
                     .map_err(|_| Error::InternalRhizomeError("client channel closed".to_owned()))?;
             }
+            ClientCommand::RegisterTupleStream(id, create_stream, sender) => {
+                let errors = vm
+                    .input_declaration(id)
+                    .map(|_| self.spawn_tuple_stream(id, create_stream));
+
+                sender
+                    .send(errors)
+                    .map_err(|_| Error::InternalRhizomeError("client channel closed".to_owned()))?;
+            }
             ClientCommand::RegisterSink(id, create_sink, sender) => {
-                let (tx, mut rx) = mpsc::channel(100);
-                let create_task = move || async move {
-                    let mut sink = Box::into_pin(create_sink());
+                let sink = self.spawn_sink(
+                    Vec::default(),
+                    Box::new(move || adapt_fact_sink(create_sink)),
+                );
 
-                    loop {
-                        match rx.next().await {
-                            Some(SinkCommand::Flush(sender)) => {
-                                sender.send(()).expect("reactor channel closed")
-                            }
-                            Some(SinkCommand::ProcessFact(fact)) => {
-                                sink.send(fact).await.expect("reactor channel closed")
-                            }
-                            None => break,
-                        };
-                    }
+                self.sinks.entry(id).or_default().push(sink);
+
+                sender
+                    .send(())
+                    .map_err(|_| Error::InternalRhizomeError("client channel closed".to_owned()))?;
+            }
+            ClientCommand::Explain(id, tuple, sender) => {
+                // Tuples of other relations can't be in this one
+                let derivation = if tuple.id() == id {
+                    vm.explain(&tuple)
+                } else {
+                    Ok(None)
                 };
 
-                self.runtime.spawn_pinned(create_task);
-                self.sinks.entry(id).or_default().push(tx);
+                sender
+                    .send(derivation)
+                    .map_err(|_| Error::InternalRhizomeError("client channel closed".to_owned()))?;
+            }
+            ClientCommand::Query(query, sender) => {
+                sender
+                    .send(vm.query(&query, &self.blockstore))
+                    .map_err(|_| Error::InternalRhizomeError("client channel closed".to_owned()))?;
+            }
+            ClientCommand::RegisterDiffSink(id, create_sink, sender) => {
+                let sink = self.spawn_sink(Vec::default(), create_sink);
+
+                self.sinks.entry(id).or_default().push(sink);
+
+                sender
+                    .send(())
+                    .map_err(|_| Error::InternalRhizomeError("client channel closed".to_owned()))?;
+            }
+            ClientCommand::Subscribe(id, filter, create_sink, sender) => {
+                let subscription = self.subscribe(vm, id, filter, create_sink).await;
+
+                sender
+                    .send(subscription)
+                    .map_err(|_| Error::InternalRhizomeError("client channel closed".to_owned()))?;
+            }
+            ClientCommand::Unsubscribe(subscription, sender) => {
+                // Dropping the sender stops the sink's task, once it's processed the
+                // events already sent to it
+                for sinks in self.sinks.values_mut() {
+                    sinks.retain(|sink| sink.id != subscription);
+                }
 
                 sender
                     .send(())
@@ -203,33 +291,132 @@ where {
         Ok(())
     }
 
-    async fn handle_event(&mut self, vm: &mut VM<T>, event: StreamEvent) -> Result<()> {
-        match event {
-            StreamEvent::Fact(input_fact) => {
-                self.blockstore.put_serializable(
-                    &input_fact,
-                    #[allow(unknown_lints, clippy::default_constructed_unit_structs)]
-                    DefaultCodec::default(),
-                    DEFAULT_MULTIHASH,
-                )?;
-
-                let cid = input_fact.cid()?;
-                let fact = Tuple::new(
-                    "evac",
-                    [
-                        ("entity", input_fact.entity()),
-                        ("attribute", input_fact.attr()),
-                        ("value", input_fact.val()),
-                    ],
-                    Some(cid),
-                );
+    /// Subscribes the sink to the tuples of a relation that match the filter, sending
+    /// it the relation's current tuples as the diffs of the last epoch to complete,
+    /// which they're up to date with.
+    async fn subscribe(
+        &mut self,
+        vm: &mut VM<T>,
+        id: RelationId,
+        filter: Vec<(ColId, Val)>,
+        create_sink: Box<dyn CreateDiffSink<T>>,
+    ) -> Result<SubscriptionId> {
+        // The filter is checked while taking the snapshot, so nothing is spawned if
+        // it's invalid
+        let snapshot = vm.query(&Query::Relation(id, filter.clone()), &self.blockstore)?;
+        let mut sink = self.spawn_sink(filter, create_sink);
+
+        if let Some(timestamp) = vm.completed_epoch() {
+            for fact in snapshot {
+                let event = SinkEvent::Diff(fact, timestamp, 1);
+
+                sink.tx.send(SinkCommand::ProcessEvent(event)).await?;
+            }
+
+            let event = SinkEvent::EpochComplete(timestamp);
+
+            sink.tx.send(SinkCommand::ProcessEvent(event)).await?;
+        }
+
+        let subscription = sink.id;
+        self.sinks.entry(id).or_default().push(sink);
+
+        Ok(subscription)
+    }
+
+    /// Spawns a task that runs the sink, which is sent the events matching the filter
+    /// once it's registered on a relation.
+    fn spawn_sink(
+        &mut self,
+        filter: Vec<(ColId, Val)>,
+        create_sink: Box<dyn CreateDiffSink<T>>,
+    ) -> Subscriber<T> {
+        let (tx, mut rx) = mpsc::channel(100);
+        let create_task = move || async move {
+            let mut sink = Box::into_pin(create_sink());
+
+            loop {
+                match rx.next().await {
+                    Some(SinkCommand::Flush(sender)) => {
+                        sender.send(()).expect("reactor channel closed")
+                    }
+                    Some(SinkCommand::ProcessEvent(event)) => {
+                        sink.send(event).await.expect("reactor channel closed")
+                    }
+                    None => break,
+                };
+            }
+        };
+
+        self.runtime.spawn_pinned(create_task);
 
-                vm.push(fact)?;
+        let id = SubscriptionId(self.next_subscription);
+        self.next_subscription += 1;
+
+        Subscriber { id, filter, tx }
+    }
+
+    /// Spawns a task that forwards the tuples of a stream to be inserted into an
+    /// input relation, returning the receiver of the errors of those rejected.
+    fn spawn_tuple_stream(
+        &mut self,
+        id: RelationId,
+        create_stream: Box<dyn CreateTupleStream>,
+    ) -> StreamErrors {
+        let (errors_tx, errors_rx) = mpsc::unbounded();
+        let mut tx = self.stream_tx.clone();
+        let create_task = move || async move {
+            let mut stream = Box::into_pin(create_stream());
+
+            while let Some(fact) = stream.next().await {
+                tx.send(StreamEvent::Tuple(id, fact, errors_tx.clone()))
+                    .await
+                    .expect("stream channel closed");
+            }
+        };
 
-                for link in input_fact.links() {
-                    let fact = Tuple::new("links", [("from", cid), ("to", *link)], None);
+        self.runtime.spawn_pinned(create_task);
 
-                    vm.push(fact)?;
+        errors_rx
+    }
+
+    /// Stores the facts and queues their tuples to be inserted, unless any fact is
+    /// invalid, in which case none are. Every fact is encoded before any is stored,
+    /// so that an aborted batch leaves nothing behind to be retracted.
+    fn insert_facts(&mut self, vm: &mut VM<T>, input_facts: &[InputTuple]) -> Result<()> {
+        let mut blocks = Vec::default();
+        let mut tuples = Vec::default();
+
+        for input_fact in input_facts {
+            let block = Block::new(
+                #[allow(unknown_lints, clippy::default_constructed_unit_structs)]
+                DefaultCodec::default(),
+                DefaultCodec::to_vec(input_fact)?,
+            );
+
+            blocks.push((DEFAULT_MULTIHASH, block));
+            tuples.extend(input_fact.tuples()?);
+        }
+
+        self.blockstore.put_many(blocks)?;
+
+        for fact in tuples {
+            vm.push(fact)?;
+        }
+
+        Ok(())
+    }
+
+    async fn handle_event(&mut self, vm: &mut VM<T>, event: StreamEvent) -> Result<()> {
+        match event {
+            StreamEvent::Fact(input_fact) => {
+                self.insert_facts(vm, &[input_fact])?;
+            }
+            StreamEvent::Tuple(id, fact, errors) => {
+                // Nothing is waiting on the tuple, so a rejected tuple is skipped, and
+                // its error is reported unless the registrant has stopped listening
+                if let Err(err) = vm.insert(id, fact) {
+                    let _ = errors.unbounded_send(err);
                 }
             }
         };
@@ -238,6 +425,20 @@ where {
     }
 }
 
+/// Adapts a sink of tuples to receive the tuples inserted into its relation,
+/// ignoring retractions and the end of each epoch.
+fn adapt_fact_sink<T>(create_sink: Box<dyn CreateSink>) -> DiffSink<T>
+where
+    T: 'static,
+{
+    Box::new(Box::into_pin(create_sink()).with_flat_map(|event| {
+        stream::iter(match event {
+            SinkEvent::Diff(fact, _, diff) if diff > 0 => Some(Ok(fact)),
+            _ => None,
+        })
+    }))
+}
+
 impl<T, BS> Debug for Reactor<T, BS>
 where
     T: Timestamp,
@@ -1,29 +1,58 @@
 use core::fmt::Debug;
-use std::{collections::VecDeque, sync::Arc};
+use std::{
+    cell::RefCell,
+    collections::{BTreeSet, HashMap, VecDeque},
+    sync::Arc,
+};
 
 use anyhow::Result;
 
 use crate::{
+    col_val::ColVal,
     error::{error, Error},
+    id::{ColId, RelationId},
+    logic::{build_query, lower_to_ram::lower_query_to_ram, Declaration},
+    provenance::{Derivation, Provenance},
     ram::{
         operation::{project::Project, search::Search, Operation},
         program::Program,
         statement::{
-            exit::Exit, insert::Insert, merge::Merge, purge::Purge, recursive::Loop, sinks::Sinks,
-            sources::Sources, swap::Swap, Statement,
+            difference::Difference,
+            exit::Exit,
+            insert::Insert,
+            merge::Merge,
+            purge::Purge,
+            remove::Remove,
+            sinks::Sinks,
+            sources::{Change, Sources},
+            swap::Swap,
+            Statement,
         },
-        Aggregation, Bindings,
+        Aggregation, Bindings, Compute,
     },
+    relation::{Source, Version},
+    runtime::{Query, SinkEvent},
     storage::blockstore::Blockstore,
     timestamp::{DefaultTimestamp, Timestamp},
     tuple::Tuple,
+    value::Val,
 };
 
+/// Receives the arguments yielded by the source of an aggregation.
+type OnYield<'a> = dyn Fn(Vec<Val>) -> Result<()> + 'a;
+
 pub(crate) struct VM<T = DefaultTimestamp> {
     timestamp: T,
-    pc: (usize, Option<usize>),
-    input: VecDeque<Tuple>,
-    output: VecDeque<Tuple>,
+    // The start of the last epoch to complete, if any has
+    completed: Option<T>,
+    // The index of the current statement within each enclosing loop or branch, outermost first
+    pc: Vec<usize>,
+    input: VecDeque<Change>,
+    output: VecDeque<SinkEvent<T>>,
+    // Only kept if the program records provenance
+    provenance: Option<RefCell<Provenance>>,
+    // The program's declarations by id, to check projected tuples against
+    declarations: HashMap<RelationId, Arc<Declaration>>,
     program: Program,
 }
 
@@ -46,9 +75,16 @@ where
     pub(crate) fn new(program: Program) -> Self {
         Self {
             timestamp: T::default(),
-            pc: (0, None),
+            completed: None,
+            pc: vec![0],
             input: VecDeque::default(),
             output: VecDeque::default(),
+            provenance: program.provenance().then(RefCell::default),
+            declarations: program
+                .declarations()
+                .values()
+                .map(|declaration| (declaration.id(), Arc::clone(declaration)))
+                .collect(),
             program,
         }
     }
@@ -57,16 +93,172 @@ where
         &self.timestamp
     }
 
+    /// The start of the last epoch to complete, which the relations are up to date
+    /// with, or None if none have.
+    pub(crate) fn completed_epoch(&self) -> Option<T> {
+        self.completed
+    }
+
     pub(crate) fn push(&mut self, fact: Tuple) -> Result<()> {
-        self.input.push_back(fact);
+        self.input.push_back(Change::Insert(fact));
+
+        Ok(())
+    }
+
+    /// Queues a tuple to be inserted into an input relation, once it's checked
+    /// against the relation's declaration.
+    pub(crate) fn insert(&mut self, id: RelationId, fact: Tuple) -> Result<()> {
+        if fact.id() != id {
+            return error(Error::TupleRelationMismatch(id, fact.id()));
+        }
+
+        let declaration = self.input_declaration(id)?;
+
+        let bindings: Vec<_> = fact
+            .cols()
+            .into_iter()
+            .filter_map(|col_id| fact.col(&col_id).map(|val| (col_id, val)))
+            .collect();
+
+        self.check_bindings(id, &bindings)?;
+
+        for col_id in declaration.schema().cols().keys() {
+            if fact.col(col_id).is_none() {
+                return error(Error::ColumnMissing(id, *col_id));
+            }
+        }
+
+        self.push(fact)
+    }
+
+    pub(crate) fn retract(&mut self, fact: Tuple) -> Result<()> {
+        self.input.push_back(Change::Retract(fact));
+
+        Ok(())
+    }
+
+    pub(crate) fn pop(&mut self) -> Result<Option<SinkEvent<T>>> {
+        let event = self.output.pop_front();
+
+        Ok(event)
+    }
+
+    /// Returns the tuples of a relation that match the given bindings, as of the
+    /// end of the last epoch.
+    pub(crate) fn search(&self, id: RelationId, bindings: Vec<(ColId, Val)>) -> Result<Vec<Tuple>> {
+        let relation = self
+            .program
+            .relation((id, Version::Total))
+            .ok_or_else(|| Error::UnrecognizedRelation(id.to_string()))?;
+
+        let tuples = relation
+            .read()
+            .or_else(|_| {
+                error(Error::InternalRhizomeError(
+                    "relation lock poisoned".to_owned(),
+                ))
+            })?
+            .search(bindings)
+            .cloned()
+            .collect();
+
+        Ok(tuples)
+    }
+
+    /// Answers a query against the total of every relation, as of the end of the
+    /// last epoch.
+    pub(crate) fn query<BS>(&self, query: &Query, blockstore: &BS) -> Result<Vec<Tuple>>
+    where
+        BS: Blockstore,
+    {
+        let f = match query {
+            Query::Relation(id, bindings) => {
+                self.check_bindings(*id, bindings)?;
+
+                return self.search(*id, bindings.clone());
+            }
+            Query::Body(f) => f,
+        };
+
+        let solutions = RefCell::new(BTreeSet::default());
+
+        for query in build_query(self.program.declarations().clone(), |body| f(body))? {
+            let operation = lower_query_to_ram(&query, self.program.relations())?;
+
+            self.do_handle_operation(&operation, blockstore, &Bindings::default(), &|args| {
+                let cols = query.vars().iter().map(|var| var.id().resolve()).zip(args);
+
+                solutions
+                    .borrow_mut()
+                    .insert(Tuple::new("query", cols, None));
+
+                Ok(())
+            })?;
+        }
+
+        Ok(solutions.into_inner().into_iter().collect())
+    }
+
+    /// Returns the declaration of an input relation, which tuples can be inserted into.
+    pub(crate) fn input_declaration(&self, id: RelationId) -> Result<Arc<Declaration>> {
+        let Some(declaration) = self.program.declarations().get(&id.resolve()) else {
+            return error(Error::UnrecognizedRelation(id.to_string()));
+        };
+
+        if declaration.source() != Source::Edb {
+            return error(Error::InsertIDB(id));
+        }
+
+        Ok(Arc::clone(declaration))
+    }
+
+    /// Checks that each binding is to a column of the relation, with a value of the
+    /// column's type. Indexed relations would otherwise ignore unknown columns.
+    fn check_bindings(&self, id: RelationId, bindings: &[(ColId, Val)]) -> Result<()> {
+        let Some(declaration) = self.program.declarations().get(&id.resolve()) else {
+            return error(Error::UnrecognizedRelation(id.to_string()));
+        };
+
+        let schema = declaration.schema();
+
+        for (col_id, val) in bindings {
+            let Some(col) = schema.get_col(col_id) else {
+                return error(Error::UnrecognizedColumnBinding(id, *col_id));
+            };
+
+            if col.col_type().check(val).is_err() {
+                return error(Error::ColumnValueTypeConflict(
+                    id,
+                    *col_id,
+                    ColVal::Lit(val.clone()),
+                    *col.col_type(),
+                ));
+            }
+        }
 
         Ok(())
     }
 
-    pub(crate) fn pop(&mut self) -> Result<Option<Tuple>> {
-        let fact = self.output.pop_front();
+    /// Explains why `tuple` is in its relation, as of the end of the last epoch.
+    pub(crate) fn explain(&self, tuple: &Tuple) -> Result<Option<Derivation>> {
+        let Some(provenance) = &self.provenance else {
+            return error(Error::ProvenanceNotRecorded);
+        };
+
+        provenance.borrow().explain(tuple, &|tuple| {
+            let bindings = tuple
+                .cols()
+                .into_iter()
+                .filter_map(|id| tuple.col(&id).map(|val| (id, val)))
+                .collect();
 
-        Ok(fact)
+            let stored = self
+                .search(tuple.id(), bindings)?
+                .into_iter()
+                .find(|other| other == tuple);
+
+            Ok(stored)
+        })
     }
 
     pub(crate) fn step_epoch<BS>(&mut self, blockstore: &BS) -> Result<()>
@@ -83,6 +275,12 @@ where
             };
         }
 
+        // Epochs with no input to evaluate don't complete
+        if self.timestamp.epoch() != start.epoch() {
+            self.completed = Some(start);
+            self.output.push_back(SinkEvent::EpochComplete(start));
+        }
+
         Ok(())
     }
 
@@ -90,21 +288,23 @@ where
     where
         BS: Blockstore,
     {
-        let continue_epoch = match &*self.load_statement()? {
+        let continue_epoch = match &*self.load_statement(&self.pc)? {
             Statement::Insert(insert) => self.handle_insert(insert, blockstore),
             Statement::Merge(merge) => self.handle_merge(merge),
             Statement::Swap(swap) => self.handle_swap(swap),
             Statement::Purge(purge) => self.handle_purge(purge),
+            Statement::Difference(difference) => self.handle_difference(difference),
+            Statement::Remove(remove) => self.handle_remove(remove),
             Statement::Exit(exit) => {
-                debug_assert!(self.pc.1.is_some());
+                debug_assert!(self.pc.len() > 1);
 
                 self.handle_exit(exit)
             }
             Statement::Sources(sources) => self.handle_sources(sources),
             Statement::Sinks(sinks) => self.handle_sinks(sinks),
-            Statement::Loop(Loop { .. }) => {
+            Statement::Loop(_) | Statement::Branch(_) => {
                 return error(Error::InternalRhizomeError(
-                    "nested loop encountered".to_owned(),
+                    "PC stopped at a loop or branch rather than its body".to_owned(),
                 ));
             }
         }?;
@@ -113,74 +313,121 @@ where
             return Ok(false);
         }
 
-        self.pc = self.step_pc()?;
-
-        if self.pc.0 == 0 {
-            self.timestamp = self.timestamp.advance_epoch();
-        } else if self.pc.1 == Some(0) {
-            self.timestamp = self.timestamp.advance_iteration();
-        };
+        self.step_pc()?;
 
         Ok(true)
     }
 
-    fn step_pc(&self) -> Result<(usize, Option<usize>)> {
-        match self.pc {
-            (outer, None) => {
-                if let Some(statement) = self.program.statements().get(self.pc.0 + 1) {
-                    if let Statement::Loop(Loop { .. }) = &**statement {
-                        Ok(((outer + 1) % self.program.statements().len(), Some(0)))
-                    } else {
-                        Ok(((outer + 1) % self.program.statements().len(), None))
+    fn step_pc(&mut self) -> Result<()> {
+        let epoch = self.timestamp.epoch();
+
+        self.advance_pc()?;
+
+        // Enter any loops that begin at the next statement, along with any branches
+        // whose condition holds
+        loop {
+            match &*self.load_statement(&self.pc)? {
+                Statement::Loop(inner) => {
+                    if inner.body().is_empty() {
+                        return error(Error::InternalRhizomeError("empty loop".to_owned()));
+                    }
+
+                    self.pc.push(0);
+
+                    if self.timestamp.epoch() == epoch {
+                        self.timestamp = self.timestamp.advance_iteration();
                     }
-                } else {
-                    Ok(((outer + 1) % self.program.statements().len(), None))
                 }
-            }
-            (outer, Some(inner)) => {
-                let outer_statement =
-                    self.program.statements().get(self.pc.0).ok_or_else(|| {
-                        Error::InternalRhizomeError("PC stepped past end of program".to_owned())
-                    })?;
-
-                match &**outer_statement {
-                    Statement::Loop(inner_statement) => {
-                        Ok((outer, Some((inner + 1) % inner_statement.body().len())))
+                Statement::Branch(inner) => {
+                    if inner.body().is_empty() {
+                        return error(Error::InternalRhizomeError("empty branch".to_owned()));
                     }
 
-                    _ => error(Error::InternalRhizomeError(
-                        "current statement must be a loop".to_owned(),
-                    )),
+                    if inner.apply()? {
+                        self.pc.push(0);
+                    } else {
+                        self.advance_pc()?;
+                    }
                 }
+                _ => return Ok(()),
             }
         }
     }
 
-    fn load_statement(&self) -> Result<Arc<Statement>> {
-        let outer_statement = self.program.statements().get(self.pc.0).ok_or_else(|| {
-            Error::InternalRhizomeError("PC stepped past end of program".to_owned())
-        })?;
+    /// Steps past the current statement, leaving any branches that end with it.
+    /// Statements wrap around to the start of the enclosing loop, or of the program.
+    fn advance_pc(&mut self) -> Result<()> {
+        loop {
+            let (index, enclosing) = self
+                .pc
+                .split_last()
+                .ok_or_else(|| Error::InternalRhizomeError("PC is empty".to_owned()))?;
 
-        match &**outer_statement {
-            Statement::Loop(loop_statement) => {
-                let inner_pc = self.pc.1.ok_or_else(|| {
-                    Error::InternalRhizomeError("inner loop PC is None".to_owned())
-                })?;
+            let depth = enclosing.len();
+            let next = *index + 1;
 
-                let inner_statement = loop_statement.body().get(inner_pc).ok_or_else(|| {
-                    Error::InternalRhizomeError("inner loop PC stepped past end of loop".to_owned())
-                })?;
+            if next < self.load_block(enclosing)?.len() {
+                self.pc[depth] = next;
 
-                Ok(Arc::clone(inner_statement))
+                return Ok(());
             }
-            _ => {
-                debug_assert!(self.pc.1.is_none());
 
-                Ok(Arc::clone(outer_statement))
+            if enclosing.is_empty() {
+                self.pc = vec![0];
+                self.timestamp = self.timestamp.advance_epoch();
+
+                return Ok(());
             }
+
+            match &*self.load_statement(enclosing)? {
+                Statement::Loop(_) => {
+                    self.pc[depth] = 0;
+                    self.timestamp = self.timestamp.advance_iteration();
+
+                    return Ok(());
+                }
+                Statement::Branch(_) => {
+                    self.pc.pop();
+                }
+                _ => {
+                    return error(Error::InternalRhizomeError(
+                        "current statement must be a loop or branch".to_owned(),
+                    ))
+                }
+            }
+        }
+    }
+
+    fn load_block(&self, pc: &[usize]) -> Result<&[Arc<Statement>]> {
+        let Some((index, enclosing)) = pc.split_last() else {
+            return Ok(self.program.statements());
+        };
+
+        let statement = self.load_block(enclosing)?.get(*index).ok_or_else(|| {
+            Error::InternalRhizomeError("PC stepped past end of program".to_owned())
+        })?;
+
+        match &**statement {
+            Statement::Loop(inner) => Ok(inner.body()),
+            Statement::Branch(inner) => Ok(inner.body()),
+            _ => error(Error::InternalRhizomeError(
+                "current statement must be a loop or branch".to_owned(),
+            )),
         }
     }
 
+    fn load_statement(&self, pc: &[usize]) -> Result<Arc<Statement>> {
+        let (index, enclosing) = pc
+            .split_last()
+            .ok_or_else(|| Error::InternalRhizomeError("PC is empty".to_owned()))?;
+
+        let statement = self.load_block(enclosing)?.get(*index).ok_or_else(|| {
+            Error::InternalRhizomeError("PC stepped past end of program".to_owned())
+        })?;
+
+        Ok(Arc::clone(statement))
+    }
+
     fn handle_insert<BS>(&mut self, insert: &Insert, blockstore: &BS) -> Result<bool>
     where
         BS: Blockstore,
@@ -197,9 +444,17 @@ where
     where
         BS: Blockstore,
     {
-        let bindings = Bindings::default();
+        let bindings = if self.provenance.is_some() {
+            Bindings::with_provenance()
+        } else {
+            Bindings::default()
+        };
 
-        self.do_handle_operation(operation, blockstore, &bindings)
+        self.do_handle_operation(operation, blockstore, &bindings, &|_| {
+            error(Error::InternalRhizomeError(
+                "yield outside of an aggregation".to_owned(),
+            ))
+        })
     }
 
     fn do_handle_operation<BS>(
@@ -207,14 +462,25 @@ where
         operation: &Operation,
         blockstore: &BS,
         bindings: &Bindings,
+        on_yield: &OnYield<'_>,
     ) -> Result<bool>
     where
         BS: Blockstore,
     {
         match operation {
-            Operation::Search(inner) => self.handle_search(inner, blockstore, bindings),
-            Operation::Project(inner) => self.handle_project(inner, blockstore, bindings),
-            Operation::Aggregation(inner) => self.handle_aggregation(inner, blockstore, bindings),
+            Operation::Search(inner) => self.handle_search(inner, blockstore, bindings, on_yield),
+            Operation::Project(inner) => self.handle_project(inner, bindings),
+            Operation::Aggregation(inner) => {
+                self.handle_aggregation(inner, blockstore, bindings, on_yield)
+            }
+            Operation::Compute(inner) => self.handle_compute(inner, blockstore, bindings, on_yield),
+            Operation::Yield(inner) => {
+                if let Some(args) = inner.apply(bindings)? {
+                    on_yield(args)?;
+                }
+
+                Ok(true)
+            }
         }?;
 
         Ok(true)
@@ -225,40 +491,70 @@ where
         search: &Search,
         blockstore: &BS,
         bindings: &Bindings,
+        on_yield: &OnYield<'_>,
     ) -> Result<bool>
     where
         BS: Blockstore,
     {
-        search.apply(blockstore, bindings, |next_bindings| {
-            self.do_handle_operation(search.operation(), blockstore, &next_bindings)
+        search.apply(bindings, |next_bindings| {
+            self.do_handle_operation(search.operation(), blockstore, &next_bindings, on_yield)
         })
     }
 
-    fn handle_project<BS>(
+    fn handle_project(&self, project: &Project, bindings: &Bindings) -> Result<bool> {
+        let Some(declaration) = self.declarations.get(&project.relation_key().0) else {
+            return error(Error::InternalRhizomeError(
+                "declaration not found".to_owned(),
+            ));
+        };
+
+        let mut provenance = self.provenance.as_ref().map(RefCell::borrow_mut);
+
+        project.apply(
+            bindings,
+            declaration,
+            self.timestamp.as_pair(),
+            provenance.as_deref_mut(),
+        )?;
+
+        Ok(true)
+    }
+
+    fn handle_aggregation<BS>(
         &self,
-        project: &Project,
+        agg: &Aggregation,
         blockstore: &BS,
         bindings: &Bindings,
+        on_yield: &OnYield<'_>,
     ) -> Result<bool>
     where
         BS: Blockstore,
     {
-        project.apply(blockstore, bindings)?;
+        let acc = RefCell::new(agg.init());
+
+        self.do_handle_operation(agg.source(), blockstore, bindings, &|args| {
+            acc.borrow_mut().step(args)
+        })?;
+
+        if let Some(next_bindings) = agg.finalize(&**acc.borrow(), bindings)? {
+            self.do_handle_operation(agg.operation(), blockstore, &next_bindings, on_yield)?;
+        }
 
         Ok(true)
     }
 
-    fn handle_aggregation<BS>(
+    fn handle_compute<BS>(
         &self,
-        agg: &Aggregation,
+        compute: &Compute,
         blockstore: &BS,
         bindings: &Bindings,
+        on_yield: &OnYield<'_>,
     ) -> Result<bool>
     where
         BS: Blockstore,
     {
-        if let Some(next_bindings) = agg.apply(blockstore, bindings)? {
-            self.do_handle_operation(agg.operation(), blockstore, &next_bindings)?;
+        if let Some(next_bindings) = compute.apply(bindings)? {
+            self.do_handle_operation(compute.operation(), blockstore, &next_bindings, on_yield)?;
         }
 
         Ok(true)
@@ -282,21 +578,41 @@ where
         Ok(true)
     }
 
+    fn handle_difference(&self, difference: &Difference) -> Result<bool> {
+        difference.apply()?;
+
+        Ok(true)
+    }
+
+    fn handle_remove(&self, remove: &Remove) -> Result<bool> {
+        let mut provenance = self.provenance.as_ref().map(RefCell::borrow_mut);
+
+        remove.apply(provenance.as_deref_mut())?;
+
+        Ok(true)
+    }
+
     fn handle_exit(&mut self, exit: &Exit) -> Result<bool> {
+        // Leave the innermost loop, so the PC steps past it
         if exit.apply()? {
-            self.pc.1 = None;
+            self.pc.pop();
         }
 
         Ok(true)
     }
 
     fn handle_sources(&mut self, sources: &Sources) -> Result<bool> {
-        Ok(sources.apply(&mut self.input)?
+        Ok(sources.apply(&mut self.input, self.timestamp.as_pair())?
             || self.timestamp().epoch_start() == self.timestamp().clock_start())
     }
 
     fn handle_sinks(&mut self, sinks: &Sinks) -> Result<bool> {
-        sinks.apply(&mut self.output)?;
+        let timestamp = self.timestamp;
+
+        sinks.apply(|fact, diff| {
+            self.output
+                .push_back(SinkEvent::Diff(fact, timestamp, diff))
+        })?;
 
         Ok(true)
     }
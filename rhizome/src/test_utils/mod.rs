@@ -102,9 +102,21 @@ macro_rules! assert_derives {
             );
         }
 
-        while let Ok(Some(fact)) = vm.pop() {
-            if let Some(relation) = facts.get_mut(&fact.id()) {
-                relation.insert(fact);
+        // Mirror each relation by applying its diffs, so that a spurious
+        // retraction fails the assertion rather than going unnoticed
+        while let Ok(Some(event)) = vm.pop() {
+            if let $crate::runtime::SinkEvent::Diff(fact, _, diff) = event {
+                if let Some(relation) = facts.get_mut(&fact.id()) {
+                    match diff {
+                        1 => assert!(relation.insert(fact.clone()), "inserted twice: {:?}", fact),
+                        -1 => assert!(
+                            relation.remove(&fact),
+                            "retracted before insertion: {:?}",
+                            fact
+                        ),
+                        _ => panic!("unexpected diff {} for {:?}", diff, fact),
+                    }
+                }
             }
         }
 
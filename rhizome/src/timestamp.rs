@@ -21,6 +21,12 @@ pub trait Timestamp:
 
     fn advance_epoch(&self) -> Self;
     fn advance_iteration(&self) -> Self;
+
+    /// The epoch and iteration of the timestamp, which stored tuples record as the
+    /// time they were derived at. Clocks that don't count both record none.
+    fn as_pair(&self) -> Option<PairTimestamp> {
+        None
+    }
 }
 
 impl Timestamp for () {
@@ -97,6 +103,10 @@ impl Timestamp for PairTimestamp {
     fn advance_iteration(&self) -> Self {
         Self(self.0, self.1 + 1)
     }
+
+    fn as_pair(&self) -> Option<PairTimestamp> {
+        Some(*self)
+    }
 }
 
 impl From<(u32, u32)> for PairTimestamp {
@@ -125,5 +135,8 @@ mod tests {
 
         assert_eq!(PairTimestamp(4, 0), PairTimestamp(3, 2).advance_epoch());
         assert_eq!(PairTimestamp(3, 3), PairTimestamp(3, 2).advance_iteration());
+
+        assert_eq!(Some(PairTimestamp(3, 2)), PairTimestamp(3, 2).as_pair());
+        assert_eq!(None, ().as_pair());
     }
 }
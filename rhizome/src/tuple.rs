@@ -1,6 +1,7 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::{
+    cmp::Ordering,
     collections::{BTreeMap, HashSet},
     fmt::Display,
 };
@@ -10,6 +11,7 @@ use cid::Cid;
 use crate::{
     id::{ColId, RelationId},
     storage::content_addressable::ContentAddressable,
+    timestamp::PairTimestamp,
     value::Val,
 };
 
@@ -60,13 +62,48 @@ impl InputTuple {
     pub fn links(&self) -> &HashSet<Cid> {
         &self.links
     }
+
+    /// The tuples that hold this fact in the `evac` and `links` relations.
+    pub(crate) fn tuples(&self) -> Result<Vec<Tuple>> {
+        let cid = self.cid()?;
+
+        let fact = Tuple::new(
+            "evac",
+            [
+                ("entity", self.entity()),
+                ("attribute", self.attr()),
+                ("value", self.val()),
+            ],
+            Some(cid),
+        );
+
+        let links = self
+            .links()
+            .iter()
+            .map(|link| Tuple::new("links", [("from", cid), ("to", *link)], None));
+
+        Ok([fact].into_iter().chain(links).collect())
+    }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+/// Whether a tuple holds in the model of a program. Tuples are only undefined
+/// in relations evaluated under the well-founded semantics.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Truth {
+    #[default]
+    True,
+    Undefined,
+}
+
+// Tuples are compared without their timestamp, so that deriving a tuple again
+// doesn't duplicate it
+#[derive(Clone, Debug)]
 pub struct Tuple {
     id: RelationId,
     cols: BTreeMap<ColId, Val>,
     cid: Option<Cid>,
+    truth: Truth,
+    timestamp: Option<PairTimestamp>,
 }
 
 impl Tuple {
@@ -81,7 +118,23 @@ impl Tuple {
             .map(|(k, v)| (k.into(), v.into()))
             .collect();
 
-        Self { id, cols, cid }
+        Self {
+            id,
+            cols,
+            cid,
+            truth: Truth::default(),
+            timestamp: None,
+        }
+    }
+
+    pub(crate) fn with_truth(mut self, truth: Truth) -> Self {
+        self.truth = truth;
+        self
+    }
+
+    pub(crate) fn with_timestamp(mut self, timestamp: Option<PairTimestamp>) -> Self {
+        self.timestamp = timestamp;
+        self
     }
 
     pub fn id(&self) -> RelationId {
@@ -99,6 +152,40 @@ impl Tuple {
     pub fn cid(&self) -> Option<Cid> {
         self.cid
     }
+
+    pub fn truth(&self) -> Truth {
+        self.truth
+    }
+
+    /// The time the tuple was first derived at, or inserted at if it's an input.
+    /// Only tuples that have been stored in a relation have a timestamp.
+    pub fn timestamp(&self) -> Option<PairTimestamp> {
+        self.timestamp
+    }
+
+    fn key(&self) -> (&RelationId, &BTreeMap<ColId, Val>, &Option<Cid>, &Truth) {
+        (&self.id, &self.cols, &self.cid, &self.truth)
+    }
+}
+
+impl PartialEq for Tuple {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Tuple {}
+
+impl PartialOrd for Tuple {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Tuple {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
 }
 
 impl Display for Tuple {
@@ -111,9 +198,15 @@ impl Display for Tuple {
             .join(", ");
 
         if let Some(cid) = self.cid {
-            write!(f, "{}({}) (CID = {})", self.id, cols, cid)
+            write!(f, "{}({}) (CID = {})", self.id, cols, cid)?;
         } else {
-            write!(f, "{}({})", self.id, cols)
+            write!(f, "{}({})", self.id, cols)?;
         }
+
+        if self.truth == Truth::Undefined {
+            write!(f, " (undefined)")?;
+        }
+
+        Ok(())
     }
 }
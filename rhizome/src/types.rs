@@ -162,6 +162,15 @@ impl IntoColType for Cid {
     }
 }
 
+impl<T> IntoColType for Vec<T>
+where
+    T: IntoColType,
+{
+    fn into_col_type() -> ColType {
+        ColType::Type(Type::List)
+    }
+}
+
 impl Display for ColType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -187,9 +196,26 @@ pub enum Type {
     Char,
     String,
     Cid,
+    List,
 }
 
 impl Type {
+    pub fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            Type::S8
+                | Type::U8
+                | Type::S16
+                | Type::U16
+                | Type::S32
+                | Type::U32
+                | Type::F32
+                | Type::S64
+                | Type::U64
+                | Type::F64
+        )
+    }
+
     pub fn check(&self, value: &Val) -> Result<()> {
         let other = &value.type_of();
 
@@ -226,6 +252,7 @@ impl Display for Type {
             Type::Char => "char",
             Type::String => "string",
             Type::Cid => "CID",
+            Type::List => "list",
         };
 
         f.write_str(s)
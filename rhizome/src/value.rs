@@ -4,10 +4,12 @@ use std::{
 };
 
 use cid::Cid;
+use num_traits::Float;
 use ordered_float::OrderedFloat;
+use pretty::RcDoc;
 use serde::{Deserialize, Serialize};
 
-use crate::types::Type;
+use crate::{pretty::Pretty, types::Type};
 
 #[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
 pub enum Val {
@@ -25,6 +27,7 @@ pub enum Val {
     Char(char),
     String(Arc<str>),
     Cid(Cid),
+    List(Arc<[Val]>),
 }
 
 impl Val {
@@ -44,6 +47,7 @@ impl Val {
             Val::Char(_) => Type::Char,
             Val::String(_) => Type::String,
             Val::Cid(_) => Type::Cid,
+            Val::List(_) => Type::List,
         }
     }
 }
@@ -126,6 +130,12 @@ impl From<&str> for Val {
     }
 }
 
+impl From<Arc<str>> for Val {
+    fn from(value: Arc<str>) -> Self {
+        Self::String(value)
+    }
+}
+
 impl From<String> for Val {
     fn from(value: String) -> Self {
         Self::String(Arc::from(value))
@@ -138,6 +148,15 @@ impl From<Cid> for Val {
     }
 }
 
+impl<T> From<Vec<T>> for Val
+where
+    T: Into<Val>,
+{
+    fn from(value: Vec<T>) -> Self {
+        Self::List(value.into_iter().map(Into::into).collect())
+    }
+}
+
 impl TryFrom<Val> for bool {
     type Error = ();
 
@@ -303,6 +322,20 @@ impl TryFrom<Val> for Cid {
     }
 }
 
+impl<T> TryFrom<Val> for Vec<T>
+where
+    T: TryFrom<Val, Error = ()>,
+{
+    type Error = ();
+
+    fn try_from(value: Val) -> Result<Self, Self::Error> {
+        match value {
+            Val::List(v) => v.iter().cloned().map(T::try_from).collect(),
+            _ => Err(()),
+        }
+    }
+}
+
 impl Display for Val {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -320,6 +353,86 @@ impl Display for Val {
             Val::Char(v) => f.write_fmt(format_args!("{v:?}")),
             Val::String(v) => f.write_fmt(format_args!("{v:?}")),
             Val::Cid(v) => f.write_fmt(format_args!("\"{v}\"")),
+            Val::List(v) => {
+                f.write_str("[")?;
+
+                for (i, val) in v.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+
+                    Display::fmt(val, f)?;
+                }
+
+                f.write_str("]")
+            }
+        }
+    }
+}
+
+impl Pretty for Val {
+    fn to_doc(&self) -> RcDoc<'_, ()> {
+        match self {
+            Val::F32(v) => float_doc(v.0),
+            Val::F64(v) => float_doc(v.0),
+            Val::Char(v) => RcDoc::as_string(quoted([*v], '\'')),
+            Val::String(v) => RcDoc::as_string(quoted(v.chars(), '"')),
+            Val::Cid(v) => RcDoc::as_string(format!("cid\"{v}\"")),
+            Val::List(v) => RcDoc::concat([
+                RcDoc::text("["),
+                RcDoc::intersperse(
+                    v.iter().map(|val| val.to_doc()),
+                    RcDoc::text(",").append(RcDoc::line()),
+                )
+                .nest(1)
+                .group(),
+                RcDoc::text("]"),
+            ]),
+            _ => RcDoc::as_string(self),
+        }
+    }
+}
+
+/// Quotes `chars` as they appear in source, escaping only what the parser
+/// requires to be escaped, unlike `Debug`.
+fn quoted(chars: impl IntoIterator<Item = char>, quote: char) -> String {
+    let mut s = String::from(quote);
+
+    for c in chars {
+        match c {
+            '\\' => s.push_str("\\\\"),
+            '\n' => s.push_str("\\n"),
+            '\r' => s.push_str("\\r"),
+            '\t' => s.push_str("\\t"),
+            '\0' => s.push_str("\\0"),
+            c if c == quote => {
+                s.push('\\');
+                s.push(c);
+            }
+            c => s.push(c),
+        }
+    }
+
+    s.push(quote);
+    s
+}
+
+// Keep the decimal point, so that floats read back as floats. Display never uses
+// exponent notation, which the parser doesn't accept. Non-finite floats have no
+// literal of their own, so they're quoted like CIDs.
+fn float_doc<'a, F: Float + Display>(v: F) -> RcDoc<'a, ()> {
+    if v.is_nan() {
+        RcDoc::text("float\"nan\"")
+    } else if v.is_infinite() && v.is_sign_negative() {
+        RcDoc::text("float\"-inf\"")
+    } else if v.is_infinite() {
+        RcDoc::text("float\"inf\"")
+    } else {
+        let s = v.to_string();
+
+        match s.contains('.') {
+            true => RcDoc::as_string(s),
+            false => RcDoc::as_string(format!("{s}.0")),
         }
     }
 }
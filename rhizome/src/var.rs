@@ -25,6 +25,11 @@ impl Var {
         Self { id, typ }
     }
 
+    /// Creates a variable with a type inferred while building a clause.
+    pub(crate) fn inferred(id: VarId, typ: ColType) -> Self {
+        Self { id, typ }
+    }
+
     pub fn id(&self) -> VarId {
         self.id
     }
@@ -49,13 +54,27 @@ impl Display for Var {
     }
 }
 
+/// Matches any value in a column of a body atom, without binding it.
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Wildcard;
+
+#[derive(Debug, Eq, PartialEq, Hash)]
 pub struct TypedVar<T> {
     id: VarId,
     typ: ColType,
     _marker: PhantomData<T>,
 }
 
+// Implemented by hand, since deriving would require T: Copy, which isn't true
+// of every column type (e.g. Arc<str>)
+impl<T> Clone for TypedVar<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for TypedVar<T> {}
+
 impl<T> TypedVar<T>
 where
     T: IntoColType,